@@ -0,0 +1,33 @@
+use std::{str::FromStr, vec::IntoIter};
+
+/// Command line options given as `--flag value` pairs.
+pub struct Args(IntoIter<String>);
+
+impl Args {
+    /// Wraps the arguments following the command.
+    #[must_use]
+    pub fn new(args: Vec<String>) -> Self {
+        Self(args.into_iter())
+    }
+
+    /// Returns the next flag, or `None` once all arguments are consumed.
+    pub fn next_flag(&mut self) -> Result<Option<String>, String> {
+        match self.0.next() {
+            Some(flag) if flag.starts_with("--") => Ok(Some(flag)),
+            Some(arg) => Err(format!("unexpected argument: {arg}")),
+            None => Ok(None),
+        }
+    }
+
+    /// Parses the value following `flag`.
+    pub fn value<T: FromStr>(&mut self, flag: &str) -> Result<T, String> {
+        let value = self
+            .0
+            .next()
+            .ok_or_else(|| format!("missing value for {flag}"))?;
+
+        value
+            .parse()
+            .map_err(|_| format!("invalid value for {flag}: {value}"))
+    }
+}
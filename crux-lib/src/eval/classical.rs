@@ -0,0 +1,135 @@
+use const_for::const_for;
+
+use crate::{
+    eval::piece_value,
+    shogi::{
+        attacks::king_attacks,
+        core::{Color, PieceType, Square},
+        position::{hand::Hand, Position},
+    },
+};
+
+/// Bonuses of each piece type by rank, seen from its owner: index 0 is the farthest rank
+/// of the opponent's camp and index 8 the own back rank.
+const RANK_BONUSES: [[i32; 9]; PieceType::COUNT] = [
+    [0, 0, 30, 20, 10, 5, 0, 0, 0],            // Pawn
+    [0, 0, 10, 6, 3, 0, 0, 0, 0],              // Lance
+    [0, 0, 10, 10, 5, 0, 0, -5, -5],           // Knight
+    [0, 10, 15, 15, 10, 5, 0, -5, -10],        // Silver
+    [-10, -5, 0, 0, 0, 5, 10, 10, 5],          // Gold
+    [0, 0, 0, 0, 0, 0, 0, 0, 0],               // Bishop
+    [20, 15, 10, 0, 0, 0, 0, 0, 0],            // Rook
+    [20, 20, 15, 10, 5, 0, -5, -10, -15],      // ProPawn
+    [20, 20, 15, 10, 5, 0, -5, -10, -15],      // ProLance
+    [20, 20, 15, 10, 5, 0, -5, -10, -15],      // ProKnight
+    [20, 20, 15, 10, 5, 0, -5, -10, -15],      // ProSilver
+    [0, 5, 5, 5, 5, 10, 10, 10, 5],            // Horse
+    [20, 15, 10, 5, 0, 0, 0, 0, 0],            // Dragon
+    [-80, -60, -40, -30, -20, -10, 0, 10, 10], // King
+];
+
+/// Bonuses of the king by file, preferring a castle on either wing.
+const KING_FILE_BONUSES: [i32; 9] = [10, 10, 5, 0, -5, 0, 5, 10, 10];
+
+/// Bonuses of promoted pieces on top of their material value.
+const PROMOTED_BONUSES: [i32; PieceType::COUNT] = [0, 0, 0, 0, 0, 0, 0, 30, 15, 15, 10, 40, 60, 0];
+
+/// Piece-square tables of Black's pieces, including the promotion bonuses.
+/// White uses them with the board rotated.
+const PIECE_SQUARE_TABLES: [[i32; Square::COUNT]; PieceType::COUNT] = {
+    let mut tables = [[0; Square::COUNT]; PieceType::COUNT];
+
+    const_for!(pt in 0..PieceType::COUNT => {
+        const_for!(sq in 0..Square::COUNT => {
+            let square = Square::from(sq);
+            let mut bonus = RANK_BONUSES[pt][square.rank().as_usize()] + PROMOTED_BONUSES[pt];
+
+            if pt == PieceType::King.as_usize() {
+                bonus += KING_FILE_BONUSES[square.file().as_usize()];
+            }
+
+            tables[pt][sq] = bonus;
+        });
+    });
+
+    tables
+};
+
+/// Scales of the value of pieces in hand, in 64ths, by how many of the type are already
+/// held: the first pieces are worth more than on the board for the drops they allow,
+/// while further ones add less.
+const HAND_SCALES: [i32; 4] = [72, 68, 64, 60];
+
+const KING_DANGER_WEIGHT: i32 = 4;
+const MAX_KING_DANGER: i32 = 800;
+
+/// Evaluates the position from the perspective of the side to move with hand-crafted terms.
+///
+/// The score sums the material on the board and in hand, piece-square tables and bonuses
+/// of promoted pieces, and a penalty for the opponent's attacks around the king.
+#[must_use]
+pub fn evaluate(pos: &Position) -> i32 {
+    let stm = pos.side_to_move();
+
+    score(pos, stm) - score(pos, stm.opposite())
+}
+
+fn score(pos: &Position, color: Color) -> i32 {
+    let mut total = 0;
+    let mut pieces = pos.color_bb(color);
+
+    while pieces.has_any() {
+        let square = pieces.pop_lsb();
+        let piece_type = pos.piece_at(square).unwrap().piece_type();
+        let square = match color {
+            Color::Black => square,
+            Color::White => square.rotate180(),
+        };
+
+        total += piece_value(piece_type) + PIECE_SQUARE_TABLES[piece_type][square];
+    }
+
+    let hand = pos.hand(color);
+
+    for &piece_type in PieceType::ALL.iter().take(Hand::HAND_PIECE_TYPES) {
+        for index in 0..hand.count(piece_type) as usize {
+            total += piece_value(piece_type) * HAND_SCALES[index.min(HAND_SCALES.len() - 1)] / 64;
+        }
+    }
+
+    total - king_danger(pos, color)
+}
+
+/// Returns the penalty for the opponent's control of the squares around the king of
+/// `color`, growing quadratically with the number of attacks.
+///
+/// Squares are weighed by the attackers minus the defenders, and every non-pawn piece in
+/// the opponent's hand counts as a potential attack by a drop.
+fn king_danger(pos: &Position, color: Color) -> i32 {
+    let Some(king_square) = pos.king_square(color) else {
+        return 0;
+    };
+
+    let enemy = color.opposite();
+    let occupied = pos.occupancy();
+    let defenders_bb = pos.color_bb(color) & !king_square.bit();
+    let mut zone = king_attacks(king_square);
+    let mut units = 0;
+
+    while zone.has_any() {
+        let square = zone.pop_lsb();
+        let attackers = pos.attackers_to(square, occupied);
+        let attacks = (attackers & pos.color_bb(enemy)).count_ones() as i32;
+        let defenses = (attackers & defenders_bb).count_ones() as i32;
+
+        units += (2 * attacks - defenses).max(0);
+    }
+
+    let hand = pos.hand(enemy);
+
+    for &piece_type in PieceType::ALL.iter().take(Hand::HAND_PIECE_TYPES).skip(1) {
+        units += hand.count(piece_type) as i32;
+    }
+
+    (KING_DANGER_WEIGHT * units * units).min(MAX_KING_DANGER)
+}
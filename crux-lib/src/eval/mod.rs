@@ -0,0 +1,70 @@
+pub mod classical;
+pub mod nnue;
+
+use crate::{
+    eval::nnue::network::Network,
+    shogi::{
+        core::{Color, PieceType},
+        position::{hand::Hand, Position},
+    },
+};
+
+/// The evaluation function used by the search.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum EvalType {
+    /// The NNUE network, see [`nnue`].
+    Nnue,
+    /// The hand-crafted evaluation, see [`classical`].
+    Classical,
+}
+
+impl Default for EvalType {
+    /// Returns [`EvalType::Nnue`] if a network is embedded, and [`EvalType::Classical`]
+    /// otherwise, since the fallback network only counts material.
+    fn default() -> Self {
+        if Network::has_embedded() {
+            EvalType::Nnue
+        } else {
+            EvalType::Classical
+        }
+    }
+}
+
+/// Material values of each piece type, in centipawns.
+///
+/// The king has no material value since it can never be captured.
+const PIECE_VALUES: [i32; PieceType::COUNT] = [
+    90, 315, 405, 495, 540, 855, 990, 540, 540, 540, 540, 945, 1395, 0,
+];
+
+/// Returns the material value of the given piece type.
+#[must_use]
+pub const fn piece_value(piece_type: PieceType) -> i32 {
+    PIECE_VALUES[piece_type]
+}
+
+/// Returns the material balance of the pieces on the board and in hand,
+/// from the perspective of the side to move.
+#[must_use]
+pub fn material(pos: &Position) -> i32 {
+    let stm = pos.side_to_move();
+
+    side_material(pos, stm) - side_material(pos, stm.opposite())
+}
+
+fn side_material(pos: &Position, color: Color) -> i32 {
+    let mut total = 0;
+
+    for piece_type in PieceType::ALL {
+        let count = pos.piece_bb(piece_type.with_color(color)).count_ones();
+        total += piece_value(piece_type) * count as i32;
+    }
+
+    let hand = pos.hand(color);
+
+    for &piece_type in PieceType::ALL.iter().take(Hand::HAND_PIECE_TYPES) {
+        total += piece_value(piece_type) * hand.count(piece_type) as i32;
+    }
+
+    total
+}
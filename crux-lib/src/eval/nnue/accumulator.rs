@@ -0,0 +1,182 @@
+use crate::{
+    eval::nnue::{
+        features::{active_features, feature_index, feature_king_square, is_feature},
+        network::{Network, L1},
+    },
+    shogi::{
+        core::Color,
+        position::{dirty::DirtyPieces, Position},
+    },
+};
+
+/// The first layer of the network for both perspectives.
+#[derive(Clone)]
+#[repr(C, align(64))]
+pub struct Accumulator {
+    pub values: [[i16; L1]; Color::COUNT],
+}
+
+impl Accumulator {
+    /// Creates an accumulator with all values zero.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            values: [[0; L1]; Color::COUNT],
+        }
+    }
+
+    /// Computes the values of `perspective` from scratch.
+    pub fn refresh(&mut self, network: &Network, pos: &Position, perspective: Color) {
+        let values = &mut self.values[perspective];
+
+        *values = network.feature_bias;
+
+        for feature in active_features(pos, perspective) {
+            add(values, &network.feature_weights[feature]);
+        }
+    }
+}
+
+impl Default for Accumulator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+struct StackEntry {
+    accumulator: Accumulator,
+    dirty_pieces: DirtyPieces,
+    computed: [bool; Color::COUNT],
+}
+
+/// Accumulators of the positions along the current search path.
+///
+/// Each move pushes the pieces it changed, taken from [`Position::dirty_pieces`], and the
+/// accumulator of the new position is only computed once it is evaluated, from the nearest
+/// computed ancestor. A move of a perspective's own king changes all of its features, so
+/// that perspective is refreshed instead.
+pub struct AccumulatorStack {
+    entries: Vec<StackEntry>,
+    len: usize,
+}
+
+impl AccumulatorStack {
+    /// Creates a stack for the root position only.
+    #[must_use]
+    pub fn new() -> Self {
+        let mut stack = Self {
+            entries: Vec::new(),
+            len: 0,
+        };
+
+        stack.reset();
+        stack
+    }
+
+    /// Empties the stack, making the next position evaluated the root.
+    pub fn reset(&mut self) {
+        self.len = 0;
+        self.push_dirty_pieces(DirtyPieces::new());
+    }
+
+    /// Records the move just made on `pos`.
+    pub fn push(&mut self, pos: &Position) {
+        self.push_dirty_pieces(*pos.dirty_pieces());
+    }
+
+    /// Discards the last move, after it is unmade.
+    pub fn pop(&mut self) {
+        debug_assert!(self.len > 1);
+
+        self.len -= 1;
+    }
+
+    /// Evaluates `pos`, which must be the position reached by the recorded moves,
+    /// from the perspective of the side to move.
+    pub fn evaluate(&mut self, network: &Network, pos: &Position) -> i32 {
+        for perspective in Color::ALL {
+            self.update(network, pos, perspective);
+        }
+
+        let top = &self.entries[self.len - 1];
+
+        network.evaluate(&top.accumulator, pos.side_to_move())
+    }
+
+    fn push_dirty_pieces(&mut self, dirty_pieces: DirtyPieces) {
+        if self.len == self.entries.len() {
+            self.entries.push(StackEntry {
+                accumulator: Accumulator::new(),
+                dirty_pieces: DirtyPieces::new(),
+                computed: [false; Color::COUNT],
+            });
+        }
+
+        let entry = &mut self.entries[self.len];
+
+        entry.dirty_pieces = dirty_pieces;
+        entry.computed = [false; Color::COUNT];
+        self.len += 1;
+    }
+
+    fn update(&mut self, network: &Network, pos: &Position, perspective: Color) {
+        let top = self.len - 1;
+        let mut base = top;
+
+        while !self.entries[base].computed[perspective] {
+            if base == 0 || self.entries[base].dirty_pieces.king_moved(perspective) {
+                let entry = &mut self.entries[top];
+
+                entry.accumulator.refresh(network, pos, perspective);
+                entry.computed[perspective] = true;
+                return;
+            }
+
+            base -= 1;
+        }
+
+        let king_square = feature_king_square(pos, perspective);
+
+        for index in base + 1..=top {
+            let (head, tail) = self.entries.split_at_mut(index);
+            let parent = &head[index - 1].accumulator.values[perspective];
+            let entry = &mut tail[0];
+            let values = &mut entry.accumulator.values[perspective];
+
+            *values = *parent;
+
+            let dirty_pieces = &entry.dirty_pieces;
+            let row = |placement| {
+                &network.feature_weights[feature_index(perspective, king_square, placement)]
+            };
+
+            for &placement in dirty_pieces.removed.iter().filter(|&&p| is_feature(p)) {
+                sub(values, row(placement));
+            }
+
+            for &placement in dirty_pieces.added.iter().filter(|&&p| is_feature(p)) {
+                add(values, row(placement));
+            }
+
+            entry.computed[perspective] = true;
+        }
+    }
+}
+
+impl Default for AccumulatorStack {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn add(values: &mut [i16; L1], weights: &[i16; L1]) {
+    for (value, &weight) in values.iter_mut().zip(weights) {
+        *value = value.wrapping_add(weight);
+    }
+}
+
+fn sub(values: &mut [i16; L1], weights: &[i16; L1]) {
+    for (value, &weight) in values.iter_mut().zip(weights) {
+        *value = value.wrapping_sub(weight);
+    }
+}
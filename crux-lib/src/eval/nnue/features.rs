@@ -0,0 +1,131 @@
+use arrayvec::ArrayVec;
+use const_for::const_for;
+
+use crate::shogi::{
+    core::{Color, PieceType, Square},
+    position::{dirty::Placement, hand::Hand, Position},
+};
+
+/// Number of piece types that appear as features, all but the king.
+const BOARD_PIECE_TYPES: usize = PieceType::COUNT - 1;
+
+/// Number of board features of one king square: a friendly or enemy piece on a square.
+const BOARD_FEATURES: usize = Color::COUNT * BOARD_PIECE_TYPES * Square::COUNT;
+
+/// Offsets of the hand piece types within the hand features of one color.
+///
+/// Each piece type has one feature per piece that can be held.
+const HAND_OFFSETS: [usize; Hand::HAND_PIECE_TYPES + 1] = {
+    let mut offsets = [0; Hand::HAND_PIECE_TYPES + 1];
+
+    const_for!(i in 0..Hand::HAND_PIECE_TYPES => {
+        offsets[i + 1] = offsets[i] + Hand::max_piece_counts(PieceType::ALL[i]) as usize;
+    });
+
+    offsets
+};
+
+/// Number of hand features of one color.
+const HAND_FEATURES: usize = HAND_OFFSETS[Hand::HAND_PIECE_TYPES];
+
+/// Number of features of one king square.
+pub const FEATURES_PER_KING: usize = BOARD_FEATURES + Color::COUNT * HAND_FEATURES;
+
+/// Number of input features of the network.
+///
+/// The feature set is HalfKP-like: every non-king piece on the board and every piece in
+/// a hand is a feature, indexed by the square of the perspective's own king.
+pub const INPUTS: usize = Square::COUNT * FEATURES_PER_KING;
+
+/// Maximum number of features active in a position for one perspective.
+///
+/// With the standard set of pieces at most 38 are active, as every piece but the kings
+/// is either on the board or in a hand, but any position built on a board is accepted.
+pub const MAX_ACTIVE_FEATURES: usize = Square::COUNT + Color::COUNT * HAND_FEATURES;
+
+/// Returns the index of the feature of `placement` seen from `perspective`, whose king
+/// stands on `king_square`.
+///
+/// The board is rotated for White, so that both perspectives see their own pieces as
+/// Black pieces. The king itself is not a feature.
+#[must_use]
+pub fn feature_index(perspective: Color, king_square: Square, placement: Placement) -> usize {
+    let king = orient(perspective, king_square).as_usize() * FEATURES_PER_KING;
+
+    match placement {
+        Placement::Board(piece, square) => {
+            debug_assert!(piece.piece_type() != PieceType::King);
+
+            let relative = (piece.color() != perspective) as usize;
+            let piece_index = relative * BOARD_PIECE_TYPES + piece.piece_type().as_usize();
+
+            king + piece_index * Square::COUNT + orient(perspective, square).as_usize()
+        }
+        Placement::Hand(color, piece_type, index) => {
+            debug_assert!(index < Hand::max_piece_counts(piece_type));
+
+            let relative = (color != perspective) as usize;
+
+            king + BOARD_FEATURES
+                + relative * HAND_FEATURES
+                + HAND_OFFSETS[piece_type.as_usize()]
+                + index as usize
+        }
+    }
+}
+
+/// Returns `true` if `placement` is a feature, which every piece but the kings is.
+#[must_use]
+pub fn is_feature(placement: Placement) -> bool {
+    !matches!(placement, Placement::Board(piece, _) if piece.piece_type() == PieceType::King)
+}
+
+/// Returns the king square the features of `perspective` are indexed by.
+///
+/// Positions without the king of `perspective`, such as tsume problems, use the square the
+/// king starts on.
+#[must_use]
+pub fn feature_king_square(pos: &Position, perspective: Color) -> Square {
+    pos.king_square(perspective)
+        .unwrap_or_else(|| orient(perspective, Square::S59))
+}
+
+/// Returns the indices of all features active in the position, seen from `perspective`.
+#[must_use]
+pub fn active_features(pos: &Position, perspective: Color) -> ArrayVec<usize, MAX_ACTIVE_FEATURES> {
+    let king_square = feature_king_square(pos, perspective);
+    let mut features = ArrayVec::new();
+    let mut pieces = pos.occupancy() & !pos.piece_type_bb(PieceType::King);
+
+    while pieces.has_any() {
+        let square = pieces.pop_lsb();
+        let piece = pos.piece_at(square).unwrap();
+
+        features.push(feature_index(
+            perspective,
+            king_square,
+            Placement::Board(piece, square),
+        ));
+    }
+
+    for color in Color::ALL {
+        let hand = pos.hand(color);
+
+        for &piece_type in PieceType::ALL.iter().take(Hand::HAND_PIECE_TYPES) {
+            for index in 0..hand.count(piece_type) {
+                let placement = Placement::Hand(color, piece_type, index);
+
+                features.push(feature_index(perspective, king_square, placement));
+            }
+        }
+    }
+
+    features
+}
+
+const fn orient(perspective: Color, square: Square) -> Square {
+    match perspective {
+        Color::Black => square,
+        Color::White => square.rotate180(),
+    }
+}
@@ -0,0 +1,3 @@
+pub mod accumulator;
+pub mod features;
+pub mod network;
@@ -0,0 +1,317 @@
+use std::{
+    fmt::{Display, Formatter, Result as FmtResult},
+    fs::File,
+    io::{self, BufReader, BufWriter, Read, Write},
+    path::Path,
+    sync::{Arc, OnceLock},
+};
+
+use crate::{
+    eval::{
+        nnue::{
+            accumulator::Accumulator,
+            features::{feature_index, FEATURES_PER_KING, INPUTS},
+        },
+        piece_value,
+    },
+    shogi::{
+        core::{Color, PieceType, Square},
+        position::{dirty::Placement, hand::Hand},
+    },
+};
+
+/// Number of neurons of the accumulator of one perspective.
+pub const L1: usize = 256;
+
+/// Quantization factor of the accumulator; activations are clipped to `0..=QA`.
+pub const QA: i32 = 255;
+
+/// Quantization factor of the output weights.
+pub const QB: i32 = 64;
+
+/// Factor converting the output of the network to centipawns.
+pub const SCALE: i32 = 400;
+
+/// Magic bytes at the start of a network file.
+const MAGIC: [u8; 8] = *b"CRUXNNUE";
+
+/// Version of the network file format.
+const VERSION: u32 = 1;
+
+/// The network embedded at build time from the file given by `CRUX_EVALFILE`, if any.
+static EMBEDDED: &[u8] = include_bytes!(concat!(env!("OUT_DIR"), "/embedded.nnue"));
+
+/// An error returned when a network cannot be loaded.
+#[derive(Debug)]
+pub enum NetworkError {
+    Io(io::Error),
+    InvalidMagic,
+    UnsupportedVersion(u32),
+    /// The file was written for a different feature set or accumulator size.
+    ArchitectureMismatch,
+}
+
+impl Display for NetworkError {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        match self {
+            NetworkError::Io(err) => write!(f, "{err}"),
+            NetworkError::InvalidMagic => write!(f, "not a network file"),
+            NetworkError::UnsupportedVersion(version) => {
+                write!(f, "unsupported network version: {version}")
+            }
+            NetworkError::ArchitectureMismatch => write!(f, "network architecture mismatch"),
+        }
+    }
+}
+
+impl From<io::Error> for NetworkError {
+    fn from(err: io::Error) -> Self {
+        NetworkError::Io(err)
+    }
+}
+
+/// A quantized NNUE network.
+///
+/// The features of each perspective are summed into an accumulator of `L1` neurons,
+/// which is clipped to `0..=QA` and fed, side to move first, into a single output neuron.
+///
+/// Network files store, in little endian, the magic bytes, the format version, the number
+/// of inputs and `L1` as `u32`, followed by the feature biases, the feature weights and the
+/// output weights as `i16`, and the output bias as `i32`.
+#[derive(Clone)]
+pub struct Network {
+    /// Weights of every input feature, one row of `L1` neurons per feature.
+    pub feature_weights: Box<[[i16; L1]]>,
+    pub feature_bias: [i16; L1],
+    /// Output weights of the accumulator of the side to move, then of the opponent.
+    pub output_weights: [[i16; L1]; Color::COUNT],
+    /// Output bias, quantized by `QA * QB`.
+    pub output_bias: i32,
+}
+
+impl Network {
+    /// Creates a network whose weights are all zero.
+    #[must_use]
+    pub fn zeroed() -> Self {
+        Self {
+            feature_weights: vec![[0; L1]; INPUTS].into_boxed_slice(),
+            feature_bias: [0; L1],
+            output_weights: [[0; L1]; Color::COUNT],
+            output_bias: 0,
+        }
+    }
+
+    /// Returns `true` if a network was embedded at build time with `CRUX_EVALFILE`.
+    #[must_use]
+    pub const fn has_embedded() -> bool {
+        !EMBEDDED.is_empty()
+    }
+
+    /// Returns the default network, shared by all users.
+    ///
+    /// This is the network embedded at build time if the `CRUX_EVALFILE` environment
+    /// variable was set to the path of a network file, relative to `crux-lib`, as in
+    /// `CRUX_EVALFILE=/path/to/network.nnue cargo build --release`. Otherwise, it is
+    /// [`Network::material`], see [`Network::has_embedded`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if the embedded network is invalid.
+    #[must_use]
+    pub fn default_network() -> Arc<Network> {
+        static DEFAULT: OnceLock<Arc<Network>> = OnceLock::new();
+
+        let network = DEFAULT.get_or_init(|| {
+            let network = if EMBEDDED.is_empty() {
+                Network::material()
+            } else {
+                Network::read(&mut &EMBEDDED[..]).expect("invalid embedded network")
+            };
+
+            Arc::new(network)
+        });
+
+        Arc::clone(network)
+    }
+
+    /// Creates a network that evaluates the material balance, up to rounding.
+    ///
+    /// Every piece type of each side counts the pieces of that type on the board and in
+    /// hand in a neuron of its own, so the network serves as a baseline until a trained
+    /// one is available.
+    #[must_use]
+    pub fn material() -> Self {
+        // Large enough to separate the counts, while the maximum number of pawns fits in QA.
+        const COUNT_WEIGHT: i16 = 14;
+
+        let mut network = Network::zeroed();
+        let neuron = |relative: usize, piece_type: PieceType| {
+            relative * PieceType::COUNT + piece_type.as_usize()
+        };
+
+        for king_square in Square::ALL {
+            for square in Square::ALL {
+                for piece_type in PieceType::ALL {
+                    if piece_type == PieceType::King {
+                        continue;
+                    }
+
+                    for color in Color::ALL {
+                        let placement = Placement::Board(piece_type.with_color(color), square);
+                        let feature = feature_index(Color::Black, king_square, placement);
+                        let relative = (color != Color::Black) as usize;
+
+                        network.feature_weights[feature][neuron(relative, piece_type)] =
+                            COUNT_WEIGHT;
+                    }
+                }
+            }
+
+            for &piece_type in PieceType::ALL.iter().take(Hand::HAND_PIECE_TYPES) {
+                for index in 0..Hand::max_piece_counts(piece_type) {
+                    for color in Color::ALL {
+                        let placement = Placement::Hand(color, piece_type, index);
+                        let feature = feature_index(Color::Black, king_square, placement);
+                        let relative = (color != Color::Black) as usize;
+
+                        network.feature_weights[feature][neuron(relative, piece_type)] =
+                            COUNT_WEIGHT;
+                    }
+                }
+            }
+        }
+
+        debug_assert!(network.feature_weights.len() == Square::COUNT * FEATURES_PER_KING);
+
+        for piece_type in PieceType::ALL {
+            let weight = piece_value(piece_type) * QA * QB / (i32::from(COUNT_WEIGHT) * SCALE);
+            let weight = weight as i16;
+
+            network.output_weights[0][neuron(0, piece_type)] = weight;
+            network.output_weights[0][neuron(1, piece_type)] = -weight;
+        }
+
+        network
+    }
+
+    /// Loads a network from a file.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, NetworkError> {
+        Self::read(&mut BufReader::new(File::open(path)?))
+    }
+
+    /// Saves the network to a file.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), NetworkError> {
+        let mut writer = BufWriter::new(File::create(path)?);
+
+        self.write(&mut writer)?;
+        writer.flush()?;
+
+        Ok(())
+    }
+
+    /// Reads a network in the file format.
+    pub fn read(reader: &mut impl Read) -> Result<Self, NetworkError> {
+        let mut magic = [0; MAGIC.len()];
+
+        reader.read_exact(&mut magic)?;
+
+        if magic != MAGIC {
+            return Err(NetworkError::InvalidMagic);
+        }
+
+        let version = read_u32(reader)?;
+
+        if version != VERSION {
+            return Err(NetworkError::UnsupportedVersion(version));
+        }
+
+        if read_u32(reader)? as usize != INPUTS || read_u32(reader)? as usize != L1 {
+            return Err(NetworkError::ArchitectureMismatch);
+        }
+
+        let mut network = Network::zeroed();
+
+        read_i16s(reader, &mut network.feature_bias)?;
+
+        for row in &mut network.feature_weights {
+            read_i16s(reader, row)?;
+        }
+
+        for row in &mut network.output_weights {
+            read_i16s(reader, row)?;
+        }
+
+        let mut bias = [0; 4];
+
+        reader.read_exact(&mut bias)?;
+        network.output_bias = i32::from_le_bytes(bias);
+
+        Ok(network)
+    }
+
+    /// Writes the network in the file format.
+    pub fn write(&self, writer: &mut impl Write) -> io::Result<()> {
+        writer.write_all(&MAGIC)?;
+        writer.write_all(&VERSION.to_le_bytes())?;
+        writer.write_all(&(INPUTS as u32).to_le_bytes())?;
+        writer.write_all(&(L1 as u32).to_le_bytes())?;
+
+        write_i16s(writer, &self.feature_bias)?;
+
+        for row in &self.feature_weights {
+            write_i16s(writer, row)?;
+        }
+
+        for row in &self.output_weights {
+            write_i16s(writer, row)?;
+        }
+
+        writer.write_all(&self.output_bias.to_le_bytes())
+    }
+
+    /// Evaluates an accumulator from the perspective of `stm`, in centipawns.
+    #[must_use]
+    pub fn evaluate(&self, accumulator: &Accumulator, stm: Color) -> i32 {
+        let us = output(&accumulator.values[stm], &self.output_weights[0]);
+        let them = output(&accumulator.values[stm.opposite()], &self.output_weights[1]);
+
+        (us + them + self.output_bias) * SCALE / (QA * QB)
+    }
+}
+
+fn output(values: &[i16; L1], weights: &[i16; L1]) -> i32 {
+    values
+        .iter()
+        .zip(weights)
+        .map(|(&value, &weight)| i32::from(value.clamp(0, QA as i16)) * i32::from(weight))
+        .sum()
+}
+
+fn read_u32(reader: &mut impl Read) -> io::Result<u32> {
+    let mut bytes = [0; 4];
+
+    reader.read_exact(&mut bytes)?;
+
+    Ok(u32::from_le_bytes(bytes))
+}
+
+fn read_i16s(reader: &mut impl Read, values: &mut [i16]) -> io::Result<()> {
+    let mut bytes = vec![0; values.len() * 2];
+
+    reader.read_exact(&mut bytes)?;
+
+    for (value, bytes) in values.iter_mut().zip(bytes.chunks_exact(2)) {
+        *value = i16::from_le_bytes([bytes[0], bytes[1]]);
+    }
+
+    Ok(())
+}
+
+fn write_i16s(writer: &mut impl Write, values: &[i16]) -> io::Result<()> {
+    let bytes = values
+        .iter()
+        .flat_map(|value| value.to_le_bytes())
+        .collect::<Vec<_>>();
+
+    writer.write_all(&bytes)
+}
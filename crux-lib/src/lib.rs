@@ -5,6 +5,9 @@
 #![feature(const_ops)]
 #![feature(const_trait_impl)]
 
+pub mod args;
+pub mod eval;
 pub mod notation;
+pub mod search;
 pub mod shogi;
 pub(crate) mod utils;
@@ -0,0 +1,793 @@
+use std::{fmt::Write, time::Duration};
+
+use crate::{
+    notation::{
+        game::{GameEnd, GameRecord, MoveRecord},
+        Notation,
+    },
+    shogi::{
+        core::{Color, File, Piece, PieceType, Rank, Square},
+        position::{hand::Hand, mv::Move, Position, PositionBuilder},
+    },
+};
+
+#[derive(Debug, Copy, Clone)]
+pub enum ParseSquareError {
+    InvalidLength,
+    InvalidFile,
+    InvalidRank,
+}
+
+#[derive(Debug, Copy, Clone)]
+pub enum ParseMoveError {
+    InvalidFormat,
+    InvalidFromSquare(ParseSquareError),
+    InvalidToSquare(ParseSquareError),
+    InvalidPieceType,
+    /// The piece after the move is promoted, which only the position tells apart from a
+    /// move of a promoted piece.
+    PositionRequired,
+    /// The sign of the move is not that of the side to move.
+    InvalidSide,
+    /// The piece after the move cannot be that of the piece on the source square.
+    PieceMismatch,
+    IllegalMove,
+}
+
+#[derive(Debug, Copy, Clone)]
+pub enum ParsePositionError {
+    InvalidFormat,
+    InvalidBoardRank,
+    InvalidBoardPiece,
+    InvalidSquare,
+    InvalidHandPieceType,
+    /// There are more pieces of a type than the game has.
+    TooManyPieces,
+    /// A piece of `PI` is not on its square of the initial position.
+    InvalidHandicap,
+    InvalidSideToMove,
+    /// The position is not structurally valid.
+    InvalidPosition,
+}
+
+#[derive(Debug, Copy, Clone)]
+pub enum ParseCsaErrorKind {
+    InvalidPosition(ParsePositionError),
+    InvalidMove(ParseMoveError),
+    InvalidTime,
+    InvalidSpecialMove,
+    /// A move follows the end of the game.
+    MoveAfterEnd,
+}
+
+/// An error in a CSA record, with the line it was found on, starting from 1.
+#[derive(Debug, Copy, Clone)]
+pub struct ParseCsaError {
+    pub line: usize,
+    pub kind: ParseCsaErrorKind,
+}
+
+/// Piece codes, indexed by `PieceType`.
+pub(crate) const PIECE_TYPE_CODES: [&str; PieceType::COUNT] = [
+    "FU", "KY", "KE", "GI", "KI", "KA", "HI", "TO", "NY", "NK", "NG", "UM", "RY", "OU",
+];
+
+/// Signs of the sides, indexed by `Color`.
+const SIGNS: [char; Color::COUNT] = ['+', '-'];
+
+/// Order of the pieces in hand in `P+` and `P-` lines.
+const HAND_ORDER: [PieceType; Hand::HAND_PIECE_TYPES] = [
+    PieceType::Rook,
+    PieceType::Bishop,
+    PieceType::Gold,
+    PieceType::Silver,
+    PieceType::Knight,
+    PieceType::Lance,
+    PieceType::Pawn,
+];
+
+/// Special moves ending the game, other than illegal actions which name the loser.
+const GAME_ENDS: [(&str, GameEnd); 9] = [
+    ("TORYO", GameEnd::Resign),
+    ("CHUDAN", GameEnd::Abort),
+    ("SENNICHITE", GameEnd::Repetition),
+    ("JISHOGI", GameEnd::Impasse),
+    ("TIME_UP", GameEnd::TimeUp),
+    ("TSUMI", GameEnd::Checkmate),
+    ("KACHI", GameEnd::EnteringKing),
+    ("ILLEGAL_MOVE", GameEnd::IllegalLoss),
+    ("HIKIWAKE", GameEnd::Draw),
+];
+
+/// Version written at the start of records.
+const VERSION: &str = "V2.2";
+
+pub struct Csa;
+
+/// Implements parsing and formatting according to the CSA standard file format, used by
+/// the Computer Shogi Association, floodgate and many game archives.
+///
+/// A move names the piece after it, so whether a move promotes depends on the position:
+/// [`Csa::parse_move_in`] and [`Csa::format_move_in`] handle every move, and the moves of
+/// the [`Notation`] methods are limited to what can be told without the position.
+///
+/// Base on the reference:
+/// http://www2.computer-shogi.org/protocol/record_v22.html
+impl Notation for Csa {
+    type ParseSquareError = ParseSquareError;
+    type ParseMoveError = ParseMoveError;
+    type ParsePositionError = ParsePositionError;
+
+    /// Parses a square from its CSA textual representation.
+    ///
+    /// The expected format is `<file><rank>` in digits, such as:
+    /// - "11"
+    /// - "55"
+    /// - "99"
+    fn parse_square(s: &str) -> Result<Square, Self::ParseSquareError> {
+        let bytes = s.as_bytes();
+
+        if bytes.len() != 2 {
+            return Err(ParseSquareError::InvalidLength);
+        }
+
+        let file = bytes[0]
+            .checked_sub(b'1')
+            .filter(|&file| file < 9)
+            .ok_or(ParseSquareError::InvalidFile)?;
+        let rank = bytes[1]
+            .checked_sub(b'1')
+            .filter(|&rank| rank < 9)
+            .ok_or(ParseSquareError::InvalidRank)?;
+
+        Ok(Square::new(File::from(file), Rank::from(rank)))
+    }
+
+    /// Parses a move from its CSA textual representation, without the position.
+    ///
+    /// The expected formats are:
+    /// - Normal move: "+7776FU"
+    /// - Drop       : "-0055KA"
+    ///
+    /// The sign is optional. Since a promoted piece after a normal move may also have moved
+    /// promoted, such moves fail with [`ParseMoveError::PositionRequired`]; use
+    /// [`Csa::parse_move_in`] for them.
+    fn parse_move(s: &str) -> Result<Move, Self::ParseMoveError> {
+        let (from, to, piece_type) = split_move(s)?;
+
+        match from {
+            None => drop_move(piece_type, to),
+            Some(_) if piece_type.is_promoted() => Err(ParseMoveError::PositionRequired),
+            Some(from) => Ok(Move::normal(from, to)),
+        }
+    }
+
+    /// Parses a position from the lines of a CSA record that describe it.
+    ///
+    /// The lines are either `PI` with the pieces removed from the initial position, or the
+    /// ranks `P1` to `P9`, followed by any `P+` and `P-` lines placing pieces on the board or
+    /// in hand (`00`), where `00AL` puts all remaining pieces in hand, and by the side to
+    /// move, `+` or `-`.
+    fn parse_position(s: &str) -> Result<Position, Self::ParsePositionError> {
+        let mut parser = PositionParser::default();
+
+        for line in s.lines().map(str::trim_end) {
+            if line.is_empty() || line.starts_with('\'') {
+                continue;
+            }
+
+            if !parser.parse_line(line)? {
+                return Err(ParsePositionError::InvalidFormat);
+            }
+        }
+
+        parser.finish()?.ok_or(ParsePositionError::InvalidFormat)
+    }
+
+    /// Formats a square into its CSA textual representation.
+    fn format_square(square: Square) -> String {
+        format!("{}{}", square.file().as_u8() + 1, square.rank().as_u8() + 1)
+    }
+
+    /// Formats a move into its CSA textual representation, without the position.
+    ///
+    /// Drops are formatted as `0055KA`. The piece after a normal move is not known without
+    /// the position, so only its squares are formatted, such as `7776`; use
+    /// [`Csa::format_move_in`] for complete moves.
+    fn format_move(mv: Move) -> String {
+        if mv.is_drop() {
+            format!(
+                "00{}{}",
+                Self::format_square(mv.to()),
+                PIECE_TYPE_CODES[mv.drop_piece_type()]
+            )
+        } else {
+            format!(
+                "{}{}",
+                Self::format_square(mv.from()),
+                Self::format_square(mv.to())
+            )
+        }
+    }
+
+    /// Formats a position into the lines `P1` to `P9`, the pieces in hand and the side to
+    /// move, each ending with a newline.
+    fn format_position(position: &Position) -> String {
+        let mut result = String::with_capacity(512);
+
+        for rank in Rank::ALL {
+            write!(result, "P{}", rank.as_u8() + 1).unwrap();
+
+            for &file in File::ALL.iter().rev() {
+                match position.piece_at(Square::new(file, rank)) {
+                    Some(piece) => write!(
+                        result,
+                        "{}{}",
+                        SIGNS[piece.color()],
+                        PIECE_TYPE_CODES[piece.piece_type()]
+                    )
+                    .unwrap(),
+                    None => result.push_str(" * "),
+                }
+            }
+
+            result.push('\n');
+        }
+
+        for color in Color::ALL {
+            let hand = position.hand(color);
+
+            if hand.is_empty() {
+                continue;
+            }
+
+            write!(result, "P{}", SIGNS[color]).unwrap();
+
+            for piece_type in HAND_ORDER {
+                for _ in 0..hand.count(piece_type) {
+                    write!(result, "00{}", PIECE_TYPE_CODES[piece_type]).unwrap();
+                }
+            }
+
+            result.push('\n');
+        }
+
+        writeln!(result, "{}", SIGNS[position.side_to_move()]).unwrap();
+
+        result
+    }
+}
+
+impl Csa {
+    /// Parses a move such as `+7776FU`, `-8822UM` or `+0055KA` played in `pos`.
+    ///
+    /// The sign is optional, but must be that of the side to move if present.
+    pub fn parse_move_in(pos: &Position, s: &str) -> Result<Move, ParseMoveError> {
+        let stm = pos.side_to_move();
+
+        if s.starts_with(['+', '-']) && !s.starts_with(SIGNS[stm]) {
+            return Err(ParseMoveError::InvalidSide);
+        }
+
+        let (from, to, piece_type) = split_move(s)?;
+
+        let mv = match from {
+            None => drop_move(piece_type, to)?,
+            Some(from) => {
+                let moving = pos
+                    .piece_at(from)
+                    .filter(|piece| piece.color() == stm)
+                    .ok_or(ParseMoveError::PieceMismatch)?
+                    .piece_type();
+
+                if moving == piece_type {
+                    Move::normal(from, to)
+                } else if moving.can_promote() && moving.promoted() == piece_type {
+                    Move::promote(from, to)
+                } else {
+                    return Err(ParseMoveError::PieceMismatch);
+                }
+            }
+        };
+
+        if pos.is_legal_move(mv) {
+            Ok(mv)
+        } else {
+            Err(ParseMoveError::IllegalMove)
+        }
+    }
+
+    /// Formats a move played in `pos` with the sign of the side to move, such as `+7776FU`.
+    #[must_use]
+    pub fn format_move_in(pos: &Position, mv: Move) -> String {
+        let sign = SIGNS[pos.side_to_move()];
+
+        if mv.is_drop() {
+            return format!("{sign}{}", Self::format_move(mv));
+        }
+
+        let piece_type = pos
+            .piece_at(mv.from())
+            .map_or(PieceType::King, Piece::piece_type);
+        let piece_type = if mv.is_promotion() {
+            piece_type.promoted()
+        } else {
+            piece_type
+        };
+
+        format!(
+            "{sign}{}{}",
+            Self::format_move(mv),
+            PIECE_TYPE_CODES[piece_type]
+        )
+    }
+
+    /// Parses a game record, validating every move against the position it is played in.
+    ///
+    /// Statements may be separated by `,` as well as by lines. Comments starting with `'*`
+    /// are kept, on the last move or on the starting position, and other comments are
+    /// ignored. The version, the players (`N+`, `N-`) and the `$EVENT` and `$START_TIME`
+    /// information are read, the position defaults to the initial position, and the time
+    /// of a move is read from the `T` line that follows it.
+    pub fn parse(s: &str) -> Result<GameRecord, ParseCsaError> {
+        let mut record = GameRecord::new(Position::startpos());
+        let mut parser = PositionParser::default();
+        let mut pos = None;
+        let mut line_number = 0;
+
+        for line in s.lines() {
+            line_number += 1;
+
+            let error = |kind| ParseCsaError {
+                line: line_number,
+                kind,
+            };
+            let line = line.trim_start_matches('\u{feff}').trim_end();
+
+            if let Some(comment) = line.strip_prefix('\'') {
+                if let Some(comment) = comment.strip_prefix('*') {
+                    record.push_comment(comment);
+                }
+
+                continue;
+            }
+
+            for statement in line.split(',').map(str::trim) {
+                if statement.is_empty() || statement.starts_with('V') {
+                    continue;
+                }
+
+                if let Some(name) = statement.strip_prefix("N+") {
+                    record.black = Some(name.to_string());
+                } else if let Some(name) = statement.strip_prefix("N-") {
+                    record.white = Some(name.to_string());
+                } else if let Some(info) = statement.strip_prefix('$') {
+                    match info.split_once(':') {
+                        Some(("EVENT", value)) => record.event = Some(value.to_string()),
+                        Some(("START_TIME", value)) => record.start_time = Some(value.to_string()),
+                        _ => {}
+                    }
+                } else if pos.is_none()
+                    && parser
+                        .parse_line(statement)
+                        .map_err(|err| error(ParseCsaErrorKind::InvalidPosition(err)))?
+                {
+                    if statement.len() == 1 {
+                        let start = parser
+                            .finish()
+                            .map_err(|err| error(ParseCsaErrorKind::InvalidPosition(err)))?
+                            .unwrap_or_else(Position::startpos);
+
+                        record.start = start.clone();
+                        pos = Some(start);
+                        parser = PositionParser::default();
+                    }
+                } else {
+                    // A position must be ended by its side to move before the moves.
+                    if pos.is_none() && parser.builder.is_some() {
+                        return Err(error(ParseCsaErrorKind::InvalidPosition(
+                            ParsePositionError::InvalidSideToMove,
+                        )));
+                    }
+
+                    let pos = pos.get_or_insert_with(|| record.start.clone());
+
+                    parse_statement(statement, pos, &mut record).map_err(error)?;
+                }
+            }
+        }
+
+        if pos.is_none() {
+            record.start = parser
+                .finish()
+                .map_err(|kind| ParseCsaError {
+                    line: line_number,
+                    kind: ParseCsaErrorKind::InvalidPosition(kind),
+                })?
+                .unwrap_or_else(Position::startpos);
+        }
+
+        Ok(record)
+    }
+
+    /// Formats a game record in CSA version 2.2, with the times of the moves in seconds.
+    #[must_use]
+    pub fn format(record: &GameRecord) -> String {
+        let mut result = String::new();
+
+        writeln!(result, "{VERSION}").unwrap();
+
+        let infos = [
+            ("N+", &record.black),
+            ("N-", &record.white),
+            ("$EVENT:", &record.event),
+            ("$START_TIME:", &record.start_time),
+        ];
+
+        for (key, value) in infos {
+            if let Some(value) = value {
+                writeln!(result, "{key}{value}").unwrap();
+            }
+        }
+
+        result.push_str(&Self::format_position(&record.start));
+        format_comments(&mut result, &record.comments);
+
+        let mut pos = record.start.clone();
+
+        for move_record in &record.moves {
+            writeln!(result, "{}", Self::format_move_in(&pos, move_record.mv)).unwrap();
+
+            if let Some(time) = move_record.time {
+                if time.subsec_millis() == 0 {
+                    writeln!(result, "T{}", time.as_secs()).unwrap();
+                } else {
+                    writeln!(result, "T{}.{:03}", time.as_secs(), time.subsec_millis()).unwrap();
+                }
+            }
+
+            format_comments(&mut result, &move_record.comments);
+            pos.make_move(move_record.mv);
+        }
+
+        if let Some(end) = record.end {
+            writeln!(result, "%{}", format_special(end, pos.side_to_move())).unwrap();
+        }
+
+        result
+    }
+}
+
+/// The lines of a position, read one at a time.
+#[derive(Default)]
+struct PositionParser {
+    builder: Option<PositionBuilder>,
+    /// Number of pieces of each hand piece type on the board or in hand, unpromoted.
+    counts: [u32; Hand::HAND_PIECE_TYPES],
+    /// Ranks read from `P1` to `P9` lines.
+    ranks: [bool; Rank::COUNT],
+    side_to_move: Option<Color>,
+}
+
+impl PositionParser {
+    /// Parses a line of a position, returning `false` if it is not one.
+    fn parse_line(&mut self, line: &str) -> Result<bool, ParsePositionError> {
+        match line {
+            "+" | "-" => {
+                if self.side_to_move.is_some() {
+                    return Err(ParsePositionError::InvalidSideToMove);
+                }
+
+                self.side_to_move = Some(if line == "+" {
+                    Color::Black
+                } else {
+                    Color::White
+                });
+
+                return Ok(true);
+            }
+            _ if !line.starts_with('P') || self.side_to_move.is_some() => return Ok(false),
+            _ => {}
+        }
+
+        let bytes = line.as_bytes();
+
+        match bytes.get(1) {
+            Some(b'I') => self.parse_handicap(&line[2..])?,
+            Some(b'1'..=b'9') => self.parse_rank(Rank::from(bytes[1] - b'1'), &line[2..])?,
+            Some(b'+') => self.parse_pieces(Color::Black, &line[2..])?,
+            Some(b'-') => self.parse_pieces(Color::White, &line[2..])?,
+            _ => return Err(ParsePositionError::InvalidFormat),
+        }
+
+        Ok(true)
+    }
+
+    /// Returns the position, or `None` if no line of a position was read.
+    fn finish(self) -> Result<Option<Position>, ParsePositionError> {
+        let Some(mut builder) = self.builder else {
+            return match self.side_to_move {
+                Some(_) => Err(ParsePositionError::InvalidFormat),
+                None => Ok(None),
+            };
+        };
+
+        let side_to_move = self
+            .side_to_move
+            .ok_or(ParsePositionError::InvalidSideToMove)?;
+
+        if !builder.verify() {
+            return Err(ParsePositionError::InvalidPosition);
+        }
+
+        builder.set_side_to_move(side_to_move);
+
+        Ok(Some(builder.build()))
+    }
+
+    fn builder(&mut self) -> &mut PositionBuilder {
+        self.builder
+            .get_or_insert_with(|| Position::empty().builder())
+    }
+
+    fn place(&mut self, square: Square, piece: Piece) -> Result<(), ParsePositionError> {
+        if self.builder().piece_at(square).is_some() {
+            return Err(ParsePositionError::InvalidSquare);
+        }
+
+        let piece_type = piece.piece_type();
+
+        if piece_type != PieceType::King {
+            self.counts[piece_type.unpromoted().as_usize()] += 1;
+        }
+
+        self.builder().place(square, piece);
+
+        Ok(())
+    }
+
+    /// Parses the pieces removed from the initial position after `PI`, such as `82HI22KA`.
+    fn parse_handicap(&mut self, s: &str) -> Result<(), ParsePositionError> {
+        if self.builder.is_some() || !s.len().is_multiple_of(4) {
+            return Err(ParsePositionError::InvalidFormat);
+        }
+
+        let startpos = Position::startpos();
+
+        for square in Square::ALL {
+            if let Some(piece) = startpos.piece_at(square) {
+                self.place(square, piece)?;
+            }
+        }
+
+        for i in (0..s.len()).step_by(4) {
+            let square = s
+                .get(i..i + 2)
+                .and_then(|s| Csa::parse_square(s).ok())
+                .ok_or(ParsePositionError::InvalidSquare)?;
+            let piece_type = s
+                .get(i + 2..i + 4)
+                .and_then(parse_piece_type)
+                .ok_or(ParsePositionError::InvalidBoardPiece)?;
+
+            // A square already removed is empty, so it cannot be removed twice.
+            match self.builder().piece_at(square) {
+                Some(piece) if piece.piece_type() == piece_type => {}
+                _ => return Err(ParsePositionError::InvalidHandicap),
+            }
+
+            if piece_type != PieceType::King {
+                self.counts[piece_type.as_usize()] -= 1;
+            }
+
+            self.builder().remove(square);
+        }
+
+        Ok(())
+    }
+
+    /// Parses a rank such as `-KY-KE * -KI-OU-KI * -KE-KY`, from the 9th file to the 1st.
+    fn parse_rank(&mut self, rank: Rank, s: &str) -> Result<(), ParsePositionError> {
+        if std::mem::replace(&mut self.ranks[rank.as_usize()], true) {
+            return Err(ParsePositionError::InvalidBoardRank);
+        }
+
+        // Trailing empty squares may have been trimmed.
+        let cells = format!("{s:<27}");
+
+        if cells.len() != 27 || !cells.is_ascii() {
+            return Err(ParsePositionError::InvalidBoardRank);
+        }
+
+        for (i, &file) in File::ALL.iter().rev().enumerate() {
+            let cell = &cells[i * 3..i * 3 + 3];
+
+            if matches!(cell.trim(), "" | "*") {
+                continue;
+            }
+
+            let color = match cell.as_bytes()[0] {
+                b'+' => Color::Black,
+                b'-' => Color::White,
+                _ => return Err(ParsePositionError::InvalidBoardPiece),
+            };
+            let piece_type =
+                parse_piece_type(&cell[1..]).ok_or(ParsePositionError::InvalidBoardPiece)?;
+
+            self.place(Square::new(file, rank), piece_type.with_color(color))?;
+        }
+
+        Ok(())
+    }
+
+    /// Parses pieces placed by a `P+` or `P-` line, such as `00HI00FU` or `59OU`.
+    fn parse_pieces(&mut self, color: Color, s: &str) -> Result<(), ParsePositionError> {
+        if !s.len().is_multiple_of(4) || !s.is_ascii() {
+            return Err(ParsePositionError::InvalidFormat);
+        }
+
+        for i in (0..s.len()).step_by(4) {
+            let (square, code) = (&s[i..i + 2], &s[i + 2..i + 4]);
+
+            if square != "00" {
+                let square =
+                    Csa::parse_square(square).map_err(|_| ParsePositionError::InvalidSquare)?;
+                let piece_type =
+                    parse_piece_type(code).ok_or(ParsePositionError::InvalidBoardPiece)?;
+
+                self.place(square, piece_type.with_color(color))?;
+                continue;
+            }
+
+            if code == "AL" {
+                for piece_type in HAND_ORDER {
+                    let remaining = Hand::max_piece_counts(piece_type)
+                        .saturating_sub(self.counts[piece_type.as_usize()]);
+
+                    for _ in 0..remaining {
+                        self.add_to_hand(color, piece_type)?;
+                    }
+                }
+
+                continue;
+            }
+
+            let piece_type = parse_piece_type(code)
+                .filter(|piece_type| piece_type.as_usize() < Hand::HAND_PIECE_TYPES)
+                .ok_or(ParsePositionError::InvalidHandPieceType)?;
+
+            self.add_to_hand(color, piece_type)?;
+        }
+
+        Ok(())
+    }
+
+    fn add_to_hand(
+        &mut self,
+        color: Color,
+        piece_type: PieceType,
+    ) -> Result<(), ParsePositionError> {
+        let count = &mut self.counts[piece_type.as_usize()];
+
+        if *count >= Hand::max_piece_counts(piece_type) {
+            return Err(ParsePositionError::TooManyPieces);
+        }
+
+        *count += 1;
+        self.builder().increment_hand_piece_count(color, piece_type);
+
+        Ok(())
+    }
+}
+
+/// Parses a move, a time or a special move of a record.
+fn parse_statement(
+    statement: &str,
+    pos: &mut Position,
+    record: &mut GameRecord,
+) -> Result<(), ParseCsaErrorKind> {
+    if let Some(time) = statement.strip_prefix('T') {
+        let time = time
+            .parse::<f64>()
+            .ok()
+            .filter(|time| time.is_finite() && *time >= 0.0)
+            .ok_or(ParseCsaErrorKind::InvalidTime)?;
+        let last = record
+            .moves
+            .last_mut()
+            .ok_or(ParseCsaErrorKind::InvalidTime)?;
+
+        last.time = Some(Duration::from_millis((time * 1000.0).round() as u64));
+        return Ok(());
+    }
+
+    if record.end.is_some() {
+        return Err(ParseCsaErrorKind::MoveAfterEnd);
+    }
+
+    if let Some(special) = statement.strip_prefix('%') {
+        let end = parse_special(special, pos.side_to_move())
+            .ok_or(ParseCsaErrorKind::InvalidSpecialMove)?;
+
+        record.end = Some(end);
+        return Ok(());
+    }
+
+    let mv = Csa::parse_move_in(pos, statement).map_err(ParseCsaErrorKind::InvalidMove)?;
+
+    pos.make_move(mv);
+    record.moves.push(MoveRecord::new(mv));
+
+    Ok(())
+}
+
+/// Splits a move such as `+7776FU` into its source square, `None` for drops, its
+/// destination and the piece type after the move.
+fn split_move(s: &str) -> Result<(Option<Square>, Square, PieceType), ParseMoveError> {
+    let s = s.strip_prefix(['+', '-']).unwrap_or(s);
+
+    if s.len() != 6 || !s.is_ascii() {
+        return Err(ParseMoveError::InvalidFormat);
+    }
+
+    let from = match &s[..2] {
+        "00" => None,
+        from => Some(Csa::parse_square(from).map_err(ParseMoveError::InvalidFromSquare)?),
+    };
+    let to = Csa::parse_square(&s[2..4]).map_err(ParseMoveError::InvalidToSquare)?;
+    let piece_type = parse_piece_type(&s[4..]).ok_or(ParseMoveError::InvalidPieceType)?;
+
+    if from == Some(to) {
+        return Err(ParseMoveError::InvalidFormat);
+    }
+
+    Ok((from, to, piece_type))
+}
+
+fn drop_move(piece_type: PieceType, to: Square) -> Result<Move, ParseMoveError> {
+    if piece_type.as_usize() < Hand::HAND_PIECE_TYPES {
+        Ok(Move::drop(piece_type, to))
+    } else {
+        Err(ParseMoveError::InvalidPieceType)
+    }
+}
+
+/// Parses a special move without its `%`, such as `TORYO`, played when `stm` is to move.
+pub(crate) fn parse_special(special: &str, stm: Color) -> Option<GameEnd> {
+    match special {
+        // The loser of an illegal action is named by its sign.
+        "+ILLEGAL_ACTION" | "-ILLEGAL_ACTION" if special.starts_with(SIGNS[stm]) => {
+            Some(GameEnd::IllegalLoss)
+        }
+        "+ILLEGAL_ACTION" | "-ILLEGAL_ACTION" => Some(GameEnd::IllegalWin),
+        _ => GAME_ENDS
+            .iter()
+            .find(|&&(name, _)| name == special)
+            .map(|&(_, end)| end),
+    }
+}
+
+/// Formats a game end as a special move without its `%`, played when `stm` is to move.
+pub(crate) fn format_special(end: GameEnd, stm: Color) -> String {
+    match end {
+        GameEnd::IllegalWin => format!("{}ILLEGAL_ACTION", SIGNS[stm.opposite()]),
+        _ => {
+            let (name, _) = GAME_ENDS.iter().find(|&&(_, e)| e == end).unwrap();
+
+            name.to_string()
+        }
+    }
+}
+
+pub(crate) fn parse_piece_type(code: &str) -> Option<PieceType> {
+    PIECE_TYPE_CODES
+        .iter()
+        .position(|&c| c == code)
+        .map(PieceType::from)
+}
+
+fn format_comments(result: &mut String, comments: &[String]) {
+    for comment in comments {
+        writeln!(result, "'*{comment}").unwrap();
+    }
+}
@@ -0,0 +1,125 @@
+use std::time::Duration;
+
+use crate::shogi::position::{mv::Move, Position};
+
+/// How a game ended after the last move of its record.
+///
+/// Results that name a winner are from the point of view of the side to move after the last
+/// move.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum GameEnd {
+    /// The side to move resigned.
+    Resign,
+    /// The game was interrupted.
+    Abort,
+    /// The game was drawn by fourfold repetition.
+    Repetition,
+    /// The game was drawn by impasse.
+    Impasse,
+    /// The side to move lost on time.
+    TimeUp,
+    /// The side to move is checkmated.
+    Checkmate,
+    /// The side to move declared a win by entering king.
+    EnteringKing,
+    /// The side to move won because the last move was illegal.
+    IllegalWin,
+    /// The side to move lost by an illegal action.
+    IllegalLoss,
+    /// The game was drawn by agreement.
+    Draw,
+}
+
+/// A move of a game record.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MoveRecord {
+    pub mv: Move,
+    /// Time spent on the move, if recorded.
+    pub time: Option<Duration>,
+    /// Comments on the position after the move, one per line.
+    pub comments: Vec<String>,
+    /// Variations played instead of the move, kept only by formats with branches.
+    pub forks: Vec<Variation>,
+}
+
+impl MoveRecord {
+    /// Creates a record of `mv` without time or comments.
+    #[must_use]
+    pub const fn new(mv: Move) -> Self {
+        Self {
+            mv,
+            time: None,
+            comments: Vec::new(),
+            forks: Vec::new(),
+        }
+    }
+}
+
+/// A branch of a game record: the moves played from the position of the move it replaces,
+/// which may branch again, and how it ended.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Variation {
+    pub moves: Vec<MoveRecord>,
+    pub end: Option<GameEnd>,
+}
+
+/// A game as stored by game record formats: its information, the position it starts from and
+/// the moves played, which are legal in turn.
+#[derive(Debug, Clone)]
+pub struct GameRecord {
+    /// Name of the event.
+    pub event: Option<String>,
+    /// Name of the black player.
+    pub black: Option<String>,
+    /// Name of the white player.
+    pub white: Option<String>,
+    /// Date and time the game started, as written in the record.
+    pub start_time: Option<String>,
+    pub start: Position,
+    /// Comments on the starting position, one per line.
+    pub comments: Vec<String>,
+    pub moves: Vec<MoveRecord>,
+    pub end: Option<GameEnd>,
+}
+
+impl GameRecord {
+    /// Creates an empty record of a game starting from `start`.
+    #[must_use]
+    pub const fn new(start: Position) -> Self {
+        Self {
+            event: None,
+            black: None,
+            white: None,
+            start_time: None,
+            start,
+            comments: Vec::new(),
+            moves: Vec::new(),
+            end: None,
+        }
+    }
+
+    /// Adds a comment to the last move, or to the starting position if there are no moves.
+    pub fn push_comment(&mut self, comment: &str) {
+        let comments = match self.moves.last_mut() {
+            Some(last) => &mut last.comments,
+            None => &mut self.comments,
+        };
+
+        comments.push(comment.to_string());
+    }
+
+    /// Returns the position after the first `count` moves.
+    ///
+    /// # Panics
+    /// Panics if `count` exceeds the number of moves.
+    #[must_use]
+    pub fn position_after(&self, count: usize) -> Position {
+        let mut pos = self.start.clone();
+
+        for record in &self.moves[..count] {
+            pos.make_move(record.mv);
+        }
+
+        pos
+    }
+}
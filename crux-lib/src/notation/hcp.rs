@@ -0,0 +1,397 @@
+use const_for::const_for;
+
+use crate::{
+    notation::record::{has_standard_pieces, PackError, Record, UnpackError},
+    shogi::{
+        core::{Color, Piece, PieceType, Square},
+        position::{hand::Hand, mv::Move, Position},
+    },
+    utils::bits::{BitReader, BitWriter},
+};
+
+/// Huffman codes of the unpromoted piece types, indexed by `PieceType`, as `(code, bits)`
+/// written least significant bit first.
+const PIECE_TYPE_CODES: [(u32, u32); Hand::HAND_PIECE_TYPES] = [
+    (0b1, 2),      // Pawn
+    (0b11, 4),     // Lance
+    (0b111, 4),    // Knight
+    (0b1011, 4),   // Silver
+    (0b1111, 5),   // Gold
+    (0b11111, 6),  // Bishop
+    (0b111111, 6), // Rook
+];
+
+/// Codes of the pieces on the board, indexed by `Piece`: the code of the piece type
+/// followed by a color bit and, unless it is a gold, a promotion bit. Kings are not coded.
+///
+/// An empty square is a single zero.
+const BOARD_CODES: [(u32, u32); Piece::COUNT] = {
+    let mut codes = [(0, 0); Piece::COUNT];
+
+    const_for!(i in 0..Piece::COUNT => {
+        let piece = Piece::from(i);
+        let piece_type = piece.piece_type();
+
+        if piece_type != PieceType::King {
+            let (code, bits) = PIECE_TYPE_CODES[piece_type.unpromoted().as_usize()];
+            let code = code | (piece.color().as_u8() as u32) << bits;
+
+            codes[i] = if piece_type == PieceType::Gold {
+                (code, bits + 1)
+            } else {
+                (code | (piece_type.is_promoted() as u32) << (bits + 1), bits + 2)
+            };
+        }
+    });
+
+    codes
+};
+
+/// Codes of the pieces in hand, indexed by `Color` and `PieceType`: the code of the piece
+/// type without its leading bit, a zero promotion bit unless it is a gold, and a color bit.
+const HAND_CODES: [[(u32, u32); Hand::HAND_PIECE_TYPES]; Color::COUNT] = {
+    let mut codes = [[(0, 0); Hand::HAND_PIECE_TYPES]; Color::COUNT];
+
+    const_for!(color in 0..Color::COUNT => {
+        const_for!(pt in 0..Hand::HAND_PIECE_TYPES => {
+            let (code, bits) = PIECE_TYPE_CODES[pt];
+            let bits = if pt == PieceType::Gold.as_usize() { bits - 1 } else { bits };
+
+            codes[color][pt] = ((code >> 1) | (color as u32) << bits, bits + 1);
+        });
+    });
+
+    codes
+};
+
+/// Piece types in the numbering of Apery used by drops, from 1 for a pawn to 7 for a gold.
+const DROP_PIECE_TYPES: [PieceType; Hand::HAND_PIECE_TYPES] = [
+    PieceType::Pawn,
+    PieceType::Lance,
+    PieceType::Knight,
+    PieceType::Silver,
+    PieceType::Bishop,
+    PieceType::Rook,
+    PieceType::Gold,
+];
+
+/// A position compressed into 256 bits with the Huffman coding of Apery, as used by
+/// dlshogi and cshogi.
+///
+/// The bits, least significant first, hold the side to move, the squares of the black and
+/// the white king in 7 bits each, then every other square from 1a to 9i, and finally the
+/// pieces in hand of Black and then White.
+///
+/// The coding differs from [`PackedSfen`](crate::notation::packed_sfen::PackedSfen) in the
+/// codes of knights and silvers and in writing the color before the promotion bit on the
+/// board. It likewise requires the standard set of pieces, and the ply is not stored.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct HuffmanCodedPos(pub [u8; HuffmanCodedPos::SIZE]);
+
+impl HuffmanCodedPos {
+    /// Size of a coded position in bytes.
+    pub const SIZE: usize = 32;
+
+    /// Codes a position.
+    pub fn pack(pos: &Position) -> Result<Self, PackError> {
+        let (Some(black_king), Some(white_king)) =
+            (pos.king_square(Color::Black), pos.king_square(Color::White))
+        else {
+            return Err(PackError::MissingKing);
+        };
+
+        if !has_standard_pieces(pos) {
+            return Err(PackError::UnsupportedPieceSet);
+        }
+
+        let mut data = [0; Self::SIZE];
+        let mut writer = BitWriter::new(&mut data);
+
+        writer.write_bit(pos.side_to_move() == Color::White);
+        writer.write_bits(black_king.as_u8() as u32, 7);
+        writer.write_bits(white_king.as_u8() as u32, 7);
+
+        for square in Square::ALL {
+            match pos.piece_at(square) {
+                Some(piece) if piece.piece_type() == PieceType::King => {}
+                Some(piece) => {
+                    let (code, bits) = BOARD_CODES[piece];
+
+                    writer.write_bits(code, bits);
+                }
+                None => writer.write_bit(false),
+            }
+        }
+
+        for color in Color::ALL {
+            let hand = pos.hand(color);
+
+            for &piece_type in PieceType::ALL.iter().take(Hand::HAND_PIECE_TYPES) {
+                let (code, bits) = HAND_CODES[color][piece_type.as_usize()];
+
+                for _ in 0..hand.count(piece_type) {
+                    writer.write_bits(code, bits);
+                }
+            }
+        }
+
+        debug_assert_eq!(writer.cursor(), Self::SIZE * 8);
+
+        Ok(Self(data))
+    }
+
+    /// Returns the side to move, without decoding the position.
+    #[must_use]
+    pub const fn side_to_move(&self) -> Color {
+        if self.0[0] & 1 == 0 {
+            Color::Black
+        } else {
+            Color::White
+        }
+    }
+
+    /// Decodes the position, at the first ply.
+    pub fn unpack(&self) -> Result<Position, UnpackError> {
+        let mut builder = Position::empty().builder();
+        let mut reader = BitReader::new(&self.0);
+
+        builder.set_side_to_move(if read_bit(&mut reader)? {
+            Color::White
+        } else {
+            Color::Black
+        });
+
+        let mut king_squares = [Square::S11; Color::COUNT];
+
+        for color in Color::ALL {
+            let square = reader.read_bits(7).ok_or(UnpackError::UnexpectedEnd)?;
+
+            if square as usize >= Square::COUNT {
+                return Err(UnpackError::InvalidKingSquare);
+            }
+
+            king_squares[color] = Square::from(square as u8);
+        }
+
+        if king_squares[Color::Black] == king_squares[Color::White] {
+            return Err(UnpackError::InvalidKingSquare);
+        }
+
+        for square in Square::ALL {
+            if let Some(color) = Color::ALL.into_iter().find(|&c| king_squares[c] == square) {
+                builder.place(square, PieceType::King.with_color(color));
+            } else if let Some(piece) = read_board_piece(&mut reader)? {
+                builder.place(square, piece);
+            }
+        }
+
+        let mut hands = [Hand::default(); Color::COUNT];
+
+        while !reader.is_empty() {
+            let (color, piece_type) = read_hand_piece(&mut reader)?;
+
+            if hands[color].count(piece_type) == Hand::max_piece_counts(piece_type) {
+                return Err(UnpackError::InvalidPosition);
+            }
+
+            hands[color].increment(piece_type);
+            builder.increment_hand_piece_count(color, piece_type);
+        }
+
+        if !builder.verify() {
+            return Err(UnpackError::InvalidPosition);
+        }
+
+        Ok(builder.build())
+    }
+}
+
+impl Record for HuffmanCodedPos {
+    const SIZE: usize = HuffmanCodedPos::SIZE;
+
+    fn decode(bytes: &[u8]) -> Result<Self, UnpackError> {
+        Ok(Self(bytes.try_into().unwrap()))
+    }
+
+    fn encode(&self, bytes: &mut [u8]) {
+        bytes.copy_from_slice(&self.0);
+    }
+}
+
+/// The result of a game, as stored in HCPE records.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum GameResult {
+    Draw,
+    BlackWin,
+    WhiteWin,
+}
+
+impl GameResult {
+    /// Returns the winner, or `None` for a draw.
+    #[must_use]
+    pub const fn winner(self) -> Option<Color> {
+        match self {
+            GameResult::Draw => None,
+            GameResult::BlackWin => Some(Color::Black),
+            GameResult::WhiteWin => Some(Color::White),
+        }
+    }
+}
+
+/// A training record of dlshogi: a coded position with its evaluation, best move and game
+/// result, in 38 bytes.
+///
+/// The evaluation is from the perspective of the side to move.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct HuffmanCodedPosAndEval {
+    pub hcp: HuffmanCodedPos,
+    pub eval: i16,
+    /// The best move, or `None` if the record has no move.
+    pub best_move: Option<Move>,
+    pub game_result: GameResult,
+}
+
+impl HuffmanCodedPosAndEval {
+    /// Size of a record in bytes.
+    pub const SIZE: usize = 38;
+
+    /// Decodes a record from its little-endian byte layout.
+    pub fn from_bytes(bytes: &[u8; Self::SIZE]) -> Result<Self, UnpackError> {
+        let field = |offset: usize| [bytes[offset], bytes[offset + 1]];
+
+        Ok(Self {
+            hcp: HuffmanCodedPos(bytes[..HuffmanCodedPos::SIZE].try_into().unwrap()),
+            eval: i16::from_le_bytes(field(32)),
+            best_move: from_move16(u16::from_le_bytes(field(34)))?,
+            game_result: match bytes[36] {
+                0 => GameResult::Draw,
+                1 => GameResult::BlackWin,
+                2 => GameResult::WhiteWin,
+                _ => return Err(UnpackError::InvalidGameResult),
+            },
+        })
+    }
+
+    /// Encodes the record in its little-endian byte layout.
+    #[must_use]
+    pub fn to_bytes(&self) -> [u8; Self::SIZE] {
+        let mut bytes = [0; Self::SIZE];
+
+        bytes[..HuffmanCodedPos::SIZE].copy_from_slice(&self.hcp.0);
+        bytes[32..34].copy_from_slice(&self.eval.to_le_bytes());
+        bytes[34..36].copy_from_slice(&self.best_move.map_or(0, to_move16).to_le_bytes());
+        bytes[36] = self.game_result as u8;
+
+        bytes
+    }
+}
+
+impl Record for HuffmanCodedPosAndEval {
+    const SIZE: usize = HuffmanCodedPosAndEval::SIZE;
+
+    fn decode(bytes: &[u8]) -> Result<Self, UnpackError> {
+        Self::from_bytes(bytes.try_into().unwrap())
+    }
+
+    fn encode(&self, bytes: &mut [u8]) {
+        bytes.copy_from_slice(&self.to_bytes());
+    }
+}
+
+/// Converts a move to the 16-bit move of Apery.
+///
+/// Normal moves store the destination, the source and a promotion flag in bit 14, while
+/// drops store the dropped piece type in place of the source, from 81 for a pawn to 87
+/// for a gold.
+#[must_use]
+pub fn to_move16(mv: Move) -> u16 {
+    let to = mv.to().as_u8() as u16;
+
+    if mv.is_drop() {
+        let piece_type = DROP_PIECE_TYPES
+            .iter()
+            .position(|&pt| pt == mv.drop_piece_type())
+            .unwrap() as u16;
+
+        ((Square::COUNT as u16 + piece_type) << 7) | to
+    } else {
+        (mv.is_promotion() as u16) << 14 | (mv.from().as_u8() as u16) << 7 | to
+    }
+}
+
+/// Converts a 16-bit move of Apery to a move.
+///
+/// Special moves, such as the null move or resignation, have the same source and
+/// destination square and carry no move, so they return `None` like an empty move.
+pub fn from_move16(value: u16) -> Result<Option<Move>, UnpackError> {
+    let to = value & 0x7f;
+    let from = (value >> 7) & 0x7f;
+    let promotion = value & (1 << 14) != 0;
+
+    if to as usize >= Square::COUNT || value >> 15 != 0 {
+        return Err(UnpackError::InvalidMove);
+    }
+
+    let to = Square::from(to as u8);
+
+    if from as usize >= Square::COUNT {
+        let piece_type = DROP_PIECE_TYPES
+            .get(from as usize - Square::COUNT)
+            .ok_or(UnpackError::InvalidMove)?;
+
+        if promotion {
+            return Err(UnpackError::InvalidMove);
+        }
+
+        return Ok(Some(Move::drop(*piece_type, to)));
+    }
+
+    let from = Square::from(from as u8);
+
+    if from == to {
+        Ok(None)
+    } else if promotion {
+        Ok(Some(Move::promote(from, to)))
+    } else {
+        Ok(Some(Move::normal(from, to)))
+    }
+}
+
+/// Reads a piece on the board, or `None` for an empty square.
+fn read_board_piece(reader: &mut BitReader) -> Result<Option<Piece>, UnpackError> {
+    let mut code = 0;
+
+    for bits in 1..=8 {
+        code |= u32::from(read_bit(reader)?) << (bits - 1);
+
+        if (code, bits) == (0, 1) {
+            return Ok(None);
+        }
+
+        if let Some(piece) = BOARD_CODES.iter().position(|&c| c == (code, bits)) {
+            return Ok(Some(Piece::from(piece)));
+        }
+    }
+
+    Err(UnpackError::InvalidPieceCode)
+}
+
+fn read_hand_piece(reader: &mut BitReader) -> Result<(Color, PieceType), UnpackError> {
+    let mut code = 0;
+
+    for bits in 1..=7 {
+        code |= u32::from(read_bit(reader)?) << (bits - 1);
+
+        for color in Color::ALL {
+            if let Some(pt) = HAND_CODES[color].iter().position(|&c| c == (code, bits)) {
+                return Ok((color, PieceType::ALL[pt]));
+            }
+        }
+    }
+
+    Err(UnpackError::InvalidPieceCode)
+}
+
+fn read_bit(reader: &mut BitReader) -> Result<bool, UnpackError> {
+    reader.read_bit().ok_or(UnpackError::UnexpectedEnd)
+}
@@ -0,0 +1,633 @@
+use std::time::Duration;
+
+use serde_json::{json, Map, Value};
+
+use crate::{
+    notation::{
+        csa::{format_special, parse_piece_type, parse_special, PIECE_TYPE_CODES},
+        game::{GameEnd, GameRecord, MoveRecord, Variation},
+        kif::{handicap_name, handicap_position},
+    },
+    shogi::{
+        core::{Color, File, PieceType, Rank, Square},
+        position::{hand::Hand, mv::Move, Position},
+    },
+};
+
+#[derive(Debug, Copy, Clone)]
+pub enum ParseMoveError {
+    InvalidFormat,
+    InvalidSquare,
+    InvalidPieceType,
+    /// The color of the move is not the side to move.
+    InvalidColor,
+    /// The piece of the move is not the piece on the source square.
+    PieceMismatch,
+    IllegalMove,
+}
+
+#[derive(Debug, Copy, Clone)]
+pub enum ParseJkfError {
+    InvalidJson,
+    InvalidHeader,
+    InvalidPreset,
+    InvalidBoard,
+    InvalidHand,
+    /// The initial position is not structurally valid.
+    InvalidPosition,
+    InvalidMoves,
+    InvalidMove(ParseMoveError),
+    InvalidTime,
+    InvalidComment,
+    InvalidSpecialMove,
+    /// A move follows the end of the game.
+    MoveAfterEnd,
+    /// Forks are on an element without a move, which cannot be replaced.
+    InvalidFork,
+}
+
+/// Presets of the initial position, with the `手合割` of the same handicap.
+const PRESETS: [(&str, &str); 14] = [
+    ("HIRATE", "平手"),
+    ("KY", "香落ち"),
+    ("KY_R", "右香落ち"),
+    ("KA", "角落ち"),
+    ("HI", "飛車落ち"),
+    ("HIKY", "飛香落ち"),
+    ("2", "二枚落ち"),
+    ("3", "三枚落ち"),
+    ("4", "四枚落ち"),
+    ("5", "五枚落ち"),
+    ("5_L", "左五枚落ち"),
+    ("6", "六枚落ち"),
+    ("8", "八枚落ち"),
+    ("10", "十枚落ち"),
+];
+
+pub struct Jkf;
+
+/// Implements reading and writing of the JSON Kifu Format, used by web-based viewers, with
+/// its forks read into the variations of the moves.
+///
+/// Base on the reference:
+/// https://github.com/na2hiro/json-kifu-format
+impl Jkf {
+    /// Parses a game record, validating every move, including those of the forks, against
+    /// the position it is played in.
+    ///
+    /// The players, the event and the start time are read from the header, where the
+    /// players of a handicap game may also be named `下手` and `上手`.
+    pub fn parse(s: &str) -> Result<GameRecord, ParseJkfError> {
+        let jkf = serde_json::from_str::<Value>(s).map_err(|_| ParseJkfError::InvalidJson)?;
+        let jkf = jkf.as_object().ok_or(ParseJkfError::InvalidJson)?;
+
+        let start = match jkf.get("initial") {
+            Some(initial) => parse_initial(initial)?,
+            None => Position::startpos(),
+        };
+        let mut record = GameRecord::new(start);
+
+        if let Some(header) = jkf.get("header") {
+            parse_header(header, &mut record)?;
+        }
+
+        let elements = match jkf.get("moves") {
+            Some(moves) => moves
+                .as_array()
+                .ok_or(ParseJkfError::InvalidMoves)?
+                .as_slice(),
+            None => &[],
+        };
+
+        // The first element holds the comments on the initial position instead of a move.
+        if let Some((first, elements)) = elements.split_first() {
+            let first = first.as_object().ok_or(ParseJkfError::InvalidMoves)?;
+
+            if first.contains_key("move") || first.contains_key("forks") {
+                return Err(ParseJkfError::InvalidMoves);
+            }
+
+            record.comments = parse_comments(first)?;
+
+            let variation = parse_variation(&record.start, elements, None)?;
+
+            record.moves = variation.moves;
+            record.end = variation.end;
+        }
+
+        Ok(record)
+    }
+
+    /// Formats a game record, writing the variations of the moves as forks.
+    ///
+    /// The initial position is written as a preset when it is one.
+    #[must_use]
+    pub fn format(record: &GameRecord) -> String {
+        let mut header = Map::new();
+        let infos = [
+            ("開始日時", &record.start_time),
+            ("棋戦", &record.event),
+            ("先手", &record.black),
+            ("後手", &record.white),
+        ];
+
+        for (key, value) in infos {
+            if let Some(value) = value {
+                header.insert(key.to_string(), json!(value));
+            }
+        }
+
+        let mut first = Map::new();
+
+        if !record.comments.is_empty() {
+            first.insert("comments".to_string(), json!(record.comments));
+        }
+
+        let mut moves = vec![Value::Object(first)];
+
+        format_variation(
+            &mut moves,
+            &record.start,
+            &record.moves,
+            record.end,
+            None,
+            [Duration::ZERO; Color::COUNT],
+        );
+
+        json!({
+            "header": header,
+            "initial": format_initial(&record.start),
+            "moves": moves,
+        })
+        .to_string()
+    }
+
+    /// Parses a move object such as `{"from":{"x":7,"y":7},"to":{"x":7,"y":6},"piece":"FU"}`
+    /// played in `pos`.
+    ///
+    /// `color` is optional but must be that of the side to move if present, and drops have
+    /// no `from`. `same`, `capture` and `relative` describe the move and are not read.
+    pub fn parse_move(pos: &Position, s: &str) -> Result<Move, ParseMoveError> {
+        let value = serde_json::from_str::<Value>(s).map_err(|_| ParseMoveError::InvalidFormat)?;
+
+        parse_move_value(pos, &value)
+    }
+
+    /// Formats a move played in `pos` as a move object, with the moving piece, the piece
+    /// captured, whether it promotes when it can, and `same` if it moves to the destination of
+    /// `last`.
+    #[must_use]
+    pub fn format_move(pos: &Position, mv: Move, last: Option<Move>) -> String {
+        format_move_value(pos, mv, last).to_string()
+    }
+}
+
+/// Parses the elements of `moves` or of a fork, played from `pos`.
+fn parse_variation(
+    pos: &Position,
+    elements: &[Value],
+    last: Option<Move>,
+) -> Result<Variation, ParseJkfError> {
+    let mut pos = pos.clone();
+    let mut last = last;
+    let mut variation = Variation::default();
+
+    for element in elements {
+        let element = element.as_object().ok_or(ParseJkfError::InvalidMoves)?;
+
+        if variation.end.is_some() {
+            return Err(ParseJkfError::MoveAfterEnd);
+        }
+
+        let comments = parse_comments(element)?;
+
+        if let Some(special) = element.get("special") {
+            if element.contains_key("forks") {
+                return Err(ParseJkfError::InvalidFork);
+            }
+
+            let end = special
+                .as_str()
+                .and_then(|special| parse_special(special, pos.side_to_move()))
+                .ok_or(ParseJkfError::InvalidSpecialMove)?;
+
+            if let Some(last) = variation.moves.last_mut() {
+                last.comments.extend(comments);
+            }
+
+            variation.end = Some(end);
+            continue;
+        }
+
+        let mv = element
+            .get("move")
+            .ok_or(ParseJkfError::InvalidMoves)
+            .and_then(|mv| parse_move_value(&pos, mv).map_err(ParseJkfError::InvalidMove))?;
+        let time = element.get("time").map(parse_time).transpose()?;
+        let forks = match element.get("forks") {
+            Some(forks) => forks
+                .as_array()
+                .ok_or(ParseJkfError::InvalidFork)?
+                .iter()
+                .map(|fork| {
+                    let fork = fork.as_array().ok_or(ParseJkfError::InvalidFork)?;
+
+                    parse_variation(&pos, fork, last)
+                })
+                .collect::<Result<_, _>>()?,
+            None => Vec::new(),
+        };
+
+        pos.make_move(mv);
+        last = Some(mv);
+        variation.moves.push(MoveRecord {
+            mv,
+            time,
+            comments,
+            forks,
+        });
+    }
+
+    Ok(variation)
+}
+
+/// Appends the elements of the moves played from `pos`, followed by the end of the game.
+///
+/// `totals` is the time spent by each side before the first move.
+fn format_variation(
+    elements: &mut Vec<Value>,
+    pos: &Position,
+    moves: &[MoveRecord],
+    end: Option<GameEnd>,
+    last: Option<Move>,
+    mut totals: [Duration; Color::COUNT],
+) {
+    let mut pos = pos.clone();
+    let mut last = last;
+
+    for record in moves {
+        let mut element = Map::new();
+
+        element.insert("move".to_string(), format_move_value(&pos, record.mv, last));
+
+        if let Some(time) = record.time {
+            let total = &mut totals[pos.side_to_move()];
+
+            *total += time;
+            element.insert("time".to_string(), format_time(time, *total));
+        }
+
+        if !record.comments.is_empty() {
+            element.insert("comments".to_string(), json!(record.comments));
+        }
+
+        if !record.forks.is_empty() {
+            let forks = record
+                .forks
+                .iter()
+                .map(|fork| {
+                    let mut fork_elements = Vec::new();
+
+                    format_variation(
+                        &mut fork_elements,
+                        &pos,
+                        &fork.moves,
+                        fork.end,
+                        last,
+                        totals,
+                    );
+
+                    Value::Array(fork_elements)
+                })
+                .collect();
+
+            element.insert("forks".to_string(), Value::Array(forks));
+        }
+
+        elements.push(Value::Object(element));
+        pos.make_move(record.mv);
+        last = Some(record.mv);
+    }
+
+    if let Some(end) = end {
+        elements.push(json!({ "special": format_special(end, pos.side_to_move()) }));
+    }
+}
+
+fn parse_move_value(pos: &Position, value: &Value) -> Result<Move, ParseMoveError> {
+    let object = value.as_object().ok_or(ParseMoveError::InvalidFormat)?;
+    let stm = pos.side_to_move();
+
+    if let Some(color) = object.get("color") {
+        let color = color.as_u64().ok_or(ParseMoveError::InvalidFormat)?;
+
+        if color != stm.as_u8() as u64 {
+            return Err(ParseMoveError::InvalidColor);
+        }
+    }
+
+    let to = parse_square(object.get("to").ok_or(ParseMoveError::InvalidFormat)?)?;
+    let piece_type = object
+        .get("piece")
+        .and_then(Value::as_str)
+        .and_then(parse_piece_type)
+        .ok_or(ParseMoveError::InvalidPieceType)?;
+    let promote = match object.get("promote") {
+        Some(promote) => promote.as_bool().ok_or(ParseMoveError::InvalidFormat)?,
+        None => false,
+    };
+
+    let mv = match object.get("from") {
+        Some(from) => {
+            let from = parse_square(from)?;
+
+            if from == to {
+                return Err(ParseMoveError::InvalidFormat);
+            }
+
+            match pos.piece_at(from) {
+                Some(piece) if piece == piece_type.with_color(stm) => {}
+                _ => return Err(ParseMoveError::PieceMismatch),
+            }
+
+            if promote {
+                Move::promote(from, to)
+            } else {
+                Move::normal(from, to)
+            }
+        }
+        None if promote => return Err(ParseMoveError::InvalidFormat),
+        None if piece_type.as_usize() < Hand::HAND_PIECE_TYPES => Move::drop(piece_type, to),
+        None => return Err(ParseMoveError::InvalidPieceType),
+    };
+
+    if pos.is_legal_move(mv) {
+        Ok(mv)
+    } else {
+        Err(ParseMoveError::IllegalMove)
+    }
+}
+
+fn format_move_value(pos: &Position, mv: Move, last: Option<Move>) -> Value {
+    let stm = pos.side_to_move();
+    let mut object = Map::new();
+
+    object.insert("color".to_string(), json!(stm.as_u8()));
+    object.insert("to".to_string(), format_square(mv.to()));
+
+    if last.is_some_and(|last| last.to() == mv.to()) {
+        object.insert("same".to_string(), json!(true));
+    }
+
+    if mv.is_drop() {
+        object.insert(
+            "piece".to_string(),
+            json!(PIECE_TYPE_CODES[mv.drop_piece_type()]),
+        );
+
+        return Value::Object(object);
+    }
+
+    // Moves are legal, so there is a piece on the source square.
+    let piece_type = pos.piece_at(mv.from()).unwrap().piece_type();
+
+    object.insert("from".to_string(), format_square(mv.from()));
+    object.insert("piece".to_string(), json!(PIECE_TYPE_CODES[piece_type]));
+
+    if piece_type.can_promote() && (mv.from().can_promote(stm) || mv.to().can_promote(stm)) {
+        object.insert("promote".to_string(), json!(mv.is_promotion()));
+    }
+
+    if let Some(captured) = pos.piece_at(mv.to()) {
+        object.insert(
+            "capture".to_string(),
+            json!(PIECE_TYPE_CODES[captured.piece_type()]),
+        );
+    }
+
+    Value::Object(object)
+}
+
+/// Parses a square such as `{"x":7,"y":6}`, where `x` is the file and `y` the rank.
+fn parse_square(value: &Value) -> Result<Square, ParseMoveError> {
+    let coordinate = |key| {
+        value
+            .get(key)
+            .and_then(Value::as_u64)
+            .filter(|coordinate| (1..=9).contains(coordinate))
+            .map(|coordinate| coordinate as u8 - 1)
+            .ok_or(ParseMoveError::InvalidSquare)
+    };
+
+    Ok(Square::new(
+        File::from(coordinate("x")?),
+        Rank::from(coordinate("y")?),
+    ))
+}
+
+fn format_square(square: Square) -> Value {
+    json!({ "x": square.file().as_u8() + 1, "y": square.rank().as_u8() + 1 })
+}
+
+/// Parses the time spent on a move from a time object such as
+/// `{"now":{"m":1,"s":10},"total":{"h":0,"m":1,"s":15}}`.
+fn parse_time(value: &Value) -> Result<Duration, ParseJkfError> {
+    let now = value
+        .get("now")
+        .and_then(Value::as_object)
+        .ok_or(ParseJkfError::InvalidTime)?;
+    let mut seconds = 0;
+
+    for (key, unit) in [("h", 3600), ("m", 60), ("s", 1)] {
+        if let Some(count) = now.get(key) {
+            seconds += count.as_u64().ok_or(ParseJkfError::InvalidTime)? * unit;
+        }
+    }
+
+    Ok(Duration::from_secs(seconds))
+}
+
+/// Formats the time spent on a move and the total time of its player.
+fn format_time(time: Duration, total: Duration) -> Value {
+    let (time, total) = (time.as_secs(), total.as_secs());
+
+    json!({
+        "now": { "m": time / 60, "s": time % 60 },
+        "total": { "h": total / 3600, "m": total / 60 % 60, "s": total % 60 },
+    })
+}
+
+fn parse_comments(element: &Map<String, Value>) -> Result<Vec<String>, ParseJkfError> {
+    let Some(comments) = element.get("comments") else {
+        return Ok(Vec::new());
+    };
+
+    comments
+        .as_array()
+        .ok_or(ParseJkfError::InvalidComment)?
+        .iter()
+        .map(|comment| {
+            comment
+                .as_str()
+                .map(str::to_string)
+                .ok_or(ParseJkfError::InvalidComment)
+        })
+        .collect()
+}
+
+fn parse_header(value: &Value, record: &mut GameRecord) -> Result<(), ParseJkfError> {
+    let header = value.as_object().ok_or(ParseJkfError::InvalidHeader)?;
+
+    for (key, value) in header {
+        let value = value.as_str().ok_or(ParseJkfError::InvalidHeader)?;
+        let field = match key.as_str() {
+            "開始日時" => &mut record.start_time,
+            "棋戦" => &mut record.event,
+            "先手" | "下手" => &mut record.black,
+            "後手" | "上手" => &mut record.white,
+            _ => continue,
+        };
+
+        *field = Some(value.to_string());
+    }
+
+    Ok(())
+}
+
+/// Parses the initial position, either a preset or `OTHER` with its `data`.
+fn parse_initial(value: &Value) -> Result<Position, ParseJkfError> {
+    let preset = value
+        .get("preset")
+        .and_then(Value::as_str)
+        .ok_or(ParseJkfError::InvalidPreset)?;
+
+    if preset != "OTHER" {
+        return PRESETS
+            .iter()
+            .find(|&&(name, _)| name == preset)
+            .and_then(|&(_, handicap)| handicap_position(handicap))
+            .ok_or(ParseJkfError::InvalidPreset);
+    }
+
+    let data = value.get("data").ok_or(ParseJkfError::InvalidPreset)?;
+    let side_to_move = match data.get("color").and_then(Value::as_u64) {
+        Some(0) => Color::Black,
+        Some(1) => Color::White,
+        _ => return Err(ParseJkfError::InvalidPreset),
+    };
+    let mut builder = Position::empty().builder();
+
+    // The board is indexed by file, then by rank.
+    let files = data
+        .get("board")
+        .and_then(Value::as_array)
+        .filter(|files| files.len() == File::COUNT)
+        .ok_or(ParseJkfError::InvalidBoard)?;
+
+    for (file, ranks) in File::ALL.into_iter().zip(files) {
+        let ranks = ranks
+            .as_array()
+            .filter(|ranks| ranks.len() == Rank::COUNT)
+            .ok_or(ParseJkfError::InvalidBoard)?;
+
+        for (rank, cell) in Rank::ALL.into_iter().zip(ranks) {
+            let cell = cell.as_object().ok_or(ParseJkfError::InvalidBoard)?;
+
+            if cell.is_empty() {
+                continue;
+            }
+
+            let color = match cell.get("color").and_then(Value::as_u64) {
+                Some(0) => Color::Black,
+                Some(1) => Color::White,
+                _ => return Err(ParseJkfError::InvalidBoard),
+            };
+            let piece_type = cell
+                .get("kind")
+                .and_then(Value::as_str)
+                .and_then(parse_piece_type)
+                .ok_or(ParseJkfError::InvalidBoard)?;
+
+            builder.place(Square::new(file, rank), piece_type.with_color(color));
+        }
+    }
+
+    let hands = data
+        .get("hands")
+        .and_then(Value::as_array)
+        .filter(|hands| hands.len() == Color::COUNT)
+        .ok_or(ParseJkfError::InvalidHand)?;
+
+    for (color, hand) in Color::ALL.into_iter().zip(hands) {
+        let hand = hand.as_object().ok_or(ParseJkfError::InvalidHand)?;
+
+        for (kind, count) in hand {
+            let piece_type = parse_piece_type(kind)
+                .filter(|piece_type| piece_type.as_usize() < Hand::HAND_PIECE_TYPES)
+                .ok_or(ParseJkfError::InvalidHand)?;
+            let count = count
+                .as_u64()
+                .filter(|&count| count <= Hand::max_piece_counts(piece_type) as u64)
+                .ok_or(ParseJkfError::InvalidHand)?;
+
+            builder.set_hand_piece_count(color, piece_type, count as u32);
+        }
+    }
+
+    if !builder.verify() {
+        return Err(ParseJkfError::InvalidPosition);
+    }
+
+    builder.set_side_to_move(side_to_move);
+
+    Ok(builder.build())
+}
+
+fn format_initial(pos: &Position) -> Value {
+    if let Some(handicap) = handicap_name(pos) {
+        let (preset, _) = PRESETS.iter().find(|&&(_, h)| h == handicap).unwrap();
+
+        return json!({ "preset": preset });
+    }
+
+    let board = File::ALL
+        .into_iter()
+        .map(|file| {
+            Rank::ALL
+                .into_iter()
+                .map(|rank| match pos.piece_at(Square::new(file, rank)) {
+                    Some(piece) => json!({
+                        "color": piece.color().as_u8(),
+                        "kind": PIECE_TYPE_CODES[piece.piece_type()],
+                    }),
+                    None => json!({}),
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+    let hands = Color::ALL
+        .into_iter()
+        .map(|color| {
+            let hand = pos.hand(color);
+
+            PieceType::ALL[..Hand::HAND_PIECE_TYPES]
+                .iter()
+                .map(|&piece_type| {
+                    (
+                        PIECE_TYPE_CODES[piece_type].to_string(),
+                        json!(hand.count(piece_type)),
+                    )
+                })
+                .collect::<Map<_, _>>()
+        })
+        .collect::<Vec<_>>();
+
+    json!({
+        "preset": "OTHER",
+        "data": {
+            "color": pos.side_to_move().as_u8(),
+            "board": board,
+            "hands": hands,
+        },
+    })
+}
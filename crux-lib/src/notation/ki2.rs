@@ -0,0 +1,487 @@
+use std::fmt::Write;
+
+use crate::{
+    notation::{
+        game::{GameEnd, GameRecord, MoveRecord},
+        kif::{
+            char_width, drop_move, format_comments, format_header, format_square, parse_piece_type,
+            parse_square, Header, ParseKifError, ParseKifErrorKind, ParseMoveError,
+            PIECE_TYPE_NAMES,
+        },
+    },
+    shogi::{
+        core::{Color, PieceType, Square},
+        movegen::generate,
+        position::{mv::Move, Position},
+    },
+};
+
+/// Direction of a move from the perspective of the side to move.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Motion {
+    /// `上`: towards the opponent.
+    Forward,
+    /// `寄`: along the rank.
+    Sideways,
+    /// `引`: away from the opponent.
+    Backward,
+}
+
+impl Motion {
+    fn of(from: Square, to: Square, color: Color) -> Self {
+        let from = from.rank().relative(color).as_u8();
+        let to = to.rank().relative(color).as_u8();
+
+        if to < from {
+            Motion::Forward
+        } else if to == from {
+            Motion::Sideways
+        } else {
+            Motion::Backward
+        }
+    }
+
+    const fn name(self) -> char {
+        match self {
+            Motion::Forward => '上',
+            Motion::Sideways => '寄',
+            Motion::Backward => '引',
+        }
+    }
+}
+
+/// Marks of the side to move before every move, indexed by `Color`.
+const SIDE_MARKS: [char; Color::COUNT] = ['▲', '△'];
+
+/// Number of moves written on a line.
+const MOVES_PER_LINE: usize = 6;
+
+/// Display width of a move with its mark, after which the next move is aligned.
+const MOVE_WIDTH: usize = 14;
+
+pub struct Ki2;
+
+/// Implements reading and writing moves and game records in KI2, the variant of KIF that
+/// names moves as in printed game records, without their source squares.
+///
+/// When several pieces of the same kind can reach the destination, moves are told apart as
+/// the Japan Shogi Association does: first by their direction (`上`, `寄`, `引`), then by
+/// the position of the piece from the side to move (`左`, `右`, or `直` for a piece moving
+/// straight forward), both if needed. A drop is marked with `打` only when a piece on the
+/// board can also move to its destination.
+///
+/// Base on the reference:
+/// https://www.shogi.or.jp/faq/kihuhyouki.html
+impl Ki2 {
+    /// Parses a game record, validating every move against the position it is played in.
+    ///
+    /// The headers and comments are those of KIF, and the end of the game is read from the
+    /// summary line, such as `まで64手で後手の勝ち`.
+    pub fn parse(s: &str) -> Result<GameRecord, ParseKifError> {
+        let mut record = GameRecord::new(Position::startpos());
+        let mut header = Header::default();
+        let mut pos = None;
+        let mut line_number = 0;
+
+        for line in s.lines() {
+            line_number += 1;
+
+            let error = |kind| ParseKifError {
+                line: line_number,
+                kind,
+            };
+            let line = line.trim_start_matches('\u{feff}').trim_end();
+            let trimmed = line.trim_start();
+
+            if trimmed.is_empty() || trimmed.starts_with(['#', '&']) {
+                continue;
+            }
+
+            if let Some(comment) = trimmed.strip_prefix('*') {
+                record.push_comment(comment);
+                continue;
+            }
+
+            // Variations follow the main line.
+            if trimmed.starts_with("変化") {
+                break;
+            }
+
+            let pos = if trimmed.starts_with(['▲', '△', '☗', '☖']) || trimmed.starts_with("まで")
+            {
+                match &mut pos {
+                    Some(pos) => pos,
+                    None => pos.insert(header.start(&mut record).map_err(error)?),
+                }
+            } else {
+                header.parse_line(line, &mut record).map_err(error)?;
+                continue;
+            };
+
+            if record.end.is_some() {
+                return Err(error(ParseKifErrorKind::MoveAfterEnd));
+            }
+
+            if let Some(summary) = trimmed.strip_prefix("まで") {
+                record.end = parse_summary(summary, pos.side_to_move());
+                continue;
+            }
+
+            for text in trimmed.split(['▲', '△', '☗', '☖']).skip(1) {
+                let last = record.moves.last().map(|last| last.mv);
+                let text = text.trim_end_matches([' ', '　']);
+                let mv = Self::parse_move(pos, text, last)
+                    .map_err(|err| error(ParseKifErrorKind::InvalidMove(err)))?;
+
+                pos.make_move(mv);
+                record.moves.push(MoveRecord::new(mv));
+            }
+        }
+
+        if pos.is_none() {
+            header.start(&mut record).map_err(|kind| ParseKifError {
+                line: line_number,
+                kind,
+            })?;
+        }
+
+        Ok(record)
+    }
+
+    /// Resolves a move such as `７六歩`, `同　銀`, `５八金右`, `２三銀左不成` or `５五角打`
+    /// played in `pos` after `last`.
+    ///
+    /// The move may start with the mark of the side to move. Names that are more explicit
+    /// than needed are accepted, as long as they match a single legal move.
+    pub fn parse_move(pos: &Position, s: &str, last: Option<Move>) -> Result<Move, ParseMoveError> {
+        let s = s.trim_start_matches(['▲', '△', '☗', '☖']);
+        let (to, s) = match s.strip_prefix('同') {
+            Some(s) => (
+                last.ok_or(ParseMoveError::MissingPreviousMove)?.to(),
+                s.trim_start_matches([' ', '　']),
+            ),
+            None => parse_square(s).ok_or(ParseMoveError::InvalidSquare)?,
+        };
+        let (piece_type, mut s) = parse_piece_type(s).ok_or(ParseMoveError::InvalidPieceType)?;
+
+        let side = take(&mut s, &['左', '右', '直']);
+        let motion = take(&mut s, &['上', '寄', '引', '行']).map(|c| match c {
+            '寄' => Motion::Sideways,
+            '引' => Motion::Backward,
+            _ => Motion::Forward,
+        });
+        let drop = take(&mut s, &['打']).is_some();
+        let promotion = if let Some(rest) = s.strip_prefix("不成") {
+            s = rest;
+            false
+        } else {
+            take(&mut s, &['成']).is_some()
+        };
+
+        if !s.is_empty() || (drop && (side.is_some() || motion.is_some() || promotion)) {
+            return Err(ParseMoveError::InvalidFormat);
+        }
+
+        if drop {
+            let mv = drop_move(piece_type, to)?;
+
+            return if pos.is_legal_move(mv) {
+                Ok(mv)
+            } else {
+                Err(ParseMoveError::IllegalMove)
+            };
+        }
+
+        let stm = pos.side_to_move();
+        let sources = sources(pos, piece_type, to);
+
+        // Without `打`, a drop is only possible if no piece on the board can move there.
+        if sources.is_empty() && side.is_none() && motion.is_none() && !promotion {
+            let mv = drop_move(piece_type, to)?;
+
+            return if pos.is_legal_move(mv) {
+                Ok(mv)
+            } else {
+                Err(ParseMoveError::IllegalMove)
+            };
+        }
+
+        let mut candidates = sources
+            .into_iter()
+            .filter(|&from| motion.is_none_or(|motion| Motion::of(from, to, stm) == motion))
+            .collect::<Vec<_>>();
+
+        let file = |square: Square| square.file().relative(stm).as_u8();
+
+        match side {
+            Some('直') => candidates.retain(|&from| is_straight(piece_type, from, to, stm)),
+            Some(side) => {
+                let target = if side == '左' {
+                    candidates.iter().map(|&from| file(from)).max()
+                } else {
+                    candidates.iter().map(|&from| file(from)).min()
+                };
+
+                candidates.retain(|&from| Some(file(from)) == target);
+            }
+            None => {}
+        }
+
+        let mut moves = candidates.into_iter().map(|from| {
+            if promotion {
+                Move::promote(from, to)
+            } else {
+                Move::normal(from, to)
+            }
+        });
+
+        match (moves.next(), moves.next()) {
+            (Some(mv), None) if pos.is_legal_move(mv) => Ok(mv),
+            (Some(_), Some(_)) => Err(ParseMoveError::AmbiguousMove),
+            _ => Err(ParseMoveError::IllegalMove),
+        }
+    }
+
+    /// Formats a game record, with its moves after the headers of KIF.
+    #[must_use]
+    pub fn format(record: &GameRecord) -> String {
+        let mut result = String::new();
+
+        format_header(&mut result, record);
+        format_comments(&mut result, &record.comments);
+
+        let mut pos = record.start.clone();
+        let mut last = None;
+        let mut line_len = 0;
+        let mut width = 0;
+
+        for move_record in &record.moves {
+            let text = format!(
+                "{}{}",
+                SIDE_MARKS[pos.side_to_move()],
+                Self::format_move(&pos, move_record.mv, last)
+            );
+
+            if line_len > 0 {
+                write!(result, "{:1$}", "", MOVE_WIDTH.saturating_sub(width).max(1)).unwrap();
+            }
+
+            result.push_str(&text);
+            line_len += 1;
+            width = text.chars().map(char_width).sum();
+
+            if line_len == MOVES_PER_LINE || !move_record.comments.is_empty() {
+                result.push('\n');
+                format_comments(&mut result, &move_record.comments);
+                line_len = 0;
+            }
+
+            pos.make_move(move_record.mv);
+            last = Some(move_record.mv);
+        }
+
+        if line_len > 0 {
+            result.push('\n');
+        }
+
+        if let Some(end) = record.end {
+            writeln!(
+                result,
+                "まで{}手で{}",
+                record.moves.len(),
+                format_summary(end, pos.side_to_move())
+            )
+            .unwrap();
+        }
+
+        result
+    }
+
+    /// Formats a move played in `pos` after `last`, without the mark of the side to move,
+    /// adding only the relative position and direction needed to tell it apart.
+    #[must_use]
+    pub fn format_move(pos: &Position, mv: Move, last: Option<Move>) -> String {
+        let to = mv.to();
+        let mut result = if last.is_some_and(|last| last.to() == to) {
+            "同　".to_string()
+        } else {
+            format_square(to)
+        };
+
+        if mv.is_drop() {
+            let piece_type = mv.drop_piece_type();
+
+            result.push_str(PIECE_TYPE_NAMES[piece_type]);
+
+            if !sources(pos, piece_type, to).is_empty() {
+                result.push('打');
+            }
+
+            return result;
+        }
+
+        let from = mv.from();
+        let stm = pos.side_to_move();
+        let piece_type = pos
+            .piece_at(from)
+            .map_or(PieceType::King, |piece| piece.piece_type());
+        let others = sources(pos, piece_type, to)
+            .into_iter()
+            .filter(|&square| square != from)
+            .collect::<Vec<_>>();
+
+        result.push_str(PIECE_TYPE_NAMES[piece_type]);
+
+        if !others.is_empty() {
+            result.push_str(&relative_name(piece_type, from, to, stm, &others));
+        }
+
+        if mv.is_promotion() {
+            result.push('成');
+        } else if piece_type.can_promote() && (from.can_promote(stm) || to.can_promote(stm)) {
+            result.push_str("不成");
+        }
+
+        result
+    }
+}
+
+/// Removes the first character of `s` if it is one of `names`, and returns it.
+fn take(s: &mut &str, names: &[char]) -> Option<char> {
+    let c = s.chars().next().filter(|c| names.contains(c))?;
+
+    *s = &s[c.len_utf8()..];
+    Some(c)
+}
+
+/// Returns the squares of the pieces of `piece_type` of the side to move that can move to
+/// `to` legally.
+fn sources(pos: &Position, piece_type: PieceType, to: Square) -> Vec<Square> {
+    let mut sources = Vec::new();
+
+    for mv in generate(pos) {
+        if mv.is_drop() || mv.to() != to || sources.contains(&mv.from()) {
+            continue;
+        }
+
+        let moves_piece = pos
+            .piece_at(mv.from())
+            .is_some_and(|piece| piece.piece_type() == piece_type);
+
+        if moves_piece && pos.is_legal_move(mv) {
+            sources.push(mv.from());
+        }
+    }
+
+    sources
+}
+
+/// Returns `true` if a piece moves straight forward, which `直` names. Dragons and horses are
+/// always told apart by `左` and `右`.
+fn is_straight(piece_type: PieceType, from: Square, to: Square, color: Color) -> bool {
+    !matches!(piece_type, PieceType::Horse | PieceType::Dragon)
+        && from.file() == to.file()
+        && Motion::of(from, to, color) == Motion::Forward
+}
+
+/// Returns the name telling the piece on `from` apart from the `others` that can also move
+/// to `to`.
+fn relative_name(
+    piece_type: PieceType,
+    from: Square,
+    to: Square,
+    color: Color,
+    others: &[Square],
+) -> String {
+    let motion = Motion::of(from, to, color);
+    let same_motion = others
+        .iter()
+        .copied()
+        .filter(|&square| Motion::of(square, to, color) == motion)
+        .collect::<Vec<_>>();
+
+    if same_motion.is_empty() {
+        return motion.name().to_string();
+    }
+
+    if is_straight(piece_type, from, to, color) {
+        return "直".to_string();
+    }
+
+    // Files increase to the left of the side to move.
+    let file = |square: Square| square.file().relative(color).as_u8();
+    let is_left = |squares: &[Square]| squares.iter().all(|&square| file(from) > file(square));
+    let is_right = |squares: &[Square]| squares.iter().all(|&square| file(from) < file(square));
+
+    let (side, alone) = if is_left(&same_motion) {
+        ('左', is_left(others))
+    } else if is_right(&same_motion) {
+        ('右', is_right(others))
+    } else {
+        return motion.name().to_string();
+    };
+
+    if alone {
+        side.to_string()
+    } else {
+        format!("{side}{}", motion.name())
+    }
+}
+
+/// Parses the summary line after `まで`, such as `64手で後手の勝ち`, from the point of view
+/// of `stm`, the side to move after the last move.
+///
+/// Returns `None` if the winner contradicts the side to move.
+fn parse_summary(summary: &str, stm: Color) -> Option<GameEnd> {
+    let (_, result) = summary.split_once("手で")?;
+    let winner = |name: &str| match name {
+        "先手" | "下手" => Some(Color::Black),
+        "後手" | "上手" => Some(Color::White),
+        _ => None,
+    };
+
+    match result {
+        "千日手" => return Some(GameEnd::Repetition),
+        "持将棋" => return Some(GameEnd::Impasse),
+        "中断" => return Some(GameEnd::Abort),
+        "詰み" => return Some(GameEnd::Checkmate),
+        "引き分け" => return Some(GameEnd::Draw),
+        _ => {}
+    }
+
+    if let Some(result) = result.strip_prefix("時間切れにより") {
+        (winner(result.strip_suffix("の勝ち")?)? == stm.opposite()).then_some(GameEnd::TimeUp)
+    } else if let Some(name) = result.strip_suffix("の反則勝ち") {
+        if winner(name)? == stm {
+            Some(GameEnd::IllegalWin)
+        } else {
+            Some(GameEnd::IllegalLoss)
+        }
+    } else if let Some(name) = result.strip_suffix("の入玉勝ち") {
+        (winner(name)? == stm).then_some(GameEnd::EnteringKing)
+    } else {
+        (winner(result.strip_suffix("の勝ち")?)? == stm.opposite()).then_some(GameEnd::Resign)
+    }
+}
+
+/// Formats the end of a game for the summary line, from the point of view of `stm`, the side
+/// to move after the last move.
+fn format_summary(end: GameEnd, stm: Color) -> String {
+    let name = |color: Color| match color {
+        Color::Black => "先手",
+        Color::White => "後手",
+    };
+
+    match end {
+        GameEnd::Resign => format!("{}の勝ち", name(stm.opposite())),
+        GameEnd::Abort => "中断".to_string(),
+        GameEnd::Repetition => "千日手".to_string(),
+        GameEnd::Impasse => "持将棋".to_string(),
+        GameEnd::TimeUp => format!("時間切れにより{}の勝ち", name(stm.opposite())),
+        GameEnd::Checkmate => "詰み".to_string(),
+        GameEnd::Draw => "引き分け".to_string(),
+        GameEnd::EnteringKing => format!("{}の入玉勝ち", name(stm)),
+        GameEnd::IllegalWin => format!("{}の反則勝ち", name(stm)),
+        GameEnd::IllegalLoss => format!("{}の反則勝ち", name(stm.opposite())),
+    }
+}
@@ -0,0 +1,853 @@
+use std::{fmt::Write, time::Duration};
+
+use crate::{
+    notation::{
+        game::{GameEnd, GameRecord, MoveRecord},
+        usi::Usi,
+        Notation,
+    },
+    shogi::{
+        core::{Color, File, Piece, PieceType, Rank, Square},
+        position::{hand::Hand, mv::Move, Position, PositionBuilder},
+    },
+};
+
+#[derive(Debug, Copy, Clone)]
+pub enum ParseMoveError {
+    InvalidFormat,
+    InvalidSquare,
+    InvalidPieceType,
+    /// `同` is used for the first move.
+    MissingPreviousMove,
+    /// The named piece is not the piece of the side to move on the source square.
+    PieceMismatch,
+    IllegalMove,
+    /// Several legal moves match a move without its source square.
+    AmbiguousMove,
+}
+
+#[derive(Debug, Copy, Clone)]
+pub enum ParseKifErrorKind {
+    InvalidHandicap,
+    InvalidBoard,
+    InvalidHand,
+    /// The starting position is not structurally valid.
+    InvalidPosition,
+    InvalidMoveNumber,
+    InvalidMove(ParseMoveError),
+    InvalidTime,
+    /// A move follows the end of the game.
+    MoveAfterEnd,
+}
+
+/// An error in a KIF record, with the line it was found on, starting from 1.
+#[derive(Debug, Copy, Clone)]
+pub struct ParseKifError {
+    pub line: usize,
+    pub kind: ParseKifErrorKind,
+}
+
+/// Full-width digits naming the files, indexed by `File`.
+pub(crate) const FILE_NAMES: [char; File::COUNT] =
+    ['１', '２', '３', '４', '５', '６', '７', '８', '９'];
+
+/// Kanji numerals naming the ranks, indexed by `Rank`.
+pub(crate) const RANK_NAMES: [char; Rank::COUNT] =
+    ['一', '二', '三', '四', '五', '六', '七', '八', '九'];
+
+/// Names of the piece types in moves, indexed by `PieceType`.
+pub(crate) const PIECE_TYPE_NAMES: [&str; PieceType::COUNT] = [
+    "歩", "香", "桂", "銀", "金", "角", "飛", "と", "成香", "成桂", "成銀", "馬", "龍", "玉",
+];
+
+/// Names of the piece types accepted in moves, with the one-character variants of board
+/// diagrams. Longer names come first, so that the first match is the longest.
+const PIECE_TYPE_ALIASES: [(&str, PieceType); 19] = [
+    ("成香", PieceType::ProLance),
+    ("成桂", PieceType::ProKnight),
+    ("成銀", PieceType::ProSilver),
+    ("歩", PieceType::Pawn),
+    ("香", PieceType::Lance),
+    ("桂", PieceType::Knight),
+    ("銀", PieceType::Silver),
+    ("金", PieceType::Gold),
+    ("角", PieceType::Bishop),
+    ("飛", PieceType::Rook),
+    ("と", PieceType::ProPawn),
+    ("杏", PieceType::ProLance),
+    ("圭", PieceType::ProKnight),
+    ("全", PieceType::ProSilver),
+    ("馬", PieceType::Horse),
+    ("龍", PieceType::Dragon),
+    ("竜", PieceType::Dragon),
+    ("玉", PieceType::King),
+    ("王", PieceType::King),
+];
+
+/// One-character names of the piece types in board diagrams, indexed by `PieceType`.
+const BOARD_PIECE_NAMES: [char; PieceType::COUNT] = [
+    '歩', '香', '桂', '銀', '金', '角', '飛', 'と', '杏', '圭', '全', '馬', '龍', '玉',
+];
+
+/// Handicaps of the `手合割` header, with the squares of the pieces removed from the white
+/// side of the initial position.
+const HANDICAPS: [(&str, &[Square]); 13] = [
+    ("香落ち", &[Square::S11]),
+    ("右香落ち", &[Square::S91]),
+    ("角落ち", &[Square::S22]),
+    ("飛車落ち", &[Square::S82]),
+    ("飛香落ち", &[Square::S82, Square::S11]),
+    ("二枚落ち", &[Square::S82, Square::S22]),
+    ("三枚落ち", &[Square::S82, Square::S22, Square::S11]),
+    (
+        "四枚落ち",
+        &[Square::S82, Square::S22, Square::S11, Square::S91],
+    ),
+    (
+        "五枚落ち",
+        &[
+            Square::S82,
+            Square::S22,
+            Square::S11,
+            Square::S91,
+            Square::S21,
+        ],
+    ),
+    (
+        "左五枚落ち",
+        &[
+            Square::S82,
+            Square::S22,
+            Square::S11,
+            Square::S91,
+            Square::S81,
+        ],
+    ),
+    (
+        "六枚落ち",
+        &[
+            Square::S82,
+            Square::S22,
+            Square::S11,
+            Square::S91,
+            Square::S21,
+            Square::S81,
+        ],
+    ),
+    (
+        "八枚落ち",
+        &[
+            Square::S82,
+            Square::S22,
+            Square::S11,
+            Square::S91,
+            Square::S21,
+            Square::S81,
+            Square::S31,
+            Square::S71,
+        ],
+    ),
+    (
+        "十枚落ち",
+        &[
+            Square::S82,
+            Square::S22,
+            Square::S11,
+            Square::S91,
+            Square::S21,
+            Square::S81,
+            Square::S31,
+            Square::S71,
+            Square::S41,
+            Square::S61,
+        ],
+    ),
+];
+
+/// Names of the ends of games in move lines.
+const GAME_ENDS: [(&str, GameEnd); 10] = [
+    ("投了", GameEnd::Resign),
+    ("中断", GameEnd::Abort),
+    ("千日手", GameEnd::Repetition),
+    ("持将棋", GameEnd::Impasse),
+    ("切れ負け", GameEnd::TimeUp),
+    ("詰み", GameEnd::Checkmate),
+    ("入玉勝ち", GameEnd::EnteringKing),
+    ("反則勝ち", GameEnd::IllegalWin),
+    ("反則負け", GameEnd::IllegalLoss),
+    ("引き分け", GameEnd::Draw),
+];
+
+/// Kanji numerals of the counts of pieces in hand, indexed by count.
+const COUNT_NAMES: [&str; 10] = ["", "一", "二", "三", "四", "五", "六", "七", "八", "九"];
+
+/// The line starting the moves.
+const MOVES_HEADER: &str = "手数----指手---------消費時間--";
+
+/// Display width of a move, after which the consumption time is aligned.
+const MOVE_WIDTH: usize = 14;
+
+pub struct Kif;
+
+/// Implements reading and writing game records in the KIF format of Kakinoki's Kifu for
+/// Windows, as also used by ShogiGUI and ShogiDokoro.
+///
+/// Records are read from text already decoded from the file, which is traditionally in
+/// Shift_JIS, and only their main line is read.
+///
+/// Base on the reference:
+/// http://kakinoki.o.oo7.jp/kif_format.html
+impl Kif {
+    /// Parses a game record, validating every move against the position it is played in.
+    ///
+    /// The starting position is taken from the board diagram if there is one, otherwise from
+    /// the `手合割` header, and is the initial position if neither is present.
+    pub fn parse(s: &str) -> Result<GameRecord, ParseKifError> {
+        let mut record = GameRecord::new(Position::startpos());
+        let mut header = Header::default();
+        let mut pos = None;
+        let mut line_number = 0;
+
+        for line in s.lines() {
+            line_number += 1;
+
+            let error = |kind| ParseKifError {
+                line: line_number,
+                kind,
+            };
+            let line = line.trim_start_matches('\u{feff}').trim_end();
+            let trimmed = line.trim_start();
+
+            if trimmed.is_empty()
+                || trimmed.starts_with(['#', '&'])
+                || trimmed.starts_with("まで")
+                || trimmed == MOVES_HEADER
+            {
+                continue;
+            }
+
+            if let Some(comment) = trimmed.strip_prefix('*') {
+                record.push_comment(comment);
+                continue;
+            }
+
+            // Variations follow the main line.
+            if trimmed.starts_with("変化") {
+                break;
+            }
+
+            if trimmed.starts_with(|c: char| c.is_ascii_digit()) {
+                let pos = match &mut pos {
+                    Some(pos) => pos,
+                    None => pos.insert(header.start(&mut record).map_err(error)?),
+                };
+
+                parse_move_line(trimmed, pos, &mut record).map_err(error)?;
+                continue;
+            }
+
+            header.parse_line(line, &mut record).map_err(error)?;
+        }
+
+        if pos.is_none() {
+            header.start(&mut record).map_err(|kind| ParseKifError {
+                line: line_number,
+                kind,
+            })?;
+        }
+
+        Ok(record)
+    }
+
+    /// Parses a move such as `７六歩(77)`, `同　歩(76)`, `２二角成(88)` or `５五角打` played
+    /// in `pos` after `last`.
+    pub fn parse_move(pos: &Position, s: &str, last: Option<Move>) -> Result<Move, ParseMoveError> {
+        let (to, s) = match s.strip_prefix('同') {
+            Some(s) => (
+                last.ok_or(ParseMoveError::MissingPreviousMove)?.to(),
+                s.trim_start_matches([' ', '　']),
+            ),
+            None => parse_square(s).ok_or(ParseMoveError::InvalidSquare)?,
+        };
+        let (piece_type, s) = parse_piece_type(s).ok_or(ParseMoveError::InvalidPieceType)?;
+
+        let mv = if let Some(s) = s.strip_prefix('打') {
+            if !s.is_empty() {
+                return Err(ParseMoveError::InvalidFormat);
+            }
+
+            drop_move(piece_type, to)?
+        } else {
+            let (promotion, s) = if let Some(s) = s.strip_prefix("不成") {
+                (false, s)
+            } else if let Some(s) = s.strip_prefix('成') {
+                (true, s)
+            } else {
+                (false, s)
+            };
+
+            // Drops are usually marked with `打`, but the source square tells them apart.
+            if s.is_empty() && !promotion {
+                drop_move(piece_type, to)?
+            } else {
+                let from = s
+                    .strip_prefix('(')
+                    .and_then(|s| s.strip_suffix(')'))
+                    .and_then(parse_digit_square)
+                    .ok_or(ParseMoveError::InvalidFormat)?;
+
+                if from == to {
+                    return Err(ParseMoveError::InvalidFormat);
+                }
+
+                match pos.piece_at(from) {
+                    Some(piece)
+                        if piece.color() == pos.side_to_move()
+                            && piece.piece_type() == piece_type => {}
+                    _ => return Err(ParseMoveError::PieceMismatch),
+                }
+
+                if promotion {
+                    Move::promote(from, to)
+                } else {
+                    Move::normal(from, to)
+                }
+            }
+        };
+
+        if pos.is_legal_move(mv) {
+            Ok(mv)
+        } else {
+            Err(ParseMoveError::IllegalMove)
+        }
+    }
+
+    /// Formats a game record.
+    ///
+    /// The starting position is written as a `手合割` header if it is the initial position or
+    /// a handicap, and as a board diagram otherwise.
+    #[must_use]
+    pub fn format(record: &GameRecord) -> String {
+        let mut result = String::new();
+
+        format_header(&mut result, record);
+        writeln!(result, "{MOVES_HEADER}").unwrap();
+        format_comments(&mut result, &record.comments);
+
+        let mut pos = record.start.clone();
+        let mut last = None;
+        let mut total_times = [Duration::ZERO; Color::COUNT];
+
+        for (i, move_record) in record.moves.iter().enumerate() {
+            let text = Self::format_move(&pos, move_record.mv, last);
+
+            write!(result, "{:>4} {text}", i + 1).unwrap();
+
+            if let Some(time) = move_record.time {
+                let total = &mut total_times[pos.side_to_move()];
+                let width = text.chars().map(char_width).sum::<usize>();
+
+                *total += time;
+
+                let (time, total) = (time.as_secs(), total.as_secs());
+
+                write!(
+                    result,
+                    "{:padding$}({:>2}:{:02}/{:02}:{:02}:{:02})",
+                    "",
+                    time / 60,
+                    time % 60,
+                    total / 3600,
+                    total / 60 % 60,
+                    total % 60,
+                    padding = MOVE_WIDTH.saturating_sub(width).max(1),
+                )
+                .unwrap();
+            }
+
+            result.push('\n');
+            format_comments(&mut result, &move_record.comments);
+
+            pos.make_move(move_record.mv);
+            last = Some(move_record.mv);
+        }
+
+        if let Some(end) = record.end {
+            let (name, _) = GAME_ENDS.iter().find(|&&(_, e)| e == end).unwrap();
+
+            writeln!(result, "{:>4} {name}", record.moves.len() + 1).unwrap();
+        }
+
+        result
+    }
+
+    /// Formats a move played in `pos` after `last`.
+    ///
+    /// The destination is written as `同` when it is that of `last`, and `不成` is added when
+    /// the moving piece could have promoted.
+    #[must_use]
+    pub fn format_move(pos: &Position, mv: Move, last: Option<Move>) -> String {
+        let mut result = if last.is_some_and(|last| last.to() == mv.to()) {
+            "同　".to_string()
+        } else {
+            format_square(mv.to())
+        };
+
+        if mv.is_drop() {
+            write!(result, "{}打", PIECE_TYPE_NAMES[mv.drop_piece_type()]).unwrap();
+        } else {
+            let from = mv.from();
+            let piece_type = pos
+                .piece_at(from)
+                .map_or(PieceType::King, Piece::piece_type);
+            let stm = pos.side_to_move();
+
+            result.push_str(PIECE_TYPE_NAMES[piece_type]);
+
+            if mv.is_promotion() {
+                result.push('成');
+            } else if piece_type.can_promote()
+                && (from.can_promote(stm) || mv.to().can_promote(stm))
+            {
+                result.push_str("不成");
+            }
+
+            write!(
+                result,
+                "({}{})",
+                from.file().as_u8() + 1,
+                from.rank().as_u8() + 1
+            )
+            .unwrap();
+        }
+
+        result
+    }
+}
+
+/// The headers of KIF and KI2 records that make up the starting position.
+#[derive(Default)]
+pub(crate) struct Header {
+    handicap: Option<Position>,
+    /// The board diagram, with the number of ranks read.
+    board: Option<(PositionBuilder, usize)>,
+    side_to_move: Option<Color>,
+}
+
+impl Header {
+    /// Parses a line of the header, a board diagram or a side to move, ignoring other lines.
+    pub(crate) fn parse_line(
+        &mut self,
+        line: &str,
+        record: &mut GameRecord,
+    ) -> Result<(), ParseKifErrorKind> {
+        if let Some(row) = line.strip_prefix('|') {
+            return self.parse_row(row);
+        }
+
+        match line.trim_start() {
+            "先手番" | "下手番" => self.side_to_move = Some(Color::Black),
+            "後手番" | "上手番" => self.side_to_move = Some(Color::White),
+            line => {
+                if let Some((key, value)) = line.split_once('：') {
+                    self.parse(key, value.trim(), record)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn parse(
+        &mut self,
+        key: &str,
+        value: &str,
+        record: &mut GameRecord,
+    ) -> Result<(), ParseKifErrorKind> {
+        let text = || Some(value.to_string()).filter(|value| !value.is_empty());
+
+        match key {
+            "開始日時" => record.start_time = text(),
+            "棋戦" => record.event = text(),
+            "先手" | "下手" => record.black = text(),
+            "後手" | "上手" => record.white = text(),
+            "手合割" => {
+                self.handicap =
+                    Some(handicap_position(value).ok_or(ParseKifErrorKind::InvalidHandicap)?);
+            }
+            "先手の持駒" | "下手の持駒" => self.parse_hand(Color::Black, value)?,
+            "後手の持駒" | "上手の持駒" => self.parse_hand(Color::White, value)?,
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    fn board(&mut self) -> &mut (PositionBuilder, usize) {
+        self.board
+            .get_or_insert_with(|| (Position::empty().builder(), 0))
+    }
+
+    /// Parses a rank of the board diagram, such as `v香v桂 ・ ・ ・ ・ ・v桂v香|一`.
+    fn parse_row(&mut self, row: &str) -> Result<(), ParseKifErrorKind> {
+        let (builder, ranks) = self.board();
+        let cells = row.chars().collect::<Vec<_>>();
+
+        if *ranks >= Rank::COUNT || cells.len() < File::COUNT * 2 {
+            return Err(ParseKifErrorKind::InvalidBoard);
+        }
+
+        let rank = Rank::from(*ranks);
+
+        for (cell, &file) in cells.chunks_exact(2).zip(File::ALL.iter().rev()) {
+            let color = match cell[0] {
+                ' ' | '　' => Color::Black,
+                'v' | 'V' => Color::White,
+                _ => return Err(ParseKifErrorKind::InvalidBoard),
+            };
+
+            if cell[1] == '・' {
+                continue;
+            }
+
+            let (piece_type, _) = parse_piece_type(cell[1].encode_utf8(&mut [0; 4]))
+                .ok_or(ParseKifErrorKind::InvalidBoard)?;
+
+            builder.place(Square::new(file, rank), piece_type.with_color(color));
+        }
+
+        *ranks += 1;
+
+        Ok(())
+    }
+
+    /// Parses pieces in hand, such as `飛　角　歩二` or `なし`.
+    fn parse_hand(&mut self, color: Color, value: &str) -> Result<(), ParseKifErrorKind> {
+        let (builder, _) = self.board();
+
+        if value.is_empty() || value == "なし" {
+            return Ok(());
+        }
+
+        for name in value.split([' ', '　']).filter(|name| !name.is_empty()) {
+            let (piece_type, count) =
+                parse_piece_type(name).ok_or(ParseKifErrorKind::InvalidHand)?;
+            let count = if count.is_empty() {
+                1
+            } else {
+                parse_count(count).ok_or(ParseKifErrorKind::InvalidHand)?
+            };
+
+            if piece_type.as_usize() >= Hand::HAND_PIECE_TYPES
+                || count > Hand::max_piece_counts(piece_type)
+            {
+                return Err(ParseKifErrorKind::InvalidHand);
+            }
+
+            builder.set_hand_piece_count(color, piece_type, count);
+        }
+
+        Ok(())
+    }
+
+    /// Builds the starting position and stores it in `record`.
+    pub(crate) fn start(&mut self, record: &mut GameRecord) -> Result<Position, ParseKifErrorKind> {
+        let start = match self.board.take() {
+            Some((mut builder, ranks)) => {
+                if ranks != Rank::COUNT {
+                    return Err(ParseKifErrorKind::InvalidBoard);
+                }
+
+                if !builder.verify() {
+                    return Err(ParseKifErrorKind::InvalidPosition);
+                }
+
+                builder.set_side_to_move(self.side_to_move.unwrap_or(Color::Black));
+                builder.build()
+            }
+            None => self.handicap.take().unwrap_or_else(Position::startpos),
+        };
+
+        record.start = start.clone();
+
+        Ok(start)
+    }
+}
+
+/// Writes the headers of a record, with the starting position as a `手合割` header if it is
+/// the initial position or a handicap, and as a board diagram otherwise.
+pub(crate) fn format_header(result: &mut String, record: &GameRecord) {
+    let handicap = handicap_name(&record.start);
+    let (black, white) = match handicap {
+        Some("平手") | None => ("先手", "後手"),
+        Some(_) => ("下手", "上手"),
+    };
+
+    let headers = [("開始日時", &record.start_time), ("棋戦", &record.event)];
+
+    for (key, value) in headers {
+        if let Some(value) = value {
+            writeln!(result, "{key}：{value}").unwrap();
+        }
+    }
+
+    match handicap {
+        Some(name) => writeln!(result, "手合割：{name}").unwrap(),
+        None => format_board(result, &record.start),
+    }
+
+    for (key, value) in [(black, &record.black), (white, &record.white)] {
+        if let Some(value) = value {
+            writeln!(result, "{key}：{value}").unwrap();
+        }
+    }
+}
+
+pub(crate) fn format_comments(result: &mut String, comments: &[String]) {
+    for comment in comments {
+        writeln!(result, "*{comment}").unwrap();
+    }
+}
+
+/// Parses a move line such as `   1 ７六歩(77)   ( 0:16/00:00:16)` or `  87 投了`.
+fn parse_move_line(
+    line: &str,
+    pos: &mut Position,
+    record: &mut GameRecord,
+) -> Result<(), ParseKifErrorKind> {
+    let digits = line
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(line.len());
+    let (number, rest) = line.split_at(digits);
+
+    if record.end.is_some() {
+        return Err(ParseKifErrorKind::MoveAfterEnd);
+    }
+
+    if number.parse::<usize>() != Ok(record.moves.len() + 1) {
+        return Err(ParseKifErrorKind::InvalidMoveNumber);
+    }
+
+    // A trailing `+` marks a move with variations.
+    let rest = rest.trim_end_matches('+').trim();
+
+    if let Some(&(_, end)) = GAME_ENDS.iter().find(|(name, _)| rest.starts_with(name)) {
+        record.end = Some(end);
+        return Ok(());
+    }
+
+    // `同` may be followed by a space, which is not the end of the move.
+    let start = if rest.starts_with('同') {
+        let len = '同'.len_utf8();
+
+        len + rest[len..].len() - rest[len..].trim_start_matches([' ', '　']).len()
+    } else {
+        0
+    };
+    let end = rest[start..].find(' ').map_or(rest.len(), |i| start + i);
+    let (text, time) = rest.split_at(end);
+
+    let last = record.moves.last().map(|last| last.mv);
+    let mv = Kif::parse_move(pos, text, last).map_err(ParseKifErrorKind::InvalidMove)?;
+    let time = time.trim();
+    let time = if time.is_empty() {
+        None
+    } else {
+        Some(parse_time(time).ok_or(ParseKifErrorKind::InvalidTime)?)
+    };
+
+    pos.make_move(mv);
+    record.moves.push(MoveRecord {
+        time,
+        ..MoveRecord::new(mv)
+    });
+
+    Ok(())
+}
+
+/// Parses the time of a move line such as `( 0:16/00:00:16)`, which is the time spent on
+/// the move followed by the total time of the player.
+fn parse_time(s: &str) -> Option<Duration> {
+    let (time, _) = s.strip_prefix('(')?.split_once('/')?;
+    let (minutes, seconds) = time.split_once(':')?;
+    let minutes = minutes.trim().parse::<u64>().ok()?;
+    let seconds = seconds.trim().parse::<u64>().ok()?;
+
+    Some(Duration::from_secs(minutes * 60 + seconds))
+}
+
+pub(crate) fn drop_move(piece_type: PieceType, to: Square) -> Result<Move, ParseMoveError> {
+    if piece_type.as_usize() < Hand::HAND_PIECE_TYPES {
+        Ok(Move::drop(piece_type, to))
+    } else {
+        Err(ParseMoveError::InvalidPieceType)
+    }
+}
+
+/// Parses a square such as `７六` at the start of `s`, returning it with the rest of `s`.
+pub(crate) fn parse_square(s: &str) -> Option<(Square, &str)> {
+    let mut chars = s.chars();
+    let file = chars.next()?;
+    let file = FILE_NAMES
+        .iter()
+        .position(|&name| name == file)
+        .or_else(|| file.to_digit(10).and_then(|d| (d as usize).checked_sub(1)))
+        .filter(|&file| file < File::COUNT)?;
+    let rank = chars.next()?;
+    let rank = RANK_NAMES.iter().position(|&name| name == rank)?;
+
+    Some((
+        Square::new(File::from(file), Rank::from(rank)),
+        chars.as_str(),
+    ))
+}
+
+/// Parses a square in ASCII digits such as `77`.
+fn parse_digit_square(s: &str) -> Option<Square> {
+    let bytes = s.as_bytes();
+
+    if bytes.len() != 2 || !(b'1'..=b'9').contains(&bytes[0]) || !(b'1'..=b'9').contains(&bytes[1])
+    {
+        return None;
+    }
+
+    Some(Square::new(
+        File::from(bytes[0] - b'1'),
+        Rank::from(bytes[1] - b'1'),
+    ))
+}
+
+/// Parses the name of a piece type at the start of `s`, returning it with the rest of `s`.
+pub(crate) fn parse_piece_type(s: &str) -> Option<(PieceType, &str)> {
+    PIECE_TYPE_ALIASES
+        .iter()
+        .find_map(|&(name, piece_type)| Some((piece_type, s.strip_prefix(name)?)))
+}
+
+/// Formats a square such as `７六`.
+#[must_use]
+pub(crate) fn format_square(square: Square) -> String {
+    format!("{}{}", FILE_NAMES[square.file()], RANK_NAMES[square.rank()])
+}
+
+/// Parses a count of pieces in hand from `一` to `十八`.
+fn parse_count(s: &str) -> Option<u32> {
+    let (tens, s) = match s.strip_prefix('十') {
+        Some(s) => (10, s),
+        None => (0, s),
+    };
+
+    if s.is_empty() {
+        return (tens > 0).then_some(tens);
+    }
+
+    let ones = COUNT_NAMES.iter().position(|&name| name == s)?;
+
+    (ones > 0).then_some(tens + ones as u32)
+}
+
+fn format_count(count: u32) -> String {
+    let count = count as usize;
+
+    if count < 10 {
+        COUNT_NAMES[count].to_string()
+    } else {
+        format!("十{}", COUNT_NAMES[count - 10])
+    }
+}
+
+/// Returns the position of a handicap of the `手合割` header.
+pub(crate) fn handicap_position(name: &str) -> Option<Position> {
+    if name == "平手" {
+        return Some(Position::startpos());
+    }
+
+    let &(_, squares) = HANDICAPS.iter().find(|&&(n, _)| n == name)?;
+    let mut builder = Position::startpos().builder();
+
+    for &square in squares {
+        builder.remove(square);
+    }
+
+    builder.set_side_to_move(Color::White);
+
+    Some(builder.build())
+}
+
+/// Returns the name of the handicap whose position is `pos`, if any.
+pub(crate) fn handicap_name(pos: &Position) -> Option<&'static str> {
+    let sfen = Usi::format_position(pos);
+
+    ["平手"]
+        .into_iter()
+        .chain(HANDICAPS.iter().map(|&(name, _)| name))
+        .find(|&name| Usi::format_position(&handicap_position(name).unwrap()) == sfen)
+}
+
+/// Writes the starting position as a board diagram.
+fn format_board(result: &mut String, pos: &Position) {
+    let hand = |color: Color| {
+        let hand = pos.hand(color);
+
+        if hand.is_empty() {
+            return "なし".to_string();
+        }
+
+        PieceType::ALL[..Hand::HAND_PIECE_TYPES]
+            .iter()
+            .rev()
+            .filter(|&&piece_type| hand.count(piece_type) > 0)
+            .map(|&piece_type| {
+                let count = hand.count(piece_type);
+                let count = if count > 1 {
+                    format_count(count)
+                } else {
+                    String::new()
+                };
+
+                format!("{}{count}", PIECE_TYPE_NAMES[piece_type])
+            })
+            .collect::<Vec<_>>()
+            .join("　")
+    };
+
+    writeln!(result, "後手の持駒：{}", hand(Color::White)).unwrap();
+    writeln!(result, "  ９ ８ ７ ６ ５ ４ ３ ２ １").unwrap();
+    writeln!(result, "+---------------------------+").unwrap();
+
+    for rank in Rank::ALL {
+        result.push('|');
+
+        for &file in File::ALL.iter().rev() {
+            match pos.piece_at(Square::new(file, rank)) {
+                Some(piece) => {
+                    result.push(if piece.color() == Color::Black {
+                        ' '
+                    } else {
+                        'v'
+                    });
+                    result.push(BOARD_PIECE_NAMES[piece.piece_type()]);
+                }
+                None => result.push_str(" ・"),
+            }
+        }
+
+        writeln!(result, "|{}", RANK_NAMES[rank]).unwrap();
+    }
+
+    writeln!(result, "+---------------------------+").unwrap();
+    writeln!(result, "先手の持駒：{}", hand(Color::Black)).unwrap();
+
+    if pos.side_to_move() == Color::White {
+        writeln!(result, "後手番").unwrap();
+    }
+}
+
+/// Returns the display width of a character, 2 for full-width characters.
+pub(crate) const fn char_width(c: char) -> usize {
+    if c.is_ascii() {
+        1
+    } else {
+        2
+    }
+}
@@ -3,6 +3,14 @@ use crate::shogi::{
     position::{mv::Move, Position},
 };
 
+pub mod csa;
+pub mod game;
+pub mod hcp;
+pub mod jkf;
+pub mod ki2;
+pub mod kif;
+pub mod packed_sfen;
+pub mod record;
 pub mod usi;
 
 /// A trait for parsing and formatting textual game notations.
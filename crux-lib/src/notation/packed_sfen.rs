@@ -0,0 +1,360 @@
+use crate::{
+    notation::record::{has_standard_pieces, PackError, Record, UnpackError},
+    shogi::{
+        core::{Color, Piece, PieceType, Square},
+        position::{hand::Hand, mv::Move, Position},
+    },
+    utils::bits::{BitReader, BitWriter},
+};
+
+/// Huffman codes of the piece types on the board, indexed by the unpromoted `PieceType`,
+/// as `(code, bits)` written least significant bit first. An empty square is a single zero.
+///
+/// A piece in hand drops the leading zero of its code.
+const HUFFMAN_CODES: [(u32, u32); Hand::HAND_PIECE_TYPES] = [
+    (0b1, 2),      // Pawn
+    (0b11, 4),     // Lance
+    (0b1011, 4),   // Knight
+    (0b111, 4),    // Silver
+    (0b1111, 5),   // Gold
+    (0b11111, 6),  // Bishop
+    (0b111111, 6), // Rook
+];
+
+/// Order in which the pieces in hand are written, following the piece types of YaneuraOu.
+const HAND_ORDER: [PieceType; Hand::HAND_PIECE_TYPES] = [
+    PieceType::Pawn,
+    PieceType::Lance,
+    PieceType::Knight,
+    PieceType::Silver,
+    PieceType::Bishop,
+    PieceType::Rook,
+    PieceType::Gold,
+];
+
+/// A position compressed into 256 bits with the Huffman coding of YaneuraOu.
+///
+/// The bits, least significant first, hold the side to move, the squares of the black and
+/// the white king in 7 bits each, then every other square from 1a to 9i, and finally the
+/// pieces in hand of Black and then White.
+///
+/// Every non-king piece takes its Huffman code, a promotion bit unless it is a gold, and a
+/// color bit, so the position fills the 256 bits exactly only with the standard set of
+/// pieces and both kings on the board. The ply is not stored.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct PackedSfen(pub [u8; PackedSfen::SIZE]);
+
+impl PackedSfen {
+    /// Size of a packed position in bytes.
+    pub const SIZE: usize = 32;
+
+    /// Packs a position.
+    pub fn pack(pos: &Position) -> Result<Self, PackError> {
+        let (Some(black_king), Some(white_king)) =
+            (pos.king_square(Color::Black), pos.king_square(Color::White))
+        else {
+            return Err(PackError::MissingKing);
+        };
+
+        if !has_standard_pieces(pos) {
+            return Err(PackError::UnsupportedPieceSet);
+        }
+
+        let mut data = [0; Self::SIZE];
+        let mut writer = BitWriter::new(&mut data);
+
+        writer.write_bit(pos.side_to_move() == Color::White);
+        writer.write_bits(black_king.as_u8() as u32, 7);
+        writer.write_bits(white_king.as_u8() as u32, 7);
+
+        for square in Square::ALL {
+            match pos.piece_at(square) {
+                Some(piece) if piece.piece_type() == PieceType::King => {}
+                Some(piece) => write_piece(&mut writer, piece, false),
+                None => writer.write_bit(false),
+            }
+        }
+
+        for color in Color::ALL {
+            let hand = pos.hand(color);
+
+            for piece_type in HAND_ORDER {
+                for _ in 0..hand.count(piece_type) {
+                    write_piece(&mut writer, piece_type.with_color(color), true);
+                }
+            }
+        }
+
+        debug_assert_eq!(writer.cursor(), Self::SIZE * 8);
+
+        Ok(Self(data))
+    }
+
+    /// Unpacks the position, at the first ply.
+    pub fn unpack(&self) -> Result<Position, UnpackError> {
+        self.unpack_at(0)
+    }
+
+    fn unpack_at(&self, ply: u32) -> Result<Position, UnpackError> {
+        let mut builder = Position::empty().builder();
+        let mut reader = BitReader::new(&self.0);
+
+        builder.set_side_to_move(if read_bit(&mut reader)? {
+            Color::White
+        } else {
+            Color::Black
+        });
+
+        let mut king_squares = [Square::S11; Color::COUNT];
+
+        for color in Color::ALL {
+            let square = reader.read_bits(7).ok_or(UnpackError::UnexpectedEnd)?;
+
+            if square as usize >= Square::COUNT {
+                return Err(UnpackError::InvalidKingSquare);
+            }
+
+            king_squares[color] = Square::from(square as u8);
+        }
+
+        if king_squares[Color::Black] == king_squares[Color::White] {
+            return Err(UnpackError::InvalidKingSquare);
+        }
+
+        for square in Square::ALL {
+            if let Some(color) = Color::ALL.into_iter().find(|&c| king_squares[c] == square) {
+                builder.place(square, PieceType::King.with_color(color));
+            } else if let Some(piece) = read_piece(&mut reader, false)? {
+                builder.place(square, piece);
+            }
+        }
+
+        let mut hands = [Hand::default(); Color::COUNT];
+
+        while !reader.is_empty() {
+            let piece = read_piece(&mut reader, true)?.ok_or(UnpackError::InvalidPieceCode)?;
+            let (color, piece_type) = (piece.color(), piece.piece_type());
+
+            if hands[color].count(piece_type) == Hand::max_piece_counts(piece_type) {
+                return Err(UnpackError::InvalidPosition);
+            }
+
+            hands[color].increment(piece_type);
+            builder.increment_hand_piece_count(color, piece_type);
+        }
+
+        builder.set_ply(ply);
+
+        if !builder.verify() {
+            return Err(UnpackError::InvalidPosition);
+        }
+
+        Ok(builder.build())
+    }
+}
+
+/// A training record of YaneuraOu: a packed position with its score, best move, ply and
+/// game result, in 40 bytes.
+///
+/// The score is from the perspective of the side to move, and the result is 1 if the side
+/// to move won the game, -1 if it lost and 0 for a draw.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct PackedSfenValue {
+    pub sfen: PackedSfen,
+    pub score: i16,
+    /// The best move, or `None` if the record has no move.
+    pub mv: Option<Move>,
+    /// The ply of the position, starting from 1 as in SFEN.
+    pub game_ply: u16,
+    pub game_result: i8,
+}
+
+impl PackedSfenValue {
+    /// Size of a record in bytes.
+    pub const SIZE: usize = 40;
+
+    /// Returns the position of the record, at its ply.
+    pub fn position(&self) -> Result<Position, UnpackError> {
+        self.sfen.unpack_at(u32::from(self.game_ply.max(1)) - 1)
+    }
+
+    /// Decodes a record from its little-endian byte layout.
+    pub fn from_bytes(bytes: &[u8; Self::SIZE]) -> Result<Self, UnpackError> {
+        let field = |offset: usize| [bytes[offset], bytes[offset + 1]];
+
+        Ok(Self {
+            sfen: PackedSfen(bytes[..PackedSfen::SIZE].try_into().unwrap()),
+            score: i16::from_le_bytes(field(32)),
+            mv: from_move16(u16::from_le_bytes(field(34)))?,
+            game_ply: u16::from_le_bytes(field(36)),
+            game_result: bytes[38] as i8,
+        })
+    }
+
+    /// Encodes the record in its little-endian byte layout.
+    #[must_use]
+    pub fn to_bytes(&self) -> [u8; Self::SIZE] {
+        let mut bytes = [0; Self::SIZE];
+
+        bytes[..PackedSfen::SIZE].copy_from_slice(&self.sfen.0);
+        bytes[32..34].copy_from_slice(&self.score.to_le_bytes());
+        bytes[34..36].copy_from_slice(&self.mv.map_or(0, to_move16).to_le_bytes());
+        bytes[36..38].copy_from_slice(&self.game_ply.to_le_bytes());
+        bytes[38] = self.game_result as u8;
+
+        bytes
+    }
+}
+
+impl Record for PackedSfen {
+    const SIZE: usize = PackedSfen::SIZE;
+
+    fn decode(bytes: &[u8]) -> Result<Self, UnpackError> {
+        Ok(Self(bytes.try_into().unwrap()))
+    }
+
+    fn encode(&self, bytes: &mut [u8]) {
+        bytes.copy_from_slice(&self.0);
+    }
+}
+
+impl Record for PackedSfenValue {
+    const SIZE: usize = PackedSfenValue::SIZE;
+
+    fn decode(bytes: &[u8]) -> Result<Self, UnpackError> {
+        Self::from_bytes(bytes.try_into().unwrap())
+    }
+
+    fn encode(&self, bytes: &mut [u8]) {
+        bytes.copy_from_slice(&self.to_bytes());
+    }
+}
+
+/// Converts a move to the 16-bit move of YaneuraOu.
+///
+/// Normal moves share the layout of [`Move::as_u16`], while drops store the piece type in
+/// the numbering of YaneuraOu, from 1 for a pawn to 7 for a gold.
+#[must_use]
+pub fn to_move16(mv: Move) -> u16 {
+    if mv.is_drop() {
+        let piece_type = HAND_ORDER
+            .iter()
+            .position(|&pt| pt == mv.drop_piece_type())
+            .unwrap() as u16;
+
+        DROP_FLAG | ((piece_type + 1) << 7) | mv.to().as_u8() as u16
+    } else {
+        mv.as_u16()
+    }
+}
+
+/// Converts a 16-bit move of YaneuraOu to a move.
+///
+/// Special moves, such as the null move or resignation, have the same source and
+/// destination square and carry no move, so they return `None` like an empty move.
+pub fn from_move16(value: u16) -> Result<Option<Move>, UnpackError> {
+    let to = value & 0x7f;
+    let from = (value >> 7) & 0x7f;
+
+    if to as usize >= Square::COUNT {
+        return Err(UnpackError::InvalidMove);
+    }
+
+    let to = Square::from(to as u8);
+
+    if value & DROP_FLAG != 0 {
+        if value & PROMOTION_FLAG != 0 || !(1..=HAND_ORDER.len() as u16).contains(&from) {
+            return Err(UnpackError::InvalidMove);
+        }
+
+        return Ok(Some(Move::drop(HAND_ORDER[from as usize - 1], to)));
+    }
+
+    if from as usize >= Square::COUNT {
+        return Err(UnpackError::InvalidMove);
+    }
+
+    let from = Square::from(from as u8);
+
+    if from == to {
+        Ok(None)
+    } else if value & PROMOTION_FLAG != 0 {
+        Ok(Some(Move::promote(from, to)))
+    } else {
+        Ok(Some(Move::normal(from, to)))
+    }
+}
+
+const DROP_FLAG: u16 = 1 << 14;
+const PROMOTION_FLAG: u16 = 1 << 15;
+
+fn write_piece(writer: &mut BitWriter, piece: Piece, in_hand: bool) {
+    let piece_type = piece.piece_type().unpromoted();
+    let (code, bits) = HUFFMAN_CODES[piece_type.as_usize()];
+
+    if in_hand {
+        writer.write_bits(code >> 1, bits - 1);
+    } else {
+        writer.write_bits(code, bits);
+    }
+
+    if piece_type != PieceType::Gold {
+        writer.write_bit(piece.is_promoted());
+    }
+
+    writer.write_bit(piece.color() == Color::White);
+}
+
+/// Reads a piece, or `None` for an empty square on the board.
+fn read_piece(reader: &mut BitReader, in_hand: bool) -> Result<Option<Piece>, UnpackError> {
+    let mut code = 0;
+    let mut bits = 0;
+
+    let piece_type = loop {
+        code |= u32::from(read_bit(reader)?) << bits;
+        bits += 1;
+
+        if !in_hand && (code, bits) == (0, 1) {
+            return Ok(None);
+        }
+
+        let found = HUFFMAN_CODES.iter().position(|&(c, b)| {
+            if in_hand {
+                (c >> 1, b - 1) == (code, bits)
+            } else {
+                (c, b) == (code, bits)
+            }
+        });
+
+        if let Some(index) = found {
+            break PieceType::ALL[index];
+        }
+
+        if bits >= 6 {
+            return Err(UnpackError::InvalidPieceCode);
+        }
+    };
+
+    let promoted = piece_type != PieceType::Gold && read_bit(reader)?;
+    let color = if read_bit(reader)? {
+        Color::White
+    } else {
+        Color::Black
+    };
+
+    if promoted && in_hand {
+        return Err(UnpackError::InvalidPieceCode);
+    }
+
+    let piece_type = if promoted {
+        piece_type.promoted()
+    } else {
+        piece_type
+    };
+
+    Ok(Some(piece_type.with_color(color)))
+}
+
+fn read_bit(reader: &mut BitReader) -> Result<bool, UnpackError> {
+    reader.read_bit().ok_or(UnpackError::UnexpectedEnd)
+}
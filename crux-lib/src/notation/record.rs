@@ -0,0 +1,151 @@
+use std::{
+    fmt::{Display, Formatter, Result as FmtResult},
+    fs::File,
+    io::{self, BufReader, ErrorKind, Read},
+    marker::PhantomData,
+    path::Path,
+};
+
+use crate::shogi::{
+    core::{Color, PieceType},
+    position::{hand::Hand, Position},
+};
+
+#[derive(Debug, Copy, Clone)]
+pub enum PackError {
+    /// One of the kings is not on the board.
+    MissingKing,
+    /// The position does not hold exactly the standard set of pieces.
+    UnsupportedPieceSet,
+}
+
+#[derive(Debug, Copy, Clone)]
+pub enum UnpackError {
+    InvalidKingSquare,
+    InvalidPieceCode,
+    /// The data ends in the middle of a piece.
+    UnexpectedEnd,
+    /// The decoded pieces do not form a structurally valid position.
+    InvalidPosition,
+    InvalidMove,
+    InvalidGameResult,
+}
+
+/// A fixed-size binary record, as stored back to back in training data files.
+pub trait Record: Sized {
+    /// Size of a record in bytes.
+    const SIZE: usize;
+
+    /// Decodes a record from exactly [`Record::SIZE`] bytes.
+    fn decode(bytes: &[u8]) -> Result<Self, UnpackError>;
+
+    /// Encodes the record into exactly [`Record::SIZE`] bytes.
+    fn encode(&self, bytes: &mut [u8]);
+}
+
+/// An error returned when a record cannot be read.
+#[derive(Debug)]
+pub enum ReadError {
+    Io(io::Error),
+    /// The data ends in the middle of a record.
+    Truncated,
+    Invalid(UnpackError),
+}
+
+impl Display for ReadError {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        match self {
+            ReadError::Io(err) => write!(f, "{err}"),
+            ReadError::Truncated => write!(f, "truncated record"),
+            ReadError::Invalid(err) => write!(f, "invalid record: {err:?}"),
+        }
+    }
+}
+
+impl From<io::Error> for ReadError {
+    fn from(err: io::Error) -> Self {
+        ReadError::Io(err)
+    }
+}
+
+/// Reads records one at a time from a file of records stored back to back, such as the
+/// multi-gigabyte datasets used for training, without loading the file into memory.
+pub struct RecordReader<T, R = BufReader<File>> {
+    reader: R,
+    buffer: Vec<u8>,
+    record: PhantomData<T>,
+}
+
+impl<T: Record> RecordReader<T> {
+    const BUFFER_SIZE: usize = 1 << 20;
+
+    /// Opens a file of records.
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        let file = File::open(path)?;
+
+        Ok(Self::new(BufReader::with_capacity(Self::BUFFER_SIZE, file)))
+    }
+
+    /// Returns the number of records in a file of records.
+    pub fn count(path: impl AsRef<Path>) -> io::Result<u64> {
+        Ok(path.as_ref().metadata()?.len() / T::SIZE as u64)
+    }
+}
+
+impl<T: Record, R: Read> RecordReader<T, R> {
+    /// Creates a reader of the records of `reader`.
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            buffer: vec![0; T::SIZE],
+            record: PhantomData,
+        }
+    }
+
+    /// Reads the next record, or returns `None` at the end of the data.
+    pub fn read_record(&mut self) -> Option<Result<T, ReadError>> {
+        let mut filled = 0;
+
+        while filled < T::SIZE {
+            match self.reader.read(&mut self.buffer[filled..]) {
+                Ok(0) => break,
+                Ok(n) => filled += n,
+                Err(err) if err.kind() == ErrorKind::Interrupted => {}
+                Err(err) => return Some(Err(err.into())),
+            }
+        }
+
+        match filled {
+            0 => None,
+            n if n < T::SIZE => Some(Err(ReadError::Truncated)),
+            _ => Some(T::decode(&self.buffer).map_err(ReadError::Invalid)),
+        }
+    }
+}
+
+impl<T: Record, R: Read> Iterator for RecordReader<T, R> {
+    type Item = Result<T, ReadError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.read_record()
+    }
+}
+
+/// Returns `true` if every non-king piece of the standard set is on the board or in a hand,
+/// which the Huffman-coded formats require to fill their 256 bits.
+pub(crate) fn has_standard_pieces(pos: &Position) -> bool {
+    PieceType::ALL
+        .iter()
+        .take(Hand::HAND_PIECE_TYPES)
+        .all(|&piece_type| {
+            let mut total = pos.piece_type_bb(piece_type).count_ones()
+                + pos.hand(Color::Black).count(piece_type)
+                + pos.hand(Color::White).count(piece_type);
+
+            if piece_type.can_promote() {
+                total += pos.piece_type_bb(piece_type.promoted()).count_ones();
+            }
+
+            total == Hand::max_piece_counts(piece_type)
+        })
+}
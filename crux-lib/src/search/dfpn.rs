@@ -0,0 +1,598 @@
+use std::{
+    sync::{atomic::Ordering, Arc},
+    time::Instant,
+};
+
+use crate::{
+    search::{Limits, Signals},
+    shogi::{
+        core::Color,
+        mate::mate1ply,
+        movegen::{generate_staged, is_legal, GenType},
+        position::{hand::Hand, key::Key, mv::Move, Position, Repetition},
+    },
+};
+
+/// Proof and disproof number of a solved node.
+const INFINITE: u32 = u32::MAX / 4;
+
+/// Maximum number of plies from the root, beyond which a node is treated as not mated.
+const MAX_MATE_PLY: usize = 1024;
+
+/// The path ply of a result that holds whatever the path to the node.
+const NO_PLY: u32 = u32::MAX;
+
+/// Number of entries in a bucket of the proof table.
+const BUCKET_SIZE: usize = 4;
+
+/// The outcome of [`DfpnSolver::solve`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MateResult {
+    /// The side to move mates with the given sequence of checks and evasions.
+    Mate(Vec<Move>),
+    /// The side to move has no forced mate.
+    NoMate,
+    /// The limits were reached or the solver was stopped before the result was known.
+    Timeout,
+}
+
+/// Proof number, disproof number and the number of plies to mate of a node.
+#[derive(Debug, Copy, Clone)]
+struct Numbers {
+    pn: u32,
+    dn: u32,
+    len: u32,
+    /// For a disproof, the shallowest ply of the path from the root that it relies on, by
+    /// repeating the position at that ply or by reaching the ply limit, or [`NO_PLY`].
+    ///
+    /// A disproof relying on a ply before the node's own may be wrong when the position is
+    /// reached by another path.
+    path_ply: u32,
+}
+
+impl Numbers {
+    const UNKNOWN: Self = Self {
+        pn: 1,
+        dn: 1,
+        len: 0,
+        path_ply: NO_PLY,
+    };
+
+    const NO_MATE: Self = Self {
+        pn: INFINITE,
+        dn: 0,
+        len: 0,
+        path_ply: NO_PLY,
+    };
+
+    /// A node disproven by repeating the position at `path_ply` or by the ply limit.
+    const fn path_no_mate(path_ply: u32) -> Self {
+        Self {
+            path_ply,
+            ..Self::NO_MATE
+        }
+    }
+
+    const fn mate(len: u32) -> Self {
+        Self {
+            pn: 0,
+            dn: INFINITE,
+            len,
+            path_ply: NO_PLY,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, Default)]
+struct Entry {
+    board_key: u64,
+    /// Combined keys of the positions before the node that a disproof relies on.
+    ancestors_key: u64,
+    /// Hand of the attacker.
+    hand: Hand,
+    pn: u32,
+    dn: u32,
+    len: u32,
+    /// Number of nodes spent on the entry, zero if the entry is empty.
+    work: u32,
+    /// Number of positions before the node that a disproof relies on, zero if it holds
+    /// whatever the path to the node.
+    ancestors: u32,
+}
+
+/// The proof and disproof numbers of positions seen by the solver.
+///
+/// Positions are keyed by their board and the hand of the attacker, so that a position
+/// proven with fewer pieces in hand proves the same board with more, and a position
+/// disproven with more pieces in hand disproves the same board with fewer.
+///
+/// A disproof relying on the path to the node is only reused when the same positions
+/// precede the node, back to the earliest position it relies on.
+struct ProofTable {
+    buckets: Box<[[Entry; BUCKET_SIZE]]>,
+}
+
+impl ProofTable {
+    fn new(size_mb: usize) -> Self {
+        let len = (size_mb * 1024 * 1024 / size_of::<[Entry; BUCKET_SIZE]>()).max(1);
+
+        Self {
+            buckets: vec![[Entry::default(); BUCKET_SIZE]; len].into_boxed_slice(),
+        }
+    }
+
+    fn clear(&mut self) {
+        self.buckets.fill([Entry::default(); BUCKET_SIZE]);
+    }
+
+    /// Returns the numbers of the node preceded by the positions of `path`.
+    fn probe(&self, board_key: Key, hand: Hand, path: &[PathNode]) -> Numbers {
+        for entry in self.bucket(board_key) {
+            if entry.work == 0 || entry.board_key != board_key.value() {
+                continue;
+            }
+
+            let ancestors = entry.ancestors as usize;
+
+            if ancestors > path.len() || ancestors_key(path, ancestors) != entry.ancestors_key {
+                continue;
+            }
+
+            let numbers = Numbers {
+                pn: entry.pn,
+                dn: entry.dn,
+                len: entry.len,
+                path_ply: if ancestors == 0 {
+                    NO_PLY
+                } else {
+                    (path.len() - ancestors) as u32
+                },
+            };
+
+            if entry.hand == hand
+                || (entry.pn == 0 && hand.dominates(entry.hand))
+                || (entry.dn == 0 && entry.hand.dominates(hand))
+            {
+                return numbers;
+            }
+        }
+
+        Numbers::UNKNOWN
+    }
+
+    /// Stores the numbers of the node preceded by the positions of `path`.
+    fn store(
+        &mut self,
+        board_key: Key,
+        hand: Hand,
+        path: &[PathNode],
+        numbers: Numbers,
+        work: u64,
+    ) {
+        let ancestors = if numbers.dn == 0 {
+            path.len().saturating_sub(numbers.path_ply as usize)
+        } else {
+            0
+        };
+        let ancestors_key = ancestors_key(path, ancestors);
+        let bucket = self.bucket_mut(board_key);
+        let index = bucket
+            .iter()
+            .position(|entry| {
+                entry.work == 0
+                    || (entry.board_key == board_key.value()
+                        && entry.hand == hand
+                        && entry.ancestors as usize == ancestors
+                        && entry.ancestors_key == ancestors_key)
+            })
+            .unwrap_or_else(|| {
+                (0..BUCKET_SIZE)
+                    .min_by_key(|&index| bucket[index].work)
+                    .unwrap()
+            });
+
+        bucket[index] = Entry {
+            board_key: board_key.value(),
+            ancestors_key,
+            hand,
+            pn: numbers.pn,
+            dn: numbers.dn,
+            len: numbers.len,
+            work: work.clamp(1, u64::from(u32::MAX)) as u32,
+            ancestors: ancestors as u32,
+        };
+    }
+
+    fn index(&self, board_key: Key) -> usize {
+        ((u128::from(board_key.value()) * self.buckets.len() as u128) >> 64) as usize
+    }
+
+    fn bucket(&self, board_key: Key) -> &[Entry; BUCKET_SIZE] {
+        &self.buckets[self.index(board_key)]
+    }
+
+    fn bucket_mut(&mut self, board_key: Key) -> &mut [Entry; BUCKET_SIZE] {
+        let index = self.index(board_key);
+
+        &mut self.buckets[index]
+    }
+}
+
+/// A move from a node being expanded, with the numbers of the position it leads to.
+struct Child {
+    mv: Move,
+    numbers: Numbers,
+}
+
+/// A position on the path from the root to the node being searched.
+struct PathNode {
+    key: Key,
+    /// Combined keys of the positions from the root to this one.
+    path_key: u64,
+}
+
+/// A depth-first proof-number (df-pn) solver for forced mates by consecutive checks.
+///
+/// The attacker only plays checks, and the defender plays every legal evasion.
+/// A position is mated when the defender has no legal move, so mates by a pawn drop
+/// (uchifuzume) are never considered, and repetitions are treated as not mated.
+/// A disproof relying on a repetition of an earlier position of the path, or on the ply
+/// limit, is only reused when the node is reached after the same positions, since it may be
+/// mated when reached by another path.
+pub struct DfpnSolver {
+    signals: Arc<Signals>,
+    table: ProofTable,
+    limits: Limits,
+    start: Instant,
+    stopped: bool,
+    nodes: u64,
+    attacker: Color,
+    path: Vec<PathNode>,
+}
+
+impl DfpnSolver {
+    /// The default size of the proof table in megabytes.
+    pub const DEFAULT_SIZE_MB: usize = 16;
+
+    /// Creates a solver controlled by the given signals with a proof table of `size_mb`
+    /// megabytes.
+    #[must_use]
+    pub fn new(signals: Arc<Signals>, size_mb: usize) -> Self {
+        Self {
+            signals,
+            table: ProofTable::new(size_mb),
+            limits: Limits::default(),
+            start: Instant::now(),
+            stopped: false,
+            nodes: 0,
+            attacker: Color::Black,
+            path: Vec::new(),
+        }
+    }
+
+    /// Returns the number of nodes searched by the last call to [`DfpnSolver::solve`].
+    #[must_use]
+    pub fn nodes(&self) -> u64 {
+        self.nodes
+    }
+
+    /// Searches for a forced mate by the side to move.
+    ///
+    /// Only `movetime` and `nodes` of `limits` are taken into account.
+    pub fn solve(&mut self, pos: &Position, limits: &Limits) -> MateResult {
+        let mut pos = pos.clone();
+
+        self.table.clear();
+        self.limits = limits.clone();
+        self.start = Instant::now();
+        self.stopped = false;
+        self.nodes = 0;
+        self.attacker = pos.side_to_move();
+        self.path.clear();
+        self.push_path(&pos);
+
+        let numbers = self.mid(&mut pos, INFINITE - 1, INFINITE - 1, 0);
+
+        if numbers.pn == 0 {
+            self.pv(&mut pos)
+                .map_or(MateResult::Timeout, MateResult::Mate)
+        } else if numbers.dn == 0 {
+            MateResult::NoMate
+        } else {
+            MateResult::Timeout
+        }
+    }
+
+    /// Expands the node until its proof number reaches `pn_threshold` or its disproof
+    /// number reaches `dn_threshold`.
+    fn mid(
+        &mut self,
+        pos: &mut Position,
+        pn_threshold: u32,
+        dn_threshold: u32,
+        ply: usize,
+    ) -> Numbers {
+        let or_node = pos.side_to_move() == self.attacker;
+        let board_key = pos.board_key();
+        let hand = pos.hand(self.attacker);
+        let start_nodes = self.nodes;
+
+        self.nodes += 1;
+
+        if or_node && mate1ply(pos).is_some() {
+            let numbers = Numbers::mate(1);
+
+            self.table
+                .store(board_key, hand, &self.path[..ply], numbers, 1);
+
+            return numbers;
+        }
+
+        let mut children = self.expand(pos, or_node);
+
+        if children.is_empty() {
+            // The attacker has no check, or the defender has no evasion.
+            let numbers = if or_node {
+                Numbers::NO_MATE
+            } else {
+                Numbers::mate(0)
+            };
+
+            self.table
+                .store(board_key, hand, &self.path[..ply], numbers, 1);
+
+            return numbers;
+        }
+
+        loop {
+            let numbers = combine(&children, or_node);
+
+            if numbers.pn >= pn_threshold || numbers.dn >= dn_threshold || self.check_stop() {
+                if !self.stopped || numbers.pn == 0 || numbers.dn == 0 {
+                    self.table.store(
+                        board_key,
+                        hand,
+                        &self.path[..ply],
+                        numbers,
+                        self.nodes - start_nodes,
+                    );
+                }
+
+                return numbers;
+            }
+
+            // The most promising child and the threshold at which another child takes over.
+            let (best, second) = if or_node {
+                best_two(&children, |numbers| numbers.pn)
+            } else {
+                best_two(&children, |numbers| numbers.dn)
+            };
+
+            let child = &children[best];
+            let (child_pn_threshold, child_dn_threshold) = if or_node {
+                (
+                    pn_threshold.min(second.saturating_add(1)),
+                    (dn_threshold - numbers.dn + child.numbers.dn).min(INFINITE - 1),
+                )
+            } else {
+                (
+                    (pn_threshold - numbers.pn + child.numbers.pn).min(INFINITE - 1),
+                    dn_threshold.min(second.saturating_add(1)),
+                )
+            };
+
+            let mv = child.mv;
+            let captured = pos.make_move(mv);
+
+            children[best].numbers = if ply + 1 >= MAX_MATE_PLY {
+                // A shorter path to the position would search it deeper.
+                Numbers::path_no_mate(0)
+            } else {
+                self.push_path(pos);
+
+                let numbers = self.mid(pos, child_pn_threshold, child_dn_threshold, ply + 1);
+
+                self.path.pop();
+                numbers
+            };
+
+            pos.unmake_move(mv, captured);
+        }
+    }
+
+    /// Returns the checks of the attacker or the evasions of the defender.
+    fn expand(&self, pos: &mut Position, or_node: bool) -> Vec<Child> {
+        let moves = if or_node {
+            generate_staged(pos, GenType::Checks)
+        } else {
+            generate_staged(pos, GenType::Evasions)
+        };
+
+        let mut children = Vec::with_capacity(moves.len());
+
+        for mv in moves {
+            if !is_legal(pos, mv) {
+                continue;
+            }
+
+            let captured = pos.make_move(mv);
+            let numbers = if is_repetition(pos) {
+                Numbers::path_no_mate(self.repeated_ply(pos))
+            } else {
+                self.table
+                    .probe(pos.board_key(), pos.hand(self.attacker), &self.path)
+            };
+
+            pos.unmake_move(mv, captured);
+
+            children.push(Child { mv, numbers });
+        }
+
+        children
+    }
+
+    /// Follows the proven moves from the root, choosing the shortest mate for the attacker
+    /// and the longest resistance for the defender.
+    ///
+    /// Nodes whose proof has been overwritten in the table are solved again. Returns `None`
+    /// if the mate cannot be followed to the end.
+    fn pv(&mut self, pos: &mut Position) -> Option<Vec<Move>> {
+        let mut pv = Vec::new();
+
+        while pv.len() < MAX_MATE_PLY {
+            let or_node = pos.side_to_move() == self.attacker;
+
+            if or_node && let Some(mv) = mate1ply(pos) {
+                pv.push(mv);
+                return Some(pv);
+            }
+
+            if !or_node && pos.legal_moves().is_empty() {
+                return Some(pv);
+            }
+
+            let best = match self.best_proven_child(pos, or_node) {
+                Some(mv) => mv,
+                None => {
+                    self.mid(pos, INFINITE - 1, INFINITE - 1, pv.len());
+                    self.best_proven_child(pos, or_node)?
+                }
+            };
+
+            pos.make_move(best);
+            pv.push(best);
+            self.push_path(pos);
+        }
+
+        None
+    }
+
+    fn best_proven_child(&self, pos: &mut Position, or_node: bool) -> Option<Move> {
+        let proven = self
+            .expand(pos, or_node)
+            .into_iter()
+            .filter(|child| child.numbers.pn == 0);
+
+        let best = if or_node {
+            proven.min_by_key(|child| child.numbers.len)
+        } else {
+            proven.max_by_key(|child| child.numbers.len)
+        };
+
+        best.map(|child| child.mv)
+    }
+
+    /// Adds `pos`, reached from the node being searched, to the path.
+    fn push_path(&mut self, pos: &Position) {
+        let parent = self.path.last().map_or(0, |node| node.path_key);
+
+        self.path.push(PathNode {
+            key: pos.key(),
+            path_key: parent ^ pos.key().value(),
+        });
+    }
+
+    /// Returns the ply of the path that `pos` repeats, or [`NO_PLY`] if it repeats a
+    /// position played before the root, which every path shares.
+    fn repeated_ply(&self, pos: &Position) -> u32 {
+        self.path
+            .iter()
+            .rposition(|node| node.key == pos.key())
+            .map_or(NO_PLY, |ply| ply as u32)
+    }
+
+    fn check_stop(&mut self) -> bool {
+        if !self.stopped {
+            self.stopped = self.signals.stop.load(Ordering::Relaxed)
+                || self.limits.nodes.is_some_and(|nodes| self.nodes >= nodes)
+                || (self.nodes.is_multiple_of(1024)
+                    && self
+                        .limits
+                        .movetime
+                        .is_some_and(|movetime| self.start.elapsed() >= movetime));
+        }
+
+        self.stopped
+    }
+}
+
+/// Returns the combined keys of the last `count` positions of `path`.
+fn ancestors_key(path: &[PathNode], count: usize) -> u64 {
+    let end = path.last().map_or(0, |node| node.path_key);
+    let start = path
+        .len()
+        .checked_sub(count + 1)
+        .map_or(0, |index| path[index].path_key);
+
+    end ^ start
+}
+
+/// Returns the numbers of a node from the numbers of its children.
+fn combine(children: &[Child], or_node: bool) -> Numbers {
+    let min_pn = children.iter().map(|child| child.numbers.pn).min().unwrap();
+    let min_dn = children.iter().map(|child| child.numbers.dn).min().unwrap();
+    let sum_pn = children
+        .iter()
+        .fold(0, |sum: u32, child| sum.saturating_add(child.numbers.pn))
+        .min(INFINITE);
+    let sum_dn = children
+        .iter()
+        .fold(0, |sum: u32, child| sum.saturating_add(child.numbers.dn))
+        .min(INFINITE);
+
+    let proven = children.iter().filter(|child| child.numbers.pn == 0);
+    let disproven = children
+        .iter()
+        .filter(|child| child.numbers.dn == 0)
+        .map(|child| child.numbers.path_ply);
+
+    if or_node {
+        Numbers {
+            pn: min_pn,
+            dn: sum_dn,
+            len: proven.map(|child| child.numbers.len + 1).min().unwrap_or(0),
+            // Every check is refuted, relying on the paths of all of them.
+            path_ply: if sum_dn == 0 {
+                disproven.min().unwrap()
+            } else {
+                NO_PLY
+            },
+        }
+    } else {
+        Numbers {
+            pn: sum_pn,
+            dn: min_dn,
+            len: proven.map(|child| child.numbers.len + 1).max().unwrap_or(0),
+            // One refuting evasion is enough, so the one relying on the least is chosen.
+            path_ply: disproven.max().unwrap_or(NO_PLY),
+        }
+    }
+}
+
+/// Returns the index of the child with the smallest value and the second smallest value.
+fn best_two(children: &[Child], value: impl Fn(Numbers) -> u32) -> (usize, u32) {
+    let mut best = 0;
+    let mut best_value = INFINITE;
+    let mut second_value = INFINITE;
+
+    for (index, child) in children.iter().enumerate() {
+        let value = value(child.numbers);
+
+        if value < best_value {
+            second_value = best_value;
+            best_value = value;
+            best = index;
+        } else if value < second_value {
+            second_value = value;
+        }
+    }
+
+    (best, second_value)
+}
+
+fn is_repetition(pos: &Position) -> bool {
+    matches!(
+        pos.repetition(1),
+        Some(Repetition::Draw | Repetition::Win | Repetition::Loss)
+    )
+}
@@ -0,0 +1,902 @@
+pub mod dfpn;
+mod ordering;
+pub mod score;
+pub mod time;
+pub mod tt;
+
+use std::{
+    mem,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use arrayvec::ArrayVec;
+
+use crate::{
+    eval::{
+        classical,
+        nnue::{accumulator::AccumulatorStack, network::Network},
+        EvalType,
+    },
+    search::{
+        ordering::{is_capture, moved_piece, History, OrderedMoves, HISTORY_MAX},
+        score::{
+            is_mate_score, mate_in, mated_in, score_from_tt, score_to_tt, SCORE_DRAW,
+            SCORE_INFINITE, SCORE_MATE_IN_MAX_PLY,
+        },
+        time::{TimeManager, TimeOptions},
+        tt::{Bound, TranspositionTable},
+    },
+    shogi::{
+        core::{Color, Piece, Square},
+        entering_king::{can_declare_win, EnteringKingRule},
+        mate::mate1ply,
+        movegen::{generate, generate_staged, is_legal, is_pseudo_legal, GenType},
+        position::{mv::Move, Position, Repetition},
+    },
+};
+
+/// Maximum search ply from the root.
+pub const MAX_PLY: usize = 128;
+
+/// Stack size of a search thread, large enough for the deepest search.
+pub const SEARCH_STACK_SIZE: usize = 64 * 1024 * 1024;
+
+const ASPIRATION_DELTA: i32 = 30;
+const RFP_MAX_DEPTH: i32 = 6;
+const RFP_MARGIN: i32 = 90;
+const LMR_MIN_DEPTH: i32 = 3;
+const LMR_MIN_MOVES: usize = 4;
+
+type PvLine = ArrayVec<Move, MAX_PLY>;
+
+/// Limits of a single search, as given by the USI `go` command.
+#[derive(Debug, Clone, Default)]
+pub struct Limits {
+    /// Remaining time of each side.
+    pub time: [Duration; Color::COUNT],
+    /// Time added to each side after every move.
+    pub increment: [Duration; Color::COUNT],
+    /// Byoyomi time available for every move once `time` runs out.
+    pub byoyomi: Duration,
+    /// Exact time to search for.
+    pub movetime: Option<Duration>,
+    /// Maximum iterative deepening depth.
+    pub depth: Option<i32>,
+    /// Maximum number of nodes to search.
+    pub nodes: Option<u64>,
+    /// Searches until stopped, ignoring the clock.
+    pub infinite: bool,
+}
+
+impl Limits {
+    /// Returns `true` if any clock parameter is specified.
+    #[must_use]
+    pub fn has_clock(&self) -> bool {
+        self.time
+            .iter()
+            .chain(&self.increment)
+            .any(|time| !time.is_zero())
+            || !self.byoyomi.is_zero()
+    }
+}
+
+/// Signals shared between a running search and its controller.
+#[derive(Debug, Default)]
+pub struct Signals {
+    /// Requests the search to stop as soon as possible.
+    pub stop: AtomicBool,
+    /// Set while the search is pondering; clearing it means `ponderhit`.
+    pub ponder: AtomicBool,
+}
+
+/// Information about a completed iteration, reported to the caller.
+#[derive(Debug, Clone)]
+pub struct Info<'a> {
+    pub depth: i32,
+    pub seldepth: usize,
+    pub score: i32,
+    pub nodes: u64,
+    pub elapsed: Duration,
+    /// Occupancy of the transposition table in permille.
+    pub hashfull: usize,
+    pub pv: &'a [Move],
+}
+
+/// The outcome of a search.
+#[derive(Debug, Clone, Default)]
+pub struct SearchResult {
+    /// The best move, or `None` if the side to move has no legal moves.
+    pub best_move: Option<Move>,
+    /// The expected reply to the best move, if known.
+    pub ponder_move: Option<Move>,
+    pub score: i32,
+    pub depth: i32,
+    pub nodes: u64,
+    pub pv: Vec<Move>,
+}
+
+/// An alpha-beta searcher.
+///
+/// Uses principal variation search with iterative deepening,
+/// aspiration windows and a quiescence search on captures.
+///
+/// With more than one thread, the search runs as Lazy SMP: helper threads search the same
+/// position independently and only share the transposition table. The searcher itself acts
+/// as the main thread, which alone manages the limits, reports and decides the result.
+pub struct Searcher {
+    signals: Arc<Signals>,
+    tt: Arc<TranspositionTable>,
+    eval_type: EvalType,
+    network: Arc<Network>,
+    accumulators: AccumulatorStack,
+    /// Index of the thread, `0` for the main thread.
+    thread_id: usize,
+    helpers: Vec<Searcher>,
+    /// Node counters of the helper threads, readable while they are searching.
+    helper_nodes: Vec<Arc<AtomicU64>>,
+    /// Stops the helper threads once the main thread is done.
+    helpers_stop: Arc<AtomicBool>,
+    /// Node count of this thread, published periodically for the main thread.
+    node_counter: Arc<AtomicU64>,
+    entering_king_rule: EnteringKingRule,
+    limits: Limits,
+    start: Instant,
+    time_options: TimeOptions,
+    time: TimeManager,
+    best_move_changes: f64,
+    pondering: bool,
+    stopped: bool,
+    nodes: u64,
+    seldepth: usize,
+    root_best: Option<Move>,
+    pv: Box<[PvLine; MAX_PLY + 1]>,
+    killers: Box<[[Option<Move>; 2]; MAX_PLY + 1]>,
+    history: Box<History>,
+}
+
+impl Searcher {
+    /// Creates a searcher controlled by the given signals and sharing the given table.
+    #[must_use]
+    pub fn new(signals: Arc<Signals>, tt: Arc<TranspositionTable>) -> Self {
+        Self {
+            signals,
+            tt,
+            eval_type: EvalType::default(),
+            network: Network::default_network(),
+            accumulators: AccumulatorStack::new(),
+            thread_id: 0,
+            helpers: Vec::new(),
+            helper_nodes: Vec::new(),
+            helpers_stop: Arc::default(),
+            node_counter: Arc::default(),
+            entering_king_rule: EnteringKingRule::default(),
+            limits: Limits::default(),
+            start: Instant::now(),
+            time_options: TimeOptions::default(),
+            time: TimeManager::default(),
+            best_move_changes: 0.0,
+            pondering: false,
+            stopped: false,
+            nodes: 0,
+            seldepth: 0,
+            root_best: None,
+            pv: Box::new([const { PvLine::new_const() }; MAX_PLY + 1]),
+            killers: Box::new([[None; 2]; MAX_PLY + 1]),
+            history: Box::new([[0; Square::COUNT]; Piece::COUNT]),
+        }
+    }
+
+    /// Returns the signals controlling this searcher.
+    #[must_use]
+    pub fn signals(&self) -> &Arc<Signals> {
+        &self.signals
+    }
+
+    /// Returns the transposition table used by this searcher.
+    #[must_use]
+    pub fn tt(&self) -> &Arc<TranspositionTable> {
+        &self.tt
+    }
+
+    /// Replaces the transposition table used by this searcher.
+    pub fn set_tt(&mut self, tt: Arc<TranspositionTable>) {
+        for helper in &mut self.helpers {
+            helper.tt = Arc::clone(&tt);
+        }
+
+        self.tt = tt;
+    }
+
+    /// Sets the evaluation function.
+    pub fn set_eval_type(&mut self, eval_type: EvalType) {
+        for helper in &mut self.helpers {
+            helper.eval_type = eval_type;
+        }
+
+        self.eval_type = eval_type;
+    }
+
+    /// Returns the network used for evaluation.
+    #[must_use]
+    pub fn network(&self) -> &Arc<Network> {
+        &self.network
+    }
+
+    /// Replaces the network used for evaluation.
+    pub fn set_network(&mut self, network: Arc<Network>) {
+        for helper in &mut self.helpers {
+            helper.network = Arc::clone(&network);
+        }
+
+        self.network = network;
+    }
+
+    /// Returns the number of threads used by a search, including the main thread.
+    #[must_use]
+    pub fn threads(&self) -> usize {
+        self.helpers.len() + 1
+    }
+
+    /// Sets the number of threads used by a search, including the main thread.
+    ///
+    /// Helper threads are created with empty histories.
+    pub fn set_threads(&mut self, threads: usize) {
+        let helpers = threads.max(1) - 1;
+
+        self.helpers.truncate(helpers);
+
+        while self.helpers.len() < helpers {
+            let mut helper = Searcher::new(Arc::clone(&self.signals), Arc::clone(&self.tt));
+
+            helper.eval_type = self.eval_type;
+            helper.network = Arc::clone(&self.network);
+            helper.thread_id = self.helpers.len() + 1;
+            helper.helpers_stop = Arc::clone(&self.helpers_stop);
+            helper.entering_king_rule = self.entering_king_rule;
+
+            self.helpers.push(helper);
+        }
+
+        self.helper_nodes = self
+            .helpers
+            .iter()
+            .map(|helper| Arc::clone(&helper.node_counter))
+            .collect();
+    }
+
+    /// Sets the rule under which positions are won by entering king.
+    pub fn set_entering_king_rule(&mut self, rule: EnteringKingRule) {
+        for helper in &mut self.helpers {
+            helper.entering_king_rule = rule;
+        }
+
+        self.entering_king_rule = rule;
+    }
+
+    /// Sets the options of the time management.
+    pub fn set_time_options(&mut self, options: TimeOptions) {
+        self.time_options = options;
+    }
+
+    /// Clears all state learned from previous searches, including the transposition table.
+    pub fn clear(&mut self) {
+        self.tt.clear();
+
+        for helper in &mut self.helpers {
+            helper.clear_history();
+        }
+
+        self.clear_history();
+    }
+
+    /// Searches the position within the given limits.
+    ///
+    /// `report` is called after every completed iteration of the main thread, with the nodes
+    /// of all threads.
+    pub fn search<F: FnMut(&Info)>(
+        &mut self,
+        pos: &Position,
+        limits: &Limits,
+        report: F,
+    ) -> SearchResult {
+        self.tt.new_search();
+        self.helpers_stop.store(false, Ordering::Relaxed);
+
+        let mut helpers = mem::take(&mut self.helpers);
+
+        let mut result = thread::scope(|scope| {
+            for helper in &mut helpers {
+                thread::Builder::new()
+                    .stack_size(SEARCH_STACK_SIZE)
+                    .spawn_scoped(scope, move || helper.iterate(pos, limits, |_| {}))
+                    .unwrap();
+            }
+
+            let result = self.iterate(pos, limits, report);
+
+            self.helpers_stop.store(true, Ordering::Relaxed);
+            result
+        });
+
+        self.helpers = helpers;
+
+        result.nodes = self.total_nodes();
+        result
+    }
+
+    /// Runs the iterative deepening loop of a single thread.
+    fn iterate<F: FnMut(&Info)>(
+        &mut self,
+        pos: &Position,
+        limits: &Limits,
+        mut report: F,
+    ) -> SearchResult {
+        let mut pos = pos.clone();
+
+        self.limits = limits.clone();
+        self.start = Instant::now();
+        self.pondering = self.signals.ponder.load(Ordering::Relaxed);
+        self.stopped = false;
+        self.nodes = 0;
+        self.node_counter.store(0, Ordering::Relaxed);
+        self.root_best = None;
+        self.killers.fill([None; 2]);
+        self.accumulators.reset();
+        self.time = TimeManager::new(limits, pos.side_to_move(), &self.time_options);
+        self.best_move_changes = 0.0;
+
+        let mut result = SearchResult {
+            score: -SCORE_INFINITE,
+            ..SearchResult::default()
+        };
+
+        let max_depth = limits
+            .depth
+            .unwrap_or(MAX_PLY as i32 - 1)
+            .clamp(1, MAX_PLY as i32 - 1);
+
+        for depth in 1..=max_depth {
+            self.seldepth = 0;
+
+            let mut delta = ASPIRATION_DELTA;
+            let (mut alpha, mut beta) = if depth >= 4 {
+                (
+                    (result.score - delta).max(-SCORE_INFINITE),
+                    (result.score + delta).min(SCORE_INFINITE),
+                )
+            } else {
+                (-SCORE_INFINITE, SCORE_INFINITE)
+            };
+
+            let score = loop {
+                let score = self.pvs::<true>(&mut pos, depth, alpha, beta, 0);
+
+                if self.stopped {
+                    break score;
+                }
+
+                if score <= alpha {
+                    beta = (alpha + beta) / 2;
+                    alpha = (score - delta).max(-SCORE_INFINITE);
+                } else if score >= beta {
+                    beta = (score + delta).min(SCORE_INFINITE);
+                } else {
+                    break score;
+                }
+
+                delta += delta / 2;
+            };
+
+            // The result of an interrupted iteration is unreliable.
+            if self.stopped && result.best_move.is_some() {
+                break;
+            }
+
+            let pv = &self.pv[0];
+
+            if pv.is_empty() {
+                break;
+            }
+
+            // Older changes count less, so that only a recent instability extends the time.
+            self.best_move_changes /= 2.0;
+
+            if self.root_best.is_some_and(|best| best != pv[0]) {
+                self.best_move_changes += 1.0;
+            }
+
+            self.root_best = Some(pv[0]);
+
+            result.best_move = Some(pv[0]);
+            result.ponder_move = pv.get(1).copied();
+            result.score = score;
+            result.depth = depth;
+            result.pv = pv.to_vec();
+
+            report(&Info {
+                depth,
+                seldepth: self.seldepth,
+                score,
+                nodes: self.total_nodes(),
+                elapsed: self.start.elapsed(),
+                hashfull: self.tt.hashfull(),
+                pv,
+            });
+
+            if self.stopped || (self.is_main() && self.should_finish(score, depth)) {
+                break;
+            }
+        }
+
+        if result.best_move.is_none() {
+            // Stopped before the first iteration completed.
+            result.best_move = pos.legal_moves().first().copied();
+        }
+
+        self.node_counter.store(self.nodes, Ordering::Relaxed);
+
+        result.nodes = self.nodes;
+        result
+    }
+
+    fn pvs<const PV: bool>(
+        &mut self,
+        pos: &mut Position,
+        depth: i32,
+        mut alpha: i32,
+        mut beta: i32,
+        ply: usize,
+    ) -> i32 {
+        let root = ply == 0;
+
+        if PV {
+            self.pv[ply].clear();
+        }
+
+        if depth <= 0 {
+            return self.qsearch::<PV>(pos, alpha, beta, ply);
+        }
+
+        if self.check_stop() {
+            return 0;
+        }
+
+        self.nodes += 1;
+        self.seldepth = self.seldepth.max(ply);
+
+        if !root {
+            match pos.repetition(1) {
+                Some(Repetition::Draw) => return SCORE_DRAW,
+                Some(Repetition::Win) => return mate_in(ply),
+                Some(Repetition::Loss) => return mated_in(ply),
+                _ => {}
+            }
+
+            if can_declare_win(pos, self.entering_king_rule) {
+                return mate_in(ply);
+            }
+
+            if ply >= MAX_PLY {
+                return self.evaluate(pos);
+            }
+
+            // Mate distance pruning.
+            alpha = alpha.max(mated_in(ply));
+            beta = beta.min(mate_in(ply + 1));
+
+            if alpha >= beta {
+                return alpha;
+            }
+        }
+
+        let key = pos.key();
+        let tt_entry = self.tt.probe(key);
+        let tt_move = tt_entry
+            .and_then(|entry| entry.mv)
+            .filter(|&mv| is_pseudo_legal(pos, mv));
+
+        if !PV && let Some(entry) = tt_entry {
+            let score = score_from_tt(entry.score, ply);
+
+            if entry.depth >= depth && cuts_off(entry.bound, score, beta) {
+                return score;
+            }
+        }
+
+        let in_check = pos.checkers().has_any();
+
+        // Mates in one are detected without searching the moves.
+        if !PV && !in_check && mate1ply(pos).is_some() {
+            return mate_in(ply + 1);
+        }
+
+        let static_eval = if in_check {
+            -SCORE_INFINITE
+        } else {
+            tt_entry.map_or_else(|| self.evaluate(pos), |entry| entry.eval)
+        };
+
+        // Reverse futility pruning.
+        if !PV
+            && !in_check
+            && depth <= RFP_MAX_DEPTH
+            && beta.abs() < SCORE_MATE_IN_MAX_PLY
+            && static_eval - RFP_MARGIN * depth >= beta
+        {
+            return static_eval;
+        }
+
+        let hash_move = if root { self.root_best } else { tt_move };
+        let moves = OrderedMoves::new(
+            pos,
+            generate(pos),
+            hash_move,
+            self.killers[ply],
+            &self.history,
+        );
+
+        let original_alpha = alpha;
+        let mut best_score = -SCORE_INFINITE;
+        let mut best_move = None;
+        let mut legal_moves = 0;
+        let mut quiets_tried = ArrayVec::<(Piece, Square), 64>::new();
+
+        for mv in moves {
+            if !is_legal(pos, mv) {
+                continue;
+            }
+
+            legal_moves += 1;
+
+            let quiet = !is_capture(pos, mv);
+            let piece = moved_piece(pos, mv);
+            let captured = pos.make_move(mv);
+            self.accumulators.push(pos);
+            let gives_check = pos.checkers().has_any();
+
+            let score = if legal_moves == 1 {
+                -self.pvs::<PV>(pos, depth - 1, -beta, -alpha, ply + 1)
+            } else {
+                let reduction = if depth >= LMR_MIN_DEPTH
+                    && legal_moves >= LMR_MIN_MOVES
+                    && quiet
+                    && !in_check
+                    && !gives_check
+                {
+                    reduction(depth, legal_moves).min(depth - 1)
+                } else {
+                    0
+                };
+
+                let mut score =
+                    -self.pvs::<false>(pos, depth - 1 - reduction, -alpha - 1, -alpha, ply + 1);
+
+                if score > alpha && reduction > 0 {
+                    score = -self.pvs::<false>(pos, depth - 1, -alpha - 1, -alpha, ply + 1);
+                }
+
+                if PV && score > alpha && score < beta {
+                    score = -self.pvs::<true>(pos, depth - 1, -beta, -alpha, ply + 1);
+                }
+
+                score
+            };
+
+            pos.unmake_move(mv, captured);
+            self.accumulators.pop();
+
+            if self.stopped {
+                return 0;
+            }
+
+            if score > best_score {
+                best_score = score;
+
+                if score > alpha {
+                    alpha = score;
+                    best_move = Some(mv);
+
+                    if PV {
+                        self.update_pv(ply, mv);
+                    }
+
+                    if score >= beta {
+                        if quiet {
+                            self.update_quiet_stats(ply, mv, piece, depth, &quiets_tried);
+                        }
+
+                        break;
+                    }
+                }
+            }
+
+            if quiet && !quiets_tried.is_full() {
+                quiets_tried.push((piece, mv.to()));
+            }
+        }
+
+        if legal_moves == 0 {
+            // In shogi, having no legal moves is a loss even when not in check.
+            return mated_in(ply);
+        }
+
+        let bound = if best_score >= beta {
+            Bound::Lower
+        } else if best_score > original_alpha {
+            Bound::Exact
+        } else {
+            Bound::Upper
+        };
+
+        self.tt.store(
+            key,
+            best_move,
+            score_to_tt(best_score, ply),
+            static_eval,
+            depth,
+            bound,
+        );
+
+        best_score
+    }
+
+    fn qsearch<const PV: bool>(
+        &mut self,
+        pos: &mut Position,
+        mut alpha: i32,
+        beta: i32,
+        ply: usize,
+    ) -> i32 {
+        if PV {
+            self.pv[ply].clear();
+        }
+
+        if self.check_stop() {
+            return 0;
+        }
+
+        self.nodes += 1;
+        self.seldepth = self.seldepth.max(ply);
+
+        if ply >= MAX_PLY {
+            return self.evaluate(pos);
+        }
+
+        let key = pos.key();
+        let tt_entry = self.tt.probe(key);
+
+        if !PV && let Some(entry) = tt_entry {
+            let score = score_from_tt(entry.score, ply);
+
+            if cuts_off(entry.bound, score, beta) {
+                return score;
+            }
+        }
+
+        let in_check = pos.checkers().has_any();
+        let original_alpha = alpha;
+        let mut best_score = -SCORE_INFINITE;
+        let mut best_move = None;
+        let mut static_eval = -SCORE_INFINITE;
+
+        let moves = if in_check {
+            OrderedMoves::evasions(pos, generate_staged(pos, GenType::Evasions))
+        } else {
+            static_eval = tt_entry.map_or_else(|| self.evaluate(pos), |entry| entry.eval);
+
+            if static_eval >= beta {
+                return static_eval;
+            }
+
+            alpha = alpha.max(static_eval);
+            best_score = static_eval;
+
+            OrderedMoves::captures(pos, generate_staged(pos, GenType::Captures))
+        };
+
+        let mut legal_moves = 0;
+
+        for mv in moves {
+            if !is_legal(pos, mv) {
+                continue;
+            }
+
+            legal_moves += 1;
+
+            let captured = pos.make_move(mv);
+            self.accumulators.push(pos);
+            let score = -self.qsearch::<PV>(pos, -beta, -alpha, ply + 1);
+            pos.unmake_move(mv, captured);
+            self.accumulators.pop();
+
+            if self.stopped {
+                return 0;
+            }
+
+            if score > best_score {
+                best_score = score;
+
+                if score > alpha {
+                    alpha = score;
+                    best_move = Some(mv);
+
+                    if PV {
+                        self.update_pv(ply, mv);
+                    }
+
+                    if score >= beta {
+                        break;
+                    }
+                }
+            }
+        }
+
+        if in_check && legal_moves == 0 {
+            return mated_in(ply);
+        }
+
+        let bound = if best_score >= beta {
+            Bound::Lower
+        } else if PV && best_score > original_alpha {
+            Bound::Exact
+        } else {
+            Bound::Upper
+        };
+
+        self.tt.store(
+            key,
+            best_move,
+            score_to_tt(best_score, ply),
+            static_eval,
+            0,
+            bound,
+        );
+
+        best_score
+    }
+
+    /// Evaluates the position at the end of the current search path.
+    fn evaluate(&mut self, pos: &Position) -> i32 {
+        match self.eval_type {
+            EvalType::Nnue => self.accumulators.evaluate(&self.network, pos),
+            EvalType::Classical => classical::evaluate(pos),
+        }
+    }
+
+    fn clear_history(&mut self) {
+        self.killers.fill([None; 2]);
+        self.history.iter_mut().for_each(|row| row.fill(0));
+    }
+
+    fn update_pv(&mut self, ply: usize, mv: Move) {
+        let (head, tail) = self.pv.split_at_mut(ply + 1);
+        let pv = &mut head[ply];
+
+        pv.clear();
+        pv.push(mv);
+        pv.try_extend_from_slice(&tail[0]).unwrap();
+    }
+
+    fn update_quiet_stats(
+        &mut self,
+        ply: usize,
+        mv: Move,
+        piece: Piece,
+        depth: i32,
+        quiets_tried: &[(Piece, Square)],
+    ) {
+        let killers = &mut self.killers[ply];
+
+        if killers[0] != Some(mv) {
+            killers[1] = killers[0];
+            killers[0] = Some(mv);
+        }
+
+        let bonus = (depth * depth).min(HISTORY_MAX);
+
+        update_history(&mut self.history[piece][mv.to()], bonus);
+
+        for &(piece, to) in quiets_tried {
+            update_history(&mut self.history[piece][to], -bonus);
+        }
+    }
+
+    fn is_main(&self) -> bool {
+        self.thread_id == 0
+    }
+
+    /// Returns the nodes searched by this thread and its helpers.
+    ///
+    /// The counts of the helpers are only published periodically, so they may lag behind.
+    fn total_nodes(&self) -> u64 {
+        self.helper_nodes
+            .iter()
+            .map(|nodes| nodes.load(Ordering::Relaxed))
+            .sum::<u64>()
+            + self.nodes
+    }
+
+    /// Returns `true` if the search must stop, polling the stop conditions.
+    ///
+    /// Helper threads only stop on a signal; the limits are managed by the main thread.
+    fn check_stop(&mut self) -> bool {
+        if self.stopped {
+            return true;
+        }
+
+        let poll = self.nodes.is_multiple_of(1024);
+
+        if poll {
+            self.node_counter.store(self.nodes, Ordering::Relaxed);
+        }
+
+        if self.signals.stop.load(Ordering::Relaxed) || self.helpers_stop.load(Ordering::Relaxed) {
+            self.stopped = true;
+        } else if !self.is_main() {
+            return false;
+        } else if self
+            .limits
+            .nodes
+            .is_some_and(|nodes| self.total_nodes() >= nodes)
+        {
+            self.stopped = true;
+        } else if poll {
+            self.update_ponder_state();
+
+            if !self.pondering && self.time.is_over(self.start.elapsed()) {
+                self.stopped = true;
+            }
+        }
+
+        self.stopped
+    }
+
+    /// Returns `true` if no further iteration should be started.
+    fn should_finish(&mut self, score: i32, depth: i32) -> bool {
+        self.update_ponder_state();
+
+        if self.pondering || self.limits.infinite {
+            return false;
+        }
+
+        // A forced mate within the searched depth cannot be improved.
+        if is_mate_score(score) && mate_in(0) - score.abs() <= depth {
+            return true;
+        }
+
+        self.time
+            .should_finish(self.start.elapsed(), self.best_move_changes)
+    }
+
+    /// Restarts the clock when a ponder search is converted by `ponderhit`.
+    fn update_ponder_state(&mut self) {
+        if self.pondering && !self.signals.ponder.load(Ordering::Relaxed) {
+            self.pondering = false;
+            self.start = Instant::now();
+        }
+    }
+}
+
+/// Returns `true` if a stored score with `bound` is enough to cut off with `beta`
+/// in a null-window search.
+fn cuts_off(bound: Bound, score: i32, beta: i32) -> bool {
+    match bound {
+        Bound::None => false,
+        Bound::Upper => score < beta,
+        Bound::Lower => score >= beta,
+        Bound::Exact => true,
+    }
+}
+
+fn reduction(depth: i32, move_count: usize) -> i32 {
+    ((depth as f64).ln() * (move_count as f64).ln() / 2.0 + 0.5) as i32
+}
+
+fn update_history(entry: &mut i32, bonus: i32) {
+    *entry += bonus - *entry * bonus.abs() / HISTORY_MAX;
+}
@@ -0,0 +1,134 @@
+use arrayvec::ArrayVec;
+
+use crate::{
+    eval::piece_value,
+    shogi::{
+        core::{Piece, Square},
+        movegen::MoveList,
+        position::{mv::Move, Position},
+        see::see_ge,
+    },
+};
+
+/// Butterfly-style history of quiet moves, indexed by the moving piece and destination.
+pub(crate) type History = [[i32; Square::COUNT]; Piece::COUNT];
+
+/// Upper bound of the absolute value of a history entry.
+pub(crate) const HISTORY_MAX: i32 = 16384;
+
+const HASH_MOVE_SCORE: i32 = 1 << 30;
+const CAPTURE_SCORE: i32 = 1 << 24;
+const KILLER_SCORE: i32 = 1 << 20;
+const BAD_CAPTURE_SCORE: i32 = -(1 << 20);
+
+/// A list of moves ordered lazily by their scores.
+///
+/// Moves are returned from the highest score to the lowest score
+/// using selection sort, which is cheap when a cutoff happens early.
+pub(crate) struct OrderedMoves {
+    moves: ArrayVec<(Move, i32), 600>,
+}
+
+impl OrderedMoves {
+    /// Scores all moves for the main search.
+    pub(crate) fn new(
+        pos: &Position,
+        moves: MoveList,
+        hash_move: Option<Move>,
+        killers: [Option<Move>; 2],
+        history: &History,
+    ) -> Self {
+        let moves = moves
+            .into_iter()
+            .map(|mv| {
+                let score = if Some(mv) == hash_move {
+                    HASH_MOVE_SCORE
+                } else if is_capture(pos, mv) {
+                    let score = if see_ge(pos, mv, 0) {
+                        CAPTURE_SCORE
+                    } else {
+                        BAD_CAPTURE_SCORE
+                    };
+
+                    score + mvv_lva(pos, mv)
+                } else if Some(mv) == killers[0] {
+                    KILLER_SCORE + 1
+                } else if Some(mv) == killers[1] {
+                    KILLER_SCORE
+                } else {
+                    history[moved_piece(pos, mv)][mv.to()]
+                };
+
+                (mv, score)
+            })
+            .collect();
+
+        Self { moves }
+    }
+
+    /// Keeps only captures that do not lose material, scored by MVV-LVA,
+    /// for the quiescence search.
+    pub(crate) fn captures(pos: &Position, moves: MoveList) -> Self {
+        let moves = moves
+            .into_iter()
+            .filter(|&mv| is_capture(pos, mv) && see_ge(pos, mv, 0))
+            .map(|mv| (mv, mvv_lva(pos, mv)))
+            .collect();
+
+        Self { moves }
+    }
+
+    /// Keeps all moves, ordering captures first, for check evasions in the quiescence search.
+    pub(crate) fn evasions(pos: &Position, moves: MoveList) -> Self {
+        let moves = moves
+            .into_iter()
+            .map(|mv| {
+                let score = if is_capture(pos, mv) {
+                    CAPTURE_SCORE + mvv_lva(pos, mv)
+                } else {
+                    0
+                };
+
+                (mv, score)
+            })
+            .collect();
+
+        Self { moves }
+    }
+}
+
+impl Iterator for OrderedMoves {
+    type Item = Move;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (best_index, _) = self
+            .moves
+            .iter()
+            .enumerate()
+            .max_by_key(|(_, (_, score))| *score)?;
+
+        Some(self.moves.swap_remove(best_index).0)
+    }
+}
+
+/// Returns `true` if `mv` captures a piece.
+pub(crate) fn is_capture(pos: &Position, mv: Move) -> bool {
+    !mv.is_drop() && pos.has_any(mv.to())
+}
+
+/// Returns the piece that makes `mv`, before any promotion.
+pub(crate) fn moved_piece(pos: &Position, mv: Move) -> Piece {
+    if mv.is_drop() {
+        mv.drop_piece_type().with_color(pos.side_to_move())
+    } else {
+        pos.piece_at(mv.from()).unwrap()
+    }
+}
+
+/// Most valuable victim, least valuable attacker.
+fn mvv_lva(pos: &Position, mv: Move) -> i32 {
+    let victim = pos.piece_at(mv.to()).unwrap();
+    let attacker = moved_piece(pos, mv);
+
+    piece_value(victim.piece_type()) * 16 - piece_value(attacker.piece_type())
+}
@@ -0,0 +1,56 @@
+use crate::search::MAX_PLY;
+
+/// A score larger than any reachable score.
+pub const SCORE_INFINITE: i32 = 32001;
+
+/// The score of delivering checkmate at the root.
+pub const SCORE_MATE: i32 = 32000;
+
+/// The lowest score that still represents a forced mate.
+pub const SCORE_MATE_IN_MAX_PLY: i32 = SCORE_MATE - MAX_PLY as i32;
+
+/// The score of a drawn position.
+pub const SCORE_DRAW: i32 = 0;
+
+/// Returns the score of delivering checkmate `ply` plies from the root.
+#[must_use]
+pub const fn mate_in(ply: usize) -> i32 {
+    SCORE_MATE - ply as i32
+}
+
+/// Returns the score of being checkmated `ply` plies from the root.
+#[must_use]
+pub const fn mated_in(ply: usize) -> i32 {
+    -SCORE_MATE + ply as i32
+}
+
+/// Returns `true` if the score represents a forced mate for either side.
+#[must_use]
+pub const fn is_mate_score(score: i32) -> bool {
+    score.abs() >= SCORE_MATE_IN_MAX_PLY
+}
+
+/// Converts a mate score relative to the root into one relative to the current node,
+/// so that it can be stored in the transposition table.
+#[must_use]
+pub const fn score_to_tt(score: i32, ply: usize) -> i32 {
+    if score >= SCORE_MATE_IN_MAX_PLY {
+        score + ply as i32
+    } else if score <= -SCORE_MATE_IN_MAX_PLY {
+        score - ply as i32
+    } else {
+        score
+    }
+}
+
+/// Converts a score read from the transposition table back into one relative to the root.
+#[must_use]
+pub const fn score_from_tt(score: i32, ply: usize) -> i32 {
+    if score >= SCORE_MATE_IN_MAX_PLY {
+        score - ply as i32
+    } else if score <= -SCORE_MATE_IN_MAX_PLY {
+        score + ply as i32
+    } else {
+        score
+    }
+}
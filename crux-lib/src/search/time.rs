@@ -0,0 +1,125 @@
+use std::time::Duration;
+
+use crate::{search::Limits, shogi::core::Color};
+
+/// Number of moves the remaining time of the clock is spread over.
+const MOVE_HORIZON: u32 = 40;
+
+/// Fraction of the remaining time of the clock a single move may use at most.
+const MAXIMUM_FRACTION: u32 = 5;
+
+/// Upper bound of the factor applied to the optimum time when the best move is unstable.
+const MAX_INSTABILITY: f64 = 2.5;
+
+/// Settings of the time manager, configurable through USI options.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct TimeOptions {
+    /// Time reserved for the communication with the GUI on every move.
+    pub network_delay: Duration,
+    /// Time spent on a move at least, as long as the clock allows it.
+    pub minimum_thinking_time: Duration,
+}
+
+impl TimeOptions {
+    /// The default network delay.
+    pub const DEFAULT_NETWORK_DELAY: Duration = Duration::from_millis(120);
+    /// The default minimum thinking time.
+    pub const DEFAULT_MINIMUM_THINKING_TIME: Duration = Duration::from_millis(2000);
+}
+
+impl Default for TimeOptions {
+    fn default() -> Self {
+        Self {
+            network_delay: Self::DEFAULT_NETWORK_DELAY,
+            minimum_thinking_time: Self::DEFAULT_MINIMUM_THINKING_TIME,
+        }
+    }
+}
+
+/// Decides how long a search may run from the limits of the USI `go` command.
+///
+/// The optimum time is the time after which no new iteration is started, extended while
+/// the best move keeps changing. The maximum time is a hard limit at which the search is
+/// aborted, and never exceeds the time left on the clock, including the byoyomi, minus the
+/// network delay.
+#[derive(Debug, Copy, Clone, Default)]
+pub struct TimeManager {
+    optimum: Option<Duration>,
+    maximum: Option<Duration>,
+}
+
+impl TimeManager {
+    /// Computes the time limits of `stm` from `limits`.
+    ///
+    /// Searches with `infinite` or without a clock or `movetime` are not limited by time.
+    #[must_use]
+    pub fn new(limits: &Limits, stm: Color, options: &TimeOptions) -> Self {
+        if limits.infinite {
+            return Self::default();
+        }
+
+        if let Some(movetime) = limits.movetime {
+            let time = movetime.saturating_sub(options.network_delay);
+
+            return Self {
+                optimum: Some(time),
+                maximum: Some(time),
+            };
+        }
+
+        if !limits.has_clock() {
+            return Self::default();
+        }
+
+        let time = limits.time[stm];
+        let increment = limits.increment[stm];
+        let byoyomi = limits.byoyomi;
+
+        // The byoyomi is lost if it is not used, so it is always part of the allocation.
+        // The increment is only added after the move, so it cannot extend the hard limit.
+        let available = (time + byoyomi).saturating_sub(options.network_delay);
+        let maximum = (time / MAXIMUM_FRACTION + increment + byoyomi).min(available);
+        let optimum = (time / MOVE_HORIZON + increment + byoyomi)
+            .max(options.minimum_thinking_time)
+            .min(maximum);
+
+        Self {
+            optimum: Some(optimum),
+            maximum: Some(maximum),
+        }
+    }
+
+    /// Returns the time after which no new iteration should be started, if limited.
+    #[must_use]
+    pub fn optimum(&self) -> Option<Duration> {
+        self.optimum
+    }
+
+    /// Returns the time at which the search must be aborted, if limited.
+    #[must_use]
+    pub fn maximum(&self) -> Option<Duration> {
+        self.maximum
+    }
+
+    /// Returns `true` if no new iteration should be started after `elapsed`.
+    ///
+    /// `best_move_changes` is a decaying count of how often the best move changed in recent
+    /// iterations; the optimum time is extended by that many times, up to the maximum time.
+    #[must_use]
+    pub fn should_finish(&self, elapsed: Duration, best_move_changes: f64) -> bool {
+        let Some(optimum) = self.optimum else {
+            return false;
+        };
+
+        let instability = (1.0 + best_move_changes).min(MAX_INSTABILITY);
+        let optimum = optimum.mul_f64(instability).min(self.maximum.unwrap());
+
+        elapsed >= optimum
+    }
+
+    /// Returns `true` if the search must be aborted after `elapsed`.
+    #[must_use]
+    pub fn is_over(&self, elapsed: Duration) -> bool {
+        self.maximum.is_some_and(|maximum| elapsed >= maximum)
+    }
+}
@@ -0,0 +1,257 @@
+use std::sync::atomic::{AtomicU64, AtomicU8, Ordering};
+
+use crate::shogi::position::{key::Key, mv::Move};
+
+/// Number of entries in a bucket, chosen so that a bucket fills a cache line.
+const BUCKET_SIZE: usize = 4;
+
+/// Offset added to the depth so that quiescence entries are distinguishable from empty ones.
+const DEPTH_OFFSET: i32 = 1;
+
+/// Mask of the 6-bit generation stored in each entry.
+const GENERATION_MASK: u8 = 0x3F;
+
+const MOVE_SHIFT: u32 = 0;
+const SCORE_SHIFT: u32 = 16;
+const EVAL_SHIFT: u32 = 32;
+const DEPTH_SHIFT: u32 = 48;
+const BOUND_SHIFT: u32 = 56;
+const GENERATION_SHIFT: u32 = 58;
+
+/// The kind of bound a stored score represents.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Bound {
+    /// No score is stored.
+    None,
+    /// The true score is at most the stored score (fail low).
+    Upper,
+    /// The true score is at least the stored score (fail high).
+    Lower,
+    /// The stored score is exact.
+    Exact,
+}
+
+impl Bound {
+    const ALL: [Self; 4] = [Self::None, Self::Upper, Self::Lower, Self::Exact];
+}
+
+/// A decoded entry of the transposition table.
+#[derive(Debug, Copy, Clone)]
+pub struct TtEntry {
+    pub mv: Option<Move>,
+    pub score: i32,
+    pub eval: i32,
+    pub depth: i32,
+    pub bound: Bound,
+}
+
+/// A single slot of the table.
+///
+/// The key is stored XORed with the data so that a torn write by another thread
+/// is detected as a key mismatch instead of being returned as a corrupted entry.
+#[derive(Default)]
+struct Entry {
+    key: AtomicU64,
+    data: AtomicU64,
+}
+
+impl Entry {
+    fn load(&self) -> (u64, u64) {
+        let data = self.data.load(Ordering::Relaxed);
+        let key = self.key.load(Ordering::Relaxed) ^ data;
+
+        (key, data)
+    }
+
+    fn save(&self, key: u64, data: u64) {
+        self.key.store(key ^ data, Ordering::Relaxed);
+        self.data.store(data, Ordering::Relaxed);
+    }
+}
+
+#[derive(Default)]
+#[repr(C, align(64))]
+struct Bucket {
+    entries: [Entry; BUCKET_SIZE],
+}
+
+const _BUCKET_FITS_CACHE_LINE: () = assert!(size_of::<Bucket>() == 64);
+
+/// A lock-free transposition table shared between search threads.
+///
+/// Entries are grouped into buckets of one cache line each. Each entry stores
+/// the best move, the score, the static evaluation, the depth, the bound type and
+/// the generation of the search that wrote it.
+pub struct TranspositionTable {
+    buckets: Box<[Bucket]>,
+    generation: AtomicU8,
+}
+
+impl TranspositionTable {
+    /// The default size of the table in megabytes.
+    pub const DEFAULT_SIZE_MB: usize = 16;
+
+    /// Creates a table of `size_mb` megabytes.
+    ///
+    /// # Panics
+    /// Panics if `size_mb` is zero.
+    #[must_use]
+    pub fn new(size_mb: usize) -> Self {
+        assert!(size_mb > 0, "the transposition table must not be empty");
+
+        let len = size_mb * 1024 * 1024 / size_of::<Bucket>();
+
+        Self {
+            buckets: std::iter::repeat_with(Bucket::default).take(len).collect(),
+            generation: AtomicU8::new(0),
+        }
+    }
+
+    /// Returns the size of the table in megabytes.
+    #[must_use]
+    pub fn size_mb(&self) -> usize {
+        self.buckets.len() * size_of::<Bucket>() / (1024 * 1024)
+    }
+
+    /// Removes all entries from the table.
+    pub fn clear(&self) {
+        for entry in self.buckets.iter().flat_map(|bucket| &bucket.entries) {
+            entry.save(0, 0);
+        }
+
+        self.generation.store(0, Ordering::Relaxed);
+    }
+
+    /// Advances the generation, making entries of earlier searches preferred for replacement.
+    pub fn new_search(&self) {
+        let generation = self.generation.load(Ordering::Relaxed);
+
+        self.generation
+            .store((generation + 1) & GENERATION_MASK, Ordering::Relaxed);
+    }
+
+    /// Looks up the entry for `key`.
+    #[must_use]
+    pub fn probe(&self, key: Key) -> Option<TtEntry> {
+        self.bucket(key)
+            .entries
+            .iter()
+            .map(Entry::load)
+            .find(|&(entry_key, data)| data != 0 && entry_key == key.value())
+            .map(|(_, data)| decode(data))
+    }
+
+    /// Stores a search result for `key`.
+    ///
+    /// If the position is already stored, the previous best move is kept when `mv` is `None`,
+    /// and a shallower result does not replace a deeper one of the same search.
+    pub fn store(
+        &self,
+        key: Key,
+        mv: Option<Move>,
+        score: i32,
+        eval: i32,
+        depth: i32,
+        bound: Bound,
+    ) {
+        debug_assert!(depth + DEPTH_OFFSET > 0 && depth + DEPTH_OFFSET <= u8::MAX as i32);
+
+        let generation = self.generation.load(Ordering::Relaxed);
+        let entries = &self.bucket(key).entries;
+
+        let replace = entries
+            .iter()
+            .find(|entry| {
+                let (entry_key, data) = entry.load();
+
+                data == 0 || entry_key == key.value()
+            })
+            .unwrap_or_else(|| {
+                entries
+                    .iter()
+                    .min_by_key(|entry| replacement_value(entry.load().1, generation))
+                    .unwrap()
+            });
+
+        let (entry_key, data) = replace.load();
+        let mut mv = mv;
+
+        if data != 0 && entry_key == key.value() {
+            let old = decode(data);
+
+            if mv.is_none() {
+                mv = old.mv;
+            }
+
+            if bound != Bound::Exact
+                && depth + 4 <= old.depth
+                && entry_generation(data) == generation
+            {
+                return;
+            }
+        }
+
+        let data = u64::from(mv.map_or(0, Move::as_u16)) << MOVE_SHIFT
+            | u64::from(score as i16 as u16) << SCORE_SHIFT
+            | u64::from(eval as i16 as u16) << EVAL_SHIFT
+            | ((depth + DEPTH_OFFSET) as u64) << DEPTH_SHIFT
+            | (bound as u64) << BOUND_SHIFT
+            | u64::from(generation) << GENERATION_SHIFT;
+
+        replace.save(key.value(), data);
+    }
+
+    /// Returns the approximate occupancy of the table by the current search, in permille.
+    #[must_use]
+    pub fn hashfull(&self) -> usize {
+        let generation = self.generation.load(Ordering::Relaxed);
+        let sample = self.buckets.iter().take(1000 / BUCKET_SIZE);
+        let mut total = 0;
+        let mut used = 0;
+
+        for entry in sample.flat_map(|bucket| &bucket.entries) {
+            let (_, data) = entry.load();
+
+            total += 1;
+
+            if data != 0 && entry_generation(data) == generation {
+                used += 1;
+            }
+        }
+
+        used * 1000 / total
+    }
+
+    fn bucket(&self, key: Key) -> &Bucket {
+        let index = (u128::from(key.value()) * self.buckets.len() as u128) >> 64;
+
+        &self.buckets[index as usize]
+    }
+}
+
+impl Default for TranspositionTable {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_SIZE_MB)
+    }
+}
+
+fn decode(data: u64) -> TtEntry {
+    TtEntry {
+        mv: Move::from_u16((data >> MOVE_SHIFT) as u16),
+        score: i32::from((data >> SCORE_SHIFT) as u16 as i16),
+        eval: i32::from((data >> EVAL_SHIFT) as u16 as i16),
+        depth: i32::from((data >> DEPTH_SHIFT) as u8) - DEPTH_OFFSET,
+        bound: Bound::ALL[(data >> BOUND_SHIFT) as usize & 0b11],
+    }
+}
+
+fn entry_generation(data: u64) -> u8 {
+    (data >> GENERATION_SHIFT) as u8 & GENERATION_MASK
+}
+
+/// Lower values are replaced first: shallow entries from old searches.
+fn replacement_value(data: u64, generation: u8) -> i32 {
+    let age = generation.wrapping_sub(entry_generation(data)) & GENERATION_MASK;
+
+    i32::from((data >> DEPTH_SHIFT) as u8) - 8 * i32::from(age)
+}
@@ -0,0 +1,105 @@
+use crate::shogi::{
+    bitboard::promotion_area,
+    core::{Color, PieceType},
+    position::Position,
+};
+
+/// Minimum number of pieces other than the king that must be in the promotion area.
+const MIN_PIECES_IN_AREA: u32 = 10;
+
+/// Points of a bishop, rook or their promoted forms.
+const MAJOR_PIECE_POINTS: u32 = 5;
+
+/// Points of any other piece except the king.
+const MINOR_PIECE_POINTS: u32 = 1;
+
+/// The rule under which a player may declare a win by entering king (nyugyoku).
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum EnteringKingRule {
+    /// Declarations are not allowed.
+    None,
+    /// The 24-point rule: 31 points or more are required to win.
+    Point24,
+    /// The 27-point rule of the CSA protocol:
+    /// 28 points or more for black and 27 points or more for white are required to win.
+    #[default]
+    Point27,
+}
+
+impl EnteringKingRule {
+    /// Returns the points `color` needs to win by declaration, or `None` if declarations
+    /// are not allowed.
+    #[must_use]
+    pub const fn required_points(self, color: Color) -> Option<u32> {
+        match self {
+            Self::None => None,
+            Self::Point24 => Some(31),
+            Self::Point27 => Some(match color {
+                Color::Black => 28,
+                Color::White => 27,
+            }),
+        }
+    }
+}
+
+/// Returns the declaration points of `color`.
+///
+/// Counts the pieces of `color` in its promotion area, excluding the king, and the pieces
+/// in its hand. Bishops, rooks and their promoted forms score 5 points; all other pieces
+/// score 1 point.
+#[must_use]
+pub const fn declaration_points(pos: &Position, color: Color) -> u32 {
+    let pieces = pos.color_bb(color) & promotion_area(color);
+    let majors = pieces
+        & (pos.piece_type_bb(PieceType::Bishop)
+            | pos.piece_type_bb(PieceType::Rook)
+            | pos.piece_type_bb(PieceType::Horse)
+            | pos.piece_type_bb(PieceType::Dragon));
+    let kings = pieces & pos.piece_type_bb(PieceType::King);
+    let minors = pieces.count_ones() - majors.count_ones() - kings.count_ones();
+
+    let hand = pos.hand(color);
+    let hand_majors = hand.count(PieceType::Bishop) + hand.count(PieceType::Rook);
+    let hand_minors = hand.count(PieceType::Pawn)
+        + hand.count(PieceType::Lance)
+        + hand.count(PieceType::Knight)
+        + hand.count(PieceType::Silver)
+        + hand.count(PieceType::Gold);
+
+    (majors.count_ones() + hand_majors) * MAJOR_PIECE_POINTS
+        + (minors + hand_minors) * MINOR_PIECE_POINTS
+}
+
+/// Returns `true` if the side to move can declare a win by entering king under `rule`.
+///
+/// All of the following conditions must hold:
+/// - The king of the side to move is in its promotion area.
+/// - At least 10 other pieces of the side to move are in its promotion area.
+/// - The side to move has enough points, as counted by [`declaration_points`].
+/// - The side to move is not in check.
+///
+/// The condition on the remaining time is left to the caller.
+#[must_use]
+pub const fn can_declare_win(pos: &Position, rule: EnteringKingRule) -> bool {
+    let stm = pos.side_to_move();
+
+    let Some(required_points) = rule.required_points(stm) else {
+        return false;
+    };
+
+    let Some(king_square) = pos.king_square(stm) else {
+        return false;
+    };
+
+    let area = promotion_area(stm);
+
+    if !area.contains(king_square) || pos.checkers().has_any() {
+        return false;
+    }
+
+    if (pos.color_bb(stm) & area).count_ones() - 1 < MIN_PIECES_IN_AREA {
+        return false;
+    }
+
+    declaration_points(pos, stm) >= required_points
+}
@@ -0,0 +1,113 @@
+use crate::shogi::{
+    attacks::{king_attacks, piece_attacks},
+    bitboard::Bitboard,
+    core::PieceType,
+    movegen::{generate_staged, is_legal, GenType},
+    position::{mv::Move, Position},
+};
+
+/// Returns a move that checkmates the opponent immediately, if any.
+///
+/// Drops from the hand are tried before moves on the board, and a candidate is rejected
+/// as soon as the opponent king has a safe square among its `king_attacks`, which keeps
+/// the common case cheap. Pawn drops are never returned, since a checkmate by a pawn drop
+/// (uchifuzume) is illegal.
+///
+/// The position is restored before returning. Positions where the side to move is in check
+/// are not handled and yield `None`.
+#[must_use]
+pub fn mate1ply(pos: &mut Position) -> Option<Move> {
+    if pos.checkers().has_any() || pos.king_square(pos.side_to_move().opposite()).is_none() {
+        return None;
+    }
+
+    let checks = generate_staged(pos, GenType::Checks);
+    let drops = checks
+        .iter()
+        .filter(|mv| mv.is_drop() && mv.drop_piece_type() != PieceType::Pawn);
+    let normals = checks.iter().filter(|mv| !mv.is_drop());
+
+    drops
+        .chain(normals)
+        .copied()
+        .find(|&mv| !king_escapes(pos, mv) && is_legal(pos, mv) && mates(pos, mv))
+}
+
+/// Returns `true` if the side to move is checkmated.
+///
+/// Unlike checking that no legal move exists, this only looks at the moves that may
+/// resolve a check, starting with the king's escape squares.
+#[must_use]
+pub fn is_checkmated(pos: &mut Position) -> bool {
+    let checkers = pos.checkers();
+
+    if checkers.is_empty() {
+        return false;
+    }
+
+    let stm = pos.side_to_move();
+    let king_square = pos.king_square(stm).unwrap();
+    let occupied = pos.occupancy() ^ king_square.bit();
+    let mut escapes = king_attacks(king_square) & !pos.color_bb(stm);
+
+    while escapes.has_any() {
+        let square = escapes.pop_lsb();
+
+        if (pos.attackers_to(square, occupied) & pos.color_bb(stm.opposite())).is_empty() {
+            return false;
+        }
+    }
+
+    if checkers.is_multiple() {
+        return true;
+    }
+
+    !generate_staged(pos, GenType::Evasions)
+        .into_iter()
+        .any(|mv| is_legal(pos, mv))
+}
+
+/// Returns `true` if the opponent king has a safe square after the checking move `mv`,
+/// including the square of the checking piece itself, without making the move.
+fn king_escapes(pos: &Position, mv: Move) -> bool {
+    let stm = pos.side_to_move();
+    let king_square = pos.king_square(stm.opposite()).unwrap();
+    let to = mv.to();
+
+    let (moved, vacated) = if mv.is_drop() {
+        (mv.drop_piece_type().with_color(stm), Bitboard::empty())
+    } else {
+        let piece = pos.piece_at(mv.from()).unwrap();
+        let moved = if mv.is_promotion() {
+            piece.promoted()
+        } else {
+            piece
+        };
+
+        (moved, mv.from().bit())
+    };
+
+    let occupied = ((pos.occupancy() & !vacated) | to.bit()) ^ king_square.bit();
+    let attackers = pos.color_bb(stm) & !vacated;
+    let blocked = pos.color_bb(stm.opposite()) & !to.bit();
+    let mut escapes = king_attacks(king_square) & !blocked & !piece_attacks(moved, to, occupied);
+
+    while escapes.has_any() {
+        let square = escapes.pop_lsb();
+
+        if (pos.attackers_to(square, occupied) & attackers).is_empty() {
+            return true;
+        }
+    }
+
+    false
+}
+
+fn mates(pos: &mut Position, mv: Move) -> bool {
+    let captured = pos.make_move(mv);
+    let mated = is_checkmated(pos);
+
+    pos.unmake_move(mv, captured);
+
+    mated
+}
@@ -1,5 +1,8 @@
 pub mod attacks;
 pub mod bitboard;
 pub mod core;
+pub mod entering_king;
+pub mod mate;
 pub mod movegen;
 pub mod position;
+pub mod see;
@@ -17,6 +17,21 @@ use crate::shogi::{
 /// moves in shogi (593), ensuring it never overflows.
 pub type MoveList = ArrayVec<Move, 600>;
 
+/// The kind of moves produced by [`generate_staged`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum GenType {
+    /// Captures, including promoting ones, and non-capturing pawn promotions.
+    Captures,
+    /// All moves that are not generated by [`GenType::Captures`], including drops.
+    Quiets,
+    /// All moves of a side in check. The side to move must be in check.
+    Evasions,
+    /// Moves that give check, including discovered checks.
+    Checks,
+    /// All moves, the same as [`generate`].
+    All,
+}
+
 /// Generates pseudo-legal moves for the given position.
 ///
 /// "Pseudo-legal" moves include:
@@ -28,21 +43,35 @@ pub type MoveList = ArrayVec<Move, 600>;
 /// verify legality using [`is_legal`].
 #[must_use]
 pub fn generate(pos: &Position) -> MoveList {
+    generate_staged(pos, GenType::All)
+}
+
+/// Generates the pseudo-legal moves of `gen_type` for the given position.
+///
+/// When the side to move is in check, only moves that may resolve the check are
+/// generated, whatever `gen_type` is. When it is not, [`GenType::Captures`] and
+/// [`GenType::Quiets`] together generate every move of [`generate`] exactly once.
+///
+/// # Debug assertions
+/// In debug builds, panics if `gen_type` is [`GenType::Evasions`] and the side to move
+/// is not in check.
+#[must_use]
+pub fn generate_staged(pos: &Position, gen_type: GenType) -> MoveList {
+    debug_assert!(gen_type != GenType::Evasions || pos.checkers().has_any());
+
     let mut moves = MoveList::new();
 
     let stm = pos.side_to_move();
     let checkers = pos.checkers();
 
-    let mut dst_mask = !pos.color_bb(stm);
+    let king_mask = !pos.color_bb(stm);
+    let mut dst_mask = king_mask;
     let mut drop_mask = !pos.occupancy();
 
-    generate_kings(&mut moves, pos, dst_mask);
-
     if checkers.is_multiple() {
-        return moves;
-    }
-
-    if checkers.has_any() {
+        dst_mask = Bitboard::empty();
+        drop_mask = Bitboard::empty();
+    } else if checkers.has_any() {
         let checker = checkers.lsb();
         let check_ray = ray_between(pos.king_square(stm).unwrap(), checker);
 
@@ -50,24 +79,84 @@ pub fn generate(pos: &Position) -> MoveList {
         drop_mask &= check_ray;
     }
 
-    generate_pawns(&mut moves, pos, dst_mask);
-    generate_lances(&mut moves, pos, dst_mask);
-    generate_knights(&mut moves, pos, dst_mask);
-    generate_silvers(&mut moves, pos, dst_mask);
-    generate_golds(&mut moves, pos, dst_mask);
-    generate_bishops(&mut moves, pos, dst_mask);
-    generate_rooks(&mut moves, pos, dst_mask);
-    generate_horses(&mut moves, pos, dst_mask);
-    generate_dragons(&mut moves, pos, dst_mask);
-    generate_drops(&mut moves, pos, drop_mask);
+    let targets = |piece_type| Targets::new(pos, gen_type, piece_type, dst_mask);
+
+    generate_kings(
+        &mut moves,
+        pos,
+        Targets::new(pos, gen_type, PieceType::King, king_mask),
+    );
+
+    if gen_type == GenType::Checks {
+        generate_discovered_checks(&mut moves, pos, king_mask, dst_mask);
+    }
+
+    if checkers.is_multiple() {
+        return moves;
+    }
+
+    generate_pawns(&mut moves, pos, targets(PieceType::Pawn));
+    generate_lances(&mut moves, pos, targets(PieceType::Lance));
+    generate_knights(&mut moves, pos, targets(PieceType::Knight));
+    generate_silvers(&mut moves, pos, targets(PieceType::Silver));
+    generate_golds(&mut moves, pos, targets(PieceType::Gold));
+    generate_bishops(&mut moves, pos, targets(PieceType::Bishop));
+    generate_rooks(&mut moves, pos, targets(PieceType::Rook));
+    generate_horses(&mut moves, pos, targets(PieceType::Horse));
+    generate_dragons(&mut moves, pos, targets(PieceType::Dragon));
+
+    if gen_type != GenType::Captures {
+        generate_drops(&mut moves, pos, gen_type, drop_mask);
+    }
 
     moves
 }
 
+/// Generates the legal moves for the given position.
+///
+/// Unlike [`generate`], moves that leave the king in check and illegal pawn drops
+/// (uchifuzume) are excluded. Perpetual checks are not considered; use
+/// [`Position::repetition`] after the move for that.
+#[must_use]
+pub fn generate_legal(pos: &Position) -> MoveList {
+    // Only a pawn drop in front of the opponent king needs a mutable position.
+    let mut scratch = None;
+
+    generate(pos)
+        .into_iter()
+        .filter(|&mv| {
+            if is_checking_pawn_drop(pos, mv) {
+                !is_uchifuzume(scratch.get_or_insert_with(|| pos.clone()), mv)
+            } else {
+                is_legal_except_uchifuzume(pos, mv)
+            }
+        })
+        .collect()
+}
+
+/// Returns `true` if `mv` is a legal move in `pos`.
+///
+/// Unlike [`is_legal`], any move is accepted, so this is suitable for validating
+/// untrusted input. Perpetual checks are not considered; use [`Position::repetition`]
+/// after the move for that.
+#[must_use]
+pub fn is_legal_move(pos: &Position, mv: Move) -> bool {
+    if !is_pseudo_legal(pos, mv) {
+        return false;
+    }
+
+    if is_checking_pawn_drop(pos, mv) {
+        return !is_uchifuzume(&mut pos.clone(), mv);
+    }
+
+    is_legal_except_uchifuzume(pos, mv)
+}
+
 /// Returns `true` if `mv` is a legal move in `pos`.
 ///
 /// Only pseudo-legal moves should be passed to this function.
-/// This checks full move legality, but does **not** consider perpetual checks.
+/// This checks full move legality, but does **not** consider perpetual checks;
+/// use [`Position::repetition`] after the move for that.
 ///
 /// # Debug assertions
 /// In debug builds, panics if `mv` is not pseudo-legal in `pos`.
@@ -75,33 +164,49 @@ pub fn generate(pos: &Position) -> MoveList {
 pub fn is_legal(pos: &mut Position, mv: Move) -> bool {
     debug_assert!(is_pseudo_legal(pos, mv));
 
-    let to = mv.to();
-    let stm = pos.side_to_move();
-    let nstm = stm.opposite();
+    if is_checking_pawn_drop(pos, mv) {
+        return !is_uchifuzume(pos, mv);
+    }
 
-    if mv.is_drop() {
-        debug_assert!(!pos.checkers().is_multiple());
+    is_legal_except_uchifuzume(pos, mv)
+}
 
-        if mv.drop_piece_type() == PieceType::Pawn
-            && let Some(king_square) = pos.king_square(nstm)
-            && pawn_attacks(stm, to).contains(king_square)
-        {
-            let mut legal = false;
+/// Returns `true` if `mv` drops a pawn that checks the opponent king.
+fn is_checking_pawn_drop(pos: &Position, mv: Move) -> bool {
+    mv.is_drop()
+        && mv.drop_piece_type() == PieceType::Pawn
+        && pos
+            .king_square(pos.side_to_move().opposite())
+            .is_some_and(|king_square| {
+                pawn_attacks(pos.side_to_move(), mv.to()).contains(king_square)
+            })
+}
 
-            pos.make_move(mv);
+/// Returns `true` if the pawn drop `mv` checkmates the opponent, which is illegal.
+fn is_uchifuzume(pos: &mut Position, mv: Move) -> bool {
+    debug_assert!(!pos.checkers().is_multiple());
 
-            if generate(pos).iter().any(|&mv| is_legal(pos, mv)) {
-                legal = true;
-            }
+    pos.make_move(mv);
 
-            pos.unmake_move(mv, None);
+    let mated = !generate(pos).iter().any(|&reply| is_legal(pos, reply));
 
-            return legal;
-        }
+    pos.unmake_move(mv, None);
+
+    mated
+}
+
+/// Checks the legality of the pseudo-legal move `mv`, except for uchifuzume.
+fn is_legal_except_uchifuzume(pos: &Position, mv: Move) -> bool {
+    if mv.is_drop() {
+        debug_assert!(!pos.checkers().is_multiple());
 
         return true;
     }
 
+    let to = mv.to();
+    let stm = pos.side_to_move();
+    let nstm = stm.opposite();
+
     let from = mv.from();
     let moving_piece = pos.piece_at(from).unwrap();
 
@@ -181,23 +286,6 @@ pub const fn is_pseudo_legal(pos: &Position, mv: Move) -> bool {
     let stm = pos.side_to_move();
     let checkers = pos.checkers();
 
-    const fn promotion_required_zone(color: Color, piece_type: PieceType) -> Bitboard {
-        let mut zone = Bitboard::empty();
-
-        if matches!(
-            piece_type,
-            PieceType::Pawn | PieceType::Lance | PieceType::Knight
-        ) {
-            zone |= Rank::Rank1.relative(color).bit();
-        }
-
-        if piece_type == PieceType::Knight {
-            zone |= Rank::Rank2.relative(color).bit();
-        }
-
-        zone
-    }
-
     if mv.is_drop() {
         if pos.has_any(to) {
             return false;
@@ -287,17 +375,85 @@ pub const fn is_pseudo_legal(pos: &Position, mv: Move) -> bool {
     true
 }
 
-fn generate_pawns(dst: &mut MoveList, pos: &Position, dst_mask: Bitboard) {
+/// Destination masks of one piece type for a generation stage.
+#[derive(Copy, Clone)]
+struct Targets {
+    /// Destinations of non-promoting moves.
+    normal: Bitboard,
+    /// Destinations of promoting moves.
+    promotion: Bitboard,
+}
+
+impl Targets {
+    const fn new(
+        pos: &Position,
+        gen_type: GenType,
+        piece_type: PieceType,
+        dst_mask: Bitboard,
+    ) -> Self {
+        let enemies = pos.color_bb(pos.side_to_move().opposite());
+        let empty = !pos.occupancy();
+        let is_pawn = piece_type == PieceType::Pawn;
+
+        match gen_type {
+            GenType::Captures => Self {
+                normal: dst_mask & enemies,
+                promotion: if is_pawn {
+                    dst_mask
+                } else {
+                    dst_mask & enemies
+                },
+            },
+            GenType::Quiets => Self {
+                normal: dst_mask & empty,
+                promotion: if is_pawn {
+                    Bitboard::empty()
+                } else {
+                    dst_mask & empty
+                },
+            },
+            GenType::Evasions | GenType::All => Self {
+                normal: dst_mask,
+                promotion: dst_mask,
+            },
+            GenType::Checks => Self {
+                normal: dst_mask & pos.check_squares(piece_type),
+                promotion: dst_mask & pos.check_squares(piece_type.promoted()),
+            },
+        }
+    }
+}
+
+/// Returns the squares a piece of `piece_type` must promote on when moving to or being
+/// dropped on them.
+const fn promotion_required_zone(color: Color, piece_type: PieceType) -> Bitboard {
+    let mut zone = Bitboard::empty();
+
+    if matches!(
+        piece_type,
+        PieceType::Pawn | PieceType::Lance | PieceType::Knight
+    ) {
+        zone |= Rank::Rank1.relative(color).bit();
+    }
+
+    if piece_type == PieceType::Knight {
+        zone |= Rank::Rank2.relative(color).bit();
+    }
+
+    zone
+}
+
+fn generate_pawns(dst: &mut MoveList, pos: &Position, targets: Targets) {
     let stm = pos.side_to_move();
     let pawns = pos.piece_bb(PieceType::Pawn.with_color(stm));
     let attacks = multi_pawn_attacks(stm, pawns);
     let non_promo_restriction = attacks & !Rank::Rank1.relative(stm).bit();
 
-    serialize_pawn_normals(dst, stm, non_promo_restriction, dst_mask);
-    serialize_pawn_promotions(dst, stm, attacks & promotion_area(stm), dst_mask);
+    serialize_pawn_normals(dst, stm, non_promo_restriction, targets.normal);
+    serialize_pawn_promotions(dst, stm, attacks & promotion_area(stm), targets.promotion);
 }
 
-fn generate_lances(dst: &mut MoveList, pos: &Position, dst_mask: Bitboard) {
+fn generate_lances(dst: &mut MoveList, pos: &Position, targets: Targets) {
     let stm = pos.side_to_move();
     let lances = pos.piece_bb(PieceType::Lance.with_color(stm));
 
@@ -306,12 +462,12 @@ fn generate_lances(dst: &mut MoveList, pos: &Position, dst_mask: Bitboard) {
         pos,
         lances,
         lance_attacks,
-        dst_mask,
+        targets,
         !Rank::Rank1.relative(stm).bit(),
     );
 }
 
-fn generate_knights(dst: &mut MoveList, pos: &Position, dst_mask: Bitboard) {
+fn generate_knights(dst: &mut MoveList, pos: &Position, targets: Targets) {
     let stm = pos.side_to_move();
     let knights = pos.piece_bb(PieceType::Knight.with_color(stm));
 
@@ -320,12 +476,12 @@ fn generate_knights(dst: &mut MoveList, pos: &Position, dst_mask: Bitboard) {
         pos,
         knights,
         knight_attacks,
-        dst_mask,
+        targets,
         !(Rank::Rank1.relative(stm).bit() | Rank::Rank2.relative(stm).bit()),
     );
 }
 
-fn generate_silvers(dst: &mut MoveList, pos: &Position, dst_mask: Bitboard) {
+fn generate_silvers(dst: &mut MoveList, pos: &Position, targets: Targets) {
     let silvers = pos.piece_bb(PieceType::Silver.with_color(pos.side_to_move()));
 
     generate_precalculated_with_color::<true, _>(
@@ -333,12 +489,12 @@ fn generate_silvers(dst: &mut MoveList, pos: &Position, dst_mask: Bitboard) {
         pos,
         silvers,
         silver_attacks,
-        dst_mask,
+        targets,
         Bitboard::all(),
     );
 }
 
-fn generate_golds(dst: &mut MoveList, pos: &Position, dst_mask: Bitboard) {
+fn generate_golds(dst: &mut MoveList, pos: &Position, targets: Targets) {
     let stm = pos.side_to_move();
     let golds = pos.piece_bb(PieceType::Gold.with_color(stm))
         | pos.piece_bb(PieceType::ProPawn.with_color(stm))
@@ -351,12 +507,12 @@ fn generate_golds(dst: &mut MoveList, pos: &Position, dst_mask: Bitboard) {
         pos,
         golds,
         gold_attacks,
-        dst_mask,
+        targets,
         Bitboard::all(),
     );
 }
 
-fn generate_bishops(dst: &mut MoveList, pos: &Position, dst_mask: Bitboard) {
+fn generate_bishops(dst: &mut MoveList, pos: &Position, targets: Targets) {
     let bishops = pos.piece_bb(PieceType::Bishop.with_color(pos.side_to_move()));
 
     generate_precalculated_with_occ::<true, _>(
@@ -364,12 +520,12 @@ fn generate_bishops(dst: &mut MoveList, pos: &Position, dst_mask: Bitboard) {
         pos,
         bishops,
         bishop_attacks,
-        dst_mask,
+        targets,
         Bitboard::all(),
     );
 }
 
-fn generate_rooks(dst: &mut MoveList, pos: &Position, dst_mask: Bitboard) {
+fn generate_rooks(dst: &mut MoveList, pos: &Position, targets: Targets) {
     let rooks = pos.piece_bb(PieceType::Rook.with_color(pos.side_to_move()));
 
     generate_precalculated_with_occ::<true, _>(
@@ -377,12 +533,12 @@ fn generate_rooks(dst: &mut MoveList, pos: &Position, dst_mask: Bitboard) {
         pos,
         rooks,
         rook_attacks,
-        dst_mask,
+        targets,
         Bitboard::all(),
     );
 }
 
-fn generate_horses(dst: &mut MoveList, pos: &Position, dst_mask: Bitboard) {
+fn generate_horses(dst: &mut MoveList, pos: &Position, targets: Targets) {
     let horses = pos.piece_bb(PieceType::Horse.with_color(pos.side_to_move()));
 
     generate_precalculated_with_occ::<false, _>(
@@ -390,12 +546,12 @@ fn generate_horses(dst: &mut MoveList, pos: &Position, dst_mask: Bitboard) {
         pos,
         horses,
         horse_attacks,
-        dst_mask,
+        targets,
         Bitboard::all(),
     );
 }
 
-fn generate_dragons(dst: &mut MoveList, pos: &Position, dst_mask: Bitboard) {
+fn generate_dragons(dst: &mut MoveList, pos: &Position, targets: Targets) {
     let dragons = pos.piece_bb(PieceType::Dragon.with_color(pos.side_to_move()));
 
     generate_precalculated_with_occ::<false, _>(
@@ -403,22 +559,28 @@ fn generate_dragons(dst: &mut MoveList, pos: &Position, dst_mask: Bitboard) {
         pos,
         dragons,
         dragon_attacks,
-        dst_mask,
+        targets,
         Bitboard::all(),
     );
 }
 
-fn generate_kings(dst: &mut MoveList, pos: &Position, dst_mask: Bitboard) {
+fn generate_kings(dst: &mut MoveList, pos: &Position, targets: Targets) {
     let kings = pos.piece_bb(PieceType::King.with_color(pos.side_to_move()));
-    generate_precalculated::<false, _>(dst, pos, kings, king_attacks, dst_mask, Bitboard::all());
+    generate_precalculated::<false, _>(dst, pos, kings, king_attacks, targets, Bitboard::all());
 }
 
-fn generate_drops(dst: &mut MoveList, pos: &Position, dst_mask: Bitboard) {
+fn generate_drops(dst: &mut MoveList, pos: &Position, gen_type: GenType, dst_mask: Bitboard) {
     let stm = pos.side_to_move();
     let hand = pos.hand(stm);
 
     let mut generate = |piece_type: PieceType, restriction: Bitboard| {
         if hand.count(piece_type) > 0 {
+            let dst_mask = if gen_type == GenType::Checks {
+                dst_mask & pos.check_squares(piece_type)
+            } else {
+                dst_mask
+            };
+
             serialize_drops(dst, piece_type, restriction, dst_mask);
         }
     };
@@ -437,12 +599,68 @@ fn generate_drops(dst: &mut MoveList, pos: &Position, dst_mask: Bitboard) {
     generate(PieceType::Rook, Bitboard::all());
 }
 
+/// Generates the moves of the pieces blocking a slider of the side to move from the
+/// opponent king that discover a check, except those that also give a direct check,
+/// which are generated together with the other moves of their piece type.
+fn generate_discovered_checks(
+    dst: &mut MoveList,
+    pos: &Position,
+    king_mask: Bitboard,
+    dst_mask: Bitboard,
+) {
+    let stm = pos.side_to_move();
+    let nstm = stm.opposite();
+
+    let Some(king_square) = pos.king_square(nstm) else {
+        return;
+    };
+
+    let occ = pos.occupancy();
+    let promo_area = promotion_area(stm);
+    let mut discoverers = pos.blockers_for_king(nstm) & pos.color_bb(stm);
+
+    while discoverers.has_any() {
+        let from = discoverers.pop_lsb();
+        let piece = pos.piece_at(from).unwrap();
+        let piece_type = piece.piece_type();
+        let mask = if piece_type == PieceType::King {
+            king_mask
+        } else {
+            dst_mask
+        };
+
+        let attacks = piece_attacks(piece, from, occ) & mask & !ray_intersecting(from, king_square);
+
+        serialize_normals(
+            dst,
+            from,
+            attacks & !promotion_required_zone(stm, piece_type),
+            !pos.check_squares(piece_type),
+        );
+
+        if piece.can_promote() {
+            let promotable = if promo_area.contains(from) {
+                attacks
+            } else {
+                attacks & promo_area
+            };
+
+            serialize_promotions(
+                dst,
+                from,
+                promotable,
+                !pos.check_squares(piece_type.promoted()),
+            );
+        }
+    }
+}
+
 fn generate_precalculated<const CAN_PROMOTE: bool, F: Fn(Square) -> Bitboard>(
     dst: &mut MoveList,
     pos: &Position,
     pieces: Bitboard,
     attack_getter: F,
-    dst_mask: Bitboard,
+    targets: Targets,
     non_promo_restriction: Bitboard,
 ) {
     generate_precalculated_with_color_and_occ::<CAN_PROMOTE, _>(
@@ -450,7 +668,7 @@ fn generate_precalculated<const CAN_PROMOTE: bool, F: Fn(Square) -> Bitboard>(
         pos,
         pieces,
         |_color, square, _occ| -> Bitboard { attack_getter(square) },
-        dst_mask,
+        targets,
         non_promo_restriction,
     );
 }
@@ -460,7 +678,7 @@ fn generate_precalculated_with_color<const CAN_PROMOTE: bool, F: Fn(Color, Squar
     pos: &Position,
     pieces: Bitboard,
     attack_getter: F,
-    dst_mask: Bitboard,
+    targets: Targets,
     non_promo_restriction: Bitboard,
 ) {
     generate_precalculated_with_color_and_occ::<CAN_PROMOTE, _>(
@@ -468,7 +686,7 @@ fn generate_precalculated_with_color<const CAN_PROMOTE: bool, F: Fn(Color, Squar
         pos,
         pieces,
         |color, square, _occ| -> Bitboard { attack_getter(color, square) },
-        dst_mask,
+        targets,
         non_promo_restriction,
     );
 }
@@ -478,7 +696,7 @@ fn generate_precalculated_with_occ<const CAN_PROMOTE: bool, F: Fn(Square, Bitboa
     pos: &Position,
     pieces: Bitboard,
     attack_getter: F,
-    dst_mask: Bitboard,
+    targets: Targets,
     non_promo_restriction: Bitboard,
 ) {
     generate_precalculated_with_color_and_occ::<CAN_PROMOTE, _>(
@@ -486,7 +704,7 @@ fn generate_precalculated_with_occ<const CAN_PROMOTE: bool, F: Fn(Square, Bitboa
         pos,
         pieces,
         |_color, square, occ| -> Bitboard { attack_getter(square, occ) },
-        dst_mask,
+        targets,
         non_promo_restriction,
     );
 }
@@ -499,7 +717,7 @@ fn generate_precalculated_with_color_and_occ<
     pos: &Position,
     pieces: Bitboard,
     attack_getter: F,
-    dst_mask: Bitboard,
+    targets: Targets,
     non_promo_restriction: Bitboard,
 ) {
     let stm = pos.side_to_move();
@@ -512,7 +730,7 @@ fn generate_precalculated_with_color_and_occ<
         let from = movable.pop_lsb();
         let attacks = attack_getter(stm, from, occ) & non_promo_restriction;
 
-        serialize_normals(dst, from, attacks, dst_mask);
+        serialize_normals(dst, from, attacks, targets.normal);
     }
 
     if CAN_PROMOTE {
@@ -522,7 +740,7 @@ fn generate_precalculated_with_color_and_occ<
             let from = promotable.pop_lsb();
             let attacks = attack_getter(stm, from, occ) & promo_area;
 
-            serialize_promotions(dst, from, attacks, dst_mask);
+            serialize_promotions(dst, from, attacks, targets.promotion);
         }
 
         promotable = pieces & promo_area;
@@ -531,7 +749,7 @@ fn generate_precalculated_with_color_and_occ<
             let from = promotable.pop_lsb();
             let attacks = attack_getter(stm, from, occ) & !promo_area;
 
-            serialize_promotions(dst, from, attacks, dst_mask);
+            serialize_promotions(dst, from, attacks, targets.promotion);
         }
     }
 }
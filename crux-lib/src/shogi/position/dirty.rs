@@ -0,0 +1,97 @@
+use std::slice::Iter;
+
+use crate::shogi::core::{Color, Piece, PieceType, Square};
+
+/// A piece at a particular place in a position.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Placement {
+    /// A piece on a square of the board.
+    Board(Piece, Square),
+    /// The `index`-th piece, counted from zero, of an unpromoted piece type in a color's hand.
+    Hand(Color, PieceType, u32),
+}
+
+/// Up to two placements, which can be pushed in const contexts.
+#[derive(Debug, Copy, Clone)]
+pub struct Placements {
+    items: [Placement; 2],
+    len: usize,
+}
+
+impl Placements {
+    /// Creates an empty list.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            // Placeholders, never read past `len`.
+            items: [Placement::Hand(Color::Black, PieceType::Pawn, 0); 2],
+            len: 0,
+        }
+    }
+
+    /// Adds a placement.
+    ///
+    /// # Panics
+    /// Panics if the list already holds two placements.
+    pub const fn push(&mut self, placement: Placement) {
+        self.items[self.len] = placement;
+        self.len += 1;
+    }
+
+    #[must_use]
+    pub const fn len(&self) -> usize {
+        self.len
+    }
+
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    #[must_use]
+    pub const fn as_slice(&self) -> &[Placement] {
+        self.items.split_at(self.len).0
+    }
+
+    pub fn iter(&self) -> Iter<'_, Placement> {
+        self.as_slice().iter()
+    }
+}
+
+impl Default for Placements {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The pieces removed and added by the last move made on a position.
+///
+/// A move changes at most two placements each way: a capture removes the moving and the
+/// captured piece from the board, and adds the moved piece to the board and the captured
+/// piece to the hand.
+#[derive(Debug, Copy, Clone, Default)]
+pub struct DirtyPieces {
+    pub removed: Placements,
+    pub added: Placements,
+}
+
+impl DirtyPieces {
+    /// Creates an empty set of changes.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            removed: Placements::new(),
+            added: Placements::new(),
+        }
+    }
+
+    /// Returns `true` if the king of the given color was moved.
+    #[must_use]
+    pub fn king_moved(&self, color: Color) -> bool {
+        let king = PieceType::King.with_color(color);
+
+        self.removed
+            .iter()
+            .any(|placement| matches!(placement, Placement::Board(piece, _) if *piece == king))
+    }
+}
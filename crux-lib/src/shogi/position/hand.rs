@@ -88,6 +88,20 @@ impl Hand {
         self.set(piece_type, self.count(piece_type) - 1);
     }
 
+    /// Returns `true` if this hand has at least as many pieces of every type as `other`.
+    #[must_use]
+    pub const fn dominates(self, other: Self) -> bool {
+        const_for!(i in 0..Self::HAND_PIECE_TYPES => {
+            let mask = Self::MASKS[i];
+
+            if self.0 & mask < other.0 & mask {
+                return false;
+            }
+        });
+
+        true
+    }
+
     /// Returns the maximum number of pieces of the given piece type.
     ///
     /// # Debug assertions
@@ -0,0 +1,81 @@
+use crate::shogi::position::{hand::Hand, key::Key};
+
+/// Number of preceding positions kept for repetition detection, enough for a full game.
+///
+/// Older positions are forgotten, so repetitions spanning more plies are not detected.
+pub(crate) const HISTORY_CAPACITY: usize = 512;
+
+/// The state of a position preceding the current one, used for repetition detection.
+#[derive(Debug, Copy, Clone)]
+pub(crate) struct HistoryEntry {
+    pub board_key: Key,
+    pub hand_key: Key,
+    /// Hand of the side to move, the only one compared since repetitions are looked for at
+    /// even distances.
+    pub hand: Hand,
+    pub in_check: bool,
+}
+
+impl HistoryEntry {
+    const EMPTY: Self = Self {
+        board_key: Key::default(),
+        hand_key: Key::default(),
+        hand: Hand::default(),
+        in_check: false,
+    };
+}
+
+/// The most recent preceding positions, in a fixed-capacity ring so that positions are
+/// copied without allocating and moves can be made in const contexts.
+#[derive(Debug, Copy, Clone)]
+pub(crate) struct History {
+    entries: [HistoryEntry; HISTORY_CAPACITY],
+    /// Index the next entry is written to.
+    head: usize,
+    len: usize,
+}
+
+impl History {
+    pub const fn new() -> Self {
+        Self {
+            entries: [HistoryEntry::EMPTY; HISTORY_CAPACITY],
+            head: 0,
+            len: 0,
+        }
+    }
+
+    /// Returns the number of positions kept.
+    pub const fn len(&self) -> usize {
+        self.len
+    }
+
+    pub const fn clear(&mut self) {
+        self.head = 0;
+        self.len = 0;
+    }
+
+    /// Adds a position, forgetting the oldest one if the history is full.
+    pub const fn push(&mut self, entry: HistoryEntry) {
+        self.entries[self.head] = entry;
+        self.head = (self.head + 1) % HISTORY_CAPACITY;
+
+        if self.len < HISTORY_CAPACITY {
+            self.len += 1;
+        }
+    }
+
+    /// Removes the most recent position, if any.
+    pub const fn pop(&mut self) {
+        if self.len > 0 {
+            self.head = (self.head + HISTORY_CAPACITY - 1) % HISTORY_CAPACITY;
+            self.len -= 1;
+        }
+    }
+
+    /// Returns the position `distance` plies before the current one, from 1 to `len`.
+    pub const fn get(&self, distance: usize) -> &HistoryEntry {
+        debug_assert!(distance > 0 && distance <= self.len);
+
+        &self.entries[(self.head + HISTORY_CAPACITY - distance) % HISTORY_CAPACITY]
+    }
+}
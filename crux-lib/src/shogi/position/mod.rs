@@ -1,4 +1,6 @@
+pub mod dirty;
 pub mod hand;
+mod history;
 pub mod key;
 pub mod mv;
 pub mod zobrist;
@@ -9,24 +11,51 @@ use const_for::const_for;
 
 use crate::shogi::{
     attacks::{
-        bishop_pseudo_attacks, gold_attacks, king_attacks, knight_attacks, lance_pseudo_attacks,
-        pawn_attacks, ray_between, rook_pseudo_attacks, silver_attacks,
+        bishop_attacks, bishop_pseudo_attacks, gold_attacks, king_attacks, knight_attacks,
+        lance_attacks, lance_pseudo_attacks, pawn_attacks, ray_between, ray_intersecting,
+        rook_attacks, rook_pseudo_attacks, silver_attacks,
     },
     bitboard::Bitboard,
     core::{Color, File, Piece, PieceType, Rank, Square, MAX_KING},
+    movegen::{generate_legal, is_legal_move, MoveList},
     position::{
+        dirty::{DirtyPieces, Placement},
         hand::Hand,
+        history::{History, HistoryEntry},
         key::Key,
         mv::Move,
         zobrist::{hand_key, piece_square_key, side_key},
     },
 };
 
+/// The kind of repetition detected by [`Position::repetition`],
+/// seen from the side to move.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Repetition {
+    /// The same position repeated without perpetual check.
+    Draw,
+    /// The opponent has been giving check on every move since the first occurrence,
+    /// so the opponent loses by perpetual check.
+    Win,
+    /// The side to move has been giving check on every move since the first occurrence,
+    /// so the side to move loses by perpetual check.
+    Loss,
+    /// The same board recurs with the side to move holding more pieces in hand.
+    Superior,
+    /// The same board recurs with the side to move holding fewer pieces in hand.
+    Inferior,
+}
+
 /// Represents a shogi position.
 ///
 /// This struct stores both the primary game state (piece placement, side to move,
 /// pieces in hand) and derived information used for fast move generation and
-/// legality checks, such as checkers, pinners, and pinned pieces.
+/// legality checks, such as checkers, pinners, and pinned pieces, as well as
+/// check squares and king blockers used to detect checking moves.
+///
+/// It also records the positions reached by [`Position::make_move`], so that
+/// repetitions can be detected, and the pieces changed by the last move, so that
+/// evaluators can be updated incrementally.
 #[derive(Debug, Clone)]
 pub struct Position {
     side_to_move: Color,
@@ -38,8 +67,13 @@ pub struct Position {
     checkers: Bitboard,
     pinners: Bitboard,
     pinned: Bitboard,
+    check_squares: [Bitboard; PieceType::COUNT],
+    blockers_for_king: [Bitboard; Color::COUNT],
     ply: u32,
-    key: Key,
+    board_key: Key,
+    hand_key: Key,
+    history: History,
+    dirty_pieces: DirtyPieces,
 }
 
 impl const Default for Position {
@@ -63,8 +97,13 @@ impl Position {
             checkers: Bitboard::empty(),
             pinners: Bitboard::empty(),
             pinned: Bitboard::empty(),
+            check_squares: [Bitboard::empty(); PieceType::COUNT],
+            blockers_for_king: [Bitboard::empty(); Color::COUNT],
             ply: 0,
-            key: Key::default(),
+            board_key: Key::default(),
+            hand_key: Key::default(),
+            history: History::new(),
+            dirty_pieces: DirtyPieces::new(),
         }
     }
 
@@ -72,7 +111,7 @@ impl Position {
     #[must_use]
     pub const fn startpos() -> Self {
         const STARTPOS: Position = {
-            let mut builder = Position::default().builder();
+            let mut builder = PositionBuilder(Position::default());
 
             const fn place(
                 builder: &mut PositionBuilder,
@@ -122,29 +161,47 @@ impl Position {
     }
 
     /// Returns a builder for modifying this `Position`.
+    ///
+    /// The move history is discarded, as it no longer leads to the modified position.
     #[must_use]
-    pub const fn builder(self) -> PositionBuilder {
+    pub const fn builder(mut self) -> PositionBuilder {
+        self.history.clear();
+
         PositionBuilder(self)
     }
 
     /// Applies a move to the position.
     ///
-    /// Updates the board state, hands, side to move, ply, and checker-related state.
-    /// /// Returns `Some(piece)` if a piece was captured, or `None` otherwise.
+    /// Updates the board state, hands, side to move, ply, and checker-related state,
+    /// and records the previous position in the history.
+    /// Returns `Some(piece)` if a piece was captured, or `None` otherwise.
     ///
     /// # Debug assertions
     /// In debug builds, panics if the move is inconsistent with the current position.
     pub const fn make_move(&mut self, mv: Move) -> Option<Piece> {
+        self.history.push(HistoryEntry {
+            board_key: self.board_key,
+            hand_key: self.hand_key,
+            hand: self.hand(self.side_to_move()),
+            in_check: self.checkers.has_any(),
+        });
+
         let stm = self.side_to_move();
         let nstm = stm.opposite();
         let to_piece = self.piece_at(mv.to());
         let moved_piece: Piece;
+        let mut dirty = DirtyPieces::new();
 
         if mv.is_drop() {
             debug_assert!(to_piece.is_none());
 
-            moved_piece = mv.drop_piece_type().with_color(stm);
-            self.decrement_hand_piece_count(stm, mv.drop_piece_type());
+            let pt = mv.drop_piece_type();
+
+            moved_piece = pt.with_color(stm);
+            self.decrement_hand_piece_count(stm, pt);
+            dirty
+                .removed
+                .push(Placement::Hand(stm, pt, self.hands[stm].count(pt)));
         } else {
             let moving_piece = self.piece_at(mv.from()).unwrap();
             debug_assert!(moving_piece.color() == stm);
@@ -156,16 +213,27 @@ impl Position {
             };
 
             self.remove(mv.from());
+            dirty
+                .removed
+                .push(Placement::Board(moving_piece, mv.from()));
 
             if let Some(captured) = to_piece {
                 debug_assert!(captured.color() == nstm && captured.piece_type() != PieceType::King);
 
-                self.increment_hand_piece_count(stm, captured.piece_type().unpromoted());
+                let pt = captured.piece_type().unpromoted();
+
+                dirty
+                    .added
+                    .push(Placement::Hand(stm, pt, self.hands[stm].count(pt)));
+                dirty.removed.push(Placement::Board(captured, mv.to()));
+                self.increment_hand_piece_count(stm, pt);
                 self.remove(mv.to());
             }
         }
 
         self.place(mv.to(), moved_piece);
+        dirty.added.push(Placement::Board(moved_piece, mv.to()));
+        self.dirty_pieces = dirty;
         self.set_side_to_move(nstm);
         self.ply += 1;
 
@@ -175,6 +243,7 @@ impl Position {
         self.clear_checker_states();
         self.update_checkers_for(mv.to());
         self.update_sliding_checkers_and_pins();
+        self.update_check_info();
 
         to_piece
     }
@@ -189,6 +258,8 @@ impl Position {
     pub const fn unmake_move(&mut self, mv: Move, captured: Option<Piece>) {
         debug_assert!(self.ply > 0);
 
+        self.history.pop();
+
         let stm = self.side_to_move();
         let nstm = stm.opposite();
         let moved_piece = self.piece_at(mv.to()).unwrap();
@@ -226,6 +297,16 @@ impl Position {
         self.clear_checker_states();
         self.update_non_sliding_checkers();
         self.update_sliding_checkers_and_pins();
+        self.update_check_info();
+    }
+
+    /// Returns the pieces changed by the last move made with [`Position::make_move`].
+    ///
+    /// Not restored by [`Position::unmake_move`], so this is only meaningful right after
+    /// a move is made.
+    #[must_use]
+    pub const fn dirty_pieces(&self) -> &DirtyPieces {
+        &self.dirty_pieces
     }
 
     /// Returns the side to move.
@@ -288,6 +369,46 @@ impl Position {
         self.king_squares[color]
     }
 
+    /// Returns a bitboard of pieces of both colors attacking the given square,
+    /// with sliding attacks computed on `occupied`.
+    ///
+    /// Pieces outside `occupied` may be included; mask the result with `occupied`
+    /// to exclude them.
+    #[must_use]
+    pub const fn attackers_to(&self, square: Square, occupied: Bitboard) -> Bitboard {
+        let golds = self.piece_type_bb(PieceType::Gold)
+            | self.piece_type_bb(PieceType::ProPawn)
+            | self.piece_type_bb(PieceType::ProLance)
+            | self.piece_type_bb(PieceType::ProKnight)
+            | self.piece_type_bb(PieceType::ProSilver);
+        let bishops = self.piece_type_bb(PieceType::Bishop) | self.piece_type_bb(PieceType::Horse);
+        let rooks = self.piece_type_bb(PieceType::Rook) | self.piece_type_bb(PieceType::Dragon);
+        let kings = self.piece_type_bb(PieceType::King)
+            | self.piece_type_bb(PieceType::Horse)
+            | self.piece_type_bb(PieceType::Dragon);
+
+        let mut attackers = (bishops & bishop_attacks(square, occupied))
+            | (rooks & rook_attacks(square, occupied))
+            | (kings & king_attacks(square));
+
+        const_for!(color in 0..Color::COUNT => {
+            let color = Color::from(color);
+            let opposite = color.opposite();
+
+            // A piece of `color` attacks `square` if the same piece of the opposite color
+            // on `square` would attack it.
+            attackers |= ((self.piece_type_bb(PieceType::Pawn) & pawn_attacks(opposite, square))
+                | (self.piece_type_bb(PieceType::Lance)
+                    & lance_attacks(opposite, square, occupied))
+                | (self.piece_type_bb(PieceType::Knight) & knight_attacks(opposite, square))
+                | (self.piece_type_bb(PieceType::Silver) & silver_attacks(opposite, square))
+                | (golds & gold_attacks(opposite, square)))
+                & self.color_bb(color);
+        });
+
+        attackers
+    }
+
     /// Returns a bitboard of pieces currently giving check to
     /// the king of the side to move.
     #[must_use]
@@ -309,6 +430,80 @@ impl Position {
         self.pinned
     }
 
+    /// Returns a bitboard of squares from which a piece of the given type of
+    /// the side to move would give check to the opponent king.
+    #[must_use]
+    pub const fn check_squares(&self, piece_type: PieceType) -> Bitboard {
+        self.check_squares[piece_type]
+    }
+
+    /// Returns a bitboard of pieces of either color that are the only piece between
+    /// the king of the given color and an opponent sliding piece.
+    ///
+    /// Pieces of the side to move in `blockers_for_king(side_to_move)` are pinned, and
+    /// those in `blockers_for_king(opponent)` give discovered check when they move
+    /// off the line.
+    #[must_use]
+    pub const fn blockers_for_king(&self, color: Color) -> Bitboard {
+        self.blockers_for_king[color]
+    }
+
+    /// Returns `true` if `mv` gives check to the opponent king.
+    ///
+    /// This covers direct checks, including those made possible by promotion or by a drop,
+    /// and discovered checks.
+    ///
+    /// # Debug assertions
+    /// In debug builds, panics if `mv` is a normal move from an empty square.
+    #[must_use]
+    pub const fn gives_check(&self, mv: Move) -> bool {
+        let to = mv.to();
+
+        if mv.is_drop() {
+            return self.check_squares(mv.drop_piece_type()).contains(to);
+        }
+
+        let from = mv.from();
+        let piece_type = self.piece_at(from).unwrap().piece_type();
+        let moved = if mv.is_promotion() {
+            piece_type.promoted()
+        } else {
+            piece_type
+        };
+
+        if self.check_squares(moved).contains(to) {
+            return true;
+        }
+
+        let nstm = self.side_to_move().opposite();
+
+        // A discovered check, unless the piece stays on the line to the king.
+        match self.king_square(nstm) {
+            Some(king_square) => {
+                self.blockers_for_king(nstm).contains(from)
+                    && !ray_intersecting(from, king_square).contains(to)
+            }
+            None => false,
+        }
+    }
+
+    /// Returns the legal moves of the side to move.
+    ///
+    /// See [`generate_legal`] for details.
+    #[must_use]
+    pub fn legal_moves(&self) -> MoveList {
+        generate_legal(self)
+    }
+
+    /// Returns `true` if `mv` is a legal move.
+    ///
+    /// Any move is accepted, including ones that are not even pseudo-legal.
+    /// See [`is_legal_move`] for details.
+    #[must_use]
+    pub fn is_legal_move(&self, mv: Move) -> bool {
+        is_legal_move(self, mv)
+    }
+
     /// Returns the current ply count.
     #[must_use]
     pub const fn ply(&self) -> u32 {
@@ -316,15 +511,101 @@ impl Position {
     }
 
     /// Returns the Zobrist hash key of the position.
+    ///
+    /// This is the combination of [`Position::board_key`] and [`Position::hand_key`].
     #[must_use]
     pub const fn key(&self) -> Key {
-        self.key
+        self.board_key ^ self.hand_key
+    }
+
+    /// Returns the Zobrist hash key of the piece placement and the side to move,
+    /// ignoring pieces in hand.
+    #[must_use]
+    pub const fn board_key(&self) -> Key {
+        self.board_key
+    }
+
+    /// Returns the Zobrist hash key of the pieces in hand of both sides.
+    #[must_use]
+    pub const fn hand_key(&self) -> Key {
+        self.hand_key
+    }
+
+    /// Detects whether the current position repeats an earlier one in the history.
+    ///
+    /// Returns a draw or a perpetual check result if the current position occurred
+    /// `count` times before, with the same pieces in hand and side to move. For the
+    /// sennichite rule, pass `3` to detect the fourth occurrence; a search usually
+    /// passes `1`. Perpetual check is judged over the moves since the earliest
+    /// of these occurrences. Only the last `HISTORY_CAPACITY` (512) positions are kept,
+    /// enough for a full game, so earlier occurrences are not counted.
+    ///
+    /// Otherwise, returns [`Repetition::Superior`] or [`Repetition::Inferior`] if the
+    /// most recent earlier position with the same board differs only in pieces in hand
+    /// and one hand of the side to move dominates the other.
+    #[must_use]
+    pub fn repetition(&self, count: usize) -> Option<Repetition> {
+        debug_assert!(count > 0);
+
+        let stm = self.side_to_move();
+        let len = self.history.len();
+        let mut occurrences = 0;
+        let mut hand_repetition = None;
+
+        // The same side is to move only at even distances,
+        // and a position cannot recur within two plies.
+        for distance in (4..=len).step_by(2) {
+            let entry = self.history.get(distance);
+
+            if entry.board_key != self.board_key {
+                continue;
+            }
+
+            if entry.hand_key == self.hand_key {
+                occurrences += 1;
+
+                if occurrences == count {
+                    return Some(self.classify_repetition(distance));
+                }
+            } else if occurrences == 0 && hand_repetition.is_none() {
+                let hand = self.hand(stm);
+                let old_hand = entry.hand;
+
+                if hand.dominates(old_hand) {
+                    hand_repetition = Some(Repetition::Superior);
+                } else if old_hand.dominates(hand) {
+                    hand_repetition = Some(Repetition::Inferior);
+                }
+            }
+        }
+
+        hand_repetition
+    }
+
+    /// Decides the result of a repetition whose first occurrence is `distance` plies ago.
+    fn classify_repetition(&self, distance: usize) -> Repetition {
+        let in_check = |ply: usize| {
+            if ply == 0 {
+                self.checkers.has_any()
+            } else {
+                self.history.get(ply).in_check
+            }
+        };
+
+        let checked = (0..distance).step_by(2).all(in_check);
+        let checking = (1..distance).step_by(2).all(in_check);
+
+        match (checked, checking) {
+            (true, false) => Repetition::Win,
+            (false, true) => Repetition::Loss,
+            _ => Repetition::Draw,
+        }
     }
 
     const fn set_side_to_move(&mut self, side_to_move: Color) {
         if self.side_to_move != side_to_move {
             self.side_to_move = side_to_move;
-            self.key ^= side_key();
+            self.board_key ^= side_key();
         }
     }
 
@@ -346,7 +627,7 @@ impl Position {
             self.king_squares[color] = Some(square);
         }
 
-        self.key ^= piece_square_key(piece, square);
+        self.board_key ^= piece_square_key(piece, square);
     }
 
     const fn remove(&mut self, square: Square) {
@@ -368,7 +649,7 @@ impl Position {
             self.king_squares[color] = None;
         }
 
-        self.key ^= piece_square_key(piece, square);
+        self.board_key ^= piece_square_key(piece, square);
     }
 
     const fn set_hand_piece_count(&mut self, color: Color, piece_type: PieceType, new_count: u32) {
@@ -401,7 +682,7 @@ impl Position {
     ) {
         let diff = hand_key(color, piece_type, old_count) ^ hand_key(color, piece_type, new_count);
 
-        self.key ^= diff;
+        self.hand_key ^= diff;
     }
 
     const fn update_checkers_for(&mut self, square: Square) {
@@ -489,6 +770,73 @@ impl Position {
         }
     }
 
+    const fn update_check_info(&mut self) {
+        self.blockers_for_king = [
+            self.slider_blockers(Color::Black),
+            self.slider_blockers(Color::White),
+        ];
+
+        let nstm = self.side_to_move().opposite();
+
+        self.check_squares = match self.king_square(nstm) {
+            Some(king_square) => {
+                let occ = self.occupancy();
+                let golds = gold_attacks(nstm, king_square);
+                let bishops = bishop_attacks(king_square, occ);
+                let rooks = rook_attacks(king_square, occ);
+                let kings = king_attacks(king_square);
+
+                [
+                    pawn_attacks(nstm, king_square),
+                    lance_attacks(nstm, king_square, occ),
+                    knight_attacks(nstm, king_square),
+                    silver_attacks(nstm, king_square),
+                    golds,
+                    bishops,
+                    rooks,
+                    golds,
+                    golds,
+                    golds,
+                    golds,
+                    bishops | kings,
+                    rooks | kings,
+                    Bitboard::empty(),
+                ]
+            }
+            None => [Bitboard::empty(); PieceType::COUNT],
+        };
+    }
+
+    /// Returns the pieces that are the only piece between the king of `color`
+    /// and an opponent sliding piece.
+    const fn slider_blockers(&self, color: Color) -> Bitboard {
+        let mut blockers = Bitboard::empty();
+
+        if let Some(king_square) = self.king_square(color) {
+            let lances = self.piece_type_bb(PieceType::Lance);
+            let bishops =
+                self.piece_type_bb(PieceType::Bishop) | self.piece_type_bb(PieceType::Horse);
+            let rooks = self.piece_type_bb(PieceType::Rook) | self.piece_type_bb(PieceType::Dragon);
+
+            let mut snipers = ((lances & lance_pseudo_attacks(color, king_square))
+                | (bishops & bishop_pseudo_attacks(king_square))
+                | (rooks & rook_pseudo_attacks(king_square)))
+                & self.color_bb(color.opposite());
+
+            let occ = self.occupancy();
+
+            while snipers.has_any() {
+                let between = ray_between(king_square, snipers.pop_lsb()) & occ;
+
+                if between.is_single() {
+                    blockers |= between;
+                }
+            }
+        }
+
+        blockers
+    }
+
     const fn clear_checker_states(&mut self) {
         self.checkers = Bitboard::empty();
         self.pinners = Bitboard::empty();
@@ -621,6 +969,12 @@ impl Display for Position {
 pub struct PositionBuilder(Position);
 
 impl PositionBuilder {
+    /// Returns the piece on the given square, if any.
+    #[must_use]
+    pub const fn piece_at(&self, square: Square) -> Option<Piece> {
+        self.0.piece_at(square)
+    }
+
     /// Sets the side to move.
     pub const fn set_side_to_move(&mut self, side_to_move: Color) -> &mut Self {
         self.0.set_side_to_move(side_to_move);
@@ -756,6 +1110,7 @@ impl PositionBuilder {
         self.0.clear_checker_states();
         self.0.update_non_sliding_checkers();
         self.0.update_sliding_checkers_and_pins();
+        self.0.update_check_info();
 
         self.0
     }
@@ -125,6 +125,17 @@ impl Move {
         self.0.get()
     }
 
+    /// Creates a move from its `u16` representation, or `None` if `value` is zero.
+    ///
+    /// `value` must have been obtained from [`Move::as_u16`].
+    #[must_use]
+    pub const fn from_u16(value: u16) -> Option<Self> {
+        match NonZeroU16::new(value) {
+            Some(value) => Some(Self(value)),
+            None => None,
+        }
+    }
+
     const FROM_SHIFT: u16 = 7;
     const DROP_PIECE_SHIFT: u16 = 7;
     const DROP_FLAG_MASK: u16 = 1 << 14;
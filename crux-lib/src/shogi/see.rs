@@ -0,0 +1,137 @@
+use crate::{
+    eval::piece_value,
+    shogi::{
+        bitboard::{promotion_area, Bitboard},
+        core::{PieceType, Square},
+        position::{mv::Move, Position},
+    },
+};
+
+/// Piece types ordered from the least valuable attacker to the most valuable one.
+const ATTACKER_ORDER: [PieceType; PieceType::COUNT] = [
+    PieceType::Pawn,
+    PieceType::Lance,
+    PieceType::Knight,
+    PieceType::Silver,
+    PieceType::ProPawn,
+    PieceType::ProLance,
+    PieceType::ProKnight,
+    PieceType::ProSilver,
+    PieceType::Gold,
+    PieceType::Bishop,
+    PieceType::Horse,
+    PieceType::Rook,
+    PieceType::Dragon,
+    PieceType::King,
+];
+
+/// Maximum number of captures in an exchange, bounded by the number of pieces.
+const MAX_EXCHANGES: usize = 40;
+
+/// Returns the value of `piece_type` used by the static exchange evaluation,
+/// which is its material value.
+#[must_use]
+pub const fn see_value(piece_type: PieceType) -> i32 {
+    piece_value(piece_type)
+}
+
+/// Returns the static exchange evaluation of `mv`.
+///
+/// Both sides repeatedly recapture on the destination square with their least valuable
+/// attacker, and either side may stop capturing when it is favorable. The result is the
+/// material balance for the side to move, including the gain of promotions by the move
+/// itself and by the recaptures. Sliding attackers hidden behind other pieces, such as
+/// lances, bishops, rooks, horses and dragons, join the exchange once the squares in
+/// front of them are vacated.
+///
+/// Pins are not taken into account, and a king only recaptures if the square is not
+/// defended by the opponent.
+///
+/// # Panics
+/// Panics if `mv` is a normal move from an empty square.
+#[must_use]
+pub fn see(pos: &Position, mv: Move) -> i32 {
+    let to = mv.to();
+    let mut gain = [0; MAX_EXCHANGES + 1];
+    let mut occupied = pos.occupancy();
+    let mut side = pos.side_to_move();
+
+    // The value of the piece standing on `to`, which is the next one to be captured.
+    let mut victim;
+
+    if mv.is_drop() {
+        victim = see_value(mv.drop_piece_type());
+        occupied |= to.bit();
+    } else {
+        let from = mv.from();
+        let piece_type = pos.piece_at(from).unwrap().piece_type();
+        let moved = if mv.is_promotion() {
+            piece_type.promoted()
+        } else {
+            piece_type
+        };
+
+        gain[0] = pos
+            .piece_at(to)
+            .map_or(0, |captured| see_value(captured.piece_type()))
+            + see_value(moved)
+            - see_value(piece_type);
+        victim = see_value(moved);
+        occupied ^= from.bit();
+    }
+
+    let mut depth = 0;
+
+    while depth < MAX_EXCHANGES {
+        side = side.opposite();
+
+        let attackers = pos.attackers_to(to, occupied) & occupied & pos.color_bb(side);
+
+        let Some((piece_type, from)) = least_valuable_attacker(pos, attackers) else {
+            break;
+        };
+
+        occupied ^= from.bit();
+
+        // The king cannot capture into a defended square.
+        if piece_type == PieceType::King
+            && (pos.attackers_to(to, occupied) & occupied & pos.color_bb(side.opposite())).has_any()
+        {
+            break;
+        }
+
+        let area = promotion_area(side);
+        let moved = if piece_type.can_promote() && (area.contains(from) || area.contains(to)) {
+            piece_type.promoted()
+        } else {
+            piece_type
+        };
+
+        depth += 1;
+        gain[depth] = victim + see_value(moved) - see_value(piece_type) - gain[depth - 1];
+        victim = see_value(moved);
+    }
+
+    while depth > 0 {
+        gain[depth - 1] = -(-gain[depth - 1]).max(gain[depth]);
+        depth -= 1;
+    }
+
+    gain[0]
+}
+
+/// Returns `true` if the static exchange evaluation of `mv` is at least `threshold`.
+///
+/// See [`see`] for the details of the evaluation.
+#[must_use]
+pub fn see_ge(pos: &Position, mv: Move, threshold: i32) -> bool {
+    see(pos, mv) >= threshold
+}
+
+fn least_valuable_attacker(pos: &Position, attackers: Bitboard) -> Option<(PieceType, Square)> {
+    ATTACKER_ORDER.iter().find_map(|&piece_type| {
+        let pieces = attackers & pos.piece_type_bb(piece_type);
+
+        pieces.has_any().then(|| (piece_type, pieces.lsb()))
+    })
+}
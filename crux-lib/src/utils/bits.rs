@@ -0,0 +1,73 @@
+/// Writes bits into a byte buffer, least significant bit first.
+pub struct BitWriter<'a> {
+    data: &'a mut [u8],
+    cursor: usize,
+}
+
+impl<'a> BitWriter<'a> {
+    pub fn new(data: &'a mut [u8]) -> Self {
+        data.fill(0);
+
+        Self { data, cursor: 0 }
+    }
+
+    /// Returns the number of bits written.
+    #[must_use]
+    pub const fn cursor(&self) -> usize {
+        self.cursor
+    }
+
+    pub fn write_bit(&mut self, bit: bool) {
+        if bit {
+            self.data[self.cursor / 8] |= 1 << (self.cursor % 8);
+        }
+
+        self.cursor += 1;
+    }
+
+    /// Writes the lowest `count` bits of `value`.
+    pub fn write_bits(&mut self, value: u32, count: u32) {
+        for i in 0..count {
+            self.write_bit(value & (1 << i) != 0);
+        }
+    }
+}
+
+/// Reads bits from a byte buffer, least significant bit first.
+pub struct BitReader<'a> {
+    data: &'a [u8],
+    cursor: usize,
+}
+
+impl<'a> BitReader<'a> {
+    pub const fn new(data: &'a [u8]) -> Self {
+        Self { data, cursor: 0 }
+    }
+
+    /// Returns `true` if all bits have been read.
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.cursor == self.data.len() * 8
+    }
+
+    /// Reads one bit, or returns `None` at the end of the buffer.
+    pub fn read_bit(&mut self) -> Option<bool> {
+        let byte = self.data.get(self.cursor / 8)?;
+        let bit = byte & (1 << (self.cursor % 8)) != 0;
+
+        self.cursor += 1;
+
+        Some(bit)
+    }
+
+    /// Reads `count` bits into the lowest bits of the result.
+    pub fn read_bits(&mut self, count: u32) -> Option<u32> {
+        let mut value = 0;
+
+        for i in 0..count {
+            value |= u32::from(self.read_bit()?) << i;
+        }
+
+        Some(value)
+    }
+}
@@ -0,0 +1,33 @@
+use crux_lib::args::Args;
+
+fn args(args: &[&str]) -> Args {
+    Args::new(args.iter().map(|arg| arg.to_string()).collect())
+}
+
+#[test]
+fn flags_and_values() {
+    let mut args = args(&["--games", "3", "--comments", "--host", "localhost"]);
+
+    assert_eq!(args.next_flag().unwrap().as_deref(), Some("--games"));
+    assert_eq!(args.value::<u32>("--games").unwrap(), 3);
+    assert_eq!(args.next_flag().unwrap().as_deref(), Some("--comments"));
+    assert_eq!(args.next_flag().unwrap().as_deref(), Some("--host"));
+    assert_eq!(args.value::<String>("--host").unwrap(), "localhost");
+    assert_eq!(args.next_flag().unwrap(), None);
+}
+
+#[test]
+fn invalid_args() {
+    assert_eq!(
+        args(&["games"]).next_flag().unwrap_err(),
+        "unexpected argument: games"
+    );
+    assert_eq!(
+        args(&[]).value::<u32>("--games").unwrap_err(),
+        "missing value for --games"
+    );
+    assert_eq!(
+        args(&["x"]).value::<u32>("--games").unwrap_err(),
+        "invalid value for --games: x"
+    );
+}
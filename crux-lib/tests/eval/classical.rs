@@ -0,0 +1,96 @@
+use crux_lib::{
+    eval::classical::evaluate,
+    notation::{usi::Usi, Notation},
+    shogi::{
+        core::{Color, PieceType, Square},
+        position::{hand::Hand, Position},
+    },
+};
+
+/// Returns the position with the board rotated and the colors swapped.
+fn flipped(pos: &Position) -> Position {
+    let mut builder = Position::empty().builder();
+
+    for square in Square::ALL {
+        if let Some(piece) = pos.piece_at(square) {
+            let piece = piece.piece_type().with_color(piece.color().opposite());
+
+            builder.place(square.rotate180(), piece);
+        }
+    }
+
+    for color in Color::ALL {
+        for &piece_type in PieceType::ALL.iter().take(Hand::HAND_PIECE_TYPES) {
+            let count = pos.hand(color).count(piece_type);
+
+            builder.set_hand_piece_count(color.opposite(), piece_type, count);
+        }
+    }
+
+    builder.set_side_to_move(pos.side_to_move().opposite());
+    builder.build()
+}
+
+fn eval(sfen: &str) -> i32 {
+    evaluate(&Usi::parse_position(sfen).unwrap())
+}
+
+#[test]
+fn startpos_is_balanced() {
+    assert_eq!(evaluate(&Position::startpos()), 0);
+}
+
+#[test]
+fn symmetric() {
+    let sfens = [
+        "l6nl/5+P1gk/2np1S3/p1p4Pp/3P2Sp1/1PPb2P1P/P5GS1/R8/LN4bKL w RGgsn5p 1",
+        "ln1g3nl/1r3kg2/p2pppsp1/2ps2p1p/1p7/2P1P1P2/PPSP1PNPP/2G2S1R1/LN2KG2L b Bb 1",
+        "4k4/9/4P4/9/9/9/9/9/9 b G2r2b3g4s4n4l17p 1",
+    ];
+
+    for sfen in sfens {
+        let pos = Usi::parse_position(sfen).unwrap();
+
+        assert_eq!(evaluate(&pos), evaluate(&flipped(&pos)), "{sfen}");
+    }
+}
+
+#[test]
+fn material() {
+    // Black has captured White's rook.
+    assert!(eval("lnsgkgsnl/7b1/ppppppppp/9/9/9/PPPPPPPPP/1B5R1/LNSGKGSNL b R 1") > 1500);
+    assert!(eval("lnsgkgsnl/7b1/ppppppppp/9/9/9/PPPPPPPPP/1B5R1/LNSGKGSNL w R 1") < -1500);
+}
+
+#[test]
+fn diminishing_hand_value() {
+    let with_pawns = |hand: &str| eval(&format!("4k4/9/9/9/9/9/9/9/4K4 b {hand} 1"));
+
+    let first = with_pawns("P") - with_pawns("-");
+    let fifth = with_pawns("5P") - with_pawns("4P");
+
+    assert!(first > fifth);
+    assert!(fifth > 0);
+}
+
+#[test]
+fn promoted_bonus() {
+    let silver = eval("4k4/9/9/9/9/9/4S4/9/4K4 b - 1");
+    let promoted_silver = eval("4k4/9/9/9/9/9/4+S4/9/4K4 b - 1");
+    let gold = eval("4k4/9/9/9/9/9/4G4/9/4K4 b - 1");
+    let tokin = eval("4k4/9/9/9/9/9/4+P4/9/4K4 b - 1");
+
+    assert!(promoted_silver > silver);
+    // A tokin moves like a gold, but is worth only a pawn to the opponent.
+    assert!(tokin > gold);
+}
+
+#[test]
+fn king_safety() {
+    // The same material, with White's gold and silver next to Black's king instead of
+    // far away.
+    let safe = eval("4k4/9/9/9/9/9/9/9/g1s1K4 b - 1");
+    let attacked = eval("4k4/9/9/9/9/9/9/3gs4/4K4 b - 1");
+
+    assert!(attacked < safe);
+}
@@ -0,0 +1,2 @@
+mod classical;
+mod nnue;
@@ -0,0 +1,178 @@
+use rand::{rngs::StdRng, RngExt, SeedableRng};
+
+use crux_lib::{
+    eval::{
+        material,
+        nnue::{
+            accumulator::AccumulatorStack,
+            features::{active_features, INPUTS},
+            network::{Network, NetworkError, L1},
+        },
+    },
+    notation::{usi::Usi, Notation},
+    shogi::{core::Color, position::Position},
+};
+
+const POSITIONS: &[&str] = &[
+    "lnsgkgsnl/1r5b1/ppppppppp/9/9/9/PPPPPPPPP/1B5R1/LNSGKGSNL b - 1",
+    "l6nl/5+P1gk/2np1S3/p1p4Pp/3P2Sp1/1PPb2P1P/P5GS1/R8/LN4bKL w RGgsn5p 1",
+    "8k/9/8P/9/9/9/9/9/8K b G 1",
+    // Without a black king, as in tsume problems.
+    "4k4/9/4P4/9/9/9/9/9/9 b G2r2b3g4s4n4l17p 1",
+];
+
+/// A network with arbitrary but deterministic weights.
+fn scrambled_network() -> Network {
+    let mut network = Network::zeroed();
+
+    for (i, row) in network.feature_weights.iter_mut().enumerate() {
+        for (j, weight) in row.iter_mut().enumerate() {
+            *weight = ((i * 31 + j * 17) % 61) as i16 - 30;
+        }
+    }
+
+    for (j, bias) in network.feature_bias.iter_mut().enumerate() {
+        *bias = (j % 64) as i16;
+    }
+
+    for (side, row) in network.output_weights.iter_mut().enumerate() {
+        for (j, weight) in row.iter_mut().enumerate() {
+            *weight = ((j * 7 + side * 3) % 41) as i16 - 20;
+        }
+    }
+
+    network.output_bias = 12345;
+    network
+}
+
+fn refreshed(network: &Network, pos: &Position) -> i32 {
+    AccumulatorStack::new().evaluate(network, pos)
+}
+
+#[test]
+fn active_features_of_startpos() {
+    let pos = Position::startpos();
+    let mut black = active_features(&pos, Color::Black);
+    let mut white = active_features(&pos, Color::White);
+
+    assert_eq!(black.len(), 38);
+
+    // The initial position looks the same from both sides.
+    black.sort_unstable();
+    white.sort_unstable();
+    assert_eq!(black, white);
+    assert!(black.iter().all(|&feature| feature < INPUTS));
+}
+
+#[test]
+fn active_features_are_distinct() {
+    for &sfen in POSITIONS {
+        let pos = Usi::parse_position(sfen).unwrap();
+
+        for color in Color::ALL {
+            let mut features = active_features(&pos, color).to_vec();
+            let len = features.len();
+
+            features.sort_unstable();
+            features.dedup();
+
+            assert_eq!(features.len(), len, "{sfen}");
+        }
+    }
+}
+
+#[test]
+fn incremental_matches_refresh() {
+    let network = scrambled_network();
+    let mut rng = StdRng::seed_from_u64(42);
+
+    for &sfen in POSITIONS {
+        for _ in 0..20 {
+            let mut pos = Usi::parse_position(sfen).unwrap();
+            let mut stack = AccumulatorStack::new();
+            let mut played = Vec::new();
+
+            assert_eq!(stack.evaluate(&network, &pos), refreshed(&network, &pos));
+
+            for _ in 0..40 {
+                let moves = pos.legal_moves();
+
+                if moves.is_empty() {
+                    break;
+                }
+
+                let mv = moves[rng.random_range(0..moves.len())];
+                let captured = pos.make_move(mv);
+
+                stack.push(&pos);
+                played.push((mv, captured));
+
+                // Skip some evaluations, so that several moves are applied at once.
+                if rng.random_range(0..3) == 0 {
+                    assert_eq!(stack.evaluate(&network, &pos), refreshed(&network, &pos));
+                }
+
+                if rng.random_range(0..4) == 0 {
+                    let (mv, captured) = played.pop().unwrap();
+
+                    pos.unmake_move(mv, captured);
+                    stack.pop();
+
+                    assert_eq!(stack.evaluate(&network, &pos), refreshed(&network, &pos));
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn material_network() {
+    let network = Network::material();
+
+    for &sfen in POSITIONS {
+        let pos = Usi::parse_position(sfen).unwrap();
+        let score = refreshed(&network, &pos);
+
+        // Each piece is off by less than one centipawn from rounding the output weights.
+        assert!((score - material(&pos)).abs() <= 40, "{sfen}: {score}");
+    }
+
+    assert_eq!(refreshed(&network, &Position::startpos()), 0);
+}
+
+#[test]
+fn write_and_read() {
+    let network = scrambled_network();
+    let mut bytes = Vec::new();
+
+    network.write(&mut bytes).unwrap();
+
+    let read = Network::read(&mut bytes.as_slice()).unwrap();
+
+    assert!(read.feature_weights == network.feature_weights);
+    assert_eq!(read.feature_bias, network.feature_bias);
+    assert_eq!(read.output_weights, network.output_weights);
+    assert_eq!(read.output_bias, network.output_bias);
+
+    // The header records the number of neurons after the magic, version and inputs.
+    let mut mismatched = bytes.clone();
+
+    mismatched[16..20].copy_from_slice(&(L1 as u32 * 2).to_le_bytes());
+    assert!(matches!(
+        Network::read(&mut mismatched.as_slice()),
+        Err(NetworkError::ArchitectureMismatch)
+    ));
+
+    let mut invalid = bytes.clone();
+
+    invalid[0] = b'X';
+    assert!(matches!(
+        Network::read(&mut invalid.as_slice()),
+        Err(NetworkError::InvalidMagic)
+    ));
+
+    assert!(matches!(
+        Network::read(&mut &bytes[..bytes.len() - 1]),
+        Err(NetworkError::Io(_))
+    ));
+}
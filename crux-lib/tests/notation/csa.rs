@@ -0,0 +1,283 @@
+use std::time::Duration;
+
+use crux_lib::{
+    notation::{
+        csa::{Csa, ParseCsaErrorKind, ParseMoveError, ParsePositionError},
+        game::GameEnd,
+        usi::Usi,
+        Notation,
+    },
+    shogi::{core::Square, position::Position},
+};
+
+use crate::shogi::movegen::TEST_SFENS;
+
+const STARTPOS: &str = "\
+P1-KY-KE-GI-KI-OU-KI-GI-KE-KY
+P2 * -HI *  *  *  *  * -KA * 
+P3-FU-FU-FU-FU-FU-FU-FU-FU-FU
+P4 *  *  *  *  *  *  *  *  * 
+P5 *  *  *  *  *  *  *  *  * 
+P6 *  *  *  *  *  *  *  *  * 
+P7+FU+FU+FU+FU+FU+FU+FU+FU+FU
+P8 * +KA *  *  *  *  * +HI * 
+P9+KY+KE+GI+KI+OU+KI+GI+KE+KY
++
+";
+
+const GAME: &str = "\
+V2.2
+N+先手太郎
+N-後手花子
+$EVENT:テスト対局
+$START_TIME:2024/05/01 10:00:00
+PI
++
+'*対局開始
++7776FU
+T5
+-3334FU
+T3
+'*角道を開ける
++8822UM
+T70
+-3122GI
+T2.500
++0045KA
+T30
+%TORYO
+";
+
+#[test]
+fn squares() {
+    assert_eq!(Csa::parse_square("11").unwrap(), Square::S11);
+    assert_eq!(Csa::parse_square("99").unwrap(), Square::S99);
+    assert_eq!(Csa::format_square(Square::S77), "77");
+
+    for s in ["01", "10", "1", "123"] {
+        assert!(Csa::parse_square(s).is_err(), "{s}");
+    }
+}
+
+#[test]
+fn positions() {
+    let pos = Csa::parse_position(STARTPOS).unwrap();
+
+    assert_eq!(
+        Usi::format_position(&pos),
+        Usi::format_position(&Position::startpos())
+    );
+    assert_eq!(Csa::format_position(&pos), STARTPOS);
+
+    for (sfen, _, _) in TEST_SFENS {
+        let pos = Usi::parse_position(sfen).unwrap();
+        let parsed = Csa::parse_position(&Csa::format_position(&pos)).unwrap();
+
+        // Positions of CSA records do not have a move number.
+        assert_eq!(
+            Usi::format_position(&parsed).rsplit_once(' ').unwrap().0,
+            sfen.rsplit_once(' ').unwrap().0,
+        );
+    }
+}
+
+#[test]
+fn handicap_and_hands() {
+    let pos = Csa::parse_position("PI82HI22KA\n-\n").unwrap();
+
+    assert_eq!(
+        Usi::format_position(&pos),
+        "lnsgkgsnl/9/ppppppppp/9/9/9/PPPPPPPPP/1B5R1/LNSGKGSNL w - 1"
+    );
+
+    let pos = Csa::parse_position("P-11OU\nP+22KI00KI\nP-00AL\n+\n").unwrap();
+
+    assert_eq!(
+        Usi::format_position(&pos),
+        "8k/7G1/9/9/9/9/9/9/9 b G2r2b2g4s4n4l18p 1"
+    );
+    assert!(Csa::format_position(&pos).contains("\nP+00KI\nP-00HI00HI00KA"));
+}
+
+#[test]
+fn invalid_positions() {
+    for (s, error) in [
+        ("PI82KA\n+\n", ParsePositionError::InvalidHandicap),
+        ("PI82HI82HI\n+\n", ParsePositionError::InvalidHandicap),
+        (
+            "P1-OU\nP+00OU\n+\n",
+            ParsePositionError::InvalidHandPieceType,
+        ),
+        ("P1-XX\n+\n", ParsePositionError::InvalidBoardPiece),
+        ("PI\n", ParsePositionError::InvalidSideToMove),
+        ("PI\n+\n+7776FU\n", ParsePositionError::InvalidFormat),
+        ("P1+FU\n+\n", ParsePositionError::InvalidPosition),
+        ("PI\nP+77FU\n+\n", ParsePositionError::InvalidSquare),
+        ("P+55KI55GI\n+\n", ParsePositionError::InvalidSquare),
+        ("P1-OU\nP1-KY\n+\n", ParsePositionError::InvalidBoardRank),
+        ("P+00HI00HI00HI\n+\n", ParsePositionError::TooManyPieces),
+        ("P-00AL\nP+00FU\n+\n", ParsePositionError::TooManyPieces),
+    ] {
+        let result = Csa::parse_position(s).unwrap_err();
+
+        assert_eq!(format!("{result:?}"), format!("{error:?}"), "{s}");
+    }
+}
+
+#[test]
+fn moves() {
+    assert_eq!(
+        Csa::parse_move("+7776FU").unwrap(),
+        Usi::parse_move("7g7f").unwrap()
+    );
+    assert_eq!(
+        Csa::parse_move("0055KA").unwrap(),
+        Usi::parse_move("B*5e").unwrap()
+    );
+    assert_eq!(Csa::format_move(Usi::parse_move("B*5e").unwrap()), "0055KA");
+    assert!(matches!(
+        Csa::parse_move("+8822UM"),
+        Err(ParseMoveError::PositionRequired)
+    ));
+
+    let mut pos = Position::startpos();
+
+    for (csa, usi) in [
+        ("+7776FU", "7g7f"),
+        ("-3334FU", "3c3d"),
+        ("+8822UM", "8h2b+"),
+    ] {
+        let mv = Usi::parse_move(usi).unwrap();
+
+        assert_eq!(Csa::parse_move_in(&pos, csa).unwrap(), mv);
+        assert_eq!(Csa::format_move_in(&pos, mv), csa);
+        pos.make_move(mv);
+    }
+
+    for (sfen, _, _) in TEST_SFENS {
+        let pos = Usi::parse_position(sfen).unwrap();
+
+        for mv in pos.legal_moves() {
+            let text = Csa::format_move_in(&pos, mv);
+
+            assert_eq!(
+                Csa::parse_move_in(&pos, &text).unwrap(),
+                mv,
+                "{sfen} {text}"
+            );
+        }
+    }
+}
+
+#[test]
+fn invalid_moves() {
+    let pos = Position::startpos();
+
+    for (text, error) in [
+        ("-3334FU", ParseMoveError::InvalidSide),
+        ("+7776KY", ParseMoveError::PieceMismatch),
+        ("+7776UM", ParseMoveError::PieceMismatch),
+        ("+7775FU", ParseMoveError::IllegalMove),
+        ("+0055KA", ParseMoveError::IllegalMove),
+        ("+0055TO", ParseMoveError::InvalidPieceType),
+        ("+7776", ParseMoveError::InvalidFormat),
+    ] {
+        let result = Csa::parse_move_in(&pos, text).unwrap_err();
+
+        assert_eq!(format!("{result:?}"), format!("{error:?}"), "{text}");
+    }
+}
+
+#[test]
+fn parse_game() {
+    let record = Csa::parse(GAME).unwrap();
+    let moves = record
+        .moves
+        .iter()
+        .map(|record| Usi::format_move(record.mv))
+        .collect::<Vec<_>>();
+
+    assert_eq!(record.event.as_deref(), Some("テスト対局"));
+    assert_eq!(record.black.as_deref(), Some("先手太郎"));
+    assert_eq!(record.white.as_deref(), Some("後手花子"));
+    assert_eq!(record.start_time.as_deref(), Some("2024/05/01 10:00:00"));
+    assert_eq!(record.comments, ["対局開始"]);
+    assert_eq!(moves, ["7g7f", "3c3d", "8h2b+", "3a2b", "B*4e"]);
+    assert_eq!(record.moves[1].comments, ["角道を開ける"]);
+    assert_eq!(record.moves[2].time, Some(Duration::from_secs(70)));
+    assert_eq!(record.moves[3].time, Some(Duration::from_millis(2500)));
+    assert_eq!(record.end, Some(GameEnd::Resign));
+}
+
+#[test]
+fn format_game() {
+    let record = Csa::parse(GAME).unwrap();
+    let formatted = Csa::format(&record);
+
+    assert!(formatted.starts_with("V2.2\nN+先手太郎\nN-後手花子\n"));
+    assert!(formatted.contains(STARTPOS));
+    assert!(formatted.ends_with("+0045KA\nT30\n%TORYO\n"));
+
+    let parsed = Csa::parse(&formatted).unwrap();
+
+    assert_eq!(parsed.moves, record.moves);
+    assert_eq!(parsed.comments, record.comments);
+    assert_eq!(parsed.end, record.end);
+}
+
+#[test]
+fn parse_variants() {
+    let record = Csa::parse(
+        "\
+' comment that is not kept
+V2
+PI82HI
+P+00HI
+-
+-3334FU,T1,+7776FU
+%+ILLEGAL_ACTION
+",
+    )
+    .unwrap();
+
+    assert_eq!(
+        Usi::format_position(&record.start),
+        "lnsgkgsnl/7b1/ppppppppp/9/9/9/PPPPPPPPP/1B5R1/LNSGKGSNL w R 1"
+    );
+    assert_eq!(record.moves[0].time, Some(Duration::from_secs(1)));
+    assert_eq!(record.moves.len(), 2);
+    // Black played the illegal action, and white is to move.
+    assert_eq!(record.end, Some(GameEnd::IllegalWin));
+    assert!(Csa::format(&record).ends_with("%+ILLEGAL_ACTION\n"));
+
+    let record = Csa::parse("+7776FU\n%-ILLEGAL_ACTION\n").unwrap();
+
+    assert_eq!(record.end, Some(GameEnd::IllegalLoss));
+    assert!(Csa::format(&record).ends_with("%ILLEGAL_MOVE\n"));
+}
+
+#[test]
+fn invalid_games() {
+    let error = Csa::parse("PI\n+\n+7776FU\n+3334FU\n").unwrap_err();
+
+    assert_eq!(error.line, 4);
+    assert!(matches!(
+        error.kind,
+        ParseCsaErrorKind::InvalidMove(ParseMoveError::InvalidSide)
+    ));
+
+    for (s, kind) in [
+        ("+7776FU\nTx\n", ParseCsaErrorKind::InvalidTime),
+        ("+7776FU\n%MATTA\n", ParseCsaErrorKind::InvalidSpecialMove),
+        ("%TORYO\n+7776FU\n", ParseCsaErrorKind::MoveAfterEnd),
+        (
+            "PI82HI\n-3334FU\n",
+            ParseCsaErrorKind::InvalidPosition(ParsePositionError::InvalidSideToMove),
+        ),
+    ] {
+        let error = Csa::parse(s).unwrap_err();
+
+        assert_eq!(error.line, 2);
+        assert_eq!(format!("{:?}", error.kind), format!("{kind:?}"), "{s}");
+    }
+}
@@ -0,0 +1,214 @@
+use crux_lib::{
+    notation::{
+        hcp::{from_move16, to_move16, GameResult, HuffmanCodedPos, HuffmanCodedPosAndEval},
+        packed_sfen::PackedSfen,
+        record::{PackError, UnpackError},
+        usi::Usi,
+        Notation,
+    },
+    shogi::{
+        core::{Color, PieceType, Square},
+        position::{mv::Move, Position},
+    },
+};
+
+use crate::shogi::movegen::TEST_SFENS;
+
+/// A position with promoted pieces and pieces in hand on both sides.
+const SFEN: &str = "l4s2l/1+S1+Pskg2/4p1p+Rp/p3np1p1/6+rn1/2+BSPP3/PP2G3P/2GKN4/L7L b BN3Pg3p 123";
+
+/// The startpos as a `HuffmanCodedPos` of cshogi.
+const STARTPOS_HCP: [u8; 32] = [
+    0x58, 0xa4, 0x49, 0x21, 0x0c, 0xd7, 0x57, 0x21, 0x7e, 0x8e, 0x4d, 0x21, 0x2c, 0xaf, 0x42, 0x78,
+    0x14, 0xc2, 0xab, 0x10, 0x9e, 0x4d, 0x21, 0x2c, 0xd7, 0x5f, 0x21, 0x3e, 0x8e, 0x49, 0x21, 0x0c,
+];
+
+/// [`SFEN`] as a `HuffmanCodedPos` of cshogi.
+const SFEN_HCP: [u8; 32] = [
+    0x68, 0x9c, 0x49, 0x21, 0x0c, 0xfc, 0xd6, 0x05, 0xde, 0xf2, 0x0f, 0x9b, 0x12, 0xb0, 0xd5, 0x25,
+    0x9e, 0x83, 0xc4, 0x02, 0xf8, 0xf4, 0xb0, 0x42, 0x30, 0x51, 0x84, 0x01, 0xc0, 0x78, 0x90, 0xbc,
+];
+
+/// The startpos with eval 0, move 7g7f and a draw, as a `HuffmanCodedPosAndEval` of cshogi.
+const STARTPOS_HCPE: [u8; 38] = [
+    0x58, 0xa4, 0x49, 0x21, 0x0c, 0xd7, 0x57, 0x21, 0x7e, 0x8e, 0x4d, 0x21, 0x2c, 0xaf, 0x42, 0x78,
+    0x14, 0xc2, 0xab, 0x10, 0x9e, 0x4d, 0x21, 0x2c, 0xd7, 0x5f, 0x21, 0x3e, 0x8e, 0x49, 0x21, 0x0c,
+    0x00, 0x00, 0x3b, 0x1e, 0x00, 0x00,
+];
+
+/// [`SFEN`] with eval -456, move B*5e and a win of White, as a `HuffmanCodedPosAndEval`
+/// of cshogi.
+const SFEN_HCPE: [u8; 38] = [
+    0x68, 0x9c, 0x49, 0x21, 0x0c, 0xfc, 0xd6, 0x05, 0xde, 0xf2, 0x0f, 0x9b, 0x12, 0xb0, 0xd5, 0x25,
+    0x9e, 0x83, 0xc4, 0x02, 0xf8, 0xf4, 0xb0, 0x42, 0x30, 0x51, 0x84, 0x01, 0xc0, 0x78, 0x90, 0xbc,
+    0x38, 0xfe, 0xa8, 0x2a, 0x02, 0x00,
+];
+
+#[test]
+fn startpos_layout() {
+    let hcp = HuffmanCodedPos::pack(&Position::startpos()).unwrap();
+
+    // Black to move, the black king on 5i (44) and the white king on 5a (36), followed by
+    // the first bit of the white lance on 1a.
+    assert_eq!(hcp.0[0], 44 << 1);
+    assert_eq!(hcp.0[1], 36 | 0x80);
+
+    // Knights and silvers are coded differently from PackedSfen.
+    assert_ne!(hcp.0, PackedSfen::pack(&Position::startpos()).unwrap().0);
+}
+
+#[test]
+fn cshogi_vectors() {
+    for (sfen, hcp, hcpe, eval, mv, game_result) in [
+        (
+            Usi::format_position(&Position::startpos()),
+            STARTPOS_HCP,
+            STARTPOS_HCPE,
+            0,
+            "7g7f",
+            GameResult::Draw,
+        ),
+        (
+            SFEN.to_string(),
+            SFEN_HCP,
+            SFEN_HCPE,
+            -456,
+            "B*5e",
+            GameResult::WhiteWin,
+        ),
+    ] {
+        let pos = Usi::parse_position(&sfen).unwrap();
+        let mv = Usi::parse_move(mv).unwrap();
+
+        assert!(pos.is_legal_move(mv));
+        assert_eq!(HuffmanCodedPos::pack(&pos).unwrap().0, hcp, "{sfen}");
+
+        // HCP does not store the ply.
+        assert_eq!(
+            Usi::format_position(&HuffmanCodedPos(hcp).unpack().unwrap())
+                .rsplit_once(' ')
+                .unwrap()
+                .0,
+            sfen.rsplit_once(' ').unwrap().0
+        );
+
+        let record = HuffmanCodedPosAndEval {
+            hcp: HuffmanCodedPos(hcp),
+            eval,
+            best_move: Some(mv),
+            game_result,
+        };
+
+        assert_eq!(record.to_bytes(), hcpe, "{sfen}");
+        assert_eq!(HuffmanCodedPosAndEval::from_bytes(&hcpe).unwrap(), record);
+    }
+}
+
+#[test]
+fn round_trip() {
+    fn check(pos: &Position) {
+        let record = HuffmanCodedPosAndEval {
+            hcp: HuffmanCodedPos::pack(pos).unwrap(),
+            eval: 321,
+            best_move: pos.legal_moves().iter().next().copied(),
+            game_result: GameResult::WhiteWin,
+        };
+        let decoded = HuffmanCodedPosAndEval::from_bytes(&record.to_bytes()).unwrap();
+
+        assert_eq!(decoded, record);
+        assert_eq!(decoded.hcp.side_to_move(), pos.side_to_move());
+
+        // HCP does not store the ply.
+        let mut sfen = Usi::format_position(pos);
+
+        sfen.replace_range(sfen.rfind(' ').unwrap().., " 1");
+        assert_eq!(Usi::format_position(&decoded.hcp.unpack().unwrap()), sfen);
+    }
+
+    for (sfen, _, _) in TEST_SFENS {
+        let mut pos = Usi::parse_position(sfen).unwrap();
+
+        check(&pos);
+
+        for &mv in pos.legal_moves().iter() {
+            let captured = pos.make_move(mv);
+
+            check(&pos);
+            pos.unmake_move(mv, captured);
+        }
+    }
+}
+
+#[test]
+fn move16() {
+    for (sfen, _, _) in TEST_SFENS {
+        let pos = Usi::parse_position(sfen).unwrap();
+
+        for &mv in pos.legal_moves().iter() {
+            assert_eq!(from_move16(to_move16(mv)).unwrap(), Some(mv));
+        }
+    }
+
+    // Promotions set bit 14 and drops count the piece type from 81 for a pawn.
+    assert_eq!(
+        to_move16(Move::promote(Square::S28, Square::S22)),
+        1 << 14 | 16 << 7 | 10
+    );
+    assert_eq!(
+        to_move16(Move::drop(PieceType::Pawn, Square::S55)),
+        81 << 7 | 40
+    );
+    assert_eq!(
+        to_move16(Move::drop(PieceType::Gold, Square::S55)),
+        87 << 7 | 40
+    );
+
+    assert_eq!(from_move16(0).unwrap(), None);
+    assert!(matches!(
+        from_move16(88 << 7 | 40),
+        Err(UnpackError::InvalidMove)
+    ));
+    assert!(matches!(
+        from_move16(1 << 14 | 81 << 7 | 40),
+        Err(UnpackError::InvalidMove)
+    ));
+}
+
+#[test]
+fn game_result() {
+    assert_eq!(GameResult::Draw.winner(), None);
+    assert_eq!(GameResult::BlackWin.winner(), Some(Color::Black));
+    assert_eq!(GameResult::WhiteWin.winner(), Some(Color::White));
+
+    let record = HuffmanCodedPosAndEval {
+        hcp: HuffmanCodedPos::pack(&Position::startpos()).unwrap(),
+        eval: 0,
+        best_move: None,
+        game_result: GameResult::BlackWin,
+    };
+    let mut bytes = record.to_bytes();
+
+    assert_eq!(bytes[36], 1);
+
+    bytes[36] = 3;
+    assert!(matches!(
+        HuffmanCodedPosAndEval::from_bytes(&bytes),
+        Err(UnpackError::InvalidGameResult)
+    ));
+}
+
+#[test]
+fn unsupported_positions() {
+    let tsume = Usi::parse_position("4k4/9/9/9/9/9/9/9/4K4 b - 1").unwrap();
+
+    assert!(matches!(
+        HuffmanCodedPos::pack(&tsume),
+        Err(PackError::UnsupportedPieceSet)
+    ));
+    assert!(HuffmanCodedPos([0xff; HuffmanCodedPos::SIZE])
+        .unpack()
+        .is_err());
+    assert!(HuffmanCodedPos([0; HuffmanCodedPos::SIZE])
+        .unpack()
+        .is_err());
+}
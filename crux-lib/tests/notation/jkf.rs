@@ -0,0 +1,252 @@
+use std::time::Duration;
+
+use crux_lib::{
+    notation::{
+        game::{GameEnd, MoveRecord},
+        jkf::{Jkf, ParseJkfError, ParseMoveError},
+        usi::Usi,
+        Notation,
+    },
+    shogi::position::Position,
+};
+
+use crate::shogi::movegen::TEST_SFENS;
+
+const GAME: &str = r#"{
+  "header": { "先手": "先手太郎", "後手": "後手花子", "棋戦": "テスト対局" },
+  "initial": { "preset": "HIRATE" },
+  "moves": [
+    { "comments": ["対局開始"] },
+    {
+      "move": { "from": { "x": 7, "y": 7 }, "to": { "x": 7, "y": 6 }, "color": 0, "piece": "FU" },
+      "time": { "now": { "m": 1, "s": 5 }, "total": { "h": 0, "m": 1, "s": 5 } }
+    },
+    {
+      "move": { "from": { "x": 3, "y": 3 }, "to": { "x": 3, "y": 4 }, "color": 1, "piece": "FU" },
+      "comments": ["角道を開ける"],
+      "forks": [
+        [
+          { "move": { "from": { "x": 8, "y": 3 }, "to": { "x": 8, "y": 4 }, "piece": "FU" } },
+          {
+            "move": { "from": { "x": 2, "y": 7 }, "to": { "x": 2, "y": 6 }, "piece": "FU" },
+            "forks": [
+              [
+                { "move": { "from": { "x": 5, "y": 7 }, "to": { "x": 5, "y": 6 }, "piece": "FU" } },
+                { "special": "CHUDAN" }
+              ]
+            ]
+          }
+        ]
+      ]
+    },
+    {
+      "move": {
+        "from": { "x": 8, "y": 8 }, "to": { "x": 2, "y": 2 }, "color": 0, "piece": "KA",
+        "promote": true, "capture": "KA"
+      }
+    },
+    {
+      "move": {
+        "from": { "x": 3, "y": 1 }, "to": { "x": 2, "y": 2 }, "color": 1, "piece": "GI",
+        "same": true, "capture": "UM"
+      }
+    },
+    { "move": { "to": { "x": 4, "y": 5 }, "color": 0, "piece": "KA" } },
+    { "special": "TORYO" }
+  ]
+}"#;
+
+fn usi_moves(moves: &[MoveRecord]) -> Vec<String> {
+    moves
+        .iter()
+        .map(|record| Usi::format_move(record.mv))
+        .collect()
+}
+
+#[test]
+fn parse_game() {
+    let record = Jkf::parse(GAME).unwrap();
+
+    assert_eq!(record.black.as_deref(), Some("先手太郎"));
+    assert_eq!(record.white.as_deref(), Some("後手花子"));
+    assert_eq!(record.event.as_deref(), Some("テスト対局"));
+    assert_eq!(record.comments, ["対局開始"]);
+    assert_eq!(
+        usi_moves(&record.moves),
+        ["7g7f", "3c3d", "8h2b+", "3a2b", "B*4e"]
+    );
+    assert_eq!(record.moves[0].time, Some(Duration::from_secs(65)));
+    assert_eq!(record.moves[1].comments, ["角道を開ける"]);
+    assert_eq!(record.end, Some(GameEnd::Resign));
+
+    let fork = &record.moves[1].forks[0];
+
+    assert_eq!(usi_moves(&fork.moves), ["8c8d", "2g2f"]);
+    assert_eq!(fork.end, None);
+    assert_eq!(usi_moves(&fork.moves[1].forks[0].moves), ["5g5f"]);
+    assert_eq!(fork.moves[1].forks[0].end, Some(GameEnd::Abort));
+}
+
+#[test]
+fn format_game() {
+    let record = Jkf::parse(GAME).unwrap();
+    let formatted = Jkf::format(&record);
+    let parsed = Jkf::parse(&formatted).unwrap();
+
+    assert!(formatted.contains(r#""initial":{"preset":"HIRATE"}"#));
+    assert!(formatted.contains(r#""total":{"h":0,"m":1,"s":5}"#));
+    assert_eq!(parsed.black, record.black);
+    assert_eq!(parsed.white, record.white);
+    assert_eq!(parsed.event, record.event);
+    assert_eq!(parsed.comments, record.comments);
+    assert_eq!(parsed.moves, record.moves);
+    assert_eq!(parsed.end, record.end);
+}
+
+#[test]
+fn initial_positions() {
+    let record =
+        Jkf::parse(r#"{ "header": { "上手": "上手" }, "initial": { "preset": "KA" } }"#).unwrap();
+
+    assert_eq!(record.white.as_deref(), Some("上手"));
+    assert_eq!(
+        Usi::format_position(&record.start),
+        "lnsgkgsnl/1r7/ppppppppp/9/9/9/PPPPPPPPP/1B5R1/LNSGKGSNL w - 1"
+    );
+    assert!(Jkf::format(&record).contains(r#""initial":{"preset":"KA"}"#));
+
+    for (sfen, _, _) in TEST_SFENS {
+        let mut record = Jkf::parse("{}").unwrap();
+
+        record.start = Usi::parse_position(sfen).unwrap();
+
+        let parsed = Jkf::parse(&Jkf::format(&record)).unwrap();
+
+        // Initial positions of JKF do not have a move number.
+        assert_eq!(
+            Usi::format_position(&parsed.start)
+                .rsplit_once(' ')
+                .unwrap()
+                .0,
+            sfen.rsplit_once(' ').unwrap().0,
+        );
+    }
+}
+
+#[test]
+fn moves() {
+    let mut pos = Position::startpos();
+
+    for usi in ["7g7f", "3c3d"] {
+        pos.make_move(Usi::parse_move(usi).unwrap());
+    }
+
+    let last = Usi::parse_move("3c3d").unwrap();
+    let mv = Usi::parse_move("8h2b+").unwrap();
+
+    assert_eq!(
+        Jkf::format_move(&pos, mv, Some(last)),
+        r#"{"capture":"KA","color":0,"from":{"x":8,"y":8},"piece":"KA","promote":true,"to":{"x":2,"y":2}}"#
+    );
+
+    pos.make_move(mv);
+
+    let mv = Usi::parse_move("3a2b").unwrap();
+
+    assert_eq!(
+        Jkf::format_move(&pos, mv, Some(last)),
+        r#"{"capture":"UM","color":1,"from":{"x":3,"y":1},"piece":"GI","to":{"x":2,"y":2}}"#
+    );
+    assert!(
+        Jkf::format_move(&pos, mv, Some(Usi::parse_move("8h2b+").unwrap()))
+            .contains(r#""same":true"#)
+    );
+
+    for (sfen, _, _) in TEST_SFENS {
+        let pos = Usi::parse_position(sfen).unwrap();
+
+        for mv in pos.legal_moves() {
+            let text = Jkf::format_move(&pos, mv, None);
+
+            assert_eq!(Jkf::parse_move(&pos, &text).unwrap(), mv, "{sfen} {text}");
+        }
+    }
+}
+
+#[test]
+fn invalid_moves() {
+    let pos = Position::startpos();
+
+    for (text, error) in [
+        (r#"{"to":{"x":7,"y":6}}"#, ParseMoveError::InvalidPieceType),
+        (
+            r#"{"from":{"x":7,"y":7},"to":{"x":7,"y":0},"piece":"FU"}"#,
+            ParseMoveError::InvalidSquare,
+        ),
+        (
+            r#"{"from":{"x":7,"y":7},"to":{"x":7,"y":6},"piece":"FU","color":1}"#,
+            ParseMoveError::InvalidColor,
+        ),
+        (
+            r#"{"from":{"x":7,"y":7},"to":{"x":7,"y":6},"piece":"KY"}"#,
+            ParseMoveError::PieceMismatch,
+        ),
+        (
+            r#"{"from":{"x":7,"y":7},"to":{"x":7,"y":5},"piece":"FU"}"#,
+            ParseMoveError::IllegalMove,
+        ),
+        (
+            r#"{"to":{"x":5,"y":5},"piece":"KA"}"#,
+            ParseMoveError::IllegalMove,
+        ),
+        (
+            r#"{"to":{"x":5,"y":5},"piece":"UM"}"#,
+            ParseMoveError::InvalidPieceType,
+        ),
+        (
+            r#"{"from":{"x":7,"y":7},"to":{"x":7,"y":6}"#,
+            ParseMoveError::InvalidFormat,
+        ),
+    ] {
+        let result = Jkf::parse_move(&pos, text).unwrap_err();
+
+        assert_eq!(format!("{result:?}"), format!("{error:?}"), "{text}");
+    }
+}
+
+#[test]
+fn invalid_games() {
+    let move_7g7f = r#"{"move":{"from":{"x":7,"y":7},"to":{"x":7,"y":6},"piece":"FU"}}"#;
+
+    for (text, error) in [
+        ("[]".to_string(), ParseJkfError::InvalidJson),
+        (
+            r#"{"initial":{"preset":"9"}}"#.to_string(),
+            ParseJkfError::InvalidPreset,
+        ),
+        (
+            r#"{"header":{"先手":1}}"#.to_string(),
+            ParseJkfError::InvalidHeader,
+        ),
+        (
+            format!(r#"{{"moves":[{{}},{{"special":"TORYO"}},{move_7g7f}]}}"#),
+            ParseJkfError::MoveAfterEnd,
+        ),
+        (
+            r#"{"moves":[{},{"special":"MATTA"}]}"#.to_string(),
+            ParseJkfError::InvalidSpecialMove,
+        ),
+        (
+            format!(r#"{{"moves":[{{}},{{"special":"TORYO","forks":[[{move_7g7f}]]}}]}}"#),
+            ParseJkfError::InvalidFork,
+        ),
+        (
+            format!(r#"{{"moves":[{{}},{move_7g7f},{move_7g7f}]}}"#),
+            ParseJkfError::InvalidMove(ParseMoveError::PieceMismatch),
+        ),
+    ] {
+        let result = Jkf::parse(&text).unwrap_err();
+
+        assert_eq!(format!("{result:?}"), format!("{error:?}"), "{text}");
+    }
+}
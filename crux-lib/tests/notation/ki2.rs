@@ -0,0 +1,189 @@
+use crux_lib::{
+    notation::{
+        game::GameEnd,
+        ki2::Ki2,
+        kif::{ParseKifErrorKind, ParseMoveError},
+        usi::Usi,
+        Notation,
+    },
+    shogi::position::Position,
+};
+
+use crate::shogi::movegen::TEST_SFENS;
+
+const GAME: &str = "\
+手合割：平手
+先手：先手太郎
+後手：後手花子
+*対局開始
+▲７六歩      △３四歩
+*角道を開ける
+▲２二角成    △同　銀      ▲４五角
+まで5手で先手の勝ち
+";
+
+#[test]
+fn parse_game() {
+    let record = Ki2::parse(GAME).unwrap();
+    let moves = record
+        .moves
+        .iter()
+        .map(|record| Usi::format_move(record.mv))
+        .collect::<Vec<_>>();
+
+    assert_eq!(record.black.as_deref(), Some("先手太郎"));
+    assert_eq!(record.white.as_deref(), Some("後手花子"));
+    assert_eq!(record.comments, ["対局開始"]);
+    assert_eq!(moves, ["7g7f", "3c3d", "8h2b+", "3a2b", "B*4e"]);
+    assert_eq!(record.moves[1].comments, ["角道を開ける"]);
+    assert_eq!(record.end, Some(GameEnd::Resign));
+}
+
+#[test]
+fn format_game() {
+    assert_eq!(Ki2::format(&Ki2::parse(GAME).unwrap()), GAME);
+}
+
+#[test]
+fn game_ends() {
+    for (summary, end) in [
+        ("まで1手で千日手", GameEnd::Repetition),
+        ("まで1手で持将棋", GameEnd::Impasse),
+        ("まで1手で中断", GameEnd::Abort),
+        ("まで1手で詰み", GameEnd::Checkmate),
+        ("まで1手で先手の勝ち", GameEnd::Resign),
+        ("まで1手で時間切れにより先手の勝ち", GameEnd::TimeUp),
+        ("まで1手で後手の入玉勝ち", GameEnd::EnteringKing),
+        ("まで1手で後手の反則勝ち", GameEnd::IllegalWin),
+        ("まで1手で先手の反則勝ち", GameEnd::IllegalLoss),
+    ] {
+        let text = format!("手合割：平手\n▲７六歩\n{summary}\n");
+        let record = Ki2::parse(&text).unwrap();
+
+        assert_eq!(record.end, Some(end), "{summary}");
+        assert_eq!(Ki2::format(&record), text);
+    }
+    // The winner contradicts the side to move, so the end is not kept.
+    for summary in [
+        "まで1手で後手の勝ち",
+        "まで1手で時間切れにより後手の勝ち",
+        "まで1手で先手の入玉勝ち",
+    ] {
+        let record = Ki2::parse(&format!("手合割：平手\n▲７六歩\n{summary}\n")).unwrap();
+
+        assert_eq!(record.end, None, "{summary}");
+        assert_eq!(Ki2::format(&record), "手合割：平手\n▲７六歩\n");
+    }
+}
+
+#[test]
+fn relative_moves() {
+    const MOVES: [(&str, &str, &str); 20] = [
+        // Two golds moving forward.
+        ("4k4/9/9/9/9/9/9/9/3GKG3 b - 1", "4i5h", "５八金右"),
+        ("4k4/9/9/9/9/9/9/9/3GKG3 b - 1", "6i5h", "５八金左"),
+        // A gold moving straight forward.
+        ("4k4/9/9/9/9/9/9/9/3GG3K b - 1", "5i5h", "５八金直"),
+        ("4k4/9/9/9/9/9/9/9/3GG3K b - 1", "6i5h", "５八金左"),
+        // Told apart by direction.
+        ("4k4/9/9/9/9/9/9/3G5/5G2K b - 1", "6h5h", "５八金寄"),
+        ("4k4/9/9/9/9/9/9/3G5/5G2K b - 1", "4i5h", "５八金上"),
+        ("4k4/9/9/9/9/9/4G4/9/5G2K b - 1", "5g5h", "５八金引"),
+        // Three golds, one of which needs both.
+        ("4k4/9/9/9/9/9/9/5G3/3G1G2K b - 1", "6i5h", "５八金左"),
+        ("4k4/9/9/9/9/9/9/5G3/3G1G2K b - 1", "4i5h", "５八金右上"),
+        ("4k4/9/9/9/9/9/9/5G3/3G1G2K b - 1", "4h5h", "５八金寄"),
+        // Dragons are told apart by side, even moving straight.
+        ("4k4/9/+R7+R/9/9/9/9/9/4K4 b - 1", "9c5c", "５三龍左"),
+        ("4k4/9/+R7+R/9/9/9/9/9/4K4 b - 1", "1c5c", "５三龍右"),
+        // The side is from the perspective of white.
+        ("k2g1g3/9/9/9/9/9/9/9/4K4 w - 1", "6a5b", "５二金右"),
+        ("k2g1g3/9/9/9/9/9/9/9/4K4 w - 1", "4a5b", "５二金左"),
+        // Promotions.
+        ("4k4/9/9/3S1S3/9/9/9/9/4K4 b - 1", "6d5c+", "５三銀左成"),
+        ("4k4/9/9/3S1S3/9/9/9/9/4K4 b - 1", "4d5c", "５三銀右不成"),
+        // Drops are marked only if a piece on the board can move there.
+        ("4k4/9/9/9/9/9/9/1B7/4K4 b B 1", "8h5e", "５五角"),
+        ("4k4/9/9/9/9/9/9/1B7/4K4 b B 1", "B*5e", "５五角打"),
+        ("4k4/9/9/9/9/9/9/1B7/4K4 b B 1", "B*5d", "５四角"),
+        ("4k4/9/9/9/9/9/9/9/4K4 b G 1", "G*5e", "５五金"),
+    ];
+
+    for (sfen, usi, text) in MOVES {
+        let pos = Usi::parse_position(sfen).unwrap();
+        let mv = Usi::parse_move(usi).unwrap();
+
+        assert_eq!(Ki2::format_move(&pos, mv, None), text, "{sfen} {usi}");
+        assert_eq!(
+            Ki2::parse_move(&pos, text, None).unwrap(),
+            mv,
+            "{sfen} {text}"
+        );
+    }
+}
+
+#[test]
+fn round_trip() {
+    for (sfen, _, _) in TEST_SFENS {
+        let pos = Usi::parse_position(sfen).unwrap();
+
+        for mv in pos.legal_moves() {
+            let text = Ki2::format_move(&pos, mv, None);
+
+            assert_eq!(
+                Ki2::parse_move(&pos, &text, None).unwrap(),
+                mv,
+                "{sfen} {text}"
+            );
+        }
+    }
+}
+
+#[test]
+fn same_square() {
+    let mut pos = Position::startpos();
+
+    for usi in ["7g7f", "3c3d", "8h2b+"] {
+        pos.make_move(Usi::parse_move(usi).unwrap());
+    }
+
+    let last = Usi::parse_move("8h2b+").unwrap();
+    let mv = Usi::parse_move("3a2b").unwrap();
+
+    assert_eq!(Ki2::format_move(&pos, mv, Some(last)), "同　銀");
+    assert_eq!(Ki2::parse_move(&pos, "△同銀", Some(last)).unwrap(), mv);
+    assert_eq!(Ki2::parse_move(&pos, "２二銀", Some(last)).unwrap(), mv);
+}
+
+#[test]
+fn invalid_moves() {
+    let golds = Usi::parse_position("4k4/9/9/9/9/9/9/9/3GKG3 b - 1").unwrap();
+    let pos = Position::startpos();
+
+    for (pos, text, error) in [
+        (&golds, "５八金", ParseMoveError::AmbiguousMove),
+        (&golds, "５八金直", ParseMoveError::IllegalMove),
+        (&golds, "５八金引", ParseMoveError::IllegalMove),
+        (&pos, "７五歩", ParseMoveError::IllegalMove),
+        (&pos, "７六歩成", ParseMoveError::IllegalMove),
+        (&pos, "７六歩右打", ParseMoveError::InvalidFormat),
+        (&pos, "７六歩生", ParseMoveError::InvalidFormat),
+        (&pos, "同　歩", ParseMoveError::MissingPreviousMove),
+    ] {
+        let result = Ki2::parse_move(pos, text, None).unwrap_err();
+
+        assert_eq!(format!("{result:?}"), format!("{error:?}"), "{text}");
+    }
+
+    let error = Ki2::parse("▲７六歩    △３四歩\n▲５八金\n").unwrap_err();
+
+    assert_eq!(error.line, 2);
+    assert!(matches!(
+        error.kind,
+        ParseKifErrorKind::InvalidMove(ParseMoveError::AmbiguousMove)
+    ));
+    assert!(matches!(
+        Ki2::parse("先手の持駒：金五\n").unwrap_err().kind,
+        ParseKifErrorKind::InvalidHand
+    ));
+}
@@ -0,0 +1,226 @@
+use std::time::Duration;
+
+use crux_lib::{
+    notation::{
+        game::{GameEnd, GameRecord, MoveRecord},
+        kif::{Kif, ParseKifErrorKind, ParseMoveError},
+        usi::Usi,
+        Notation,
+    },
+    shogi::{core::Color, position::Position},
+};
+
+const GAME: &str = "\
+開始日時：2024/05/01 10:00:00
+棋戦：テスト対局
+手合割：平手
+先手：先手太郎
+後手：後手花子
+手数----指手---------消費時間--
+*対局開始
+   1 ７六歩(77)    ( 0:05/00:00:05)
+   2 ３四歩(33)    ( 0:03/00:00:03)
+*角道を開ける
+   3 ２二角成(88)  ( 1:10/00:01:15)
+   4 同　銀(31)    ( 0:02/00:00:05)
+   5 ４五角打      ( 0:30/00:01:45)
+   6 投了
+";
+
+fn usi_moves(record: &GameRecord) -> Vec<String> {
+    record
+        .moves
+        .iter()
+        .map(|record| Usi::format_move(record.mv))
+        .collect()
+}
+
+#[test]
+fn parse_game() {
+    let record = Kif::parse(GAME).unwrap();
+
+    assert_eq!(record.start_time.as_deref(), Some("2024/05/01 10:00:00"));
+    assert_eq!(record.event.as_deref(), Some("テスト対局"));
+    assert_eq!(record.black.as_deref(), Some("先手太郎"));
+    assert_eq!(record.white.as_deref(), Some("後手花子"));
+    assert_eq!(
+        Usi::format_position(&record.start),
+        Usi::format_position(&Position::startpos())
+    );
+    assert_eq!(record.comments, ["対局開始"]);
+    assert_eq!(
+        usi_moves(&record),
+        ["7g7f", "3c3d", "8h2b+", "3a2b", "B*4e"]
+    );
+    assert_eq!(record.moves[1].comments, ["角道を開ける"]);
+    assert_eq!(record.moves[2].time, Some(Duration::from_secs(70)));
+    assert_eq!(record.end, Some(GameEnd::Resign));
+}
+
+#[test]
+fn format_game() {
+    assert_eq!(Kif::format(&Kif::parse(GAME).unwrap()), GAME);
+}
+
+#[test]
+fn parse_variants() {
+    let record = Kif::parse(
+        "\
+# コメント
+手数----指手---------消費時間--
+1 ７六歩(77)
+2 ３四歩(33)   (00:01/00:00:01)+
+3 ２二角成(88)
+4 同 銀(31)
+5 ５五角打
+6 ４二玉(51)
+変化：6手
+6 ２二角打
+",
+    )
+    .unwrap();
+
+    assert_eq!(
+        usi_moves(&record),
+        ["7g7f", "3c3d", "8h2b+", "3a2b", "B*5e", "5a4b"]
+    );
+    assert_eq!(record.moves[1].time, Some(Duration::from_secs(1)));
+    assert_eq!(record.end, None);
+}
+
+#[test]
+fn handicap() {
+    let record = Kif::parse("手合割：香落ち\n上手：上手\n下手：下手\n   1 ３四歩(33)\n").unwrap();
+
+    assert_eq!(record.black.as_deref(), Some("下手"));
+    assert_eq!(record.white.as_deref(), Some("上手"));
+    assert_eq!(
+        Usi::format_position(&record.start),
+        "lnsgkgsn1/1r5b1/ppppppppp/9/9/9/PPPPPPPPP/1B5R1/LNSGKGSNL w - 1"
+    );
+    assert_eq!(usi_moves(&record), ["3c3d"]);
+    assert_eq!(
+        Kif::format(&record),
+        "手合割：香落ち\n下手：下手\n上手：上手\n手数----指手---------消費時間--\n   1 ３四歩(33)\n"
+    );
+
+    assert!(matches!(
+        Kif::parse("手合割：九枚落ち\n").unwrap_err().kind,
+        ParseKifErrorKind::InvalidHandicap
+    ));
+}
+
+#[test]
+fn board_diagram() {
+    const BOARD: &str = "\
+後手の持駒：飛　角　金二　銀　桂三　香二　歩十七
+  ９ ８ ７ ６ ５ ４ ３ ２ １
++---------------------------+
+| ・ ・ ・ ・ ・ ・ ・ ・v玉|一
+| ・ ・ ・ ・ ・ ・ ・ ・ ・|二
+| ・ ・ ・ ・ ・ ・ ・ 龍 ・|三
+| ・ ・ ・ ・ ・ ・ ・ ・ ・|四
+| ・ ・ ・ ・ ・ ・ ・ ・ ・|五
+| ・ ・ ・ ・ ・ ・ ・ ・ ・|六
+| ・ ・ ・ ・ ・ ・ ・ ・ ・|七
+| ・ ・ ・ ・ ・ ・ ・ ・ ・|八
+| ・ ・ ・ ・ 玉 ・ ・ ・ 杏|九
++---------------------------+
+先手の持駒：金　歩
+後手番
+手数----指手---------消費時間--
+   1 １二歩打
+   2 ２一金打
+   3 詰み
+";
+
+    let record = Kif::parse(BOARD).unwrap();
+
+    assert_eq!(
+        Usi::format_position(&record.start),
+        "8k/9/7+R1/9/9/9/9/9/4K3+L w GPrb2gs3n2l17p 1"
+    );
+    assert_eq!(usi_moves(&record), ["P*1b", "G*2a"]);
+    assert_eq!(record.end, Some(GameEnd::Checkmate));
+    assert_eq!(Kif::format(&record), BOARD);
+}
+
+#[test]
+fn invalid_hands() {
+    for text in [
+        "先手の持駒：飛三\n",
+        "後手の持駒：歩十九\n",
+        "先手の持駒：王\n",
+    ] {
+        let error = Kif::parse(text).unwrap_err();
+
+        assert_eq!(error.line, 1, "{text}");
+        assert!(
+            matches!(error.kind, ParseKifErrorKind::InvalidHand),
+            "{text}"
+        );
+    }
+}
+
+#[test]
+fn format_moves() {
+    let pos = Usi::parse_position("4k4/9/9/4S4/9/9/9/9/4K4 b P 1").unwrap();
+    let mut record = GameRecord::new(pos.clone());
+
+    for (text, usi) in [
+        ("５三銀不成(54)", "5d5c"),
+        ("５三銀成(54)", "5d5c+"),
+        ("５五歩打", "P*5e"),
+        ("５八玉(59)", "5i5h"),
+    ] {
+        let mv = Usi::parse_move(usi).unwrap();
+
+        assert_eq!(Kif::format_move(&pos, mv, None), text);
+        assert_eq!(Kif::parse_move(&pos, text, None).unwrap(), mv);
+    }
+
+    let mv = Usi::parse_move("5d5c").unwrap();
+
+    record.moves.push(MoveRecord::new(mv));
+    assert_eq!(record.position_after(1).side_to_move(), Color::White);
+    assert_eq!(
+        Kif::format_move(&pos, Usi::parse_move("5i4h").unwrap(), Some(mv)),
+        "４八玉(59)"
+    );
+}
+
+#[test]
+fn invalid_moves() {
+    let pos = Position::startpos();
+
+    for (text, error) in [
+        ("同　歩(77)", ParseMoveError::MissingPreviousMove),
+        ("７六(77)", ParseMoveError::InvalidPieceType),
+        ("０六歩(07)", ParseMoveError::InvalidSquare),
+        ("７六香(77)", ParseMoveError::PieceMismatch),
+        ("７五歩(77)", ParseMoveError::IllegalMove),
+        ("５五角打", ParseMoveError::IllegalMove),
+        ("７六歩(7)", ParseMoveError::InvalidFormat),
+    ] {
+        let result = Kif::parse_move(&pos, text, None).unwrap_err();
+
+        assert_eq!(format!("{result:?}"), format!("{error:?}"), "{text}");
+    }
+
+    let error = Kif::parse("   1 ７六歩(77)\n   2 ７五歩(76)\n").unwrap_err();
+
+    assert_eq!(error.line, 2);
+    assert!(matches!(
+        error.kind,
+        ParseKifErrorKind::InvalidMove(ParseMoveError::PieceMismatch)
+    ));
+
+    let error = Kif::parse("   1 ７六歩(77)\n   3 ３四歩(33)\n").unwrap_err();
+
+    assert_eq!(error.line, 2);
+    assert!(matches!(error.kind, ParseKifErrorKind::InvalidMoveNumber));
+
+    let error = Kif::parse("   1 投了\n   2 ７六歩(77)\n").unwrap_err();
+
+    assert!(matches!(error.kind, ParseKifErrorKind::MoveAfterEnd));
+}
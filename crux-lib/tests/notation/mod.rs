@@ -1 +1,8 @@
+mod csa;
+mod hcp;
+mod jkf;
+mod ki2;
+mod kif;
+mod packed_sfen;
+mod record;
 mod usi;
@@ -0,0 +1,211 @@
+use crux_lib::{
+    notation::{
+        packed_sfen::{from_move16, to_move16, PackedSfen, PackedSfenValue},
+        record::{PackError, UnpackError},
+        usi::Usi,
+        Notation,
+    },
+    shogi::{
+        core::{PieceType, Square},
+        position::{mv::Move, Position},
+    },
+};
+
+use crate::shogi::movegen::TEST_SFENS;
+
+/// A position with promoted pieces and pieces in hand on both sides.
+const SFEN: &str = "l4s2l/1+S1+Pskg2/4p1p+Rp/p3np1p1/6+rn1/2+BSPP3/PP2G3P/2GKN4/L7L b BN3Pg3p 123";
+
+/// The startpos as packed by the `SfenPacker` of YaneuraOu.
+const STARTPOS_PACKED: [u8; 32] = [
+    0x58, 0xa4, 0x51, 0x22, 0x0c, 0xeb, 0x67, 0x22, 0x7e, 0x96, 0x53, 0x22, 0x1c, 0xaf, 0x44, 0x78,
+    0x24, 0xc2, 0x2b, 0x11, 0x9e, 0x53, 0x22, 0x1c, 0xeb, 0x6f, 0x22, 0x3e, 0x96, 0x51, 0x22, 0x0c,
+];
+
+/// [`SFEN`] as packed by the `SfenPacker` of YaneuraOu.
+const SFEN_PACKED: [u8; 32] = [
+    0x68, 0x9c, 0x51, 0x22, 0x0c, 0xfc, 0xe5, 0x0a, 0xde, 0xf4, 0x0f, 0xa7, 0x14, 0x70, 0xe6, 0x2a,
+    0x9e, 0x85, 0xc2, 0x01, 0xf8, 0xf2, 0x70, 0x41, 0x30, 0x92, 0x84, 0x01, 0x40, 0x79, 0x90, 0xbc,
+];
+
+/// The startpos with score 0, move 7g7f, ply 1 and a draw, as a `PackedSfenValue` of YaneuraOu.
+const STARTPOS_VALUE: [u8; 40] = [
+    0x58, 0xa4, 0x51, 0x22, 0x0c, 0xeb, 0x67, 0x22, 0x7e, 0x96, 0x53, 0x22, 0x1c, 0xaf, 0x44, 0x78,
+    0x24, 0xc2, 0x2b, 0x11, 0x9e, 0x53, 0x22, 0x1c, 0xeb, 0x6f, 0x22, 0x3e, 0x96, 0x51, 0x22, 0x0c,
+    0x00, 0x00, 0x3b, 0x1e, 0x01, 0x00, 0x00, 0x00,
+];
+
+/// [`SFEN`] with score -456, move N*5e, ply 123 and a loss, as a `PackedSfenValue` of YaneuraOu.
+const SFEN_VALUE: [u8; 40] = [
+    0x68, 0x9c, 0x51, 0x22, 0x0c, 0xfc, 0xe5, 0x0a, 0xde, 0xf4, 0x0f, 0xa7, 0x14, 0x70, 0xe6, 0x2a,
+    0x9e, 0x85, 0xc2, 0x01, 0xf8, 0xf2, 0x70, 0x41, 0x30, 0x92, 0x84, 0x01, 0x40, 0x79, 0x90, 0xbc,
+    0x38, 0xfe, 0xa8, 0x41, 0x7b, 0x00, 0xff, 0x00,
+];
+
+#[test]
+fn startpos_layout() {
+    let packed = PackedSfen::pack(&Position::startpos()).unwrap();
+
+    // Black to move, the black king on 5i (44) and the white king on 5a (36), followed by
+    // the first bit of the white lance on 1a.
+    assert_eq!(packed.0[0], 44 << 1);
+    assert_eq!(packed.0[1], 36 | 0x80);
+
+    assert_eq!(
+        Usi::format_position(&packed.unpack().unwrap()),
+        Usi::format_position(&Position::startpos())
+    );
+}
+
+#[test]
+fn yaneuraou_vectors() {
+    for (sfen, packed, value, score, mv, game_ply, game_result) in [
+        (
+            Usi::format_position(&Position::startpos()),
+            STARTPOS_PACKED,
+            STARTPOS_VALUE,
+            0,
+            "7g7f",
+            1,
+            0,
+        ),
+        (
+            SFEN.to_string(),
+            SFEN_PACKED,
+            SFEN_VALUE,
+            -456,
+            "N*5e",
+            123,
+            -1,
+        ),
+    ] {
+        let pos = Usi::parse_position(&sfen).unwrap();
+        let mv = Usi::parse_move(mv).unwrap();
+
+        assert!(pos.is_legal_move(mv));
+        assert_eq!(PackedSfen::pack(&pos).unwrap().0, packed, "{sfen}");
+        // A packed position has no ply, which only the record carries.
+        assert_eq!(
+            Usi::format_position(&PackedSfen(packed).unpack().unwrap())
+                .rsplit_once(' ')
+                .unwrap()
+                .0,
+            sfen.rsplit_once(' ').unwrap().0
+        );
+
+        let record = PackedSfenValue {
+            sfen: PackedSfen(packed),
+            score,
+            mv: Some(mv),
+            game_ply,
+            game_result,
+        };
+
+        assert_eq!(record.to_bytes(), value, "{sfen}");
+        assert_eq!(PackedSfenValue::from_bytes(&value).unwrap(), record);
+        assert_eq!(Usi::format_position(&record.position().unwrap()), sfen);
+    }
+}
+
+#[test]
+fn round_trip() {
+    fn check(pos: &Position) {
+        let value = PackedSfenValue {
+            sfen: PackedSfen::pack(pos).unwrap(),
+            score: -123,
+            mv: pos.legal_moves().iter().next().copied(),
+            game_ply: pos.ply() as u16 + 1,
+            game_result: 1,
+        };
+        let decoded = PackedSfenValue::from_bytes(&value.to_bytes()).unwrap();
+
+        assert_eq!(decoded, value);
+        assert_eq!(
+            Usi::format_position(&decoded.position().unwrap()),
+            Usi::format_position(pos)
+        );
+    }
+
+    for (sfen, _, _) in TEST_SFENS {
+        let mut pos = Usi::parse_position(sfen).unwrap();
+
+        check(&pos);
+
+        for &mv in pos.legal_moves().iter() {
+            let captured = pos.make_move(mv);
+
+            check(&pos);
+            pos.unmake_move(mv, captured);
+        }
+    }
+}
+
+#[test]
+fn move16() {
+    for (sfen, _, _) in TEST_SFENS {
+        let pos = Usi::parse_position(sfen).unwrap();
+
+        for &mv in pos.legal_moves().iter() {
+            assert_eq!(from_move16(to_move16(mv)).unwrap(), Some(mv));
+
+            if !mv.is_drop() {
+                assert_eq!(to_move16(mv), mv.as_u16());
+            }
+        }
+    }
+
+    // Drops number the piece types from 1 for a pawn to 7 for a gold.
+    assert_eq!(
+        to_move16(Move::drop(PieceType::Pawn, Square::S55)),
+        1 << 14 | 1 << 7 | 40
+    );
+    assert_eq!(
+        to_move16(Move::drop(PieceType::Gold, Square::S55)),
+        1 << 14 | 7 << 7 | 40
+    );
+    assert_eq!(
+        to_move16(Move::drop(PieceType::Rook, Square::S55)),
+        1 << 14 | 6 << 7 | 40
+    );
+
+    // The empty, null and resignation moves carry no move.
+    assert_eq!(from_move16(0).unwrap(), None);
+    assert_eq!(from_move16(1 << 7 | 1).unwrap(), None);
+    assert_eq!(from_move16(2 << 7 | 2).unwrap(), None);
+
+    assert!(matches!(
+        from_move16(1 << 14 | 8 << 7),
+        Err(UnpackError::InvalidMove)
+    ));
+    assert!(matches!(from_move16(81), Err(UnpackError::InvalidMove)));
+}
+
+#[test]
+fn unsupported_positions() {
+    let tsume = Usi::parse_position("4k4/9/9/9/9/9/9/9/4K4 b - 1").unwrap();
+    let no_king = Usi::parse_position("4k4/9/9/9/9/9/9/9/9 b RB2G2S2N2L9Pr2g2s2n2l9p 1").unwrap();
+
+    assert!(matches!(
+        PackedSfen::pack(&tsume),
+        Err(PackError::UnsupportedPieceSet)
+    ));
+    assert!(matches!(
+        PackedSfen::pack(&no_king),
+        Err(PackError::MissingKing)
+    ));
+}
+
+#[test]
+fn invalid_data() {
+    let mut packed = PackedSfen::pack(&Position::startpos()).unwrap();
+
+    // Both kings on 5i.
+    packed.0[1] = (packed.0[1] & 0x80) | 44;
+    assert!(matches!(
+        packed.unpack(),
+        Err(UnpackError::InvalidKingSquare)
+    ));
+
+    assert!(PackedSfen([0xff; PackedSfen::SIZE]).unpack().is_err());
+    assert!(PackedSfen([0; PackedSfen::SIZE]).unpack().is_err());
+}
@@ -0,0 +1,108 @@
+use std::{fs, process};
+
+use crux_lib::{
+    notation::{
+        hcp::{GameResult, HuffmanCodedPos, HuffmanCodedPosAndEval},
+        packed_sfen::{PackedSfen, PackedSfenValue},
+        record::{ReadError, Record, RecordReader},
+        usi::Usi,
+        Notation,
+    },
+    shogi::position::Position,
+};
+
+fn game_records() -> Vec<PackedSfenValue> {
+    let mut pos = Position::startpos();
+    let mut records = Vec::new();
+
+    for i in 0..20 {
+        let mv = pos.legal_moves()[i % 3];
+
+        records.push(PackedSfenValue {
+            sfen: PackedSfen::pack(&pos).unwrap(),
+            score: i as i16 * 10,
+            mv: Some(mv),
+            game_ply: pos.ply() as u16 + 1,
+            game_result: 0,
+        });
+        pos.make_move(mv);
+    }
+
+    records
+}
+
+fn encode<T: Record>(records: &[T]) -> Vec<u8> {
+    let mut bytes = vec![0; records.len() * T::SIZE];
+
+    for (record, chunk) in records.iter().zip(bytes.chunks_exact_mut(T::SIZE)) {
+        record.encode(chunk);
+    }
+
+    bytes
+}
+
+#[test]
+fn read_records() {
+    let records = game_records();
+    let bytes = encode(&records);
+    let read = RecordReader::<PackedSfenValue, _>::new(&bytes[..])
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+
+    assert_eq!(read, records);
+}
+
+#[test]
+fn truncated_record() {
+    let records = game_records();
+    let bytes = encode(&records);
+    let mut reader = RecordReader::<PackedSfenValue, _>::new(&bytes[..bytes.len() - 1]);
+
+    for _ in 0..records.len() - 1 {
+        assert!(reader.next().unwrap().is_ok());
+    }
+
+    assert!(matches!(reader.next(), Some(Err(ReadError::Truncated))));
+    assert!(reader.next().is_none());
+}
+
+#[test]
+fn invalid_record() {
+    let mut bytes = encode(&game_records());
+
+    // A drop of an eighth piece type.
+    bytes[34..36].copy_from_slice(&(1u16 << 14 | 8 << 7).to_le_bytes());
+
+    let mut reader = RecordReader::<PackedSfenValue, _>::new(&bytes[..]);
+
+    assert!(matches!(reader.next(), Some(Err(ReadError::Invalid(_)))));
+    assert!(reader.next().unwrap().is_ok());
+}
+
+#[test]
+fn open_file() {
+    let records = game_records()
+        .iter()
+        .map(|record| HuffmanCodedPosAndEval {
+            hcp: HuffmanCodedPos::pack(&record.position().unwrap()).unwrap(),
+            eval: record.score,
+            best_move: record.mv,
+            game_result: GameResult::Draw,
+        })
+        .collect::<Vec<_>>();
+    let path = std::env::temp_dir().join(format!("crux-records-{}.hcpe", process::id()));
+
+    fs::write(&path, encode(&records)).unwrap();
+
+    let count = RecordReader::<HuffmanCodedPosAndEval>::count(&path).unwrap();
+    let read = RecordReader::<HuffmanCodedPosAndEval>::open(&path)
+        .unwrap()
+        .map(|record| Usi::format_position(&record.unwrap().hcp.unpack().unwrap()))
+        .collect::<Vec<_>>();
+
+    fs::remove_file(&path).unwrap();
+
+    assert_eq!(count, records.len() as u64);
+    assert_eq!(read.len(), records.len());
+    assert_eq!(read[0], Usi::format_position(&Position::startpos()));
+}
@@ -0,0 +1,109 @@
+use std::sync::{atomic::Ordering, Arc};
+
+use crux_lib::{
+    notation::{usi::Usi, Notation},
+    search::{
+        dfpn::{DfpnSolver, MateResult},
+        Limits, Signals,
+    },
+};
+
+/// Positions with a forced mate.
+const MATES: &[&str] = &[
+    "4k4/9/4P4/9/9/9/9/9/4K4 b G 1",
+    "k1s+P2+P2/rp2+N2p+P/P1p1Ppp1g/1SlN1g1P1/3pSP2p/pP7/G1P5K/LBg+n2+bRL/LNS3P2 b - 231",
+    "l6n1/3kP3l/1pr3g1b/1B1pGGp1S/p5Pp1/2p2P1PL/P1+n1R1+pg1/1P6P/LN1K3S1 b 2SPn3p 125",
+    "s1+N1+B2Gl/+N3P1pnr/2ps4+b/p3ppl2/3l1knpp/Sp5P1/2Pp5/L1G4GK/3g1+s3 w r6p 246",
+    "2sg3R1/l1B2s1g1/1p2k1n2/P4pppl/1NPpP2Pp/2G1pP3/pP1P2P1P/1+B2GS2R/L4KSNL b Np 71",
+    "ps1ps1+N2/l1r1B+P3/1p4k1p/Lsg1pp3/1npgg2pL/1P2n1p2/P1NP1+p2S/4G2RL/3K1PB1P b 2p 229",
+];
+
+/// Positions without a forced mate.
+const NO_MATES: &[&str] = &[
+    // No checks at all.
+    "4k4/9/9/9/9/9/9/9/4K4 b - 1",
+    // A pawn drop would mate, which is illegal (uchifuzume).
+    "7lk/9/8G/9/9/9/9/9/K8 b P 1",
+    // Startpos.
+    "lnsgkgsnl/1r5b1/ppppppppp/9/9/9/PPPPPPPPP/1B5R1/LNSGKGSNL b - 1",
+];
+
+/// A position whose mate passes through a position that the search first disproves by
+/// repeating an earlier position of the path, and then reaches again by a transposition.
+const MATE_THROUGH_REPETITION: &str =
+    "1n1+S1lb1l/r5+P1g/nL1Sp2pN/4Pp3/1G3gp1p/L2k5/2+pp1S1K1/NPR2PG2/9 b SPb6p 1";
+
+fn solve(sfen: &str, limits: &Limits) -> MateResult {
+    let pos = Usi::parse_position(sfen).unwrap();
+    let mut solver = DfpnSolver::new(Arc::new(Signals::default()), 1);
+
+    solver.solve(&pos, limits)
+}
+
+/// Asserts that `sfen` is solved with a mate by consecutive checks.
+fn assert_mate(sfen: &str) {
+    let MateResult::Mate(pv) = solve(sfen, &Limits::default()) else {
+        panic!("no mate found: {sfen}");
+    };
+
+    assert!(pv.len() % 2 == 1, "{sfen}");
+
+    let mut pos = Usi::parse_position(sfen).unwrap();
+
+    for (ply, &mv) in pv.iter().enumerate() {
+        assert!(pos.is_legal_move(mv), "{sfen}");
+        pos.make_move(mv);
+        assert_eq!(ply % 2 == 0, pos.checkers().has_any(), "{sfen}");
+    }
+
+    assert!(pos.legal_moves().is_empty(), "{sfen}");
+}
+
+#[test]
+fn mates() {
+    for &sfen in MATES {
+        assert_mate(sfen);
+    }
+}
+
+#[test]
+fn mate_through_repetition() {
+    assert_mate(MATE_THROUGH_REPETITION);
+}
+
+#[test]
+fn mate_in_one() {
+    let result = solve("4k4/9/4P4/9/9/9/9/9/4K4 b G 1", &Limits::default());
+    let expected = Usi::parse_move("G*5b").unwrap();
+
+    assert!(matches!(result, MateResult::Mate(pv) if pv.len() == 1 && pv[0] == expected));
+}
+
+#[test]
+fn no_mates() {
+    for &sfen in NO_MATES {
+        assert_eq!(
+            solve(sfen, &Limits::default()),
+            MateResult::NoMate,
+            "{sfen}"
+        );
+    }
+}
+
+#[test]
+fn timeout() {
+    let limits = Limits {
+        nodes: Some(1),
+        ..Limits::default()
+    };
+
+    assert_eq!(solve(MATES[1], &limits), MateResult::Timeout);
+
+    let pos = Usi::parse_position(MATES[1]).unwrap();
+    let signals = Arc::new(Signals::default());
+    let mut solver = DfpnSolver::new(Arc::clone(&signals), 1);
+
+    signals.stop.store(true, Ordering::Relaxed);
+
+    assert_eq!(solver.solve(&pos, &Limits::default()), MateResult::Timeout);
+}
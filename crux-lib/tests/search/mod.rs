@@ -0,0 +1,148 @@
+mod dfpn;
+mod time;
+mod tt;
+
+use std::{
+    sync::{atomic::Ordering, Arc},
+    thread,
+    time::Duration,
+};
+
+use crux_lib::{
+    eval::EvalType,
+    notation::{usi::Usi, Notation},
+    search::{score::mate_in, Limits, Searcher, Signals},
+    shogi::position::Position,
+};
+
+fn search(sfen: &str, depth: i32) -> (Option<String>, i32) {
+    let pos = Usi::parse_position(sfen).unwrap();
+    let mut searcher = Searcher::new(Arc::new(Signals::default()), Arc::default());
+    let limits = Limits {
+        depth: Some(depth),
+        ..Limits::default()
+    };
+
+    let result = searcher.search(&pos, &limits, |_| {});
+
+    (result.best_move.map(Usi::format_move), result.score)
+}
+
+#[test]
+fn mate_in_one() {
+    let (best_move, score) = search("8k/9/8P/9/9/9/9/9/8K b G 1", 3);
+
+    assert_eq!(best_move.as_deref(), Some("G*1b"));
+    assert_eq!(score, mate_in(1));
+}
+
+#[test]
+fn wins_material() {
+    // The rook on 5e is hanging to the bishop on 9a.
+    let (best_move, score) = search("B7k/9/9/9/4r4/9/9/9/K8 b - 1", 3);
+
+    assert!(matches!(best_move.as_deref(), Some("9a5e" | "9a5e+")));
+    assert!(score > 0);
+}
+
+#[test]
+fn startpos_returns_move() {
+    let pos = Position::startpos();
+    let mut searcher = Searcher::new(Arc::new(Signals::default()), Arc::default());
+    let limits = Limits {
+        depth: Some(4),
+        ..Limits::default()
+    };
+
+    let result = searcher.search(&pos, &limits, |_| {});
+
+    assert!(result.best_move.is_some());
+    assert_eq!(result.depth, 4);
+    assert_eq!(result.pv.first().copied(), result.best_move);
+}
+
+#[test]
+fn multithreaded_search() {
+    let pos = Position::startpos();
+    let mut searcher = Searcher::new(Arc::new(Signals::default()), Arc::default());
+    let limits = Limits {
+        depth: Some(5),
+        ..Limits::default()
+    };
+
+    searcher.set_threads(4);
+    assert_eq!(searcher.threads(), 4);
+
+    let mut reported_nodes = 0;
+    let result = searcher.search(&pos, &limits, |info| reported_nodes = info.nodes);
+
+    assert!(result.best_move.is_some_and(|mv| pos.is_legal_move(mv)));
+    assert_eq!(result.depth, 5);
+    assert!(result.nodes >= reported_nodes);
+
+    searcher.set_threads(1);
+    assert_eq!(searcher.threads(), 1);
+}
+
+#[test]
+fn multithreaded_mate_in_one() {
+    let pos = Usi::parse_position("8k/9/8P/9/9/9/9/9/8K b G 1").unwrap();
+    let mut searcher = Searcher::new(Arc::new(Signals::default()), Arc::default());
+    let limits = Limits {
+        depth: Some(3),
+        ..Limits::default()
+    };
+
+    searcher.set_threads(3);
+
+    let result = searcher.search(&pos, &limits, |_| {});
+
+    assert_eq!(
+        result.best_move.map(Usi::format_move).as_deref(),
+        Some("G*1b")
+    );
+    assert_eq!(result.score, mate_in(1));
+}
+
+#[test]
+fn multithreaded_stop() {
+    let pos = Position::startpos();
+    let signals = Arc::new(Signals::default());
+    let mut searcher = Searcher::new(Arc::clone(&signals), Arc::default());
+    let limits = Limits {
+        infinite: true,
+        ..Limits::default()
+    };
+
+    searcher.set_threads(4);
+
+    let stopper = thread::spawn(move || {
+        thread::sleep(Duration::from_millis(200));
+        signals.stop.store(true, Ordering::Relaxed);
+    });
+
+    let result = searcher.search(&pos, &limits, |_| {});
+
+    stopper.join().unwrap();
+
+    assert!(result.best_move.is_some());
+}
+
+#[test]
+fn classical_evaluation() {
+    // The rook on 5e is hanging to the bishop on 9a.
+    let pos = Usi::parse_position("B7k/9/9/9/4r4/9/9/9/K8 b - 1").unwrap();
+    let mut searcher = Searcher::new(Arc::new(Signals::default()), Arc::default());
+    let limits = Limits {
+        depth: Some(3),
+        ..Limits::default()
+    };
+
+    searcher.set_eval_type(EvalType::Classical);
+
+    let result = searcher.search(&pos, &limits, |_| {});
+    let best_move = result.best_move.map(Usi::format_move);
+
+    assert!(matches!(best_move.as_deref(), Some("9a5e" | "9a5e+")));
+    assert!(result.score > 0);
+}
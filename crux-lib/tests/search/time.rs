@@ -0,0 +1,153 @@
+use std::time::Duration;
+
+use crux_lib::{
+    search::{
+        time::{TimeManager, TimeOptions},
+        Limits,
+    },
+    shogi::core::Color,
+};
+
+fn ms(millis: u64) -> Duration {
+    Duration::from_millis(millis)
+}
+
+fn clock(time: u64, increment: u64, byoyomi: u64) -> Limits {
+    let mut limits = Limits {
+        byoyomi: ms(byoyomi),
+        ..Limits::default()
+    };
+
+    limits.time[Color::Black] = ms(time);
+    limits.increment[Color::Black] = ms(increment);
+    limits
+}
+
+#[test]
+fn unlimited() {
+    let options = TimeOptions::default();
+    let infinite = Limits {
+        infinite: true,
+        ..clock(60_000, 0, 10_000)
+    };
+
+    for limits in [Limits::default(), infinite] {
+        let time = TimeManager::new(&limits, Color::Black, &options);
+
+        assert_eq!(time.optimum(), None);
+        assert_eq!(time.maximum(), None);
+        assert!(!time.should_finish(ms(1 << 40), 0.0));
+        assert!(!time.is_over(ms(1 << 40)));
+    }
+}
+
+#[test]
+fn movetime() {
+    let limits = Limits {
+        movetime: Some(ms(5000)),
+        ..Limits::default()
+    };
+    let time = TimeManager::new(&limits, Color::Black, &TimeOptions::default());
+
+    assert_eq!(time.optimum(), Some(ms(4880)));
+    assert_eq!(time.maximum(), Some(ms(4880)));
+    assert!(!time.is_over(ms(4879)));
+    assert!(time.is_over(ms(4880)));
+}
+
+#[test]
+fn byoyomi_only() {
+    let options = TimeOptions::default();
+
+    for byoyomi in [100, 1000, 3000, 10_000] {
+        let time = TimeManager::new(&clock(0, 0, byoyomi), Color::Black, &options);
+        let available = ms(byoyomi).saturating_sub(options.network_delay);
+
+        assert_eq!(time.maximum(), Some(available));
+        assert!(time.optimum().unwrap() <= available);
+        assert!(time.should_finish(available, 10.0));
+    }
+}
+
+#[test]
+fn never_exceeds_clock() {
+    let options = TimeOptions::default();
+
+    for time in [50, 500, 5000, 60_000, 600_000] {
+        for increment in [0, 1000, 10_000] {
+            for byoyomi in [0, 1000] {
+                let limits = clock(time, increment, byoyomi);
+                let manager = TimeManager::new(&limits, Color::Black, &options);
+                let available = ms(time + byoyomi).saturating_sub(options.network_delay);
+                let optimum = manager.optimum().unwrap();
+                let maximum = manager.maximum().unwrap();
+
+                assert!(maximum <= available);
+                assert!(optimum <= maximum);
+                assert!(manager.should_finish(maximum, 100.0));
+            }
+        }
+    }
+}
+
+#[test]
+fn uses_side_to_move_clock() {
+    let mut limits = clock(600_000, 0, 0);
+
+    limits.time[Color::White] = ms(1000);
+
+    let options = TimeOptions::default();
+    let black = TimeManager::new(&limits, Color::Black, &options);
+    let white = TimeManager::new(&limits, Color::White, &options);
+
+    assert!(black.maximum() > white.maximum());
+    assert!(white.maximum() <= Some(ms(880)));
+}
+
+#[test]
+fn minimum_thinking_time() {
+    let options = TimeOptions {
+        network_delay: Duration::ZERO,
+        minimum_thinking_time: ms(3000),
+    };
+
+    // Plenty of time: the minimum applies.
+    let time = TimeManager::new(&clock(60_000, 0, 0), Color::Black, &options);
+
+    assert_eq!(time.optimum(), Some(ms(3000)));
+
+    // Little time: the minimum is clamped by the maximum.
+    let time = TimeManager::new(&clock(5000, 0, 0), Color::Black, &options);
+
+    assert_eq!(time.maximum(), Some(ms(1000)));
+    assert_eq!(time.optimum(), Some(ms(1000)));
+}
+
+#[test]
+fn instability_extension() {
+    let options = TimeOptions {
+        network_delay: Duration::ZERO,
+        minimum_thinking_time: Duration::ZERO,
+    };
+    let time = TimeManager::new(&clock(400_000, 0, 0), Color::Black, &options);
+    let optimum = time.optimum().unwrap();
+    let maximum = time.maximum().unwrap();
+
+    assert_eq!(optimum, ms(10_000));
+    assert_eq!(maximum, ms(80_000));
+
+    // A stable best move finishes at the optimum time.
+    assert!(!time.should_finish(optimum - ms(1), 0.0));
+    assert!(time.should_finish(optimum, 0.0));
+
+    // An unstable best move extends the optimum time, up to a bounded factor.
+    assert!(!time.should_finish(optimum, 1.0));
+    assert!(time.should_finish(optimum * 2, 1.0));
+    assert!(!time.should_finish(optimum * 2, 100.0));
+    assert!(time.should_finish(optimum * 3, 100.0));
+
+    // The extension never goes past the maximum time.
+    let time = TimeManager::new(&clock(4000, 0, 0), Color::Black, &options);
+
+    assert!(time.should_finish(time.maximum().unwrap(), 100.0));
+}
@@ -0,0 +1,85 @@
+use crux_lib::{
+    search::tt::{Bound, TranspositionTable},
+    shogi::{
+        core::{PieceType, Square},
+        position::{key::Key, mv::Move},
+    },
+};
+
+#[test]
+fn store_and_probe() {
+    let tt = TranspositionTable::new(1);
+    let key = Key::from(0x0123_4567_89AB_CDEF);
+    let mv = Move::normal(Square::S77, Square::S76);
+
+    assert!(tt.probe(key).is_none());
+
+    tt.store(key, Some(mv), -120, 35, 7, Bound::Lower);
+
+    let entry = tt.probe(key).unwrap();
+
+    assert_eq!(entry.mv, Some(mv));
+    assert_eq!(entry.score, -120);
+    assert_eq!(entry.eval, 35);
+    assert_eq!(entry.depth, 7);
+    assert_eq!(entry.bound, Bound::Lower);
+    assert!(tt.probe(Key::from(0xFEDC_BA98_7654_3210)).is_none());
+}
+
+#[test]
+fn keeps_move_without_new_move() {
+    let tt = TranspositionTable::new(1);
+    let key = Key::from(42);
+    let mv = Move::drop(PieceType::Gold, Square::S52);
+
+    tt.store(key, Some(mv), 10, 0, 3, Bound::Exact);
+    tt.store(key, None, 20, 0, 5, Bound::Upper);
+
+    let entry = tt.probe(key).unwrap();
+
+    assert_eq!(entry.mv, Some(mv));
+    assert_eq!(entry.score, 20);
+    assert_eq!(entry.depth, 5);
+}
+
+#[test]
+fn quiescence_depth() {
+    let tt = TranspositionTable::new(1);
+    let key = Key::from(7);
+
+    tt.store(key, None, 0, 0, 0, Bound::Upper);
+
+    assert_eq!(tt.probe(key).unwrap().depth, 0);
+}
+
+#[test]
+fn clear() {
+    let tt = TranspositionTable::new(1);
+    let key = Key::from(7);
+
+    tt.store(key, None, 1, 1, 1, Bound::Exact);
+    tt.clear();
+
+    assert!(tt.probe(key).is_none());
+    assert_eq!(tt.hashfull(), 0);
+}
+
+#[test]
+fn hashfull() {
+    let tt = TranspositionTable::new(1);
+
+    assert_eq!(tt.size_mb(), 1);
+    assert_eq!(tt.hashfull(), 0);
+
+    for i in 0..100_000u64 {
+        let key = Key::from(i.wrapping_mul(0x9E37_79B9_7F4A_7C15));
+
+        tt.store(key, None, 0, 0, 1, Bound::Exact);
+    }
+
+    assert!(tt.hashfull() > 500);
+
+    tt.new_search();
+
+    assert_eq!(tt.hashfull(), 0);
+}
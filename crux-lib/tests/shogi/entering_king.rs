@@ -0,0 +1,99 @@
+use crux_lib::{
+    notation::{usi::Usi, Notation},
+    shogi::{
+        core::Color,
+        entering_king::{can_declare_win, declaration_points, EnteringKingRule},
+    },
+};
+
+#[test]
+fn required_points() {
+    let cases = [
+        (EnteringKingRule::None, Color::Black, None),
+        (EnteringKingRule::None, Color::White, None),
+        (EnteringKingRule::Point24, Color::Black, Some(31)),
+        (EnteringKingRule::Point24, Color::White, Some(31)),
+        (EnteringKingRule::Point27, Color::Black, Some(28)),
+        (EnteringKingRule::Point27, Color::White, Some(27)),
+    ];
+
+    for (rule, color, points) in cases {
+        assert_eq!(rule.required_points(color), points);
+    }
+}
+
+#[test]
+fn declaration_points_of_both_sides() {
+    let cases = [
+        ("GGGG1SSSS/4K4/RRBB5/9/9/9/9/9/4k4 b - 1", 28, 0),
+        ("GGGG1SSSS/4K4/RRBB5/9/9/9/9/9/4k4 b 3P2p 1", 31, 2),
+        ("4K4/9/9/9/9/9/5bbrr/4k4/ssss2ggg w - 1", 0, 27),
+        ("4k4/+R8/9/9/4K4/9/9/9/9 b B 1", 10, 0),
+    ];
+
+    for (sfen, black, white) in cases {
+        let pos = Usi::parse_position(sfen).unwrap();
+
+        assert_eq!(declaration_points(&pos, Color::Black), black);
+        assert_eq!(declaration_points(&pos, Color::White), white);
+    }
+}
+
+#[test]
+fn can_declare() {
+    let cases = [
+        // 28 points: enough for black under the 27-point rule only.
+        (
+            "GGGG1SSSS/4K4/RRBB5/9/9/9/9/9/4k4 b - 1",
+            [false, false, true],
+        ),
+        // 31 points.
+        (
+            "GGGG1SSSS/4K4/RRBB5/9/9/9/9/9/4k4 b 3P 1",
+            [false, true, true],
+        ),
+        // 27 points: enough for white but not for black.
+        (
+            "4K4/9/9/9/9/9/5bbrr/4k4/ssss2ggg w - 1",
+            [false, false, true],
+        ),
+        (
+            "SSSS2GGG/4K4/RRBB5/9/9/9/9/9/4k4 b - 1",
+            [false, false, false],
+        ),
+        // Not the side to move.
+        (
+            "GGGG1SSSS/4K4/RRBB5/9/9/9/9/9/4k4 w 3P 1",
+            [false, false, false],
+        ),
+        // The king is outside the promotion area.
+        (
+            "GGGG1SSSS/9/RRBB5/4K4/9/9/9/9/4k4 b 3P 1",
+            [false, false, false],
+        ),
+        // In check.
+        (
+            "GGGGpSSSS/4K4/RRBB5/9/9/9/9/9/4k4 b 3P 1",
+            [false, false, false],
+        ),
+        // Fewer than 10 pieces in the promotion area.
+        (
+            "GGGG5/4K4/RRBB5/9/9/9/9/9/4k4 b 4S6P 1",
+            [false, false, false],
+        ),
+    ];
+
+    let rules = [
+        EnteringKingRule::None,
+        EnteringKingRule::Point24,
+        EnteringKingRule::Point27,
+    ];
+
+    for (sfen, expected) in cases {
+        let pos = Usi::parse_position(sfen).unwrap();
+
+        for (rule, expected) in rules.into_iter().zip(expected) {
+            assert_eq!(can_declare_win(&pos, rule), expected, "{sfen} {rule:?}");
+        }
+    }
+}
@@ -0,0 +1,220 @@
+use crux_lib::{
+    notation::{usi::Usi, Notation},
+    shogi::{
+        mate::{is_checkmated, mate1ply},
+        position::Position,
+    },
+};
+
+/// Hand-made problems with a single intended mate.
+const TSUME_PROBLEMS: &[(&str, &str)] = &[
+    // Gold drop on the head of the king.
+    ("4k4/9/4P4/9/9/9/9/9/4K4 b G 1", "G*5b"),
+    // Knight drop that cannot be captured.
+    ("7gk/7bl/9/9/9/9/9/K8/9 b N 1", "N*2c"),
+    // Double check by a knight uncovering a lance.
+    ("3sks3/3g1g3/9/9/4N4/9/9/9/4L3K b - 1", "5e4c"),
+    // White to move.
+    ("8k/9/9/9/9/9/4p4/9/4K4 w g 1", "G*5h"),
+];
+
+/// Positions where a pawn drop would mate, which is illegal (uchifuzume).
+const UCHIFUZUME: &[(&str, &str)] = &[
+    ("7lk/9/8G/9/9/9/9/9/K8 b P 1", "P*1b"),
+    ("kl7/9/G8/9/9/9/9/9/8K b P 1", "P*9b"),
+];
+
+/// Positions reached by random play where the side to move can mate in one.
+const MATE_IN_ONE: &[&str] = &[
+    "+S5k1p/2Ls2p2/N1+Pr2n1n/G2pPp1pG/3G2P1P/P2Pg4/KL3SNSL/L+p2p2P1/1P1+bR4 w 3Pb 200",
+    "+P+Ns2+P1nl/4+b1n2/2k1gps2/pp1pPg1pp/1S2pGP2/1P1P1P3/+l1P4PP/N3+r2SR/L1K2G2L w bp 102",
+    "1sg2k3/ln1s3L+N/ppPp1pPsp/5gp2/PP2s2P1/LG2PnG1P/2p+b3RL/3B2R2/2K2+p1N1 w 3p 172",
+    "3rk1b2/+B1g4Pp/4s1g2/P1p4pP/1p1nKpR2/L2PPP3/NPP1SG1l1/2+n3S1+l/SL7 w G5Pn 126",
+    "8k/2sP4l/g1n1nspLp/1p2ppPB1/pGpr3PP/4G3R/+lP1pK1S1N/P1S5L/4P3B b Gn3p 167",
+    "p+N1G+N2r1/1+N5s1/+P1kg1p1+bG/4R1PsP/1SlppP2p/1P2P1pP+p/9/2s2+p1+bG/K1+nP2+l2 b L2Pl 295",
+    "l2g1kg2/2r1g1P+P1/spnn1G1+b1/1P2Pp1Bl/P2ps3N/3P2K2/L1p3S1P/2P2PR2/3S3NL b 5P 121",
+    "p4sk2/3+Pn1ssg/2r6/S6bl/1G1pg1p1P/3PPnNGL/r1p1B3L/Pp4Pp1/L1K2P2+n w P4p 206",
+    "4k4/Prr2s2l/1+N1P1sngp/lnp2PPP1/L6+P1/2P1gN2P/+b+p4Gp1/6+s1L/1K1SP1BG1 w 4Pp 168",
+    "1pk2+P3/Ps6l/2+P2p2p/pP2nr3/2bpPPppb/lgR3P1P/g4KGN1/3+npG2L/SN4+s2 b SPlp 189",
+    "ln3k1n1/1s2g3l/pPprgp+BpN/3pp1p2/P8/3PPP1PP/LSP3P2/3K1G2R/BN1G2S1L b S2P 57",
+    "lnsk1+B2l/r2p5/P4p1sp/3N1gpp1/1p2p1G2/1P1gPPPP1/N1PPnK2P/L1S2S2R/2BG4L b 2P 103",
+    "r1l3+B2/k5p1p/p1+R1p3P/3p3pN/1P1Ggp1P1/P1pLN1P2/+s1N5N/SpP2g2S/L1B4KL b GS2Pp 265",
+    "1+P2k3+N/GP3sl2/2nP2+Spp/ppPnPp+B2/G1p3pP1/P1Rnp3P/1GBpS1K2/+l7R/3L4L b GSP 263",
+    "2+S3+Rs1/2s5l/4l+NnGl/1p2pp1Pp/pg1G3k1/P2p2pB1/GPpPP1+pp1/2B1RP3/L+n4KN+p w Ps 216",
+    "1r1k1+BG2/lG7/2P3npl/sp1P1pP1L/p1B1p1s2/2n1P2P1/1PK1SP+p2/2GS1R3/LN5N1 b GP4p 95",
+    "1+P7/l1GP2LP1/l3s2Gs/1gs2P3/p2ppk1l1/P3P2bP/Np+nG2p1n/+n5+s2/2KR2P1+p w 3Prbp 218",
+    "+P+N6g/8l/3p1ks2/r1p2pg1p/lpPgp2sP/LP1lPK3/2NP1PNN1/+pSG3PP1/B6B+r w SPp 198",
+    "l+P1G5/rP2kP3/n2Ps1sPl/1g2p3p/P1+B3pNP/3G1pP1L/L1+bSP4/+p3S+n1p1/1NK1R4 w 2Pgp 180",
+    "ppkp2s1l/l6g1/1+P1+R4n/1G1Ppr3/1Pl4P1/6G1p/N1PSP1p1b/L2KSp1SP/2B1+n1P2 b GN2Pp 173",
+    "6bn1/5g1k1/l2nppp1l/p1r1g1s1p/2PpP1SpP/L1pR1PPP1/2+nP2G2/Pp2GB2L/2K4NS w sp 138",
+    "4+B1n2/3SG2Pl/5r2S/2ps2Snk/pG4p2/LPPPpg2P/1BN2+pP+p1/PpKp+r1lGp/5N+p1L w p 212",
+    "g5s2/n+Ps4n+N/+PLrp1pp+P1/2rg3Pp/1kP1p1g1P/3BgSP1b/1Kp6/L4P1+pL/1+p2+p4 b Lsnp 245",
+    "+P+N1rn+Pp2/1P1p3+S1/k2b2s1l/ll1sP+P2N/p3p+bPgP/1gG+p5/1g2SRKN1/5P1+p+p/L1P6 w 2p 266",
+    "l1s3kr1/2P1p1g1l/+P2r3sP/1+P3g2p/pNgP1n1P1/P5p2/1PNps+bPSN/1b1G5/L3K3L w 4P 140",
+    "1+R4s1+P/S1+B5p/3n1gP2/1Pp1pp2r/PL1G2kP1/2G4p1/N3Psp1l/L1PS+nP2N/+b+lG4KP w P2p 182",
+    "1lr1bk1+P1/p2p3Pl/1p1G1S1rs/1n1n1G2p/l2gSp3/P1P1P1p2/LSNK1P1nP/3G1B2+p/3P5 b P2p 237",
+    "+N3+P2nk/s2p+S3l/4+RP1gp/1b1PP3n/l3g1p2/Ppp1pp1pP/1P1+p1LK1L/RS2GGP1b/5S1N1 w p 170",
+    "l2pk4/3P4l/ng2+Sp2n/1sp1N2P1/p1r2Pppp/1PP1pGP2/gS+b+p1SRGN/1p1B4L/1+p3K3 b Lp 157",
+    "1nk3snl/lsg2r1b1/3pNpp1p/ppp1p2p1/6P2/2PP3PP/PPB1PP3/6S1R/L1SGKG1NL b G 33",
+    "1k3b+Np+P/3P1gp1P/3gg1+P2/1N2s1sPS/1pP2p3/1P1l3Rg/PL1p+pP2L/r2l3sp/4K2N1 w bn2p 290",
+    "3+N2pnp/1r2p3k/n5+S1+b/1pppgp3/lPPs1gLp1/p2P3+pL/L1G3R1N/sK1S5/B5+pP1 b G3p 165",
+    "+Prbp5/3s1kg1P/+L3gp1ln/2p1p1K2/pn1G2Pp1/1pP2P2G/PPNP1NSR1/1S3S1P1/B1L2L3 w 2P 142",
+    "l6n1/rk1gP1PPP/ppPp5/1s3p1+PS/1NpB5/P2P1PG1l/1+png2NR1/L3pBS1L/K7+s w 2Pg 138",
+    "5p1+Np/1pPp1s2l/+P+P1+N1k2n/2bPp1pgB/3GP2pG/Lr2S1P1K/2p1G1s2/4L3L/R+s1+n3P1 w 2Pp 208",
+    "l1B1kS+P2/3s1g1bl/3p+N1n2/ppg6/1npP2pp1/PP2P3p/N4P1g1/L1PGK1SPR/4s1R1L b 3p 127",
+    "r2bp+PG1k/3s2Pb1/l1G5+P/1PG6/P1PPPNp2/1n1G1P1R1/S1L1K2PS/pSpp5/4+p+l2L b 2N2P 243",
+    "1+P4s+N1/P1+P5p/lp2lkp2/4p1PS+R/1Bp1gpGp+n/p2pl+b2R/1KPGs3+p/4L2S1/1NGP1+p+n1P b - 187",
+    "1+N1p1+L2p/2p+P1+Bpk1/+Sp3nr2/2G3lP1/p1PNPnS2/PP3p1p1/4pG1+l1/7s1/1KGB1GSrL b P2p 215",
+    "5g2l/nnr1lb3/+PPg1R2pk/5s2p/1pp1p1pPP/G3npG2/l1PpL1b2/4K1S2/+pS1N1S3 w 4p 180",
+    "6b+Pn/l2s1k2l/3l1gpgs/1+P1pp4/NpB1PG3/2p2pPpp/1R1+r1sNg1/pP1P5/LS2K4 w n3p 174",
+    "n+P1G5/2+S1k3+P/Lp1n1p1r1/4p3R/pLPNn2sP/5P3/1+b1pP1P+b1/2pG1G1S+p/1P1SL1K2 w 3Pgl 180",
+    "G1s1+N2n1/6rgl/l1npb2pp/1P2p1p1P/p1SkP1PP1/Lpp2S1R1/4G3L/3PKp3/B3G1SN1 b 3p 129",
+    "+Pn5n1/+PL2r1kb+P/1llSgg3/1p5sl/1PGspKp2/4PG1+p1/N1PP2PP1/+pB1pR4/4+sP1NP w P 156",
+    "l3gn2b/4grsPl/1pnpk1+B2/p1pPp4/2P2P2p/1P4P2/P3PR2P/L1sGK3L/1NS2+nS2 w 2Pgp 88",
+    "l2G2+S1p/2P2kg2/p2n1gn1S/3+RPN1pg/PP1pp1KPl/L1pP1PPSP/6NR1/+b7L/1+s+p+p+b4 b P 255",
+    "1n2kp1gs/1gR5l/l2p1+Pn1P/p4+b1B1/1pp1P2S1/PPR6/L1PP2GP1/2K3p1L/1NG1+p1+n2 b 3P2s 123",
+    "1ng1k1gn1/l3r2sl/1pp1p1p1b/p4S1np/G5PP1/1P1p4+r/P1P1P4/LB2+s1+ppL/1N3GS1K w P2p 82",
+    "1n5n1/l1s2g+B2/p1rp4s/1pp2p1p1/1kP5p/P3p1PPl/1P1PPP3/1BRSl1GSP/LN1KG2NG b P 71",
+    "4g1+B2/l3k2+P+L/pPrp5/S3Npgp1/2g3pP1/4K2lp/SppPPGP2/+sR4+n1L/2N4Sb w N4p 154",
+    "ln1kp1p1+P/1s5b1/r1g1+P3p/2pp1pspn/1p2lPP2/1PPG5/1+b1PN1GPP/5SRS1/1+p3K1NL b GL 97",
+    "1+P2s1r+L1/1+S3k2l/4ppn1r/p2s1P1pp/N1pp2pg1/PGP1PK2L/L1N6/1N+p1g+pS1+b/b1g6 w P2p 208",
+    "6k1r/l1+B4b1/+P3p2+P1/2sg1pN2/p1P2P2L/LG1R2Pp1/N1N1P1NSp/1L2K4/SP2GS+p2 b G3P2p 159",
+    "2+N1+Np3/g6r1/2sgsPP+S1/6Bnl/kP1K3g1/2PP4r/ppp5p/+l3P2g+p/2+lN1S2L w 5Pbp 290",
+    "5k3/l1s2r2P/p1Pgp+S+Pgn/1p2Pp3/1N1p2N1K/P4P2L/LPBP2NP1/2SLG1R2/+b2s2g2 b 4P 117",
+    "2p1gr1p1/P+LG5l/1+P1+N1p3/1p1Pk1bbp/1P2s3P/1n5P1/S+p2+p1G+nL/L3P+s1NG/S2KR4 w P3p 204",
+    "ln4s1b/gk2s4/pp1g1bppn/2pppp3/P1PS2L2/4P4/1PNP1PPPP/2GG3+r1/L1SK3NL w Pr 56",
+    "G+P4+N2/3L2p1P/4lp3/k1s3PPn/r2Sg2bL/2S4nS/+p1NpR+p2g/2P1PP1+pB/PK1P+l4 w 3Pg 264",
+    "1n3k3/G2+N1s1+N1/3p1p3/2RP2GP1/1Lp1pP1pG/2+s1N1p1P/p1L2b1r1/+b2K2+s+p1/2Ps+l3L w G5p 232",
+    "1p+P1pn1+P1/P2Pb1gPp/L1s1+P+bkps/1R7/1n3P1s1/1P6R/n3N1G1l/+l1GG2p1+s/2KL1+p3 w 3Pp 212",
+    "4+bpr1n/s5pk1/l1s3s1P/4g2P1/G+BNlP4/1PR2P3/Pp1G2PS1/1L1P2L+n1/+n2K5 b G7P 213",
+    "+N2k2p2/Pl3rgsP/p3G2+PG/1n2PpPp1/2s4PN/2PP1P+l1R/1P3s2L/l2p4S/1+pB+n1KB2 w Pgp 224",
+    "2rg1k3/6sPl/2gs4+S/p1pp1p2p/PL1PpPG1P/NSP1P1p2/1+pN1L1G1N/R4lbp+b/6K2 w N2p 136",
+    "G5g2/4+R3l/k3r2p1/2+P2p3/pSNppPPPp/1P3GnGL/3P5/lS2l3K/BN1+b+nS+pS1 b P4p 219",
+    "ks4+P2/4+S+P1r1/gpl1+P1NP+b/lsp3P1l/pgPN1Spg1/4R3P/1+p1pp3L/PN1P2Np1/B3K4 w Pg 240",
+    "1n2B2n1/lp5rl/p+P4gsg/2p1g3p/PkPN2pp1/3pSp1SP/1G4N1L/LB1S5/4PPRK1 b P3p 149",
+    "1+P1B5/lsg2P+P2/1S1psr3/l1N1P1NGP/l3k1PP1/p1P1n4/gl+nPK3+p/1p1B3p1/2gR3s1 w 3Pp 260",
+    "6kn1/2g+S2sb1/g1npNr1b1/2G2P2l/N1PL1pp1P/3S2Pp1/3Pl2PS/GPp6/1K3+p2L w 2Pr3p 170",
+    "p6+P1/1p2rPp1+N/3+P4l/1sp1ppsgl/g3L1+b2/2P1P1k1p/P1+r2l+n2/+pgSB1G1ps/3K3+n1 w n3p 204",
+    "p1+B4pn/l3p+N2l/g+N2g1p1n/2pR1p3/1pP2kPP1/P3P2S1/LSGp1P2p/1+s1P3GL/2b1KS3 b R2p 195",
+    "8+B/1+S4k1+S/l2pG3l/p+PP3Gp1/2B1p4/1pS1PPPP1/P1pP1KS1+p/L+nNR1G2L/2G2+n1NP b R2P 141",
+    "8b/2nL+S1r1l/P3ggkln/1pp1p4/7sP/1sKp1NP1p/1PP1P+b1PN/p1GG4L/4+pRS2 w 4P 164",
+    "+R7+N/g2Sk3+P/p4l1p+N/3GpppRG/1pp1P2L1/1PPBLK2p/2npnG3/+s2s2B1+p/+l4+s3 b 4p 247",
+    "7Gb/l1+P+R5/P+Pn3kpl/2s1n3s/LPppPpS2/3GNP2p/1gPP1RPP1/p1+b3S1L/4+p1KN1 b GP 155",
+    "l1rk5/s1g3s2/pp1Nsg3/+b1p1p2+Bl/4P2Pp/2P2PPGP/PPNP4N/LS+n2GR1L/5K3 b 4P 99",
+    "1n3g1pl/1r1sP+Pp2/lppn1P2g/p3pp3/1P1Pk1+B1B/2P2gR1P/P3+p1+nPL/L2+s4p/1NgS1K1S1 w - 118",
+    "1ns2kb2/2pgg1+bgl/l4rps1/4p2pp/p2p1pK2/Pp3RPg1/1S1N4N/L2SPP2L/+n8 w 5p 158",
+    "1n4B2/2rk1s3/l4g1pl/p1Ppp1p2/3P2Gn1/1S1GP2Pp/PP5S1/Lp1+n1GR1L/1N1+p1KB2 w S2P2p 142",
+    "2G2+P+P1G/1p3P3/+P1s+N3P+B/1k6P/N1p6/Sr1gpppll/N2B+p1P1L/L1K2GR2/2sP3NS w 2P2p 240",
+    "6bG1/k1p2s1p+S/3P1P2L/pp3g+N2/2PrP2Pp/1r1p1G2n/+nP+b2LP2/P4S3/2K1G2+p+l w Psnl2p 220",
+];
+
+/// Positions reached by random play where the side to move cannot mate in one.
+const NO_MATE_IN_ONE: &[&str] = &[
+    "+S6n1/3srk3/ng1p1p2l/Gp4p1p/p2Gp2p1/2p1PPG1P/P2P2P1L/L2S2SbR/1NB2K1N1 b L3p 97",
+    "+S6n1/3sr1k2/1g1p1p2l/Gp4p1p/pnL1p2p1/2pGPP1GP/P2P2P1L/L2S2SbR/1NB2K1N1 w 3p 102",
+    "lnsgkg1n1/3r2sbl/pppppp1pp/6p2/9/1P3P3/P1PPP1PPP/1B1S2SR1/LN1GKG1NL b - 9",
+    "1gs1ks3/l2r1n2l/ppnp1p2p/4p2p1/PPP1g1pn1/3PP2PP/2p1S1PR1/L3KGS1L/2BG3NB w p 72",
+    "lnk2g1nl/1r4+B1r/1plg1p3/pPp2s3/2P1p3S/2KpPGp1p/P4PPPN/1S1+b2S2/1N4G1L b P2p 79",
+    "5+Pg1l/lr1k4r/npbg1p2n/2lpp1GpP/P1SNs4/1Ppn1PpS1/1K2P1PPp/2P5L/5S+bG1 w p 156",
+    "l3gg1n1/1sr1k4/ppn1p3l/3p1spp1/2p5p/PP2PPPP1/N1PP+b1G1P/1B1G2R2/L1S2KSNL b P 47",
+    "l2r2bg1/5gs2/+P4k2n/1+Ppppp2l/1n3SpPB/3PPP2P/2P3Pp1/RP2GGS1L/LS3K1N1 b Pnp 87",
+    "1ns1k2nb/l4rgsl/ppp1gpppp/3p5/1P2p4/5P1P1/P1PP2P1P/1BSGG1R2/LN1K2SNL b p 27",
+    "lns3s1l/r3k4/p1+Bpgpn1p/P3pgpp1/1pp2n3/1P3PPP1/2PP1K2P/2S4GR/LNBG2S1L w p 62",
+    "1n1gg1bn1/1rsk4l/lp1p1pspp/p1pPp1p2/7P1/PP2PP2P/2P3P2/1BS2G1R1/LN1GK1SNL w - 28",
+    "l2+S3n1/1P2k3p/6NpL/2Ggp4/ppP3pPS/3NPpP2/PG3P2P/LRGB2K1R/1NSS4L b 2Pbp 123",
+    "l3+S2n1/1P3k2p/3P2NpL/2Ggp2S1/ppP1P1pP1/2bp1pP2/P1G2P2P/LRGB2K1R/1NSS4L w Pn 132",
+    "l1sg1gsnl/4rk1b1/ppnpppp1p/2p4p1/9/2P5P/PPBPPPPP1/1R2KG3/LNS1G1SNL b - 21",
+    "1s6l/6s1l/6nGP/1pgGpp1P1/p5N2/P2pS1pBk/+sPpPP1P+p1/RG3P3/L1+nB2KN+p w Rlp 172",
+    "1+R6l/1s4s1l/4l1nGP/1pg1pp1Pp/p2GS1Nk1/P2p2pB1/GPpPP+p1p1/R4P2S/L1+nB2KN+p w - 186",
+    "1r1k1+BG2/l7l/1sg3npP/1p1P1pP2/p1p1p1s2/2PKP2P1/BP2SP+p1L/3S1R1G1/LNG4N1 w NP2p 78",
+    "2s1kgbn1/+Ng6l/3p4p/lpp1pspp1/p4G2P/2G2SPP1/PPPPPP2L/L4R3/BNS1K2N1 w Rp 64",
+    "+P+N6g/P3r3l/3p1ks2/2p2pg1p/lpPgp2sP/LP1lP4/2NPKPNN1/+pSG3PP1/B6B+r b SP 193",
+    "ln3gsnl/rsg1k2b1/p2pp1ppp/1pp2p3/1P2P4/9/P1PP1PPPP/LB1GG3R/1NS1K1SNL b - 21",
+    "l4G3/rP+P3rsl/ns1g3+Pp/B3p1p2/Pppk1p2P/5P2L/L1PsG1P2/pb1GS4/1N1K1+n1N1 b 2Pp 127",
+    "lns2gsnl/2gr1k1b1/p1p1ppppp/1p1p5/9/2PP3P1/PPN1PPP1P/1B2GR3/L1S1KGSNL w - 12",
+    "1n1rnk1n1/4g2b1/l1sgpppsl/p1pp3pp/1SPP2S2/P3PPPP1/L+p6P/1+p1G1R2L/3KBG1N1 w - 78",
+    "1nsgkgs1l/l2r5/p2p3pn/5p1bp/1pp1pP1P1/1PGPP1p2/P1P3P1P/LB2SKG2/1NS2R1NL b - 45",
+    "4s2+N1/nrs1kbg1p/1p1pg4/l2g1p3/pPp1r1p1L/2PP2Sp1/PK2G3P/LB+p5L/NN4S2 b 4p 101",
+    "4s2+N1/nrs1kbg1p/1p1p5/l2ggp3/pPp1r1p1L/2PP3p1/PK2GS2P/LB+p5L/NN4S2 b 4p 103",
+    "6bn1/l2g1k1sl/1rsgs3p/1ppG1pppP/1n2pP3/pPP3PP1/P1GpS3N/1B3R2L/LN2K4 b Pp 117",
+    "6bn1/l2g3sl/1rsgs1k1p/1pp1GpppP/1n2pP3/pPP3PP1/P1GpS3N/1B3R2L/LN2K4 b Pp 119",
+    "6bn1/l2g1k1sl/sr1gs3p/1pp1GpppP/1P2pP3/p1P3PP1/PG2S3N/1B1K1R1NL/LN7 w 2Pp 128",
+    "3p2kn1/lP1g4l/1r2s1bsp/1s1G3pP/Gpp1pppP1/pGP1P1P2/P2S1R2N/1B2K2NL/LN7 b Pp 153",
+    "2s2g1nl/l2k2s2/1rppbg1pp/1p1Ppp2n/LN4p2/2PSPPPPP/1P6L/3KGRS2/2B1G2N1 w Pp 56",
+    "r2+Ps2nk/s4b2l/2N4gp/1p2Pg2n/l1Pp2pp1/1B2pp1PP/PP1P1LK1L/RS3G3/3G1SPN1 b 2P 145",
+    "l3k1snl/1sg1rg1b1/nppp1pppp/9/pP2p4/5PPPP/PGPPP3N/LB1S1K1RL/1N3GS2 w - 26",
+    "l1g3sn1/1s1krg1bl/nppp1pppp/1P7/4p4/p4PPPP/PGPPP3N/LB1S3RL/1N2GKS2 b - 33",
+    "l1g3sn1/1+P1kr2bl/n1p1gpp1p/7p1/4p2P1/5PPRP/pGPpP3N/LB1PG1K1L/1N4S2 w Ss2p 52",
+    "l2k1Ssnb/g4r2l/n1p1gpp1p/3s3p1/1p5P1/G3pPP1P/p1Pp1G1RN/LB1P1S2L/1N3K3 w 3p 70",
+    "lnsgkgsnl/1r5b1/ppppppppp/9/9/9/PPPPPPPPP/1B5R1/LNSGKGSNL b - 1",
+    "l3b2n1/1sr3+N1l/p2k1PNpP/Pp2pgs2/1Pppg3S/LGPP5/5+p1PL/K3Pp+p2/BNS2Rg2 b p 147",
+    "l1r1+N2+Np/2k2P2l/P6pn/1Bs1p1s1P/1PGPg1g1S/L1p1r1P1L/7P1/KS2Ppp+p1/BN3+pg2 w Pp 182",
+    "lp3r1+Pp/pG1P3+N1/P3k2P1/LP2p4/2Pp1s2S/2p1gr1gL/NS1+sn1n+b1/1K2Ppp2/BL3+p+pg1 b p 261",
+];
+
+#[test]
+fn tsume_problems() {
+    for &(sfen, expected) in TSUME_PROBLEMS {
+        let mut pos = Usi::parse_position(sfen).unwrap();
+        let mv = mate1ply(&mut pos).unwrap_or_else(|| panic!("no mate found: {sfen}"));
+
+        assert_eq!(Usi::format_move(mv), expected, "{sfen}");
+        assert_mates(&mut pos, sfen);
+    }
+}
+
+#[test]
+fn uchifuzume() {
+    for &(sfen, drop) in UCHIFUZUME {
+        let mut pos = Usi::parse_position(sfen).unwrap();
+        let drop = Usi::parse_move(drop).unwrap();
+
+        assert!(!pos.is_legal_move(drop), "{sfen}");
+        assert_eq!(mate1ply(&mut pos), None, "{sfen}");
+    }
+}
+
+#[test]
+fn mate_in_one() {
+    for &sfen in MATE_IN_ONE {
+        let mut pos = Usi::parse_position(sfen).unwrap();
+        assert_mates(&mut pos, sfen);
+    }
+}
+
+#[test]
+fn no_mate_in_one() {
+    for &sfen in NO_MATE_IN_ONE {
+        let mut pos = Usi::parse_position(sfen).unwrap();
+        assert_eq!(mate1ply(&mut pos), None, "{sfen}");
+    }
+}
+
+#[test]
+fn checkmated() {
+    let mut pos = Usi::parse_position("4k4/4G4/4P4/9/9/9/9/9/4K4 w - 1").unwrap();
+    assert!(is_checkmated(&mut pos));
+
+    // The king can capture the unprotected gold.
+    let mut pos = Usi::parse_position("4k4/4G4/9/9/9/9/9/9/4K4 w - 1").unwrap();
+    assert!(!is_checkmated(&mut pos));
+
+    // Not in check.
+    let mut pos = Position::startpos();
+    assert!(!is_checkmated(&mut pos));
+}
+
+/// Asserts that [`mate1ply`] finds a legal move after which the opponent has no legal move,
+/// and that the position is left unchanged.
+fn assert_mates(pos: &mut Position, sfen: &str) {
+    let key = pos.key();
+    let mv = mate1ply(pos).unwrap_or_else(|| panic!("no mate found: {sfen}"));
+
+    assert_eq!(pos.key(), key);
+    assert!(pos.is_legal_move(mv), "{sfen}");
+
+    pos.make_move(mv);
+
+    assert!(pos.legal_moves().is_empty(), "{sfen}");
+    assert!(is_checkmated(pos), "{sfen}");
+}
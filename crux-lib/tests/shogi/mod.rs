@@ -1,5 +1,8 @@
 mod attacks;
 mod bitboard;
 mod core;
-mod movegen;
+mod entering_king;
+mod mate;
+pub(crate) mod movegen;
 mod position;
+mod see;
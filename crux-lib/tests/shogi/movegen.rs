@@ -1,4 +1,4 @@
-const TEST_SFENS: &[(&str, u64, u64)] = &[
+pub(crate) const TEST_SFENS: &[(&str, u64, u64)] = &[
     (
         "l4s2l/1+S1+Pskg2/4p1p+Rp/p3np1p1/6+rn1/2+BSPP3/PP2G3P/2GKN4/L7L b BN3Pg3p 123",
         15723,
@@ -506,8 +506,12 @@ use std::time::Instant;
 use crux_lib::{
     notation::{usi::Usi, Notation},
     shogi::{
-        movegen::{generate, is_legal, is_pseudo_legal},
-        position::Position,
+        core::{PieceType, Square},
+        movegen::{
+            generate, generate_legal, generate_staged, is_legal, is_legal_move, is_pseudo_legal,
+            GenType, MoveList,
+        },
+        position::{hand::Hand, mv::Move, Position},
     },
 };
 
@@ -519,6 +523,166 @@ fn perft2() {
     }
 }
 
+#[test]
+fn gives_check() {
+    fn walk(pos: &mut Position, depth: i32) {
+        for mv in generate(pos) {
+            if !is_legal(pos, mv) {
+                continue;
+            }
+
+            let gives_check = pos.gives_check(mv);
+            let captured = pos.make_move(mv);
+
+            assert_eq!(gives_check, pos.checkers().has_any());
+
+            if depth > 1 {
+                walk(pos, depth - 1);
+            }
+
+            pos.unmake_move(mv, captured);
+        }
+    }
+
+    for (sfen, _, _) in TEST_SFENS {
+        let mut pos = Usi::parse_position(sfen).unwrap();
+        walk(&mut pos, 2);
+    }
+}
+
+#[test]
+fn staged_perft2() {
+    for (sfen, expected, _) in TEST_SFENS {
+        let mut pos = Usi::parse_position(sfen).unwrap();
+        assert_eq!(staged_perft(&mut pos, 2), *expected);
+    }
+}
+
+#[test]
+fn staged_generation() {
+    fn sorted(moves: MoveList) -> Vec<u16> {
+        let mut moves: Vec<_> = moves.iter().map(|mv| mv.as_u16()).collect();
+        moves.sort_unstable();
+        moves
+    }
+
+    fn walk(pos: &mut Position, depth: i32) {
+        let all = generate(pos);
+
+        if pos.checkers().has_any() {
+            assert_eq!(
+                sorted(generate_staged(pos, GenType::Evasions)),
+                sorted(all.clone())
+            );
+        }
+
+        let captures = generate_staged(pos, GenType::Captures);
+
+        for mv in &captures {
+            assert!(pos.piece_at(mv.to()).is_some() || mv.is_promotion());
+        }
+
+        let mut union = captures;
+        union.extend(generate_staged(pos, GenType::Quiets));
+        assert_eq!(sorted(union), sorted(all.clone()));
+
+        let checks: MoveList = all
+            .iter()
+            .copied()
+            .filter(|&mv| pos.gives_check(mv))
+            .collect();
+        assert_eq!(
+            sorted(generate_staged(pos, GenType::Checks)),
+            sorted(checks)
+        );
+
+        if depth > 1 {
+            for mv in all {
+                if is_legal(pos, mv) {
+                    let captured = pos.make_move(mv);
+                    walk(pos, depth - 1);
+                    pos.unmake_move(mv, captured);
+                }
+            }
+        }
+    }
+
+    for (sfen, _, _) in TEST_SFENS {
+        let mut pos = Usi::parse_position(sfen).unwrap();
+        walk(&mut pos, 2);
+    }
+}
+
+#[test]
+fn legal_generation() {
+    let candidates: Vec<_> = Square::ALL
+        .iter()
+        .flat_map(|&from| {
+            Square::ALL
+                .iter()
+                .filter(move |&&to| to != from)
+                .flat_map(move |&to| [Move::normal(from, to), Move::promote(from, to)])
+        })
+        .chain(
+            PieceType::ALL
+                .iter()
+                .take(Hand::HAND_PIECE_TYPES)
+                .flat_map(|&piece_type| {
+                    Square::ALL
+                        .iter()
+                        .map(move |&to| Move::drop(piece_type, to))
+                }),
+        )
+        .collect();
+
+    for (sfen, _, _) in TEST_SFENS {
+        let mut pos = Usi::parse_position(sfen).unwrap();
+        let legal = generate_legal(&pos);
+
+        let expected: MoveList = generate(&pos)
+            .into_iter()
+            .filter(|&mv| is_legal(&mut pos, mv))
+            .collect();
+        assert_eq!(legal, expected);
+        assert_eq!(pos.legal_moves(), expected);
+
+        for &mv in &candidates {
+            assert_eq!(pos.is_legal_move(mv), legal.contains(&mv), "{sfen} {mv:?}");
+        }
+    }
+}
+
+#[test]
+fn uchifuzume() {
+    let pos = Usi::parse_position("7lk/9/8G/9/9/9/9/9/K8 b P 1").unwrap();
+    let drop = Usi::parse_move("P*1b").unwrap();
+
+    assert!(is_pseudo_legal(&pos, drop));
+    assert!(!is_legal_move(&pos, drop));
+    assert!(!pos.legal_moves().contains(&drop));
+
+    // The same drop is legal once the king can escape.
+    let pos = Usi::parse_position("8k/9/8G/9/9/9/9/9/K8 b P 1").unwrap();
+
+    assert!(pos.is_legal_move(drop));
+    assert!(pos.legal_moves().contains(&drop));
+}
+
+#[test]
+fn is_legal_move_rejects_invalid_moves() {
+    let pos = Position::startpos();
+
+    for mv in ["5e5d", "1a1b", "8h2b", "2h2a", "7g7f+", "P*5e", "5i4i"] {
+        let mv = Usi::parse_move(mv).unwrap();
+        assert!(!pos.is_legal_move(mv), "{mv:?}");
+    }
+
+    for mv in ["7g7f", "2h3h", "5i4h"] {
+        let mv = Usi::parse_move(mv).unwrap();
+        assert!(pos.is_legal_move(mv), "{mv:?}");
+    }
+}
+
 #[test]
 #[cfg_attr(debug_assertions, ignore)]
 fn perft3() {
@@ -569,3 +733,24 @@ fn perft<const SPEED_TEST: bool>(pos: &mut Position, depth: i32) -> u64 {
 
     total
 }
+
+fn staged_perft(pos: &mut Position, depth: i32) -> u64 {
+    let mut moves = generate_staged(pos, GenType::Captures);
+    moves.extend(generate_staged(pos, GenType::Quiets));
+
+    let mut total = 0;
+
+    for mv in moves {
+        if is_legal(pos, mv) {
+            if depth == 1 {
+                total += 1;
+            } else {
+                let captured = pos.make_move(mv);
+                total += staged_perft(pos, depth - 1);
+                pos.unmake_move(mv, captured);
+            }
+        }
+    }
+
+    total
+}
@@ -89,3 +89,35 @@ fn decrement() {
         assert_eq!(hand.count(piece_type), max_count - 1);
     }
 }
+
+#[test]
+fn dominates() {
+    let empty = Hand::default();
+
+    assert!(empty.dominates(empty));
+
+    for &piece_type in PieceType::ALL.iter().take(Hand::HAND_PIECE_TYPES) {
+        let mut hand = Hand::default();
+        hand.set(piece_type, Hand::max_piece_counts(piece_type));
+
+        assert!(hand.dominates(empty));
+        assert!(!empty.dominates(hand));
+        assert!(hand.dominates(hand));
+    }
+
+    let mut lhs = Hand::default();
+    lhs.set(PieceType::Pawn, 3);
+    lhs.set(PieceType::Gold, 1);
+
+    let mut rhs = Hand::default();
+    rhs.set(PieceType::Pawn, 2);
+    rhs.set(PieceType::Rook, 1);
+
+    assert!(!lhs.dominates(rhs));
+    assert!(!rhs.dominates(lhs));
+
+    rhs.set(PieceType::Rook, 0);
+
+    assert!(lhs.dominates(rhs));
+    assert!(!rhs.dominates(lhs));
+}
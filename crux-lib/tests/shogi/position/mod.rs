@@ -6,7 +6,7 @@ use crux_lib::{
         position::{
             key::Key,
             zobrist::{hand_key, piece_square_key, side_key},
-            Position, PositionBuilder,
+            Position, PositionBuilder, Repetition,
         },
     },
 };
@@ -399,6 +399,72 @@ fn unmake_move() {
     }
 }
 
+#[test]
+fn attackers_to() {
+    let pos = Usi::parse_position("4k4/9/4g4/4p4/9/5N3/9/4R4/K3L4 b - 1").unwrap();
+    let occupied = pos.occupancy();
+
+    assert_eq!(
+        pos.attackers_to(Square::S54, occupied) & occupied,
+        Square::S53.bit() | Square::S58.bit() | Square::S46.bit()
+    );
+
+    // The lance attacks through the vacated rook square.
+    let occupied = occupied ^ Square::S58.bit();
+
+    assert_eq!(
+        pos.attackers_to(Square::S54, occupied) & occupied,
+        Square::S53.bit() | Square::S59.bit() | Square::S46.bit()
+    );
+}
+
+#[test]
+fn check_squares() {
+    let pos = Usi::parse_position("9/9/9/9/4k4/9/9/9/K8 b - 1").unwrap();
+
+    assert_eq!(pos.check_squares(PieceType::Pawn), Square::S56.bit());
+    assert_eq!(
+        pos.check_squares(PieceType::Knight),
+        Square::S47.bit() | Square::S67.bit()
+    );
+    assert_eq!(
+        pos.check_squares(PieceType::Gold),
+        Square::S54.bit()
+            | Square::S45.bit()
+            | Square::S65.bit()
+            | Square::S46.bit()
+            | Square::S56.bit()
+            | Square::S66.bit()
+    );
+    assert_eq!(
+        pos.check_squares(PieceType::Lance),
+        Square::S56.bit() | Square::S57.bit() | Square::S58.bit() | Square::S59.bit()
+    );
+    assert_eq!(pos.check_squares(PieceType::King), Bitboard::empty());
+}
+
+#[test]
+fn blockers_for_king() {
+    let pos = Usi::parse_position("4k4/9/4G4/9/4R4/9/2b6/1P7/K8 b - 1").unwrap();
+
+    assert_eq!(pos.blockers_for_king(Color::White), Square::S53.bit());
+    assert_eq!(pos.blockers_for_king(Color::Black), Square::S88.bit());
+
+    for (mv, expected) in [
+        ("5c4c", true),
+        ("5c5b", true),
+        ("5c5d", false),
+        ("5e4e", false),
+        ("8h8g", false),
+    ] {
+        assert_eq!(
+            pos.gives_check(Usi::parse_move(mv).unwrap()),
+            expected,
+            "{mv}"
+        );
+    }
+}
+
 #[test]
 fn checkers() {
     let cases = [
@@ -497,6 +563,146 @@ fn key() {
     );
 }
 
+#[test]
+fn board_and_hand_keys() {
+    let mut pos = Usi::parse_position("4k4/9/9/9/9/9/9/4p4/4K4 b G 1").unwrap();
+    let board_key = piece_square_key(Piece::WhiteKing, Square::S51)
+        ^ piece_square_key(Piece::WhitePawn, Square::S58)
+        ^ piece_square_key(Piece::BlackKing, Square::S59);
+    let gold_key = hand_key(Color::Black, PieceType::Gold, 1);
+
+    assert_eq!(pos.board_key(), board_key);
+    assert_eq!(pos.hand_key(), gold_key);
+    assert_eq!(pos.key(), board_key ^ gold_key);
+
+    pos.make_move(Usi::parse_move("5i5h").unwrap());
+
+    assert_eq!(
+        pos.board_key(),
+        piece_square_key(Piece::WhiteKing, Square::S51)
+            ^ piece_square_key(Piece::BlackKing, Square::S58)
+            ^ side_key()
+    );
+    assert_eq!(
+        pos.hand_key(),
+        gold_key ^ hand_key(Color::Black, PieceType::Pawn, 1)
+    );
+    assert_eq!(pos.key(), pos.board_key() ^ pos.hand_key());
+}
+
+fn play(sfen: &str, moves: &[&str]) -> Position {
+    let mut pos = Usi::parse_position(sfen).unwrap();
+
+    for mv in moves {
+        pos.make_move(Usi::parse_move(mv).unwrap());
+    }
+
+    pos
+}
+
+#[test]
+fn repetition_draw() {
+    const CYCLE: [&str; 4] = ["5i5h", "5a5b", "5h5i", "5b5a"];
+
+    let mut pos = Position::startpos();
+
+    assert_eq!(pos.repetition(1), None);
+
+    for (i, mv) in CYCLE.iter().cycle().take(12).enumerate() {
+        pos.make_move(Usi::parse_move(mv).unwrap());
+
+        let repetitions = (i + 1) / 4;
+
+        assert_eq!(pos.repetition(1).is_some(), repetitions >= 1);
+        assert_eq!(pos.repetition(3).is_some(), repetitions >= 3);
+    }
+
+    assert_eq!(pos.repetition(3), Some(Repetition::Draw));
+
+    let mv = Usi::parse_move("5b5a").unwrap();
+
+    pos.unmake_move(mv, None);
+
+    assert_eq!(pos.repetition(2), Some(Repetition::Draw));
+    assert_eq!(pos.repetition(3), None);
+}
+
+#[test]
+fn repetition_perpetual_check() {
+    const SFEN: &str = "4k4/9/9/9/9/9/9/9/K4R3 b - 1";
+
+    let pos = play(SFEN, &["4i5i", "5a4a", "5i4i", "4a5a", "4i5i"]);
+
+    assert_eq!(pos.repetition(1), Some(Repetition::Win));
+
+    let pos = play(SFEN, &["4i5i", "5a4a", "5i4i", "4a5a", "4i5i", "5a4a"]);
+
+    assert_eq!(pos.repetition(1), Some(Repetition::Loss));
+}
+
+#[test]
+fn repetition_hand() {
+    const SFEN: &str = "4k4/9/9/9/9/9/9/9/K3G4 b p 1";
+    const MOVES: [&str; 9] = [
+        "5i5h", "P*5g", "5h5g", "5a4a", "5g5h", "4a4b", "5h5i", "4b5a", "5i5h",
+    ];
+
+    let pos = play(SFEN, &MOVES[..8]);
+
+    assert_eq!(pos.repetition(1), Some(Repetition::Superior));
+
+    let pos = play(SFEN, &MOVES);
+
+    assert_eq!(pos.repetition(1), Some(Repetition::Inferior));
+}
+
+/// Returns the squares around the rectangle from file `left` to `right` and from rank `top`
+/// to `bottom`, going around from the bottom left corner.
+fn ring(left: u8, right: u8, top: char, bottom: char) -> Vec<String> {
+    let mut squares = Vec::new();
+
+    squares.extend((right..=left).rev().map(|file| format!("{file}{bottom}")));
+    squares.extend((top..bottom).rev().map(|rank| format!("{right}{rank}")));
+    squares.extend((right + 1..=left).map(|file| format!("{file}{top}")));
+    squares.extend((char::from(top as u8 + 1)..bottom).map(|rank| format!("{left}{rank}")));
+
+    squares
+}
+
+#[test]
+fn repetition_long_cycle() {
+    // The kings go around rings of 24 and 16 squares, so the position recurs every 96 plies
+    // and its fourth occurrence spans 288 plies.
+    let rings = [ring(9, 1, 'e', 'i'), ring(7, 1, 'a', 'c')];
+    let mut pos = Usi::parse_position("9/9/2k6/9/9/9/9/9/K8 b - 1").unwrap();
+
+    for ply in 0..288 {
+        let ring = &rings[ply % 2];
+        let step = ply / 2;
+        let mv = format!(
+            "{}{}",
+            ring[step % ring.len()],
+            ring[(step + 1) % ring.len()]
+        );
+
+        assert_eq!(pos.repetition(3), None);
+        pos.make_move(Usi::parse_move(&mv).unwrap());
+    }
+
+    assert_eq!(pos.repetition(3), Some(Repetition::Draw));
+}
+
+#[test]
+fn builder_clears_history() {
+    let pos = play(
+        "lnsgkgsnl/1r5b1/ppppppppp/9/9/9/PPPPPPPPP/1B5R1/LNSGKGSNL b - 1",
+        &["5i5h", "5a5b", "5h5i", "5b5a"],
+    );
+
+    assert_eq!(pos.repetition(1), Some(Repetition::Draw));
+    assert_eq!(pos.builder().build().repetition(1), None);
+}
+
 #[test]
 fn display_empty() {
     let pos = Position::empty();
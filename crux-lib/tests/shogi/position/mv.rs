@@ -51,3 +51,18 @@ fn drop() {
         }
     }
 }
+
+#[test]
+fn from_u16() {
+    let moves = [
+        Move::normal(Square::S77, Square::S76),
+        Move::promote(Square::S88, Square::S22),
+        Move::drop(PieceType::Gold, Square::S52),
+    ];
+
+    for mv in moves {
+        assert_eq!(Move::from_u16(mv.as_u16()), Some(mv));
+    }
+
+    assert_eq!(Move::from_u16(0), None);
+}
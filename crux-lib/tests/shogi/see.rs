@@ -0,0 +1,52 @@
+use crux_lib::{
+    notation::{usi::Usi, Notation},
+    shogi::see::{see, see_ge},
+};
+
+const SEE_TEST_CASES: &[(&str, &str, i32)] = &[
+    // Undefended pawn.
+    ("4k4/9/9/4p4/4P4/9/9/9/4K4 b - 1", "5e5d", 90),
+    // Quiet move to a safe square.
+    ("4k4/9/9/9/9/9/9/4R4/4K4 b - 1", "5h6h", 0),
+    // Rook captures a defended pawn.
+    ("4k4/9/4g4/4p4/9/9/9/4R4/4K4 b - 1", "5h5d", -900),
+    // The lance behind the rook joins the exchange.
+    ("4k4/9/4g4/4p4/9/9/9/4R4/K3L4 b - 1", "5h5d", -360),
+    // Drop to an attacked square.
+    ("4k4/9/9/4p4/9/9/9/9/4K4 b G 1", "G*5e", -540),
+    // Drop to a safe square.
+    ("4k4/9/9/4p4/9/9/9/9/4K4 b G 1", "G*5g", 0),
+    // Promotion gain.
+    ("4k4/9/4p4/4P4/9/9/9/9/4K4 b - 1", "5d5c+", 540),
+    // The promoted pawn is recaptured.
+    ("4k4/4g4/4p4/4P4/9/9/9/9/4K4 b - 1", "5d5c+", 0),
+    // The recapturing silver promotes.
+    ("4k4/9/9/9/9/9/3s1S3/9/K8 b - 1", "4g5h", -540),
+    // The king recaptures an undefended piece.
+    ("9/9/4k4/4p4/4G4/9/9/9/K8 b - 1", "5e5d", -450),
+    // The king cannot recapture a defended piece.
+    ("9/9/4k4/4p4/4G4/9/9/9/K3L4 b - 1", "5e5d", 90),
+    // White to move.
+    ("4k4/4r4/9/9/9/9/4p4/4P4/K8 w - 1", "5g5h+", 540),
+];
+
+#[test]
+fn see_values() {
+    for &(sfen, mv, expected) in SEE_TEST_CASES {
+        let pos = Usi::parse_position(sfen).unwrap();
+        let mv = Usi::parse_move(mv).unwrap();
+
+        assert_eq!(see(&pos, mv), expected, "{sfen} {mv:?}");
+    }
+}
+
+#[test]
+fn see_ge_thresholds() {
+    for &(sfen, mv, expected) in SEE_TEST_CASES {
+        let pos = Usi::parse_position(sfen).unwrap();
+        let mv = Usi::parse_move(mv).unwrap();
+
+        assert!(see_ge(&pos, mv, expected));
+        assert!(!see_ge(&pos, mv, expected + 1));
+    }
+}
@@ -1,5 +1,8 @@
 #![feature(const_ops)]
 #![feature(const_trait_impl)]
 
+mod args;
+mod eval;
 mod notation;
+mod search;
 mod shogi;
@@ -0,0 +1,497 @@
+use std::{
+    collections::BTreeMap,
+    fs::{self, File, OpenOptions},
+    io::{BufRead, BufReader, BufWriter, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        mpsc, Arc,
+    },
+    thread,
+    time::Instant,
+};
+
+use rand::{rngs::StdRng, RngExt, SeedableRng};
+
+use crux_lib::{
+    args::Args,
+    eval::{nnue::network::Network, EvalType},
+    notation::{
+        packed_sfen::{PackedSfen, PackedSfenValue},
+        usi::Usi,
+        Notation,
+    },
+    search::{tt::TranspositionTable, Limits, Searcher, Signals, SEARCH_STACK_SIZE},
+    shogi::{
+        entering_king::{can_declare_win, EnteringKingRule},
+        position::{mv::Move, Position, Repetition},
+    },
+};
+
+/// Odd constant mixing the game index into the seed, so that every game has its own
+/// random stream.
+pub(crate) const SEED_MIXER: u64 = 0x9E37_79B9_7F4A_7C15;
+
+/// Format of the records written by the data generation.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum DataFormat {
+    /// Lines of the SFEN, the best move, the score and the result, separated by tabs.
+    Text,
+    /// `PackedSfenValue` records, as read by the trainer.
+    Psv,
+}
+
+/// Settings of the data generation.
+#[derive(Debug, Clone)]
+pub struct DatagenOptions {
+    /// File the records are appended to.
+    pub output: PathBuf,
+    pub format: DataFormat,
+    /// Total number of games, including those generated before resuming.
+    pub games: u64,
+    pub threads: usize,
+    pub seed: u64,
+    /// Depth searched for every position.
+    pub depth: Option<i32>,
+    /// Nodes searched for every position.
+    pub nodes: Option<u64>,
+    /// Number of random moves played from the opening position.
+    pub random_plies: u32,
+    /// File of opening positions, one `startpos` or `sfen` with optional moves per line.
+    pub book: Option<PathBuf>,
+    /// Size of the transposition table of each thread in megabytes.
+    pub hash: usize,
+    pub eval_type: EvalType,
+    /// Network file used instead of the default network.
+    pub eval_file: Option<PathBuf>,
+    /// Score at which a game is adjudicated as won.
+    pub eval_limit: i32,
+    /// Number of plies after which a game is adjudicated as drawn.
+    pub max_ply: u32,
+}
+
+impl DatagenOptions {
+    /// Parses the options from the arguments following the `datagen` command.
+    pub fn parse(mut args: Args) -> Result<Self, String> {
+        let mut options = Self {
+            output: PathBuf::new(),
+            format: DataFormat::Text,
+            games: 1000,
+            threads: 1,
+            seed: 1,
+            depth: None,
+            nodes: None,
+            random_plies: 8,
+            book: None,
+            hash: 16,
+            eval_type: EvalType::default(),
+            eval_file: None,
+            eval_limit: 3000,
+            max_ply: 320,
+        };
+        let mut output = None;
+
+        while let Some(flag) = args.next_flag()? {
+            match flag.as_str() {
+                "--output" => output = Some(args.value(&flag)?),
+                "--format" => {
+                    options.format = match args.value::<String>(&flag)?.as_str() {
+                        "text" => DataFormat::Text,
+                        "psv" => DataFormat::Psv,
+                        value => return Err(format!("invalid value for {flag}: {value}")),
+                    };
+                }
+                "--games" => options.games = args.value(&flag)?,
+                "--threads" => options.threads = args.value(&flag)?,
+                "--seed" => options.seed = args.value(&flag)?,
+                "--depth" => options.depth = Some(args.value(&flag)?),
+                "--nodes" => options.nodes = Some(args.value(&flag)?),
+                "--random-plies" => options.random_plies = args.value(&flag)?,
+                "--book" => options.book = Some(args.value(&flag)?),
+                "--hash" => options.hash = args.value(&flag)?,
+                "--eval" => {
+                    options.eval_type = match args.value::<String>(&flag)?.as_str() {
+                        "nnue" => EvalType::Nnue,
+                        "classical" => EvalType::Classical,
+                        value => return Err(format!("invalid value for {flag}: {value}")),
+                    };
+                }
+                "--eval-file" => options.eval_file = Some(args.value(&flag)?),
+                "--eval-limit" => options.eval_limit = args.value(&flag)?,
+                "--max-ply" => options.max_ply = args.value(&flag)?,
+                _ => return Err(format!("unknown option: {flag}")),
+            }
+        }
+
+        options.output = output.ok_or("missing option: --output")?;
+        options.threads = options.threads.max(1);
+
+        if options.depth.is_none() && options.nodes.is_none() {
+            options.depth = Some(8);
+        }
+
+        Ok(options)
+    }
+}
+
+/// The progress of a data generation, saved next to the output so that it can be resumed.
+///
+/// Games are written in order, so the progress is the number of games written and the
+/// length of the output they take up; anything written after that is discarded on resume.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+struct Progress {
+    seed: u64,
+    games: u64,
+    bytes: u64,
+}
+
+impl Progress {
+    fn path(output: &Path) -> PathBuf {
+        let mut path = output.as_os_str().to_owned();
+
+        path.push(".progress");
+        PathBuf::from(path)
+    }
+
+    fn load(output: &Path) -> Result<Option<Self>, String> {
+        let path = Self::path(output);
+
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        let content =
+            fs::read_to_string(&path).map_err(|err| format!("{}: {err}", path.display()))?;
+        let mut values = content.split_whitespace();
+        let mut field = |name: &str| -> Result<u64, String> {
+            (values.next() == Some(name))
+                .then(|| values.next()?.parse().ok())
+                .flatten()
+                .ok_or_else(|| format!("{}: invalid progress file", path.display()))
+        };
+
+        Ok(Some(Self {
+            seed: field("seed")?,
+            games: field("games")?,
+            bytes: field("bytes")?,
+        }))
+    }
+
+    /// Saves the progress, replacing the previous file at once.
+    fn save(&self, output: &Path) -> Result<(), String> {
+        let path = Self::path(output);
+        let mut tmp = path.clone().into_os_string();
+
+        tmp.push(".tmp");
+
+        let content = format!(
+            "seed {}\ngames {}\nbytes {}\n",
+            self.seed, self.games, self.bytes
+        );
+
+        fs::write(&tmp, content)
+            .and_then(|()| fs::rename(&tmp, &path))
+            .map_err(|err| format!("{}: {err}", path.display()))
+    }
+}
+
+/// Generates training data by self-play.
+///
+/// Every game starts from the initial position or a random book position, followed by
+/// random moves, and continues with the best move of a fixed depth or nodes search. Each
+/// searched position not in check is written with the best move, the score and the game
+/// result from the side to move (`1`, `0` or `-1`), either as a line of these separated by
+/// tabs after the SFEN, or as a `PackedSfenValue`. Positions that cannot be packed, such as
+/// book positions without the standard set of pieces, are left out of the latter.
+///
+/// Games only depend on the seed and their index, and are written in order whatever the
+/// number of threads, so the output is deterministic. An interrupted generation continues
+/// from the last game written when run again with the same output.
+pub fn run(options: &DatagenOptions) -> Result<(), String> {
+    let book = load_book(options.book.as_deref())?;
+    let network = match &options.eval_file {
+        Some(path) => {
+            Arc::new(Network::load(path).map_err(|err| format!("{}: {err}", path.display()))?)
+        }
+        None => Network::default_network(),
+    };
+
+    let output_error = |err| format!("{}: {err}", options.output.display());
+    let mut progress = match Progress::load(&options.output)? {
+        Some(progress) if progress.seed != options.seed => {
+            return Err(format!(
+                "cannot resume with seed {}, the output was generated with seed {}",
+                options.seed, progress.seed
+            ));
+        }
+        Some(progress) => progress,
+        None if options.output.exists() => {
+            return Err(format!("{} already exists", options.output.display()));
+        }
+        None => Progress {
+            seed: options.seed,
+            games: 0,
+            bytes: 0,
+        },
+    };
+
+    let mut file = OpenOptions::new()
+        .create(true)
+        .write(true)
+        .truncate(false)
+        .open(&options.output)
+        .map_err(output_error)?;
+
+    file.set_len(progress.bytes).map_err(output_error)?;
+    file.seek(SeekFrom::End(0)).map_err(output_error)?;
+
+    if progress.games > 0 {
+        eprintln!("resuming after {} games", progress.games);
+    }
+
+    let mut writer = BufWriter::new(file);
+    let next_game = Arc::new(AtomicU64::new(progress.games));
+    let (sender, receiver) = mpsc::channel();
+    let start = Instant::now();
+    let mut positions = 0;
+
+    thread::scope(|scope| -> Result<(), String> {
+        for _ in 0..options.threads {
+            let sender = sender.clone();
+            let next_game = Arc::clone(&next_game);
+            let network = Arc::clone(&network);
+            let book = &book;
+
+            thread::Builder::new()
+                .stack_size(SEARCH_STACK_SIZE)
+                .spawn_scoped(scope, move || {
+                    let tt = Arc::new(TranspositionTable::new(options.hash));
+                    let mut searcher = Searcher::new(Arc::new(Signals::default()), tt);
+
+                    searcher.set_eval_type(options.eval_type);
+                    searcher.set_network(network);
+
+                    loop {
+                        let index = next_game.fetch_add(1, Ordering::Relaxed);
+
+                        if index >= options.games {
+                            break;
+                        }
+
+                        let records = play_game(&mut searcher, options, book, index);
+
+                        if sender.send((index, records)).is_err() {
+                            break;
+                        }
+                    }
+                })
+                .map_err(|err| err.to_string())?;
+        }
+
+        drop(sender);
+
+        let mut pending = BTreeMap::new();
+
+        for (index, records) in receiver {
+            pending.insert(index, records);
+
+            while let Some(records) = pending.remove(&progress.games) {
+                for record in &records {
+                    writer.write_all(record).map_err(output_error)?;
+                    progress.bytes += record.len() as u64;
+                }
+
+                writer.flush().map_err(output_error)?;
+                progress.games += 1;
+                progress.save(&options.output)?;
+                positions += records.len();
+
+                let elapsed = start.elapsed().as_secs_f64();
+
+                eprintln!(
+                    "games {}/{} positions {positions} ({:.0} positions/s)",
+                    progress.games,
+                    options.games,
+                    positions as f64 / elapsed.max(1e-3)
+                );
+            }
+        }
+
+        Ok(())
+    })
+}
+
+fn play_game(
+    searcher: &mut Searcher,
+    options: &DatagenOptions,
+    book: &[Position],
+    index: u64,
+) -> Vec<Vec<u8>> {
+    let mut rng = StdRng::seed_from_u64(options.seed.wrapping_add(index.wrapping_mul(SEED_MIXER)));
+    let mut pos = if book.is_empty() {
+        Position::startpos()
+    } else {
+        book[rng.random_range(0..book.len())].clone()
+    };
+
+    for _ in 0..options.random_plies {
+        let moves = pos.legal_moves();
+
+        if moves.is_empty() {
+            break;
+        }
+
+        pos.make_move(moves[rng.random_range(0..moves.len())]);
+    }
+
+    // The table is cleared so that the game does not depend on the games played before.
+    searcher.clear();
+
+    let limits = Limits {
+        depth: options.depth,
+        nodes: options.nodes,
+        ..Limits::default()
+    };
+    let rule = EnteringKingRule::default();
+    let mut records = Vec::new();
+
+    let winner = 'game: {
+        for _ in 0..options.max_ply {
+            let stm = pos.side_to_move();
+
+            match pos.repetition(1) {
+                Some(Repetition::Draw) => break 'game None,
+                Some(Repetition::Win) => break 'game Some(stm),
+                Some(Repetition::Loss) => break 'game Some(stm.opposite()),
+                _ => {}
+            }
+
+            if can_declare_win(&pos, rule) {
+                break 'game Some(stm);
+            }
+
+            let result = searcher.search(&pos, &limits, |_| {});
+
+            let Some(best_move) = result.best_move else {
+                break 'game Some(stm.opposite());
+            };
+
+            if result.score.abs() >= options.eval_limit {
+                break 'game Some(if result.score > 0 {
+                    stm
+                } else {
+                    stm.opposite()
+                });
+            }
+
+            if pos.checkers().is_empty() {
+                let snapshot = match options.format {
+                    DataFormat::Text => Some(Snapshot::Sfen(Usi::format_position(&pos))),
+                    DataFormat::Psv => PackedSfen::pack(&pos)
+                        .ok()
+                        .map(|sfen| Snapshot::Packed(sfen, pos.ply() as u16 + 1)),
+                };
+
+                if let Some(snapshot) = snapshot {
+                    records.push((snapshot, best_move, result.score, stm));
+                }
+            }
+
+            pos.make_move(best_move);
+        }
+
+        None
+    };
+
+    records
+        .into_iter()
+        .map(|(snapshot, best_move, score, stm)| {
+            let result = match winner {
+                Some(color) if color == stm => 1,
+                Some(_) => -1,
+                None => 0,
+            };
+
+            snapshot.encode(best_move, score, result)
+        })
+        .collect()
+}
+
+/// A searched position, kept in the output format until the game result is known.
+enum Snapshot {
+    Sfen(String),
+    /// The packed position and its ply.
+    Packed(PackedSfen, u16),
+}
+
+impl Snapshot {
+    fn encode(self, best_move: Move, score: i32, result: i8) -> Vec<u8> {
+        match self {
+            Snapshot::Sfen(sfen) => {
+                let mv = Usi::format_move(best_move);
+
+                format!("{sfen}\t{mv}\t{score}\t{result}\n").into_bytes()
+            }
+            Snapshot::Packed(sfen, game_ply) => PackedSfenValue {
+                sfen,
+                score: score.clamp(i16::MIN.into(), i16::MAX.into()) as i16,
+                mv: Some(best_move),
+                game_ply,
+                game_result: result,
+            }
+            .to_bytes()
+            .to_vec(),
+        }
+    }
+}
+
+/// Loads opening positions, one per line in the format of the USI `position` command
+/// without the command itself, or as a bare SFEN. Empty lines and lines starting with `#`
+/// are skipped.
+fn load_book(path: Option<&Path>) -> Result<Vec<Position>, String> {
+    let Some(path) = path else {
+        return Ok(Vec::new());
+    };
+
+    let error = |line: usize, message: &str| format!("{}:{line}: {message}", path.display());
+    let file = File::open(path).map_err(|err| format!("{}: {err}", path.display()))?;
+    let mut book = Vec::new();
+
+    for (number, line) in BufReader::new(file).lines().enumerate() {
+        let line = line.map_err(|err| format!("{}: {err}", path.display()))?;
+        let line = line.trim();
+
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let (position, moves) = match line.split_once(" moves ") {
+            Some((position, moves)) => (position, moves),
+            None => (line.strip_suffix(" moves").unwrap_or(line), ""),
+        };
+
+        let mut pos = match position.strip_prefix("sfen ").unwrap_or(position) {
+            "startpos" => Position::startpos(),
+            sfen => {
+                Usi::parse_position(sfen).map_err(|err| error(number + 1, &format!("{err:?}")))?
+            }
+        };
+
+        for token in moves.split_whitespace() {
+            let mv = Usi::parse_move(token)
+                .ok()
+                .filter(|&mv| pos.is_legal_move(mv))
+                .ok_or_else(|| error(number + 1, &format!("illegal move '{token}'")))?;
+
+            pos.make_move(mv);
+        }
+
+        book.push(pos);
+    }
+
+    if book.is_empty() {
+        return Err(format!("{}: no positions", path.display()));
+    }
+
+    Ok(book)
+}
@@ -1 +1,67 @@
-fn main() {}
+mod datagen;
+mod train;
+
+use std::{env, process::ExitCode};
+
+use crux_lib::args::Args;
+
+use crate::{datagen::DatagenOptions, train::TrainOptions};
+
+const USAGE: &str = "\
+usage: crux-trainer <command> [options]
+
+commands:
+  datagen   generate training data by self-play
+    --output <path>         file the records are appended to (required)
+    --format <text|psv>     format of the records [text]
+    --games <n>             total number of games [1000]
+    --threads <n>           number of threads [1]
+    --seed <n>              seed of the random opening moves [1]
+    --depth <n>             depth searched per position [8 without --nodes]
+    --nodes <n>             nodes searched per position
+    --random-plies <n>      random moves played from the opening position [8]
+    --book <path>           opening positions, one `startpos` or `sfen` per line
+    --hash <mb>             transposition table size per thread [16]
+    --eval <nnue|classical> evaluation function [nnue if a network is embedded,
+                            classical otherwise]
+    --eval-file <path>      network file instead of the default network
+    --eval-limit <cp>       score at which a game is adjudicated [3000]
+    --max-ply <n>           plies after which a game is drawn [320]
+
+  train     train a network on binary records
+    --data <path>           file of training records, repeatable (required)
+    --format <psv|hcpe>     format of the records [psv]
+    --output <dir>          directory of the checkpoint and networks (required)
+    --epochs <n>            total number of epochs [10]
+    --batch-size <n>        positions per batch [16384]
+    --shuffle <n>           batches shuffled together [64]
+    --threads <n>           number of threads [1]
+    --seed <n>              seed of the initial weights and the shuffling [1]
+    --optimizer <adam|sgd>  optimizer [adam]
+    --lr <rate>             learning rate [0.001]
+    --lr-decay <factor>     learning rate factor after every epoch [0.9]
+    --wdl <weight>          weight of the game result in the target [0.5]
+    --eval-scale <cp>       score scale of the winning probability [600]
+    --max-score <cp>        score beyond which records are skipped [3000]
+    --save-every <n>        batches between two checkpoints [1000]";
+
+fn main() -> ExitCode {
+    let mut args = env::args().skip(1);
+
+    let result = match args.next().as_deref() {
+        Some("datagen") => DatagenOptions::parse(Args::new(args.collect()))
+            .and_then(|options| datagen::run(&options)),
+        Some("train") => {
+            TrainOptions::parse(Args::new(args.collect())).and_then(|options| train::run(&options))
+        }
+        _ => Err(USAGE.to_string()),
+    };
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("{err}");
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,245 @@
+use std::{
+    fs::{self, File},
+    io::{self, BufReader, BufWriter, Read, Write},
+    path::Path,
+};
+
+use crate::train::{
+    model::{Model, PARAMETERS},
+    optimizer::{Optimizer, OptimizerKind},
+};
+
+/// Magic bytes at the start of a checkpoint file.
+const MAGIC: [u8; 8] = *b"CRUXCKPT";
+
+/// Version of the checkpoint file format.
+const VERSION: u32 = 1;
+
+/// The position of the next batch in the training.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Cursor {
+    pub epoch: u64,
+    /// Index of the shuffled chunk of records within the epoch.
+    pub chunk: u64,
+    /// Index of the batch within the chunk.
+    pub batch: u64,
+}
+
+/// Settings that determine the order of the batches, which must not change on resume.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Fingerprint {
+    pub seed: u64,
+    pub records: u64,
+    pub batch_size: u64,
+    pub shuffle: u64,
+}
+
+/// Saves the state of the training, replacing the previous checkpoint at once.
+///
+/// Checkpoints store, in little endian, the magic bytes and the format version, the
+/// fingerprint, the cursor, the optimizer and its number of steps as `u64`, followed by
+/// the parameters of the model and the moments of the optimizer as `f32`.
+pub fn save(
+    path: &Path,
+    fingerprint: Fingerprint,
+    cursor: Cursor,
+    model: &Model,
+    optimizer: &Optimizer,
+) -> Result<(), String> {
+    let mut tmp = path.as_os_str().to_owned();
+
+    tmp.push(".tmp");
+
+    let write = || -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(&tmp)?);
+        let header = [
+            fingerprint.seed,
+            fingerprint.records,
+            fingerprint.batch_size,
+            fingerprint.shuffle,
+            cursor.epoch,
+            cursor.chunk,
+            cursor.batch,
+            optimizer.kind as u64,
+            optimizer.steps,
+        ];
+
+        writer.write_all(&MAGIC)?;
+        writer.write_all(&VERSION.to_le_bytes())?;
+
+        for value in header {
+            writer.write_all(&value.to_le_bytes())?;
+        }
+
+        for values in [&model.params, &optimizer.m, &optimizer.v] {
+            write_f32s(&mut writer, values)?;
+        }
+
+        writer.flush()?;
+        fs::rename(&tmp, path)
+    };
+
+    write().map_err(|err| format!("{}: {err}", path.display()))
+}
+
+/// Loads a checkpoint saved by [`save`], which must have been saved with the same
+/// fingerprint and kind of optimizer to be resumed.
+pub fn load(
+    path: &Path,
+    fingerprint: Fingerprint,
+    kind: OptimizerKind,
+) -> Result<(Cursor, Model, Optimizer), String> {
+    let error = |message: &str| format!("{}: {message}", path.display());
+    let file = File::open(path).map_err(|err| error(&err.to_string()))?;
+    let mut reader = BufReader::new(file);
+
+    let mut read = || -> io::Result<Result<_, &str>> {
+        let mut magic = [0; MAGIC.len()];
+        let mut version = [0; 4];
+
+        reader.read_exact(&mut magic)?;
+        reader.read_exact(&mut version)?;
+
+        if magic != MAGIC || u32::from_le_bytes(version) != VERSION {
+            return Ok(Err("invalid checkpoint"));
+        }
+
+        let mut header = [0; 9];
+
+        for value in &mut header {
+            let mut bytes = [0; 8];
+
+            reader.read_exact(&mut bytes)?;
+            *value = u64::from_le_bytes(bytes);
+        }
+
+        let [seed, records, batch_size, shuffle, epoch, chunk, batch, saved_kind, steps] = header;
+        let saved_kind = match saved_kind {
+            0 => OptimizerKind::Sgd,
+            1 => OptimizerKind::Adam,
+            _ => return Ok(Err("invalid checkpoint")),
+        };
+        let saved = Fingerprint {
+            seed,
+            records,
+            batch_size,
+            shuffle,
+        };
+
+        if saved != fingerprint || saved_kind != kind {
+            return Ok(Err(
+                "cannot resume with different data, seed, batch size, shuffle or optimizer",
+            ));
+        }
+
+        let mut model = Model {
+            params: vec![0.0; PARAMETERS],
+        };
+        let mut optimizer = Optimizer::new(kind);
+
+        optimizer.steps = steps;
+
+        for values in [&mut model.params, &mut optimizer.m, &mut optimizer.v] {
+            read_f32s(&mut reader, values)?;
+        }
+
+        let cursor = Cursor {
+            epoch,
+            chunk,
+            batch,
+        };
+
+        Ok(Ok((cursor, model, optimizer)))
+    };
+
+    read()
+        .map_err(|err| error(&err.to_string()))?
+        .map_err(error)
+}
+
+fn write_f32s(writer: &mut impl Write, values: &[f32]) -> io::Result<()> {
+    for chunk in values.chunks(1 << 16) {
+        let bytes = chunk
+            .iter()
+            .flat_map(|value| value.to_le_bytes())
+            .collect::<Vec<_>>();
+
+        writer.write_all(&bytes)?;
+    }
+
+    Ok(())
+}
+
+fn read_f32s(reader: &mut impl Read, values: &mut [f32]) -> io::Result<()> {
+    let mut bytes = Vec::new();
+
+    for chunk in values.chunks_mut(1 << 16) {
+        bytes.resize(chunk.len() * 4, 0);
+        reader.read_exact(&mut bytes)?;
+
+        for (value, bytes) in chunk.iter_mut().zip(bytes.chunks_exact(4)) {
+            *value = f32::from_le_bytes(bytes.try_into().unwrap());
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::process;
+
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let path = std::env::temp_dir().join(format!("crux-checkpoint-{}.bin", process::id()));
+        let fingerprint = Fingerprint {
+            seed: 1,
+            records: 1000,
+            batch_size: 16,
+            shuffle: 4,
+        };
+        let cursor = Cursor {
+            epoch: 2,
+            chunk: 3,
+            batch: 4,
+        };
+        let model = Model {
+            params: (0..PARAMETERS).map(|i| i as f32 / 7.0).collect(),
+        };
+        let mut optimizer = Optimizer::new(OptimizerKind::Adam);
+
+        optimizer.steps = 5;
+        optimizer.m.iter_mut().step_by(3).for_each(|m| *m = 0.25);
+        optimizer.v.iter_mut().step_by(5).for_each(|v| *v = 0.5);
+
+        save(&path, fingerprint, cursor, &model, &optimizer).unwrap();
+
+        let loaded = load(&path, fingerprint, OptimizerKind::Adam);
+        let other_seed = load(
+            &path,
+            Fingerprint {
+                seed: 2,
+                ..fingerprint
+            },
+            OptimizerKind::Adam,
+        );
+        let other_optimizer = load(&path, fingerprint, OptimizerKind::Sgd);
+
+        fs::remove_file(&path).unwrap();
+
+        let (loaded_cursor, loaded_model, loaded_optimizer) = loaded.unwrap();
+
+        assert_eq!(loaded_cursor, cursor);
+        assert!(loaded_model.params == model.params);
+        assert_eq!(loaded_optimizer.kind, OptimizerKind::Adam);
+        assert_eq!(loaded_optimizer.steps, 5);
+        assert!(loaded_optimizer.m == optimizer.m);
+        assert!(loaded_optimizer.v == optimizer.v);
+
+        for result in [other_seed, other_optimizer] {
+            assert!(result.is_err_and(|err| err.contains("cannot resume")));
+        }
+    }
+}
@@ -0,0 +1,207 @@
+use std::{
+    fs::File,
+    io::{BufReader, Read, Seek, SeekFrom},
+    path::{Path, PathBuf},
+};
+
+#[cfg(test)]
+use rand::{rngs::StdRng, RngExt, SeedableRng};
+
+use crux_lib::{
+    notation::{
+        hcp::{HuffmanCodedPos, HuffmanCodedPosAndEval},
+        packed_sfen::{PackedSfen, PackedSfenValue},
+        record::{ReadError, Record, RecordReader},
+    },
+    shogi::position::Position,
+};
+
+/// Format of the training data files.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum RecordFormat {
+    /// `PackedSfenValue` records of YaneuraOu, as written by `datagen --format psv`.
+    Psv,
+    /// `HuffmanCodedPosAndEval` records of dlshogi.
+    Hcpe,
+}
+
+impl RecordFormat {
+    /// Size of a record in bytes.
+    const fn size(self) -> usize {
+        match self {
+            RecordFormat::Psv => PackedSfenValue::SIZE,
+            RecordFormat::Hcpe => HuffmanCodedPosAndEval::SIZE,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+enum PackedPosition {
+    Sfen(PackedSfen),
+    Hcp(HuffmanCodedPos),
+}
+
+/// A training position with its targets, still packed to keep batches small.
+#[derive(Debug, Copy, Clone)]
+pub struct Sample {
+    position: PackedPosition,
+    /// Score from the side to move, in centipawns.
+    pub score: i16,
+    /// Game result from the side to move: 1 for a win, 0.5 for a draw and 0 for a loss.
+    pub result: f32,
+}
+
+impl Sample {
+    /// Unpacks the position, or returns `None` if the record is invalid.
+    pub fn position(&self) -> Option<Position> {
+        match self.position {
+            PackedPosition::Sfen(sfen) => sfen.unpack().ok(),
+            PackedPosition::Hcp(hcp) => hcp.unpack().ok(),
+        }
+    }
+}
+
+#[cfg(test)]
+impl Sample {
+    /// Creates samples of positions reached by random moves from the starting position.
+    pub fn random(seed: u64, count: usize) -> Vec<Sample> {
+        let mut rng = StdRng::seed_from_u64(seed);
+
+        (0..count)
+            .map(|_| {
+                let mut pos = Position::startpos();
+
+                for _ in 0..rng.random_range(0..80) {
+                    let moves = pos.legal_moves();
+
+                    if moves.is_empty() {
+                        break;
+                    }
+
+                    pos.make_move(moves[rng.random_range(0..moves.len())]);
+                }
+
+                Sample {
+                    position: PackedPosition::Sfen(PackedSfen::pack(&pos).unwrap()),
+                    score: rng.random_range(-1000..=1000),
+                    result: [0.0, 0.5, 1.0][rng.random_range(0..3)],
+                }
+            })
+            .collect()
+    }
+}
+
+/// Training data files read as one sequence of records.
+pub struct DataSet {
+    format: RecordFormat,
+    /// The files with their number of records.
+    files: Vec<(PathBuf, u64)>,
+    max_score: i32,
+}
+
+impl DataSet {
+    /// Opens the data files, whose records with a score beyond `max_score` are skipped.
+    pub fn open(paths: &[PathBuf], format: RecordFormat, max_score: i32) -> Result<Self, String> {
+        let files = paths
+            .iter()
+            .map(|path| {
+                let len = path
+                    .metadata()
+                    .map_err(|err| format!("{}: {err}", path.display()))?
+                    .len();
+
+                Ok((path.clone(), len / format.size() as u64))
+            })
+            .collect::<Result<_, String>>()?;
+
+        Ok(Self {
+            format,
+            files,
+            max_score,
+        })
+    }
+
+    /// Returns the total number of records.
+    pub fn len(&self) -> u64 {
+        self.files.iter().map(|&(_, len)| len).sum()
+    }
+
+    /// Reads up to `count` records from the record at `start` on, skipping invalid ones and
+    /// those beyond the score limit.
+    pub fn read(&self, start: u64, count: u64) -> Result<Vec<Sample>, String> {
+        let mut samples = Vec::new();
+        let mut offset = 0;
+        let end = start + count;
+
+        for (path, len) in &self.files {
+            let (first, last) = (start.max(offset), end.min(offset + len));
+
+            if first < last {
+                let (skip, count) = (first - offset, last - first);
+
+                match self.format {
+                    RecordFormat::Psv => {
+                        read_file(path, skip, count, &mut samples, |r: PackedSfenValue| {
+                            Sample {
+                                position: PackedPosition::Sfen(r.sfen),
+                                score: r.score,
+                                result: (f32::from(r.game_result.clamp(-1, 1)) + 1.0) / 2.0,
+                            }
+                        })?
+                    }
+                    RecordFormat::Hcpe => read_file(
+                        path,
+                        skip,
+                        count,
+                        &mut samples,
+                        |r: HuffmanCodedPosAndEval| {
+                            let result = match r.game_result.winner() {
+                                Some(color) if color == r.hcp.side_to_move() => 1.0,
+                                Some(_) => 0.0,
+                                None => 0.5,
+                            };
+
+                            Sample {
+                                position: PackedPosition::Hcp(r.hcp),
+                                score: r.eval,
+                                result,
+                            }
+                        },
+                    )?,
+                }
+            }
+
+            offset += len;
+        }
+
+        samples.retain(|sample| i32::from(sample.score).abs() <= self.max_score);
+
+        Ok(samples)
+    }
+}
+
+fn read_file<T: Record>(
+    path: &Path,
+    skip: u64,
+    count: u64,
+    samples: &mut Vec<Sample>,
+    sample: impl Fn(T) -> Sample,
+) -> Result<(), String> {
+    let error = |err: String| format!("{}: {err}", path.display());
+    let mut file = File::open(path).map_err(|err| error(err.to_string()))?;
+
+    file.seek(SeekFrom::Start(skip * T::SIZE as u64))
+        .map_err(|err| error(err.to_string()))?;
+
+    let reader = BufReader::new(file).take(count * T::SIZE as u64);
+
+    for record in RecordReader::<T, _>::new(reader) {
+        match record {
+            Ok(record) => samples.push(sample(record)),
+            Err(ReadError::Invalid(_)) => {}
+            Err(err) => return Err(error(err.to_string())),
+        }
+    }
+
+    Ok(())
+}
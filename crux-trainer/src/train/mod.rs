@@ -0,0 +1,251 @@
+mod checkpoint;
+mod data;
+mod model;
+mod optimizer;
+
+use std::{fs, path::PathBuf, time::Instant};
+
+use rand::{rngs::StdRng, seq::SliceRandom, SeedableRng};
+
+use crux_lib::args::Args;
+
+use crate::{
+    datagen::SEED_MIXER,
+    train::{
+        checkpoint::{Cursor, Fingerprint},
+        data::{DataSet, RecordFormat},
+        model::{backward, LossSettings, Model},
+        optimizer::{Optimizer, OptimizerKind},
+    },
+};
+
+/// Number of batches between two progress reports.
+const REPORT_INTERVAL: u64 = 100;
+
+/// Settings of the training.
+#[derive(Debug, Clone)]
+pub struct TrainOptions {
+    /// Files of training records.
+    pub data: Vec<PathBuf>,
+    pub format: RecordFormat,
+    /// Directory the checkpoint and the networks are written to.
+    pub output: PathBuf,
+    /// Total number of epochs, including those trained before resuming.
+    pub epochs: u64,
+    pub batch_size: usize,
+    /// Number of batches shuffled together.
+    pub shuffle: usize,
+    pub threads: usize,
+    pub seed: u64,
+    pub optimizer: OptimizerKind,
+    pub lr: f32,
+    /// Factor the learning rate is multiplied by after every epoch.
+    pub lr_decay: f32,
+    pub loss: LossSettings,
+    /// Score beyond which records are skipped.
+    pub max_score: i32,
+    /// Number of batches between two checkpoints, besides the end of every epoch.
+    pub save_every: u64,
+}
+
+impl TrainOptions {
+    /// Parses the options from the arguments following the `train` command.
+    pub fn parse(mut args: Args) -> Result<Self, String> {
+        let mut options = Self {
+            data: Vec::new(),
+            format: RecordFormat::Psv,
+            output: PathBuf::new(),
+            epochs: 10,
+            batch_size: 16384,
+            shuffle: 64,
+            threads: 1,
+            seed: 1,
+            optimizer: OptimizerKind::Adam,
+            lr: 0.001,
+            lr_decay: 0.9,
+            loss: LossSettings {
+                wdl: 0.5,
+                eval_scale: 600.0,
+            },
+            max_score: 3000,
+            save_every: 1000,
+        };
+        let mut output = None;
+
+        while let Some(flag) = args.next_flag()? {
+            match flag.as_str() {
+                "--data" => options.data.push(args.value(&flag)?),
+                "--format" => {
+                    options.format = match args.value::<String>(&flag)?.as_str() {
+                        "psv" => RecordFormat::Psv,
+                        "hcpe" => RecordFormat::Hcpe,
+                        value => return Err(format!("invalid value for {flag}: {value}")),
+                    };
+                }
+                "--output" => output = Some(args.value(&flag)?),
+                "--epochs" => options.epochs = args.value(&flag)?,
+                "--batch-size" => options.batch_size = args.value(&flag)?,
+                "--shuffle" => options.shuffle = args.value(&flag)?,
+                "--threads" => options.threads = args.value(&flag)?,
+                "--seed" => options.seed = args.value(&flag)?,
+                "--optimizer" => {
+                    options.optimizer = match args.value::<String>(&flag)?.as_str() {
+                        "sgd" => OptimizerKind::Sgd,
+                        "adam" => OptimizerKind::Adam,
+                        value => return Err(format!("invalid value for {flag}: {value}")),
+                    };
+                }
+                "--lr" => options.lr = args.value(&flag)?,
+                "--lr-decay" => options.lr_decay = args.value(&flag)?,
+                "--wdl" => options.loss.wdl = args.value(&flag)?,
+                "--eval-scale" => options.loss.eval_scale = args.value(&flag)?,
+                "--max-score" => options.max_score = args.value(&flag)?,
+                "--save-every" => options.save_every = args.value(&flag)?,
+                _ => return Err(format!("unknown option: {flag}")),
+            }
+        }
+
+        if options.data.is_empty() {
+            return Err("missing option: --data".to_string());
+        }
+
+        options.output = output.ok_or("missing option: --output")?;
+        options.threads = options.threads.max(1);
+        options.batch_size = options.batch_size.max(1);
+        options.shuffle = options.shuffle.max(1);
+        options.save_every = options.save_every.max(1);
+
+        Ok(options)
+    }
+}
+
+/// Trains a network on the records of the data files.
+///
+/// Every epoch reads the records in chunks of `shuffle` batches, shuffled with a seed that
+/// depends on the epoch and the chunk, so the batches are the same whatever the number of
+/// threads. The state of the training is saved to `checkpoint.bin` in the output directory
+/// regularly and after every epoch, when the network is also exported as `epoch-<n>.nnue`
+/// in the `EvalFile` format. An interrupted training continues from the last checkpoint
+/// when run again with the same output.
+pub fn run(options: &TrainOptions) -> Result<(), String> {
+    let data = DataSet::open(&options.data, options.format, options.max_score)?;
+    let records = data.len();
+
+    if records == 0 {
+        return Err("no training records".to_string());
+    }
+
+    fs::create_dir_all(&options.output)
+        .map_err(|err| format!("{}: {err}", options.output.display()))?;
+
+    let checkpoint_path = options.output.join("checkpoint.bin");
+    let fingerprint = Fingerprint {
+        seed: options.seed,
+        records,
+        batch_size: options.batch_size as u64,
+        shuffle: options.shuffle as u64,
+    };
+
+    let (mut cursor, mut model, mut optimizer) = if checkpoint_path.exists() {
+        let (cursor, model, optimizer) =
+            checkpoint::load(&checkpoint_path, fingerprint, options.optimizer)?;
+
+        eprintln!(
+            "resuming at epoch {} after {} steps",
+            cursor.epoch + 1,
+            optimizer.steps
+        );
+
+        (cursor, model, optimizer)
+    } else {
+        let cursor = Cursor {
+            epoch: 0,
+            chunk: 0,
+            batch: 0,
+        };
+
+        (
+            cursor,
+            Model::new(options.seed),
+            Optimizer::new(options.optimizer),
+        )
+    };
+
+    let chunk_records = (options.batch_size * options.shuffle) as u64;
+    let chunks = records.div_ceil(chunk_records);
+    let save = |cursor, model: &Model, optimizer: &Optimizer| {
+        checkpoint::save(&checkpoint_path, fingerprint, cursor, model, optimizer)
+    };
+
+    eprintln!("{records} records, {chunks} chunks per epoch");
+
+    while cursor.epoch < options.epochs {
+        let lr = options.lr * options.lr_decay.powi(cursor.epoch as i32);
+        let start = Instant::now();
+        let mut positions = 0;
+        let mut loss = 0.0;
+
+        while cursor.chunk < chunks {
+            let mut samples = data.read(cursor.chunk * chunk_records, chunk_records)?;
+            let index = cursor.epoch * chunks + cursor.chunk;
+            let mut rng = StdRng::seed_from_u64(
+                options
+                    .seed
+                    .wrapping_add(index.wrapping_add(1).wrapping_mul(SEED_MIXER)),
+            );
+
+            samples.shuffle(&mut rng);
+
+            for batch in samples
+                .chunks(options.batch_size)
+                .skip(cursor.batch as usize)
+            {
+                let gradients = backward(&model, batch, options.loss, options.threads);
+
+                optimizer.step(&mut model, &gradients, lr, options.threads);
+                cursor.batch += 1;
+                positions += batch.len();
+                loss += gradients.loss;
+
+                if optimizer.steps % REPORT_INTERVAL == 0 {
+                    let elapsed = start.elapsed().as_secs_f64();
+
+                    eprintln!(
+                        "epoch {} chunk {}/{chunks} loss {:.6} ({:.0} positions/s)",
+                        cursor.epoch + 1,
+                        cursor.chunk + 1,
+                        loss / positions as f64,
+                        positions as f64 / elapsed.max(1e-3)
+                    );
+                }
+
+                if optimizer.steps % options.save_every == 0 {
+                    save(cursor, &model, &optimizer)?;
+                }
+            }
+
+            cursor.chunk += 1;
+            cursor.batch = 0;
+        }
+
+        cursor.epoch += 1;
+        cursor.chunk = 0;
+
+        let path = options.output.join(format!("epoch-{}.nnue", cursor.epoch));
+
+        model
+            .to_network()
+            .save(&path)
+            .map_err(|err| format!("{}: {err}", path.display()))?;
+        save(cursor, &model, &optimizer)?;
+
+        eprintln!(
+            "epoch {} done, loss {:.6}, saved {}",
+            cursor.epoch,
+            loss / positions.max(1) as f64,
+            path.display()
+        );
+    }
+
+    Ok(())
+}
@@ -0,0 +1,387 @@
+use std::thread;
+
+use rand::{rngs::StdRng, RngExt, SeedableRng};
+
+use crux_lib::{
+    eval::nnue::{
+        features::{active_features, INPUTS},
+        network::{Network, L1, QA, QB, SCALE},
+    },
+    shogi::core::Color,
+};
+
+use crate::train::data::Sample;
+
+/// Offset of the feature biases in the parameters, after the feature weights.
+pub const FEATURE_BIAS: usize = INPUTS * L1;
+/// Offset of the output weights, side to move first.
+pub const OUTPUT_WEIGHTS: usize = FEATURE_BIAS + L1;
+/// Offset of the output bias.
+pub const OUTPUT_BIAS: usize = OUTPUT_WEIGHTS + Color::COUNT * L1;
+/// Number of parameters.
+pub const PARAMETERS: usize = OUTPUT_BIAS + 1;
+
+/// Bound of every parameter, which keeps the quantized accumulator within `i16`.
+pub const WEIGHT_CLIP: f32 = 1.98;
+
+/// The network in floating point, with the architecture of the engine's [`Network`].
+///
+/// The parameters are stored in one array: the weights of every feature, one row of `L1`
+/// per feature, then the feature biases, the output weights and the output bias. The
+/// accumulator is clipped to `0..=1`, which the quantization maps to `0..=QA`, and the
+/// output is in units of `SCALE` centipawns.
+pub struct Model {
+    pub params: Vec<f32>,
+}
+
+impl Model {
+    /// Creates a model with random weights.
+    pub fn new(seed: u64) -> Self {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut params = vec![0.0; PARAMETERS];
+        let output_range = 1.0 / (Color::COUNT as f32 * L1 as f32).sqrt();
+
+        for param in &mut params[..FEATURE_BIAS] {
+            *param = rng.random_range(-0.1..0.1);
+        }
+
+        for param in &mut params[OUTPUT_WEIGHTS..OUTPUT_BIAS] {
+            *param = rng.random_range(-output_range..output_range);
+        }
+
+        Self { params }
+    }
+
+    /// Quantizes the model into a network for the engine.
+    pub fn to_network(&self) -> Network {
+        let mut network = Network::zeroed();
+        let quantize = |value: f32, factor: i32| {
+            (value * factor as f32)
+                .round()
+                .clamp(i16::MIN.into(), i16::MAX.into()) as i16
+        };
+
+        let rows = self.params[..FEATURE_BIAS].chunks_exact(L1);
+
+        for (row, weights) in network.feature_weights.iter_mut().zip(rows) {
+            for (value, &weight) in row.iter_mut().zip(weights) {
+                *value = quantize(weight, QA);
+            }
+        }
+
+        for (value, &bias) in network
+            .feature_bias
+            .iter_mut()
+            .zip(&self.params[FEATURE_BIAS..OUTPUT_WEIGHTS])
+        {
+            *value = quantize(bias, QA);
+        }
+
+        let rows = self.params[OUTPUT_WEIGHTS..OUTPUT_BIAS].chunks_exact(L1);
+
+        for (row, weights) in network.output_weights.iter_mut().zip(rows) {
+            for (value, &weight) in row.iter_mut().zip(weights) {
+                *value = quantize(weight, QB);
+            }
+        }
+
+        network.output_bias = (self.params[OUTPUT_BIAS] * (QA * QB) as f32).round() as i32;
+        network
+    }
+
+    fn accumulate(&self, features: &[usize]) -> [f32; L1] {
+        let mut values: [f32; L1] = self.params[FEATURE_BIAS..OUTPUT_WEIGHTS]
+            .try_into()
+            .unwrap();
+
+        for &feature in features {
+            let row = &self.params[feature * L1..(feature + 1) * L1];
+
+            for (value, &weight) in values.iter_mut().zip(row) {
+                *value += weight;
+            }
+        }
+
+        values
+    }
+}
+
+/// How the loss of a position is computed.
+#[derive(Debug, Copy, Clone)]
+pub struct LossSettings {
+    /// Weight of the game result in the target, against the score of the search.
+    pub wdl: f32,
+    /// Score in centipawns at which the winning probability is `sigmoid(1)`.
+    pub eval_scale: f32,
+}
+
+/// The gradients of the loss of a batch.
+///
+/// The gradients of the feature weights are kept sparse: the gradient of each accumulator
+/// of the batch, and the active features pointing to them, sorted by feature.
+pub struct Gradients {
+    /// Gradients of the parameters from the feature biases on.
+    pub dense: Vec<f32>,
+    /// Gradients of the accumulators, two per sample, side to move first.
+    pub accumulators: Vec<[f32; L1]>,
+    /// Pairs of an active feature and the index of its accumulator.
+    pub features: Vec<(u32, u32)>,
+    /// Sum of the losses of the batch.
+    pub loss: f64,
+}
+
+impl Gradients {
+    /// Returns the gradient of the weights of a feature from its `entries` in `features`.
+    pub fn feature_gradient(&self, entries: &[(u32, u32)]) -> [f32; L1] {
+        let mut gradient = [0.0; L1];
+
+        for &(_, accumulator) in entries {
+            for (sum, &value) in gradient
+                .iter_mut()
+                .zip(&self.accumulators[accumulator as usize])
+            {
+                *sum += value;
+            }
+        }
+
+        gradient
+    }
+}
+
+/// Computes the mean squared error of a batch and its gradients, on `threads` threads.
+///
+/// The prediction is the winning probability `sigmoid(output * SCALE / eval_scale)`, and
+/// the target blends the game result with the winning probability of the score.
+pub fn backward(
+    model: &Model,
+    batch: &[Sample],
+    settings: LossSettings,
+    threads: usize,
+) -> Gradients {
+    let chunk_size = batch.len().div_ceil(threads.max(1)).max(1);
+
+    let parts = thread::scope(|scope| {
+        let handles = batch
+            .chunks(chunk_size)
+            .enumerate()
+            .map(|(i, chunk)| {
+                let first = (i * chunk_size * Color::COUNT) as u32;
+
+                scope.spawn(move || backward_chunk(model, chunk, first, settings, batch.len()))
+            })
+            .collect::<Vec<_>>();
+
+        handles
+            .into_iter()
+            .map(|handle| handle.join().unwrap())
+            .collect::<Vec<_>>()
+    });
+
+    let mut gradients = Gradients {
+        dense: vec![0.0; PARAMETERS - FEATURE_BIAS],
+        accumulators: Vec::with_capacity(batch.len() * Color::COUNT),
+        features: Vec::new(),
+        loss: 0.0,
+    };
+
+    for part in parts {
+        for (sum, value) in gradients.dense.iter_mut().zip(part.dense) {
+            *sum += value;
+        }
+
+        gradients.accumulators.extend(part.accumulators);
+        gradients.features.extend(part.features);
+        gradients.loss += part.loss;
+    }
+
+    gradients.features.sort_unstable();
+    gradients
+}
+
+fn backward_chunk(
+    model: &Model,
+    chunk: &[Sample],
+    first: u32,
+    settings: LossSettings,
+    batch_size: usize,
+) -> Gradients {
+    let mut gradients = Gradients {
+        dense: vec![0.0; PARAMETERS - FEATURE_BIAS],
+        accumulators: vec![[0.0; L1]; chunk.len() * Color::COUNT],
+        features: Vec::new(),
+        loss: 0.0,
+    };
+
+    let output_weights = &model.params[OUTPUT_WEIGHTS..OUTPUT_BIAS];
+    let scale = SCALE as f32 / settings.eval_scale;
+
+    for (i, sample) in chunk.iter().enumerate() {
+        let Some(pos) = sample.position() else {
+            continue;
+        };
+
+        let stm = pos.side_to_move();
+        let features = [
+            active_features(&pos, stm),
+            active_features(&pos, stm.opposite()),
+        ];
+        let accumulators = features
+            .each_ref()
+            .map(|features| model.accumulate(features));
+
+        let mut output = model.params[OUTPUT_BIAS];
+
+        for (accumulator, weights) in accumulators.iter().zip(output_weights.chunks_exact(L1)) {
+            for (&value, &weight) in accumulator.iter().zip(weights) {
+                output += value.clamp(0.0, 1.0) * weight;
+            }
+        }
+
+        let prediction = sigmoid(output * scale);
+        let target = settings.wdl * sample.result
+            + (1.0 - settings.wdl) * sigmoid(f32::from(sample.score) / settings.eval_scale);
+        let error = prediction - target;
+
+        gradients.loss += f64::from(error * error);
+
+        let gradient = 2.0 * error * prediction * (1.0 - prediction) * scale / batch_size as f32;
+        let (feature_bias, rest) = gradients.dense.split_at_mut(L1);
+        let (output_weight_gradients, output_bias) = rest.split_at_mut(Color::COUNT * L1);
+
+        output_bias[0] += gradient;
+
+        for side in 0..Color::COUNT {
+            let index = i * Color::COUNT + side;
+            let weights = &output_weights[side * L1..(side + 1) * L1];
+            let weight_gradients = &mut output_weight_gradients[side * L1..(side + 1) * L1];
+            let accumulator_gradient = &mut gradients.accumulators[index];
+
+            for j in 0..L1 {
+                let value = accumulators[side][j];
+
+                weight_gradients[j] += gradient * value.clamp(0.0, 1.0);
+
+                if value > 0.0 && value < 1.0 {
+                    accumulator_gradient[j] = gradient * weights[j];
+                    feature_bias[j] += accumulator_gradient[j];
+                }
+            }
+
+            let accumulator = first + index as u32;
+
+            gradients.features.extend(
+                features[side]
+                    .iter()
+                    .map(|&feature| (feature as u32, accumulator)),
+            );
+        }
+    }
+
+    gradients
+}
+
+fn sigmoid(x: f32) -> f32 {
+    1.0 / (1.0 + (-x).exp())
+}
+
+#[cfg(test)]
+mod tests {
+    use crux_lib::eval::nnue::accumulator::AccumulatorStack;
+
+    use super::*;
+
+    const SETTINGS: LossSettings = LossSettings {
+        wdl: 0.3,
+        eval_scale: 400.0,
+    };
+
+    /// Returns the output of the model for the position of a sample, in units of `SCALE`.
+    fn forward(model: &Model, sample: &Sample) -> f32 {
+        let pos = sample.position().unwrap();
+        let stm = pos.side_to_move();
+        let mut output = model.params[OUTPUT_BIAS];
+
+        for (side, perspective) in [stm, stm.opposite()].into_iter().enumerate() {
+            let accumulator = model.accumulate(&active_features(&pos, perspective));
+            let weights =
+                &model.params[OUTPUT_WEIGHTS + side * L1..OUTPUT_WEIGHTS + (side + 1) * L1];
+
+            for (&value, &weight) in accumulator.iter().zip(weights) {
+                output += value.clamp(0.0, 1.0) * weight;
+            }
+        }
+
+        output
+    }
+
+    #[test]
+    fn gradients() {
+        let mut model = Model::new(1);
+        let batch = Sample::random(2, 8);
+        let gradients = backward(&model, &batch, SETTINGS, 3);
+        let loss = |model: &Model| backward(model, &batch, SETTINGS, 1).loss / batch.len() as f64;
+
+        let mut checked = vec![(OUTPUT_BIAS, gradients.dense[OUTPUT_BIAS - FEATURE_BIAS])];
+
+        for index in (FEATURE_BIAS..OUTPUT_BIAS).step_by(37) {
+            checked.push((index, gradients.dense[index - FEATURE_BIAS]));
+        }
+
+        for entries in gradients.features.chunk_by(|a, b| a.0 == b.0).step_by(11) {
+            let gradient = gradients.feature_gradient(entries);
+
+            for j in (0..L1).step_by(29) {
+                checked.push((entries[0].0 as usize * L1 + j, gradient[j]));
+            }
+        }
+
+        let step = 1e-3;
+
+        for (index, gradient) in checked {
+            let param = model.params[index];
+
+            model.params[index] = param + step;
+            let plus = loss(&model);
+            model.params[index] = param - step;
+            let minus = loss(&model);
+            model.params[index] = param;
+
+            let numeric = ((plus - minus) / (2.0 * f64::from(step))) as f32;
+
+            assert!(
+                (numeric - gradient).abs() <= 1e-4 + 0.02 * gradient.abs(),
+                "parameter {index}: {numeric} != {gradient}"
+            );
+        }
+    }
+
+    #[test]
+    fn quantization() {
+        let mut model = Model::new(3);
+
+        // Parameters on the quantization grid are quantized exactly, so only the division of
+        // the output into centipawns rounds.
+        let snap = |params: &mut [f32], factor: i32| {
+            for param in params {
+                *param = (*param * factor as f32).round() / factor as f32;
+            }
+        };
+
+        snap(&mut model.params[..OUTPUT_WEIGHTS], QA);
+        snap(&mut model.params[OUTPUT_WEIGHTS..OUTPUT_BIAS], QB);
+        snap(&mut model.params[OUTPUT_BIAS..], QA * QB);
+
+        let network = model.to_network();
+
+        for sample in Sample::random(4, 32) {
+            let pos = sample.position().unwrap();
+            let expected = forward(&model, &sample) * SCALE as f32;
+            let actual = AccumulatorStack::new().evaluate(&network, &pos);
+
+            assert!(
+                (actual as f32 - expected).abs() < 1.001,
+                "{actual} != {expected}"
+            );
+        }
+    }
+}
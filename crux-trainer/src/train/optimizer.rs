@@ -0,0 +1,222 @@
+use std::thread;
+
+use crux_lib::eval::nnue::network::L1;
+
+use crate::train::model::{Gradients, Model, FEATURE_BIAS, PARAMETERS, WEIGHT_CLIP};
+
+const BETA1: f32 = 0.9;
+const BETA2: f32 = 0.999;
+const EPSILON: f32 = 1e-8;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum OptimizerKind {
+    Sgd,
+    Adam,
+}
+
+/// Updates the parameters of a model from the gradients of each batch.
+///
+/// Only the rows of the features active in a batch are updated, so Adam keeps the moments
+/// of the other rows as they were instead of decaying them, as sparse variants of Adam do.
+pub struct Optimizer {
+    pub kind: OptimizerKind,
+    /// Number of steps taken.
+    pub steps: u64,
+    /// First moments of Adam, empty for SGD.
+    pub m: Vec<f32>,
+    /// Second moments of Adam, empty for SGD.
+    pub v: Vec<f32>,
+}
+
+impl Optimizer {
+    pub fn new(kind: OptimizerKind) -> Self {
+        let len = match kind {
+            OptimizerKind::Sgd => 0,
+            OptimizerKind::Adam => PARAMETERS,
+        };
+
+        Self {
+            kind,
+            steps: 0,
+            m: vec![0.0; len],
+            v: vec![0.0; len],
+        }
+    }
+
+    /// Takes a step with learning rate `lr`, updating the feature rows on `threads` threads.
+    pub fn step(&mut self, model: &mut Model, gradients: &Gradients, lr: f32, threads: usize) {
+        self.steps += 1;
+
+        let lr = match self.kind {
+            OptimizerKind::Sgd => lr,
+            OptimizerKind::Adam => {
+                let t = self.steps as i32;
+
+                lr * (1.0 - BETA2.powi(t)).sqrt() / (1.0 - BETA1.powi(t))
+            }
+        };
+
+        let (rows, dense) = model.params.split_at_mut(FEATURE_BIAS);
+        let (m_rows, m_dense) = split_moments(&mut self.m);
+        let (v_rows, v_dense) = split_moments(&mut self.v);
+
+        update(self.kind, lr, dense, m_dense, v_dense, &gradients.dense);
+
+        // Features are split between the threads at row boundaries, so that every thread
+        // owns the rows it updates.
+        let features = &gradients.features;
+        let part_size = features.len().div_ceil(threads.max(1)).max(1);
+        let mut bounds = vec![0];
+
+        for i in (part_size..features.len()).step_by(part_size) {
+            let feature = features[i].0 as usize;
+
+            if feature > *bounds.last().unwrap() {
+                bounds.push(feature);
+            }
+        }
+
+        bounds.push(FEATURE_BIAS / L1);
+
+        let kind = self.kind;
+        let rows = split_rows(rows, &bounds);
+        let m_rows = split_rows(m_rows, &bounds);
+        let v_rows = split_rows(v_rows, &bounds);
+
+        thread::scope(|scope| {
+            for (i, ((rows, m_rows), v_rows)) in
+                rows.into_iter().zip(m_rows).zip(v_rows).enumerate()
+            {
+                let first_feature = bounds[i];
+                let start = features.partition_point(|&(f, _)| (f as usize) < first_feature);
+                let end = features.partition_point(|&(f, _)| (f as usize) < bounds[i + 1]);
+                let features = &features[start..end];
+
+                scope.spawn(move || {
+                    for entries in features.chunk_by(|a, b| a.0 == b.0) {
+                        let offset = (entries[0].0 as usize - first_feature) * L1;
+                        let range = offset..offset + L1;
+                        let gradient = gradients.feature_gradient(entries);
+                        let moments = |moments: &mut [f32]| {
+                            if moments.is_empty() {
+                                0..0
+                            } else {
+                                range.clone()
+                            }
+                        };
+                        let m_range = moments(m_rows);
+                        let v_range = moments(v_rows);
+
+                        update(
+                            kind,
+                            lr,
+                            &mut rows[range.clone()],
+                            &mut m_rows[m_range],
+                            &mut v_rows[v_range],
+                            &gradient,
+                        );
+                    }
+                });
+            }
+        });
+    }
+}
+
+/// Splits the moments into those of the feature weights and the rest, or two empty slices.
+fn split_moments(moments: &mut [f32]) -> (&mut [f32], &mut [f32]) {
+    if moments.is_empty() {
+        moments.split_at_mut(0)
+    } else {
+        moments.split_at_mut(FEATURE_BIAS)
+    }
+}
+
+/// Splits feature rows at the feature `bounds`, or into empty slices if `rows` is empty.
+fn split_rows<'a>(mut rows: &'a mut [f32], bounds: &[usize]) -> Vec<&'a mut [f32]> {
+    bounds
+        .windows(2)
+        .map(|bound| {
+            let len = if rows.is_empty() {
+                0
+            } else {
+                (bound[1] - bound[0]) * L1
+            };
+            let (head, tail) = std::mem::take(&mut rows).split_at_mut(len);
+
+            rows = tail;
+            head
+        })
+        .collect()
+}
+
+fn update(
+    kind: OptimizerKind,
+    lr: f32,
+    params: &mut [f32],
+    m: &mut [f32],
+    v: &mut [f32],
+    gradients: &[f32],
+) {
+    match kind {
+        OptimizerKind::Sgd => {
+            for (param, &gradient) in params.iter_mut().zip(gradients) {
+                *param = (*param - lr * gradient).clamp(-WEIGHT_CLIP, WEIGHT_CLIP);
+            }
+        }
+        OptimizerKind::Adam => {
+            for (((param, m), v), &gradient) in params.iter_mut().zip(m).zip(v).zip(gradients) {
+                *m = BETA1 * *m + (1.0 - BETA1) * gradient;
+                *v = BETA2 * *v + (1.0 - BETA2) * gradient * gradient;
+                *param = (*param - lr * *m / (v.sqrt() + EPSILON)).clamp(-WEIGHT_CLIP, WEIGHT_CLIP);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::train::{
+        data::Sample,
+        model::{backward, LossSettings},
+    };
+
+    use super::*;
+
+    #[test]
+    fn threads() {
+        let settings = LossSettings {
+            wdl: 0.5,
+            eval_scale: 400.0,
+        };
+        let batches = [Sample::random(5, 16), Sample::random(6, 16)];
+        let initial = |i: usize| (i % 201) as f32 / 1000.0 - 0.1;
+
+        for kind in [OptimizerKind::Sgd, OptimizerKind::Adam] {
+            let train = |threads| {
+                let mut model = Model {
+                    params: (0..PARAMETERS).map(initial).collect(),
+                };
+                let mut optimizer = Optimizer::new(kind);
+
+                for batch in &batches {
+                    let gradients = backward(&model, batch, settings, 1);
+
+                    optimizer.step(&mut model, &gradients, 0.01, threads);
+                }
+
+                (model.params, optimizer.m, optimizer.v)
+            };
+
+            let expected = train(1);
+
+            assert!((0..FEATURE_BIAS).any(|i| expected.0[i] != initial(i)));
+
+            for threads in [2, 3, 8] {
+                assert!(
+                    train(threads) == expected,
+                    "{kind:?} with {threads} threads"
+                );
+            }
+        }
+    }
+}
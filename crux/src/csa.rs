@@ -0,0 +1,790 @@
+use std::{
+    io::{BufRead, BufReader, Write},
+    net::TcpStream,
+    sync::{
+        mpsc::{self, Receiver, Sender},
+        Arc,
+    },
+    thread,
+    time::Duration,
+};
+
+use crux_lib::{
+    args::Args,
+    eval::nnue::network::Network,
+    notation::csa::Csa,
+    search::{tt::TranspositionTable, Limits, SearchResult, Searcher, Signals},
+    shogi::{core::Color, entering_king::can_declare_win, position::Position},
+};
+
+use crate::{
+    options::{Options, INTERNAL_EVAL_FILE},
+    search_thread::SearchThread,
+};
+
+const DEFAULT_PORT: u16 = 4081;
+
+/// Settings of the CSA client mode.
+#[derive(Debug, Clone)]
+pub struct CsaOptions {
+    pub host: String,
+    pub port: u16,
+    pub user: String,
+    pub password: String,
+    /// Number of games played before logging out.
+    pub games: u32,
+    /// Sends the score and the principal variation as a comment of every move.
+    pub comments: bool,
+    /// Engine options, as set by the USI `setoption` command.
+    pub engine: Options,
+}
+
+impl CsaOptions {
+    /// Parses the options from the arguments following the `csa` command.
+    pub fn parse(mut args: Args) -> Result<Self, String> {
+        let mut options = Self {
+            host: String::from("localhost"),
+            port: DEFAULT_PORT,
+            user: String::new(),
+            password: String::new(),
+            games: 1,
+            comments: false,
+            engine: Options::default(),
+        };
+        let mut user = None;
+        let mut password = None;
+
+        while let Some(flag) = args.next_flag()? {
+            match flag.as_str() {
+                "--host" => options.host = args.value(&flag)?,
+                "--port" => options.port = args.value(&flag)?,
+                "--user" => user = Some(args.value(&flag)?),
+                "--password" => password = Some(args.value(&flag)?),
+                "--games" => options.games = args.value(&flag)?,
+                "--comments" => options.comments = true,
+                "--option" => {
+                    let option = args.value::<String>(&flag)?;
+                    let (name, value) = option
+                        .split_once('=')
+                        .ok_or_else(|| format!("invalid value for {flag}: {option}"))?;
+
+                    options
+                        .engine
+                        .set(name, Some(value))
+                        .map_err(|err| err.to_string())?;
+                }
+                _ => return Err(format!("unknown option: {flag}")),
+            }
+        }
+
+        options.user = user.ok_or("missing option: --user")?;
+        options.password = password.ok_or("missing option: --password")?;
+
+        Ok(options)
+    }
+}
+
+/// An event handled by the client: a line from the server, or the reply of a finished search.
+enum Event {
+    Line(String),
+    Reply(String),
+    Disconnected,
+}
+
+/// The time control of a game, in the time unit of the server.
+#[derive(Debug, Clone)]
+struct TimeRule {
+    unit: Duration,
+    total: u64,
+    byoyomi: u64,
+    increment: u64,
+}
+
+impl Default for TimeRule {
+    fn default() -> Self {
+        Self {
+            unit: Duration::from_secs(1),
+            total: 0,
+            byoyomi: 0,
+            increment: 0,
+        }
+    }
+}
+
+/// The information sent in `BEGIN Game_Summary`.
+#[derive(Debug, Default)]
+struct GameSummary {
+    game_id: String,
+    color: Option<Color>,
+    time: TimeRule,
+    /// The lines of `BEGIN Position`, including the moves already played.
+    position: String,
+}
+
+/// A game in progress.
+struct Game {
+    color: Color,
+    pos: Position,
+    time: TimeRule,
+    /// Remaining time of each side.
+    clocks: [Duration; Color::COUNT],
+    /// Set from the start of a search until the server echoes a move or ends the game.
+    thinking: bool,
+    /// The reason of the game end, such as `#SENNICHITE`, sent before the result.
+    reason: Option<String>,
+}
+
+impl Game {
+    /// Sets up a game from its summary, replaying the moves already played.
+    fn new(summary: GameSummary) -> Result<Self, String> {
+        let record = Csa::parse(&summary.position)
+            .map_err(|err| format!("invalid position in game summary: {err:?}"))?;
+        let color = summary.color.ok_or("missing Your_Turn in game summary")?;
+        let total = summary.time.unit * summary.time.total as u32;
+        let mut game = Game {
+            color,
+            pos: record.start.clone(),
+            time: summary.time,
+            clocks: [total; Color::COUNT],
+            thinking: false,
+            reason: None,
+        };
+
+        for move_record in &record.moves {
+            // Times are in the unit of the server, which the record reads as seconds.
+            let elapsed = move_record.time.unwrap_or_default().as_secs() as u32;
+
+            game.consume(game.pos.side_to_move(), elapsed);
+            game.pos.make_move(move_record.mv);
+        }
+
+        Ok(game)
+    }
+
+    /// Plays a move sent by the server, such as `+7776FU,T12`.
+    fn play(&mut self, line: &str) -> Result<(), String> {
+        let mut statements = line.split(',');
+        let text = statements.next().unwrap_or_default();
+        let mv = Csa::parse_move_in(&self.pos, text)
+            .map_err(|err| format!("invalid move from server '{text}': {err:?}"))?;
+        let elapsed = statements
+            .find_map(|statement| statement.strip_prefix('T'))
+            .and_then(|time| time.parse::<u32>().ok())
+            .unwrap_or_default();
+
+        self.consume(self.pos.side_to_move(), elapsed);
+        self.pos.make_move(mv);
+        self.thinking = false;
+
+        Ok(())
+    }
+
+    /// Charges `elapsed` units to the clock of `color`, adding the increment.
+    fn consume(&mut self, color: Color, elapsed: u32) {
+        let clock = &mut self.clocks[color];
+
+        *clock = clock.saturating_sub(self.time.unit * elapsed)
+            + self.time.unit * self.time.increment as u32;
+    }
+
+    fn limits(&self) -> Limits {
+        Limits {
+            time: self.clocks,
+            increment: [self.time.unit * self.time.increment as u32; Color::COUNT],
+            byoyomi: self.time.unit * self.time.byoyomi as u32,
+            ..Limits::default()
+        }
+    }
+}
+
+/// A client of a CSA game server, playing with the engine.
+///
+/// Lines from the server are read on a separate thread and searches run on their own thread,
+/// so that the end of a game can be handled while searching.
+///
+/// Based on the reference:
+/// http://www2.computer-shogi.org/protocol/tcp_ip_server_121.html
+pub struct CsaClient {
+    stream: TcpStream,
+    options: CsaOptions,
+    events: Receiver<Event>,
+    sender: Sender<Event>,
+    search_thread: SearchThread,
+}
+
+impl CsaClient {
+    /// Connects to the server and sets up the engine.
+    pub fn connect(options: CsaOptions) -> Result<Self, String> {
+        let address = format!("{}:{}", options.host, options.port);
+        let stream = TcpStream::connect(&address).map_err(|err| format!("{address}: {err}"))?;
+        let reader = stream.try_clone().map_err(|err| err.to_string())?;
+        let (sender, events) = mpsc::channel();
+        let lines = sender.clone();
+
+        thread::spawn(move || {
+            for line in BufReader::new(reader).lines() {
+                let Ok(line) = line else {
+                    break;
+                };
+
+                if lines.send(Event::Line(line)).is_err() {
+                    return;
+                }
+            }
+
+            let _ = lines.send(Event::Disconnected);
+        });
+
+        let signals = Arc::new(Signals::default());
+        let engine = &options.engine;
+        let tt = Arc::new(TranspositionTable::new(engine.hash));
+        let mut searcher = Searcher::new(Arc::clone(&signals), tt);
+
+        searcher.set_threads(engine.threads);
+        engine.apply(&mut searcher);
+
+        if engine.eval_file != INTERNAL_EVAL_FILE {
+            let network = Network::load(&engine.eval_file)
+                .map_err(|err| format!("failed to load network '{}': {err}", engine.eval_file))?;
+
+            searcher.set_network(Arc::new(network));
+        }
+
+        Ok(Self {
+            stream,
+            options,
+            events,
+            sender,
+            search_thread: SearchThread::new(signals, searcher),
+        })
+    }
+
+    /// Logs in, plays the number of games of the options and logs out.
+    pub fn run(&mut self) -> Result<(), String> {
+        let login = format!("LOGIN {} {}", self.options.user, self.options.password);
+
+        self.send(&login)?;
+
+        let response = self.read_line()?;
+
+        if response != format!("LOGIN:{} OK", self.options.user) {
+            return Err(format!("login failed: {response}"));
+        }
+
+        for _ in 0..self.options.games {
+            self.play_game()?;
+        }
+
+        self.send("LOGOUT")?;
+
+        // The server may close the connection without confirming.
+        while let Ok(line) = self.read_line() {
+            if line == "LOGOUT:completed" {
+                break;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Plays a game from its summary to its result.
+    fn play_game(&mut self) -> Result<(), String> {
+        let summary = self.read_summary()?;
+
+        self.send(&format!("AGREE {}", summary.game_id))?;
+
+        let response = self.read_line()?;
+
+        if response.starts_with("REJECT:") {
+            println!("game rejected: {response}");
+            return Ok(());
+        }
+
+        if response != format!("START:{}", summary.game_id) {
+            return Err(format!("unexpected response to AGREE: {response}"));
+        }
+
+        let mut game = Game::new(summary)?;
+
+        self.search_thread.searcher_mut().clear();
+
+        loop {
+            if game.pos.side_to_move() == game.color && !game.thinking {
+                self.think(&game);
+                game.thinking = true;
+            }
+
+            match self.events.recv().unwrap_or(Event::Disconnected) {
+                Event::Line(line) => {
+                    println!("< {line}");
+
+                    if line.starts_with(['+', '-']) {
+                        game.play(&line)?;
+                    } else if let Some(result) = line.strip_prefix('#') {
+                        if matches!(result, "WIN" | "LOSE" | "DRAW" | "CENSORED") {
+                            self.search_thread.stop();
+
+                            match game.reason.take() {
+                                Some(reason) => println!("game over: {result} by {reason}"),
+                                None => println!("game over: {result}"),
+                            }
+                            return Ok(());
+                        }
+
+                        game.reason = Some(line);
+                    }
+                }
+                Event::Reply(reply) => {
+                    self.search_thread.join();
+                    self.send(&reply)?;
+                }
+                Event::Disconnected => return Err(String::from("disconnected from server")),
+            }
+        }
+    }
+
+    /// Reads lines until a complete game summary has been received.
+    fn read_summary(&mut self) -> Result<GameSummary, String> {
+        let mut summary = GameSummary::default();
+        let mut in_position = false;
+
+        while self.read_line()? != "BEGIN Game_Summary" {}
+
+        loop {
+            let line = self.read_line()?;
+
+            match line.as_str() {
+                "END Game_Summary" => break,
+                "BEGIN Position" => in_position = true,
+                "END Position" => in_position = false,
+                _ if in_position => {
+                    summary.position.push_str(&line);
+                    summary.position.push('\n');
+                }
+                _ => {
+                    let Some((key, value)) = line.split_once(':') else {
+                        continue;
+                    };
+
+                    let number = || {
+                        value
+                            .parse()
+                            .map_err(|_| format!("invalid value of {key}: {value}"))
+                    };
+
+                    match key {
+                        "Game_ID" => summary.game_id = value.to_string(),
+                        "Your_Turn" => {
+                            summary.color = match value {
+                                "+" => Some(Color::Black),
+                                "-" => Some(Color::White),
+                                _ => return Err(format!("invalid value of {key}: {value}")),
+                            };
+                        }
+                        "Time_Unit" => summary.time.unit = parse_time_unit(value)?,
+                        "Total_Time" => summary.time.total = number()?,
+                        "Byoyomi" => summary.time.byoyomi = number()?,
+                        "Increment" => summary.time.increment = number()?,
+                        _ => {}
+                    }
+                }
+            }
+        }
+
+        Ok(summary)
+    }
+
+    /// Starts searching the position of `game` for the move to send.
+    fn think(&mut self, game: &Game) {
+        let sender = self.sender.clone();
+        let pos = game.pos.clone();
+        let limits = game.limits();
+        let comments = self.options.comments;
+        let entering_king_rule = self.options.engine.entering_king_rule;
+
+        self.search_thread.start(false, move |searcher| {
+            let reply = if can_declare_win(&pos, entering_king_rule) {
+                String::from("%KACHI")
+            } else {
+                let result = searcher.search(&pos, &limits, |_| {});
+
+                match result.best_move {
+                    Some(mv) if comments => {
+                        format!(
+                            "{},'* {}",
+                            Csa::format_move_in(&pos, mv),
+                            comment(&pos, &result)
+                        )
+                    }
+                    Some(mv) => Csa::format_move_in(&pos, mv),
+                    None => String::from("%TORYO"),
+                }
+            };
+
+            let _ = sender.send(Event::Reply(reply));
+        });
+    }
+
+    /// Reads the next line from the server, skipping keep-alive lines and the replies of
+    /// searches stopped by the end of a game.
+    fn read_line(&mut self) -> Result<String, String> {
+        loop {
+            match self.events.recv().unwrap_or(Event::Disconnected) {
+                Event::Line(line) if line.is_empty() => {}
+                Event::Line(line) => {
+                    println!("< {line}");
+                    return Ok(line);
+                }
+                Event::Reply(_) => {}
+                Event::Disconnected => return Err(String::from("disconnected from server")),
+            }
+        }
+    }
+
+    fn send(&mut self, line: &str) -> Result<(), String> {
+        // Passwords are not written to the log.
+        match line.strip_prefix("LOGIN ") {
+            Some(_) => println!("> LOGIN {} *****", self.options.user),
+            None => println!("> {line}"),
+        }
+
+        writeln!(self.stream, "{line}").map_err(|err| err.to_string())
+    }
+}
+
+/// Formats the score from the point of view of black and the expected moves after the best
+/// move, as in `-52 -3334FU +2726FU`.
+fn comment(pos: &Position, result: &SearchResult) -> String {
+    let score = match pos.side_to_move() {
+        Color::Black => result.score,
+        Color::White => -result.score,
+    };
+    let mut pos = pos.clone();
+    let mut comment = score.to_string();
+
+    for (i, &mv) in result.pv.iter().enumerate() {
+        if !pos.is_legal_move(mv) {
+            break;
+        }
+
+        // The first move of the principal variation is the move sent.
+        if i > 0 {
+            comment.push(' ');
+            comment.push_str(&Csa::format_move_in(&pos, mv));
+        }
+
+        pos.make_move(mv);
+    }
+
+    comment
+}
+
+/// Parses a time unit such as `1sec`, `1min` or `100msec`.
+fn parse_time_unit(value: &str) -> Result<Duration, String> {
+    let digits = value
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(value.len());
+    let (count, unit) = value.split_at(digits);
+    let count = if count.is_empty() {
+        1
+    } else {
+        count
+            .parse()
+            .map_err(|_| format!("invalid time unit: {value}"))?
+    };
+
+    match unit {
+        "msec" => Ok(Duration::from_millis(count)),
+        "sec" => Ok(Duration::from_secs(count)),
+        "min" => Ok(Duration::from_secs(count * 60)),
+        _ => Err(format!("invalid time unit: {value}")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::{BufRead, BufReader, Write},
+        net::{TcpListener, TcpStream},
+        thread::{self, JoinHandle},
+    };
+
+    use crux_lib::notation::{usi::Usi, Notation};
+
+    use super::*;
+
+    /// The summary of a game on floodgate, with two moves already played.
+    const SUMMARY: &str = "\
+BEGIN Game_Summary
+Protocol_Version:1.2
+Protocol_Mode:Server
+Format:Shogi 1.0
+Declaration:Jishogi 1.1
+Game_ID:wdoor+floodgate-300-10F+crux+other+20260101120000
+Name+:other
+Name-:crux
+Your_Turn:-
+Rematch_On_Draw:NO
+To_Move:+
+Max_Moves:256
+BEGIN Time
+Time_Unit:1sec
+Total_Time:300
+Byoyomi:0
+Increment:10
+Least_Time_Per_Move:0
+END Time
+BEGIN Position
+P1-KY-KE-GI-KI-OU-KI-GI-KE-KY
+P2 * -HI *  *  *  *  * -KA * 
+P3-FU-FU-FU-FU-FU-FU-FU-FU-FU
+P4 *  *  *  *  *  *  *  *  * 
+P5 *  *  *  *  *  *  *  *  * 
+P6 *  *  *  *  *  *  *  *  * 
+P7+FU+FU+FU+FU+FU+FU+FU+FU+FU
+P8 * +KA *  *  *  *  * +HI * 
+P9+KY+KE+GI+KI+OU+KI+GI+KE+KY
+P+
+P-
++
++2726FU,T12
+-3334FU,T6
+END Position
+END Game_Summary
+";
+
+    /// A mock server on a loopback port, running `script` on the first connection and
+    /// returning the lines it received.
+    fn serve(
+        script: impl FnOnce(&mut Connection) + Send + 'static,
+    ) -> (u16, JoinHandle<Vec<String>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut connection = Connection {
+                reader: BufReader::new(stream.try_clone().unwrap()),
+                stream,
+                received: Vec::new(),
+            };
+
+            script(&mut connection);
+            connection.received
+        });
+
+        (port, server)
+    }
+
+    struct Connection {
+        reader: BufReader<TcpStream>,
+        stream: TcpStream,
+        received: Vec<String>,
+    }
+
+    impl Connection {
+        fn send(&mut self, text: &str) {
+            self.stream.write_all(text.as_bytes()).unwrap();
+        }
+
+        fn receive(&mut self) -> String {
+            let mut line = String::new();
+
+            self.reader.read_line(&mut line).unwrap();
+
+            let line = line.trim_end().to_string();
+
+            self.received.push(line.clone());
+            line
+        }
+
+        /// Agrees to a game and plays the moves of the client, echoing them with their time.
+        fn start(&mut self, summary: &str, game_id: &str) {
+            self.send(summary);
+            assert_eq!(self.receive(), format!("AGREE {game_id}"));
+            self.send(&format!("START:{game_id}\n"));
+        }
+
+        fn echo_move(&mut self) -> String {
+            let mv = self.receive();
+
+            self.send(&format!("{mv},T1\n"));
+            mv
+        }
+    }
+
+    fn connect(port: u16, games: u32) -> CsaClient {
+        let args = [
+            "--port",
+            &port.to_string(),
+            "--user",
+            "crux",
+            "--password",
+            "secret",
+            "--games",
+            &games.to_string(),
+            "--option",
+            "Hash=1",
+        ];
+        let options =
+            CsaOptions::parse(Args::new(args.iter().map(|arg| arg.to_string()).collect())).unwrap();
+
+        CsaClient::connect(options).unwrap()
+    }
+
+    /// A game summary for a game starting from `position` with 1 second per move.
+    fn summary(game_id: &str, color: char, position: &str) -> String {
+        format!(
+            "BEGIN Game_Summary\nGame_ID:{game_id}\nYour_Turn:{color}\nBEGIN Time\n\
+             Time_Unit:1sec\nTotal_Time:0\nByoyomi:1\nEND Time\nBEGIN Position\n{position}\
+             END Position\nEND Game_Summary\n"
+        )
+    }
+
+    #[test]
+    fn time_units() {
+        assert_eq!(parse_time_unit("1sec").unwrap(), Duration::from_secs(1));
+        assert_eq!(parse_time_unit("sec").unwrap(), Duration::from_secs(1));
+        assert_eq!(parse_time_unit("1min").unwrap(), Duration::from_secs(60));
+        assert_eq!(
+            parse_time_unit("100msec").unwrap(),
+            Duration::from_millis(100)
+        );
+        assert!(parse_time_unit("1hour").is_err());
+        assert!(parse_time_unit("").is_err());
+    }
+
+    #[test]
+    fn read_summary() {
+        let (port, server) = serve(|connection| {
+            // Keep-alive lines may be sent at any time.
+            connection.send("\n");
+            connection.send(SUMMARY);
+        });
+        let mut client = connect(port, 1);
+        let summary = client.read_summary().unwrap();
+
+        server.join().unwrap();
+
+        assert_eq!(
+            summary.game_id,
+            "wdoor+floodgate-300-10F+crux+other+20260101120000"
+        );
+        assert_eq!(summary.color, Some(Color::White));
+        assert_eq!(summary.time.unit, Duration::from_secs(1));
+        assert_eq!(summary.time.total, 300);
+        assert_eq!(summary.time.byoyomi, 0);
+        assert_eq!(summary.time.increment, 10);
+
+        let game = Game::new(summary).unwrap();
+
+        assert_eq!(
+            Usi::format_position(&game.pos),
+            "lnsgkgsnl/1r5b1/pppppp1pp/6p2/9/7P1/PPPPPPP1P/1B5R1/LNSGKGSNL b - 3"
+        );
+        // Each side gains the increment after its move.
+        assert_eq!(
+            game.clocks,
+            [Duration::from_secs(298), Duration::from_secs(304)]
+        );
+    }
+
+    #[test]
+    fn clocks() {
+        let mut game = Game {
+            color: Color::Black,
+            pos: Position::startpos(),
+            time: TimeRule {
+                unit: Duration::from_millis(100),
+                total: 600,
+                byoyomi: 0,
+                increment: 50,
+            },
+            clocks: [Duration::from_secs(60); Color::COUNT],
+            thinking: true,
+            reason: None,
+        };
+
+        game.play("+7776FU,T30").unwrap();
+        assert!(!game.thinking);
+        assert_eq!(game.clocks[Color::Black], Duration::from_secs(62));
+
+        // A clock that runs out still gains the increment.
+        game.play("-3334FU,T1000").unwrap();
+        assert_eq!(game.clocks[Color::White], Duration::from_secs(5));
+
+        // Moves without a time cost nothing.
+        game.play("+2726FU").unwrap();
+        assert_eq!(game.clocks[Color::Black], Duration::from_secs(67));
+
+        let limits = game.limits();
+
+        assert_eq!(
+            limits.time,
+            [Duration::from_secs(67), Duration::from_secs(5)]
+        );
+        assert_eq!(limits.increment, [Duration::from_secs(5); Color::COUNT]);
+        assert_eq!(limits.byoyomi, Duration::ZERO);
+
+        assert!(game.play("+2625FU").is_err());
+    }
+
+    #[test]
+    fn comments() {
+        let mut pos = Position::startpos();
+        let pv = ["7g7f", "3c3d", "2g2f"].map(|mv| Usi::parse_move(mv).unwrap());
+        let result = SearchResult {
+            best_move: Some(pv[0]),
+            score: 52,
+            pv: pv.to_vec(),
+            ..SearchResult::default()
+        };
+
+        assert_eq!(comment(&pos, &result), "52 -3334FU +2726FU");
+
+        // Scores are from the point of view of black, and the moves stop at an illegal one.
+        pos.make_move(pv[0]);
+
+        let result = SearchResult {
+            best_move: Some(pv[1]),
+            score: 52,
+            pv: vec![pv[1], pv[2], pv[2]],
+            ..SearchResult::default()
+        };
+
+        assert_eq!(comment(&pos, &result), "-52 +2726FU");
+    }
+
+    #[test]
+    fn play_games() {
+        let (port, server) = serve(|connection| {
+            assert_eq!(connection.receive(), "LOGIN crux secret");
+            connection.send("LOGIN:crux OK\n");
+
+            // The server moves first and the game is drawn by repetition after a move of each
+            // side.
+            connection.start(&summary("draw", '-', "PI\n+\n"), "draw");
+            connection.send("+7776FU,T1\n");
+            assert!(connection.echo_move().starts_with('-'));
+            connection.send("#SENNICHITE\n#DRAW\n");
+
+            // The client moves in a game already in progress and loses by perpetual check.
+            connection.start(
+                &summary("loss", '+', "PI\n+\n+2726FU,T1\n-8384FU,T1\n"),
+                "loss",
+            );
+            assert!(connection.echo_move().starts_with('+'));
+            connection.send("#OUTE_SENNICHITE\n#LOSE\n");
+
+            assert_eq!(connection.receive(), "LOGOUT");
+            connection.send("LOGOUT:completed\n");
+        });
+        let mut client = connect(port, 2);
+
+        client.run().unwrap();
+
+        let received = server.join().unwrap();
+
+        // The login, an agreement and a move for each game, and the logout, and nothing else.
+        assert_eq!(received.len(), 6);
+    }
+}
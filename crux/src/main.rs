@@ -1 +1,51 @@
-fn main() {}
+mod csa;
+mod options;
+mod search_thread;
+mod usi;
+
+use std::{env, process::ExitCode};
+
+use crux_lib::args::Args;
+
+use crate::{
+    csa::{CsaClient, CsaOptions},
+    usi::UsiEngine,
+};
+
+const USAGE: &str = "\
+usage: crux [command] [options]
+
+Without a command, the engine speaks USI on standard input and output.
+
+commands:
+  csa       play on a CSA game server
+    --user <name>           login name (required)
+    --password <password>   login password (required)
+    --host <host>           host of the server [localhost]
+    --port <n>              port of the server [4081]
+    --games <n>             number of games played before logging out [1]
+    --comments              send the score and the expected moves with every move
+    --option <name=value>   USI option of the engine, repeatable";
+
+fn main() -> ExitCode {
+    let mut args = env::args().skip(1);
+
+    let result = match args.next().as_deref() {
+        None => {
+            UsiEngine::new().run();
+            Ok(())
+        }
+        Some("csa") => CsaOptions::parse(Args::new(args.collect()))
+            .and_then(CsaClient::connect)
+            .and_then(|mut client| client.run()),
+        _ => Err(USAGE.to_string()),
+    };
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("{err}");
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,210 @@
+use std::{
+    fmt::{Display, Formatter, Result as FmtResult},
+    time::Duration,
+};
+
+use crux_lib::{
+    eval::EvalType,
+    search::{dfpn::DfpnSolver, time::TimeOptions, tt::TranspositionTable, Searcher},
+    shogi::entering_king::EnteringKingRule,
+};
+
+/// Largest `Hash` accepted, as in other USI engines, since a table that cannot be allocated
+/// aborts the engine.
+const MAX_HASH_MB: usize = 32768;
+const MAX_THREADS: usize = 1024;
+
+/// The value of the `EvalFile` option that selects the network embedded in the engine.
+pub const INTERNAL_EVAL_FILE: &str = "<internal>";
+const MAX_NETWORK_DELAY_MS: usize = 10_000;
+const MAX_MINIMUM_THINKING_TIME_MS: usize = 100_000;
+
+/// The values of the `EnteringKingRule` combo option.
+const ENTERING_KING_RULES: [(&str, EnteringKingRule); 3] = [
+    ("NoEnteringKing", EnteringKingRule::None),
+    ("CSARule24", EnteringKingRule::Point24),
+    ("CSARule27", EnteringKingRule::Point27),
+];
+
+/// The values of the `Evaluation` combo option.
+const EVAL_TYPES: [(&str, EvalType); 2] =
+    [("NNUE", EvalType::Nnue), ("Classical", EvalType::Classical)];
+
+/// An error returned when a `setoption` command cannot be applied.
+#[derive(Debug, Clone)]
+pub enum OptionError {
+    UnknownOption(String),
+    MissingValue(String),
+    InvalidValue(String, String),
+}
+
+impl Display for OptionError {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        match self {
+            OptionError::UnknownOption(name) => write!(f, "unknown option: {name}"),
+            OptionError::MissingValue(name) => write!(f, "missing value for option: {name}"),
+            OptionError::InvalidValue(name, value) => {
+                write!(f, "invalid value for option {name}: {value}")
+            }
+        }
+    }
+}
+
+/// Engine options configurable through the USI `setoption` command.
+#[derive(Debug, Clone)]
+pub struct Options {
+    pub ponder: bool,
+    /// Size of the transposition table in megabytes.
+    pub hash: usize,
+    /// Size of the proof table of the mate solver in megabytes.
+    pub mate_hash: usize,
+    pub threads: usize,
+    pub entering_king_rule: EnteringKingRule,
+    pub time: TimeOptions,
+    pub eval_type: EvalType,
+    /// Path of the network file, or [`INTERNAL_EVAL_FILE`].
+    pub eval_file: String,
+}
+
+impl Options {
+    /// Prints the `option` lines sent in response to the `usi` command.
+    pub fn print(&self) {
+        println!("option name USI_Ponder type check default false");
+        println!(
+            "option name Hash type spin default {} min 1 max {MAX_HASH_MB}",
+            TranspositionTable::DEFAULT_SIZE_MB
+        );
+        println!("option name Clear Hash type button");
+        println!(
+            "option name MateHash type spin default {} min 1 max {MAX_HASH_MB}",
+            DfpnSolver::DEFAULT_SIZE_MB
+        );
+        println!("option name Threads type spin default 1 min 1 max {MAX_THREADS}");
+        println!(
+            "option name EnteringKingRule type combo default {}{}",
+            combo_name(&ENTERING_KING_RULES, self.entering_king_rule),
+            ENTERING_KING_RULES
+                .iter()
+                .map(|(name, _)| format!(" var {name}"))
+                .collect::<String>()
+        );
+        println!(
+            "option name Evaluation type combo default {}{}",
+            combo_name(&EVAL_TYPES, self.eval_type),
+            EVAL_TYPES
+                .iter()
+                .map(|(name, _)| format!(" var {name}"))
+                .collect::<String>()
+        );
+        println!("option name EvalFile type string default {INTERNAL_EVAL_FILE}");
+        println!(
+            "option name NetworkDelay type spin default {} min 0 max {MAX_NETWORK_DELAY_MS}",
+            TimeOptions::DEFAULT_NETWORK_DELAY.as_millis()
+        );
+        println!(
+            "option name MinimumThinkingTime type spin default {} min 0 max {MAX_MINIMUM_THINKING_TIME_MS}",
+            TimeOptions::DEFAULT_MINIMUM_THINKING_TIME.as_millis()
+        );
+    }
+
+    /// Sets up `searcher` with the options read at the start of every search.
+    pub fn apply(&self, searcher: &mut Searcher) {
+        searcher.set_entering_king_rule(self.entering_king_rule);
+        searcher.set_time_options(self.time);
+        searcher.set_eval_type(self.eval_type);
+    }
+
+    /// Applies a `setoption name <name> [value <value>]` command.
+    ///
+    /// Buttons such as `Clear Hash` are accepted here but acted on by the caller.
+    pub fn set(&mut self, name: &str, value: Option<&str>) -> Result<(), OptionError> {
+        match name {
+            "USI_Ponder" => self.ponder = parse_check(name, value)?,
+            // `USI_Hash` is still sent by some GUIs in place of `Hash`.
+            "Hash" | "USI_Hash" => self.hash = parse_spin(name, value, 1, MAX_HASH_MB)?,
+            "Clear Hash" => {}
+            "MateHash" => self.mate_hash = parse_spin(name, value, 1, MAX_HASH_MB)?,
+            "Threads" => self.threads = parse_spin(name, value, 1, MAX_THREADS)?,
+            "EnteringKingRule" => {
+                self.entering_king_rule = parse_combo(name, value, &ENTERING_KING_RULES)?;
+            }
+            "Evaluation" => self.eval_type = parse_combo(name, value, &EVAL_TYPES)?,
+            "EvalFile" => {
+                self.eval_file = value
+                    .filter(|value| !value.is_empty())
+                    .ok_or_else(|| OptionError::MissingValue(name.to_string()))?
+                    .to_string();
+            }
+            "NetworkDelay" => {
+                let millis = parse_spin(name, value, 0, MAX_NETWORK_DELAY_MS)?;
+                self.time.network_delay = Duration::from_millis(millis as u64);
+            }
+            "MinimumThinkingTime" => {
+                let millis = parse_spin(name, value, 0, MAX_MINIMUM_THINKING_TIME_MS)?;
+                self.time.minimum_thinking_time = Duration::from_millis(millis as u64);
+            }
+            _ => return Err(OptionError::UnknownOption(name.to_string())),
+        }
+
+        Ok(())
+    }
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            ponder: false,
+            hash: TranspositionTable::DEFAULT_SIZE_MB,
+            mate_hash: DfpnSolver::DEFAULT_SIZE_MB,
+            threads: 1,
+            entering_king_rule: EnteringKingRule::default(),
+            time: TimeOptions::default(),
+            eval_type: EvalType::default(),
+            eval_file: INTERNAL_EVAL_FILE.to_string(),
+        }
+    }
+}
+
+fn parse_check(name: &str, value: Option<&str>) -> Result<bool, OptionError> {
+    match value {
+        Some("true") => Ok(true),
+        Some("false") => Ok(false),
+        Some(value) => Err(OptionError::InvalidValue(
+            name.to_string(),
+            value.to_string(),
+        )),
+        None => Err(OptionError::MissingValue(name.to_string())),
+    }
+}
+
+fn parse_spin(
+    name: &str,
+    value: Option<&str>,
+    min: usize,
+    max: usize,
+) -> Result<usize, OptionError> {
+    let value = value.ok_or_else(|| OptionError::MissingValue(name.to_string()))?;
+
+    value
+        .parse()
+        .ok()
+        .filter(|value| (min..=max).contains(value))
+        .ok_or_else(|| OptionError::InvalidValue(name.to_string(), value.to_string()))
+}
+
+fn parse_combo<T: Copy>(
+    name: &str,
+    value: Option<&str>,
+    vars: &[(&str, T)],
+) -> Result<T, OptionError> {
+    let value = value.ok_or_else(|| OptionError::MissingValue(name.to_string()))?;
+
+    vars.iter()
+        .find(|(var, _)| *var == value)
+        .map(|&(_, var)| var)
+        .ok_or_else(|| OptionError::InvalidValue(name.to_string(), value.to_string()))
+}
+
+fn combo_name<T: PartialEq>(vars: &[(&'static str, T)], value: T) -> &'static str {
+    vars.iter().find(|(_, var)| *var == value).unwrap().0
+}
@@ -0,0 +1,71 @@
+use std::{
+    sync::{atomic::Ordering, Arc},
+    thread::{self, JoinHandle},
+};
+
+use crux_lib::search::{Searcher, Signals, SEARCH_STACK_SIZE};
+
+/// The searcher of the engine, moved to a thread of its own while searching so that
+/// commands can still be handled.
+pub struct SearchThread {
+    signals: Arc<Signals>,
+    searcher: Option<Searcher>,
+    thread: Option<JoinHandle<Searcher>>,
+}
+
+impl SearchThread {
+    /// Wraps a searcher controlled by `signals`.
+    #[must_use]
+    pub fn new(signals: Arc<Signals>, searcher: Searcher) -> Self {
+        Self {
+            signals,
+            searcher: Some(searcher),
+            thread: None,
+        }
+    }
+
+    /// Runs `search` with the searcher on a new thread, after resetting the stop signal and
+    /// setting the ponder signal to `ponder`.
+    ///
+    /// # Panics
+    /// Panics if a search is already running.
+    pub fn start(&mut self, ponder: bool, search: impl FnOnce(&mut Searcher) + Send + 'static) {
+        let mut searcher = self.searcher.take().expect("a search is already running");
+
+        self.signals.stop.store(false, Ordering::Relaxed);
+        self.signals.ponder.store(ponder, Ordering::Relaxed);
+
+        let thread = thread::Builder::new()
+            .stack_size(SEARCH_STACK_SIZE)
+            .spawn(move || {
+                search(&mut searcher);
+                searcher
+            })
+            .unwrap();
+
+        self.thread = Some(thread);
+    }
+
+    /// Stops the running search, if any, and waits for it to finish.
+    pub fn stop(&mut self) {
+        if self.thread.is_some() {
+            self.signals.stop.store(true, Ordering::Relaxed);
+            self.join();
+        }
+    }
+
+    /// Waits for the running search, if any, to finish.
+    pub fn join(&mut self) {
+        if let Some(thread) = self.thread.take() {
+            self.searcher = Some(thread.join().unwrap());
+        }
+    }
+
+    /// Returns the searcher.
+    ///
+    /// # Panics
+    /// Panics while a search is running.
+    pub fn searcher_mut(&mut self) -> &mut Searcher {
+        self.searcher.as_mut().expect("a search is running")
+    }
+}
@@ -0,0 +1,458 @@
+use std::{
+    io::{self, BufRead},
+    sync::{atomic::Ordering, Arc},
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+use crux_lib::{
+    eval::{nnue::network::Network, EvalType},
+    notation::{usi::Usi, Notation},
+    search::{
+        dfpn::{DfpnSolver, MateResult},
+        score::{is_mate_score, SCORE_MATE},
+        tt::TranspositionTable,
+        Info, Limits, Searcher, Signals, SEARCH_STACK_SIZE,
+    },
+    shogi::{core::Color, entering_king::can_declare_win, position::Position},
+};
+
+use crate::{
+    options::{Options, INTERNAL_EVAL_FILE},
+    search_thread::SearchThread,
+};
+
+const ENGINE_NAME: &str = concat!("Crux ", env!("CARGO_PKG_VERSION"));
+const ENGINE_AUTHOR: &str = "KazApps, m5t0";
+
+/// The USI front end of the engine.
+///
+/// Reads commands from standard input and writes responses to standard output.
+/// Searches run on a separate thread so that `stop` and `ponderhit` can be handled
+/// while searching.
+///
+/// Based on the reference:
+/// https://shogidokoro2.stars.ne.jp/usi.html
+pub struct UsiEngine {
+    pos: Position,
+    options: Options,
+    signals: Arc<Signals>,
+    search_thread: SearchThread,
+    /// The mate solver, allocated by the first `go mate` and kept until `MateHash` changes.
+    solver: Option<DfpnSolver>,
+    mate_thread: Option<JoinHandle<DfpnSolver>>,
+}
+
+impl UsiEngine {
+    /// Creates an engine set up with the initial position.
+    #[must_use]
+    pub fn new() -> Self {
+        let signals = Arc::new(Signals::default());
+        let options = Options::default();
+        let tt = Arc::new(TranspositionTable::new(options.hash));
+
+        Self {
+            pos: Position::startpos(),
+            options,
+            search_thread: SearchThread::new(
+                Arc::clone(&signals),
+                Searcher::new(Arc::clone(&signals), tt),
+            ),
+            signals,
+            solver: None,
+            mate_thread: None,
+        }
+    }
+
+    /// Runs the command loop until `quit` is received or standard input is closed.
+    pub fn run(&mut self) {
+        for line in io::stdin().lock().lines() {
+            let Ok(line) = line else {
+                break;
+            };
+
+            if !self.handle_command(&line) {
+                break;
+            }
+        }
+
+        self.stop();
+    }
+
+    /// Handles a single command line.
+    ///
+    /// Returns `false` if the engine should quit.
+    fn handle_command(&mut self, line: &str) -> bool {
+        let mut tokens = line.split_whitespace();
+
+        let Some(command) = tokens.next() else {
+            return true;
+        };
+
+        match command {
+            "usi" => {
+                println!("id name {ENGINE_NAME}");
+                println!("id author {ENGINE_AUTHOR}");
+                self.options.print();
+                self.warn_material_network();
+                println!("usiok");
+            }
+            "isready" => {
+                self.warn_material_network();
+                println!("readyok");
+            }
+            "setoption" => self.setoption(tokens),
+            "usinewgame" => {
+                self.stop();
+                self.searcher_mut().clear();
+            }
+            "position" => self.position(tokens),
+            "go" => self.go(tokens),
+            "stop" => self.stop(),
+            "ponderhit" => self.signals.ponder.store(false, Ordering::Relaxed),
+            "gameover" => self.stop(),
+            "quit" => return false,
+            _ => println!("info string unknown command: {command}"),
+        }
+
+        true
+    }
+
+    fn setoption<'a>(&mut self, mut tokens: impl Iterator<Item = &'a str>) {
+        if tokens.next() != Some("name") {
+            println!("info string invalid setoption command");
+            return;
+        }
+
+        let mut name = Vec::new();
+        let mut value = Vec::new();
+        let mut in_value = false;
+
+        for token in tokens {
+            if !in_value && token == "value" {
+                in_value = true;
+            } else if in_value {
+                value.push(token);
+            } else {
+                name.push(token);
+            }
+        }
+
+        let name = name.join(" ");
+        let value = value.join(" ");
+        let value = in_value.then_some(value.as_str());
+
+        if let Err(err) = self.options.set(&name, value) {
+            println!("info string {err}");
+            return;
+        }
+
+        match name.as_str() {
+            "Hash" | "USI_Hash" => {
+                self.stop();
+
+                let size = self.options.hash;
+
+                if self.searcher_mut().tt().size_mb() != size {
+                    let tt = Arc::new(TranspositionTable::new(size));
+
+                    self.searcher_mut().set_tt(tt);
+                }
+            }
+            "Clear Hash" => {
+                self.stop();
+                self.searcher_mut().tt().clear();
+            }
+            "MateHash" => {
+                self.stop();
+                self.solver = None;
+            }
+            "EvalFile" => {
+                self.stop();
+
+                let path = self.options.eval_file.clone();
+
+                if path == INTERNAL_EVAL_FILE {
+                    self.searcher_mut().set_network(Network::default_network());
+                } else {
+                    match Network::load(&path) {
+                        Ok(network) => {
+                            self.searcher_mut().set_network(Arc::new(network));
+                            println!("info string loaded network '{path}'");
+                        }
+                        Err(err) => println!("info string failed to load network '{path}': {err}"),
+                    }
+                }
+            }
+            "Threads" => {
+                self.stop();
+
+                let threads = self.options.threads;
+
+                self.searcher_mut().set_threads(threads);
+            }
+            _ => {}
+        }
+    }
+
+    fn position<'a>(&mut self, mut tokens: impl Iterator<Item = &'a str>) {
+        let mut pos = match tokens.next() {
+            Some("startpos") => {
+                if tokens.next().is_some_and(|token| token != "moves") {
+                    println!("info string invalid position command");
+                    return;
+                }
+
+                Position::startpos()
+            }
+            Some("sfen") => {
+                let sfen = tokens
+                    .by_ref()
+                    .take_while(|&token| token != "moves")
+                    .collect::<Vec<_>>()
+                    .join(" ");
+
+                match Usi::parse_position(&sfen) {
+                    Ok(pos) => pos,
+                    Err(err) => {
+                        println!("info string invalid sfen '{sfen}': {err:?}");
+                        return;
+                    }
+                }
+            }
+            _ => {
+                println!("info string invalid position command");
+                return;
+            }
+        };
+
+        for token in tokens {
+            let mv = match Usi::parse_move(token) {
+                Ok(mv) => mv,
+                Err(err) => {
+                    println!("info string invalid move '{token}': {err:?}");
+                    return;
+                }
+            };
+
+            if !pos.is_legal_move(mv) {
+                println!("info string illegal move '{token}'");
+                return;
+            }
+
+            pos.make_move(mv);
+        }
+
+        self.pos = pos;
+    }
+
+    fn go<'a>(&mut self, tokens: impl Iterator<Item = &'a str>) {
+        let mut tokens = tokens.peekable();
+
+        if tokens.next_if_eq(&"mate").is_some() {
+            self.go_mate(tokens);
+            return;
+        }
+
+        let (limits, ponder) = match parse_go(tokens) {
+            Ok(params) => params,
+            Err(token) => {
+                println!("info string invalid go parameter '{token}'");
+                return;
+            }
+        };
+
+        self.stop();
+
+        let signals = Arc::clone(&self.signals);
+        let pos = self.pos.clone();
+        let print_ponder = self.options.ponder;
+        let entering_king_rule = self.options.entering_king_rule;
+
+        self.options.apply(self.search_thread.searcher_mut());
+        self.search_thread.start(ponder, move |searcher| {
+            let bestmove = if can_declare_win(&pos, entering_king_rule) {
+                String::from("win")
+            } else {
+                let result = searcher.search(&pos, &limits, print_info);
+
+                match (result.best_move, result.ponder_move) {
+                    (Some(best), Some(ponder)) if print_ponder => format!(
+                        "{} ponder {}",
+                        Usi::format_move(best),
+                        Usi::format_move(ponder)
+                    ),
+                    (Some(best), _) => Usi::format_move(best),
+                    (None, _) => String::from("resign"),
+                }
+            };
+
+            // `bestmove` must not be sent before `stop` or `ponderhit`
+            // while searching infinitely or pondering.
+            while !signals.stop.load(Ordering::Relaxed)
+                && (limits.infinite || signals.ponder.load(Ordering::Relaxed))
+            {
+                thread::sleep(Duration::from_millis(1));
+            }
+
+            println!("bestmove {bestmove}");
+        });
+    }
+
+    /// Handles `go mate <ms|infinite>`, searching for a forced mate with the df-pn solver.
+    fn go_mate<'a>(&mut self, mut tokens: impl Iterator<Item = &'a str>) {
+        let mut limits = Limits::default();
+
+        match tokens.next() {
+            Some("infinite") => limits.infinite = true,
+            Some(token) => match token.parse::<u64>() {
+                Ok(millis) => limits.movetime = Some(Duration::from_millis(millis)),
+                Err(_) => {
+                    println!("info string invalid go mate parameter '{token}'");
+                    return;
+                }
+            },
+            None => {
+                println!("info string missing go mate parameter");
+                return;
+            }
+        }
+
+        self.stop();
+
+        let signals = Arc::clone(&self.signals);
+        let size = self.options.mate_hash;
+        let mut solver = self
+            .solver
+            .take()
+            .unwrap_or_else(|| DfpnSolver::new(Arc::clone(&signals), size));
+        let pos = self.pos.clone();
+
+        signals.stop.store(false, Ordering::Relaxed);
+        signals.ponder.store(false, Ordering::Relaxed);
+
+        let thread = thread::Builder::new()
+            .stack_size(SEARCH_STACK_SIZE)
+            .spawn(move || {
+                match solver.solve(&pos, &limits) {
+                    MateResult::Mate(pv) => {
+                        let pv = pv
+                            .iter()
+                            .map(|&mv| Usi::format_move(mv))
+                            .collect::<Vec<_>>()
+                            .join(" ");
+
+                        println!("checkmate {pv}");
+                    }
+                    MateResult::NoMate => println!("checkmate nomate"),
+                    MateResult::Timeout => println!("checkmate timeout"),
+                }
+
+                solver
+            })
+            .unwrap();
+
+        self.mate_thread = Some(thread);
+    }
+
+    /// Stops the running search or mate search, if any, and waits for it to finish.
+    fn stop(&mut self) {
+        self.search_thread.stop();
+
+        if let Some(thread) = self.mate_thread.take() {
+            self.signals.stop.store(true, Ordering::Relaxed);
+            self.solver = Some(thread.join().unwrap());
+        }
+    }
+
+    /// Reports that the default network only counts material, when it is selected while no
+    /// network was embedded at build time and no other one is loaded.
+    fn warn_material_network(&self) {
+        if self.options.eval_type == EvalType::Nnue
+            && self.options.eval_file == INTERNAL_EVAL_FILE
+            && !Network::has_embedded()
+        {
+            println!(
+                "info string no network is embedded (build with CRUX_EVALFILE), using a material network"
+            );
+        }
+    }
+
+    fn searcher_mut(&mut self) -> &mut Searcher {
+        self.search_thread.searcher_mut()
+    }
+}
+
+impl Default for UsiEngine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Parses the arguments of the `go` command into search limits and the ponder flag.
+///
+/// Returns the offending token on failure.
+fn parse_go<'a>(mut tokens: impl Iterator<Item = &'a str>) -> Result<(Limits, bool), &'a str> {
+    let mut limits = Limits::default();
+    let mut ponder = false;
+
+    while let Some(token) = tokens.next() {
+        match token {
+            "infinite" => limits.infinite = true,
+            "ponder" => ponder = true,
+            "btime" | "wtime" | "byoyomi" | "binc" | "winc" | "movetime" | "nodes" => {
+                let value = tokens
+                    .next()
+                    .and_then(|value| value.parse::<u64>().ok())
+                    .ok_or(token)?;
+                let time = Duration::from_millis(value);
+
+                match token {
+                    "btime" => limits.time[Color::Black] = time,
+                    "wtime" => limits.time[Color::White] = time,
+                    "byoyomi" => limits.byoyomi = time,
+                    "binc" => limits.increment[Color::Black] = time,
+                    "winc" => limits.increment[Color::White] = time,
+                    "movetime" => limits.movetime = Some(time),
+                    _ => limits.nodes = Some(value),
+                }
+            }
+            "depth" => {
+                let value = tokens
+                    .next()
+                    .and_then(|value| value.parse::<i32>().ok())
+                    .ok_or(token)?;
+
+                limits.depth = Some(value);
+            }
+            _ => return Err(token),
+        }
+    }
+
+    Ok((limits, ponder))
+}
+
+/// Prints a USI `info` line for a completed iteration.
+fn print_info(info: &Info) {
+    let score = if is_mate_score(info.score) {
+        let plies = SCORE_MATE - info.score.abs();
+
+        format!("mate {}", if info.score > 0 { plies } else { -plies })
+    } else {
+        format!("cp {}", info.score)
+    };
+
+    let millis = info.elapsed.as_millis();
+    let nps = info.nodes as u128 * 1000 / millis.max(1);
+    let pv = info
+        .pv
+        .iter()
+        .map(|&mv| Usi::format_move(mv))
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    println!(
+        "info depth {} seldepth {} score {score} nodes {} nps {nps} time {millis} hashfull {} pv {pv}",
+        info.depth, info.seldepth, info.nodes, info.hashfull,
+    );
+}